@@ -0,0 +1,104 @@
+use std::fmt;
+use std::io;
+use std::net::{Shutdown, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+///token used to cancel an in-flight request from another thread.
+///
+///Aborting shuts down the connection the request is using, whether it was opened for it, reused from the pool
+///or opened by a [`Connector`](crate::Connector), so a blocked `send()` returns
+///[`HttpError::Aborted`](crate::HttpError::Aborted) right away instead of waiting for the timeout. A request
+///sent over a shared HTTP/2 or HTTP/3 connection has its stream cancelled instead, leaving the connection to
+///the others.
+///Once aborted, the request and its clones fail with `Aborted` whenever they are sent.
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle {
+    aborted: Arc<AtomicBool>,
+    streams: Arc<Mutex<Vec<Closer>>>,
+}
+
+//shuts down a connection used by the request
+struct Closer(Box<dyn Fn() + Send + Sync>);
+
+impl fmt::Debug for Closer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Closer")
+    }
+}
+
+impl AbortHandle {
+    ///cancel the request
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    /// use std::thread;
+    ///
+    /// let request = Client::new().get("https://www.rust-lang.org").unwrap();
+    /// let handle = request.abort_handle();
+    /// thread::spawn(move || handle.abort());
+    /// let res = request.send();
+    /// ```
+    pub fn abort(&self) {
+        self.aborted.store(true, Ordering::SeqCst);
+        for shutdown in self.streams.lock().unwrap().drain(..) {
+            (shutdown.0)();
+        }
+    }
+
+    ///return if the request was aborted
+    pub fn is_aborted(&self) -> bool {
+        self.aborted.load(Ordering::SeqCst)
+    }
+
+    //keep a handle on the socket so `abort` can shut it down
+    pub(crate) fn register(&self, stream: &TcpStream) -> io::Result<()> {
+        let stream = stream.try_clone()?;
+        self.register_with(Box::new(move || {
+            let _ = stream.shutdown(Shutdown::Both);
+        }));
+        Ok(())
+    }
+
+    //keep a function shutting a connection down for `abort`, calling it at once if already aborted
+    pub(crate) fn register_with(&self, shutdown: Box<dyn Fn() + Send + Sync>) {
+        self.streams.lock().unwrap().push(Closer(shutdown));
+        if self.is_aborted() {
+            self.abort();
+        }
+    }
+
+    //forget the sockets of a finished request
+    pub(crate) fn clear(&self) {
+        self.streams.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn abort_unblocks_read() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || listener.accept().unwrap());
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        let _peer = server.join().unwrap();
+
+        let handle = AbortHandle::default();
+        handle.register(&stream).unwrap();
+
+        let aborter = handle.clone();
+        thread::spawn(move || aborter.abort());
+
+        let mut buf = Vec::new();
+        let _ = stream.read_to_end(&mut buf);
+        assert!(handle.is_aborted());
+        assert!(buf.is_empty());
+    }
+}
@@ -0,0 +1,118 @@
+use super::params::{split_list, split_params};
+
+use std::time::Duration;
+
+//freshness of an alternative without `ma` parameter(RFC 7838 3.1)
+const DEFAULT_MAX_AGE: Duration = Duration::from_secs(24 * 60 * 60);
+
+///alternative service advertised by an `Alt-Svc` header(RFC 7838), such as HTTP/3 on another port.
+/// # Example
+/// ```
+/// use smolhttp::AltSvc;
+/// use std::time::Duration;
+///
+/// let services = AltSvc::parse(r#"h3=":443"; ma=86400, h2="alt.example.com:8443""#);
+/// assert_eq!(services[0].protocol(), "h3");
+/// assert_eq!(services[0].host(), None);
+/// assert_eq!(services[0].port(), 443);
+/// assert_eq!(services[1].host(), Some("alt.example.com"));
+/// assert_eq!(services[1].max_age(), Duration::from_secs(86400));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AltSvc {
+    protocol: String,
+    host: Option<String>,
+    port: u16,
+    max_age: Duration,
+    persist: bool,
+}
+
+impl AltSvc {
+    ///parse an `Alt-Svc` header value, `clear` and invalid alternatives giving none
+    pub fn parse(value: &str) -> Vec<Self> {
+        split_list(value)
+            .into_iter()
+            .filter_map(|alternative| {
+                let mut params = split_params(alternative).into_iter();
+                let (protocol, authority) = params.next()?;
+                let (host, port) = authority.rsplit_once(':')?;
+                let mut service = Self {
+                    protocol: decode_protocol(&protocol),
+                    host: Some(host.trim_start_matches('[').trim_end_matches(']').to_owned()).filter(|host| !host.is_empty()),
+                    port: port.parse().ok()?,
+                    max_age: DEFAULT_MAX_AGE,
+                    persist: false,
+                };
+                for (name, value) in params {
+                    match name.as_str() {
+                        "ma" => service.max_age = value.parse().map(Duration::from_secs).unwrap_or(DEFAULT_MAX_AGE),
+                        "persist" => service.persist = value == "1",
+                        _ => (),
+                    }
+                }
+                Some(service)
+            })
+            .collect()
+    }
+
+    ///return the ALPN protocol id of the alternative, like `h3` or `h2`
+    pub fn protocol(&self) -> &str {
+        &self.protocol
+    }
+
+    ///return the host of the alternative, `None` when it is the host of the origin
+    pub fn host(&self) -> Option<&str> {
+        self.host.as_deref()
+    }
+
+    ///return the port of the alternative
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    ///return how long the alternative can be used(default 24 hours)
+    pub fn max_age(&self) -> Duration {
+        self.max_age
+    }
+
+    ///return if the alternative survives network changes
+    pub fn persist(&self) -> bool {
+        self.persist
+    }
+}
+
+//percent decode a protocol id, which can't be `%` encoded bytes outside of tokens
+fn decode_protocol(protocol: &str) -> String {
+    let bytes = protocol.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], protocol.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())) {
+            (b'%', Some(byte)) => {
+                out.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                out.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn alternatives() {
+        assert!(AltSvc::parse("clear").is_empty());
+        let services = AltSvc::parse(r#"h3-29=":8443"; persist=1, w%3Dx%25y="[::1]:443"; ma=60, h2="bad""#);
+        assert_eq!(services.len(), 2);
+        assert_eq!((services[0].protocol(), services[0].port(), services[0].persist()), ("h3-29", 8443, true));
+        assert_eq!(services[0].max_age(), DEFAULT_MAX_AGE);
+        assert_eq!((services[1].protocol(), services[1].host()), ("w=x%y", Some("::1")));
+        assert_eq!(services[1].max_age(), Duration::from_secs(60));
+    }
+}
@@ -0,0 +1,957 @@
+//! Async version of [`Client`](crate::Client), available with the `tokio` feature, or with the `wasm` feature
+//! on `wasm32` targets.
+//!
+//! The surface is the same as the blocking client, only `send()` is an `async fn`. Requests go through the
+//! same middlewares, redirects, HSTS store, cookie jar, cache, retries and connection limits, over connections
+//! of their own. Names are resolved and connections opened by tokio, trying the addresses like the blocking
+//! client does, and each HTTP/2 connection is driven by a task of its own. HTTP/3 connections are shared with
+//! the blocking requests of the Client. A custom [`Resolver`](crate::Resolver) is called on the blocking
+//! threads of tokio, and so are requests opening their connections with a [`Connector`](crate::Connector)
+//! sent by the blocking client. Like the blocking client, request and response bodies sent over HTTP/2 and
+//! HTTP/3 are held in memory.
+//!
+//! On `wasm32`, requests are sent with the `fetch` API of the browser or the web worker, after going through
+//! the middlewares. The browser follows the redirects and handles cookies, HSTS, caching and connections
+//! itself, and the settings of the Client for them are ignored. Requests aren't retried, and an
+//! [`AbortHandle`] only cancels a request that hasn't been sent yet.
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), smolhttp::HttpError> {
+//! let content = smolhttp::r#async::Client::new()
+//!   .get("https://www.rust-lang.org")?
+//!   .send()
+//!   .await?
+//!   .text();
+//! println!("{content}");
+//! # Ok(())
+//! # }
+//! ```
+
+use super::checksum;
+use super::request;
+use super::AbortHandle;
+use super::Algorithm;
+use super::Body;
+use super::HttpError;
+use super::Response;
+use super::SigningRequest;
+use super::Url;
+
+use std::time::Duration;
+
+//the blocking threads and the sockets of tokio aren't available to wasm32, where the browser fetches the requests
+#[cfg(target_arch = "wasm32")]
+use super::fetch;
+
+#[cfg(not(target_arch = "wasm32"))]
+use super::conn::{self, Framing, Head, HeaderLimits, Pooled, Slot, SocketOptions, Split};
+#[cfg(not(target_arch = "wasm32"))]
+use super::dns;
+#[cfg(not(target_arch = "wasm32"))]
+use super::spill::Spill;
+#[cfg(not(target_arch = "wasm32"))]
+use super::tls::TlsConnector;
+#[cfg(not(target_arch = "wasm32"))]
+use super::{chunked, ErrorKind, HeaderMap, Method, Timings};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::future::{self, Future};
+#[cfg(not(target_arch = "wasm32"))]
+use std::io::{self, Write};
+#[cfg(not(target_arch = "wasm32"))]
+use std::net::{self, SocketAddr};
+#[cfg(not(target_arch = "wasm32"))]
+use std::pin::Pin;
+#[cfg(not(target_arch = "wasm32"))]
+use std::task::{Context, Poll};
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::{Instant, SystemTime};
+
+#[cfg(not(target_arch = "wasm32"))]
+use socket2::SockRef;
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader, ReadBuf};
+#[cfg(not(target_arch = "wasm32"))]
+use tokio::net::{TcpSocket, TcpStream};
+
+//how long a request expecting `100 Continue` waits before sending its body anyway
+#[cfg(not(target_arch = "wasm32"))]
+const CONTINUE_TIMEOUT: Duration = Duration::from_secs(1);
+
+///async http client object.
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    inner: request::Client,
+}
+
+///async http request object, returned by the methods of [`Client`].
+#[derive(Debug, Clone)]
+pub struct RequestBuilder {
+    inner: request::RequestBuilder,
+}
+
+impl From<request::Client> for Client {
+    fn from(inner: request::Client) -> Self {
+        Self { inner }
+    }
+}
+
+impl Client {
+    ///return an async Client with the default configuration
+    /// # Example
+    /// ```
+    /// use smolhttp::r#async::Client;
+    ///
+    /// let client = Client::new();
+    /// let configured = Client::from(smolhttp::Client::builder().timeout(5).build().unwrap());
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///return a GET request to `url`, relative to the base url if the Client has one
+    pub fn get(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.get(url)? })
+    }
+
+    ///return a POST request to `url`, relative to the base url if the Client has one
+    pub fn post(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.post(url)? })
+    }
+
+    ///return a PUT request to `url`, relative to the base url if the Client has one
+    pub fn put(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.put(url)? })
+    }
+
+    ///return a HEAD request to `url`, relative to the base url if the Client has one
+    pub fn head(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.head(url)? })
+    }
+
+    ///return a DELETE request to `url`, relative to the base url if the Client has one
+    pub fn delete(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.delete(url)? })
+    }
+
+    ///return an OPTIONS request to `url`, relative to the base url if the Client has one
+    pub fn options(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.options(url)? })
+    }
+
+    ///return a PATCH request to `url`, relative to the base url if the Client has one
+    pub fn patch(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.patch(url)? })
+    }
+
+    ///return a TRACE request to `url`, relative to the base url if the Client has one
+    pub fn trace(&self, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.trace(url)? })
+    }
+
+    ///return a request to `url` with the `method`, which must be a valid http token
+    pub fn request(&self, method: &str, url: &str) -> Result<RequestBuilder, HttpError> {
+        Ok(RequestBuilder { inner: self.inner.request(method, url)? })
+    }
+}
+
+impl RequestBuilder {
+    ///set Request's custom headers, names must be valid http tokens and values can't hold control characters
+    pub fn headers(self, data: Vec<(String, String)>) -> Result<Self, HttpError> {
+        Ok(Self { inner: self.inner.headers(data)? })
+    }
+
+    ///set a single header, replacing any previous value of it whatever its case
+    pub fn header(self, name: &str, value: &str) -> Result<Self, HttpError> {
+        Ok(Self { inner: self.inner.header(name, value)? })
+    }
+
+    ///remove a header, whatever its case
+    pub fn remove_header(self, name: &str) -> Self {
+        Self { inner: self.inner.remove_header(name) }
+    }
+
+    ///set Request's body, bytes or any [`Body`]
+    pub fn body<B: Into<Body>>(self, data: B) -> Self {
+        Self { inner: self.inner.body(data) }
+    }
+
+    ///set the timeout of this request, overriding the one of the Client
+    pub fn timeout(self, timeout: Duration) -> Self {
+        Self { inner: self.inner.timeout(timeout) }
+    }
+
+    ///keep the response body exactly as received, without decoding its `Content-Encoding`
+    pub fn raw(self) -> Self {
+        Self { inner: self.inner.raw() }
+    }
+
+    ///sign the request with `signer`, called with its final method, target, headers and body just before it is sent
+    pub fn signer<F>(self, signer: F) -> Self
+    where
+        F: Fn(&SigningRequest) -> Result<Vec<(String, String)>, HttpError> + Send + Sync + 'static,
+    {
+        Self { inner: self.inner.signer(signer) }
+    }
+
+    ///send the path and the query of the url exactly as written, without percent encoding them
+    pub fn raw_path(self) -> Result<Self, HttpError> {
+        Ok(Self { inner: self.inner.raw_path()? })
+    }
+
+    ///fail with [`HttpError::Checksum`] when the body of a successful response doesn't hash to the `hex` digest
+    pub fn expect_checksum(self, algorithm: Algorithm, hex: &str) -> Result<Self, HttpError> {
+        Ok(Self { inner: self.inner.expect_checksum(algorithm, hex)? })
+    }
+
+    ///return the parsed url of this request
+    pub fn url(&self) -> &Url {
+        self.inner.url()
+    }
+
+    ///return a handle that can abort this request before it is sent
+    pub fn abort_handle(&self) -> AbortHandle {
+        self.inner.abort_handle()
+    }
+
+    ///return the exact bytes sent for this request, its head then its body, without connecting
+    pub fn preview(&self) -> Vec<u8> {
+        self.inner.preview()
+    }
+
+    ///return an equivalent `curl` command, with its arguments quoted for a POSIX shell
+    pub fn to_curl(&self) -> String {
+        self.inner.to_curl()
+    }
+
+    ///send http(s) request
+    /// # Example
+    /// ```no_run
+    /// # async fn run() {
+    /// use smolhttp::r#async::Client;
+    ///
+    /// let res = Client::new().get("https://www.google.com").unwrap().send().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn send(self) -> Result<Response, HttpError> {
+        let checksum = self.inner.checksum.clone();
+        #[cfg(not(target_arch = "wasm32"))]
+        let res = {
+            let started = Instant::now();
+            follow(self.inner).await?.with_elapsed(started.elapsed())
+        };
+        #[cfg(target_arch = "wasm32")]
+        let res = fetch::send(self.inner).await?;
+        checksum::verify(checksum.as_ref(), res)
+    }
+}
+
+//connection of the async client
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) struct Connection {
+    io: Io,
+    //the socket of the connection, to peek at it while it is idle and shut it down when the request is aborted
+    socket: net::TcpStream,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+enum Io {
+    Plain(TcpStream),
+    Tls(Box<tokio_native_tls::TlsStream<TcpStream>>),
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Connection {
+    //return the protocol selected by the server through ALPN
+    #[cfg(feature = "alpn")]
+    fn alpn(&self) -> Option<String> {
+        match self.io {
+            Io::Tls(ref stream) => stream.get_ref().negotiated_alpn().ok().flatten().map(|alpn| String::from_utf8_lossy(&alpn).into_owned()),
+            Io::Plain(_) => None,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Pooled for Connection {
+    //the socket is non blocking like the stream it was cloned from
+    fn is_alive(&self) -> bool {
+        matches!(self.socket.peek(&mut [0u8; 1]), Err(ref err) if err.kind() == io::ErrorKind::WouldBlock)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AsyncRead for Connection {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut().io {
+            Io::Plain(ref mut stream) => Pin::new(stream).poll_read(cx, buf),
+            Io::Tls(ref mut stream) => Pin::new(&mut **stream).poll_read(cx, buf),
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl AsyncWrite for Connection {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut().io {
+            Io::Plain(ref mut stream) => Pin::new(stream).poll_write(cx, buf),
+            Io::Tls(ref mut stream) => Pin::new(&mut **stream).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut().io {
+            Io::Plain(ref mut stream) => Pin::new(stream).poll_flush(cx),
+            Io::Tls(ref mut stream) => Pin::new(&mut **stream).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut().io {
+            Io::Plain(ref mut stream) => Pin::new(stream).poll_shutdown(cx),
+            Io::Tls(ref mut stream) => Pin::new(&mut **stream).poll_shutdown(cx),
+        }
+    }
+}
+
+//run `f` on the blocking threads of tokio
+#[cfg(not(target_arch = "wasm32"))]
+async fn blocking<T, F>(f: F) -> Result<T, HttpError>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, HttpError> + Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(io::Error::other)?
+}
+
+//send the request, following the redirects like the blocking client
+#[cfg(not(target_arch = "wasm32"))]
+async fn follow(mut request: request::RequestBuilder) -> Result<Response, HttpError> {
+    let mut history = Vec::new();
+    let mut res = send_one(&request).await?;
+    while request.redirect(&res, &mut history)? {
+        res = send_one(&request).await?;
+    }
+    Ok(res.with_history(history))
+}
+
+//send the request to its current url, going through the middlewares
+#[cfg(not(target_arch = "wasm32"))]
+async fn send_one(request: &request::RequestBuilder) -> Result<Response, HttpError> {
+    if request.client.inner.middleware.is_empty() && request.signer.is_none() {
+        return send_stored(request).await;
+    }
+    let request = request.intercept()?;
+    let res = send_stored(&request).await?;
+    request.intercept_response(res)
+}
+
+//send the request prepared with the HSTS store, the cookie jar and the cache, then update them from the response
+#[cfg(not(target_arch = "wasm32"))]
+async fn send_stored(request: &request::RequestBuilder) -> Result<Response, HttpError> {
+    let (request, cached) = request.prepare()?;
+    let res = dispatch(&request).await?;
+    Ok(request.complete(cached, res))
+}
+
+//send the request, retrying according to the retry policy
+#[cfg(not(target_arch = "wasm32"))]
+async fn dispatch(request: &request::RequestBuilder) -> Result<Response, HttpError> {
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        request.begin_attempt(attempt)?;
+        let started = Instant::now();
+        let res = match tokio::time::timeout(request.effective_timeout(), send_once(request)).await {
+            Ok(res) => res.map(|res| res.with_url(&request.raw_url)),
+            Err(_) => Err(HttpError::from(io::Error::new(io::ErrorKind::TimedOut, "request timed out")).context(ErrorKind::Timeout, &request.raw_url)),
+        };
+        match request.end_attempt(attempt, &res, started.elapsed())? {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => return res,
+        }
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn send_once(request: &request::RequestBuilder) -> Result<Response, HttpError> {
+    let started = (SystemTime::now(), Instant::now());
+    let mut timings = Timings::default();
+    let res = transfer(request, started.1, &mut timings).await?;
+    Ok(request.finish(res, started, timings))
+}
+
+//send the request over a connection kept open by a previous request or a new one
+#[cfg(not(target_arch = "wasm32"))]
+async fn transfer(request: &request::RequestBuilder, started: Instant, timings: &mut Timings) -> Result<Response, HttpError> {
+    if let Some(res) = request.send_local() {
+        return res;
+    }
+    //connectors open blocking connections, such requests are sent by the blocking client
+    if request.client.inner.connector.is_some() {
+        let (request, timeout, mut spent) = (request.clone(), request.effective_timeout(), *timings);
+        let (res, spent) = blocking(move || request.transfer(started, timeout, &mut spent).map(|res| (res, spent))).await?;
+        *timings = spent;
+        return Ok(res);
+    }
+    #[cfg(feature = "http3")]
+    if request.uses_h3() {
+        let body = read_all(request).await?;
+        if let Some(res) = request.send_async_h3(body, started, request.effective_timeout(), timings).await? {
+            return Ok(res);
+        }
+    }
+
+    let key = request.pool_key();
+    let pooled = request.client.inner.async_pool.take(&key);
+    request.record_pool(pooled.is_some());
+    if let Some((stream, slot)) = pooled {
+        request.abort.register(&stream.socket)?;
+        match exchange(request, stream, slot, timings).await {
+            //the server closed the connection while it was idle, the request is sent once more over a new one
+            Err(err) if conn::is_stale(&err) && request.body.as_ref().is_none_or(Body::is_replayable) => {
+                event!(debug, "connection to {} closed while idle, reconnecting", key);
+                request.client.verbose('*', || "Connection closed while idle, reconnecting");
+            }
+            res => return res,
+        }
+    } else {
+        #[cfg(feature = "http2")]
+        if request.uses_h2() {
+            return send_h2(request, started, timings).await;
+        }
+    }
+    let slot = acquire(request).await?;
+    let stream = connect(request, started, timings).await?;
+    exchange(request, stream, slot, timings).await
+}
+
+//send the request over the HTTP/2 connection to its origin, opening one if there is none yet and falling back
+//to HTTP/1.1 when the server doesn't negotiate HTTP/2
+#[cfg(all(not(target_arch = "wasm32"), feature = "http2"))]
+async fn send_h2(request: &request::RequestBuilder, started: Instant, timings: &mut Timings) -> Result<Response, HttpError> {
+    let body = read_all(request).await?;
+    if let Some(res) = request.send_async_h2(None, body.as_deref(), timings).await? {
+        return Ok(res);
+    }
+    let (tcp, socket) = open_tcp(request, started, timings).await?;
+    let stream = handshake(request, tcp, socket, request.client.inner.h2.tls_connector(&request.client)?, started, timings).await?;
+    if stream.alpn().as_deref() != Some("h2") {
+        let slot = acquire(request).await?;
+        return exchange(request, stream, slot, timings).await;
+    }
+    let connection = request.open_async_h2(stream).await?;
+    request.send_async_h2(Some(connection), body.as_deref(), timings).await?.ok_or(HttpError::Parse("HTTP/2 stream refused"))
+}
+
+//read the whole body of the request, sent at once over HTTP/2 and HTTP/3, on the blocking threads unless it is
+//in memory
+#[cfg(all(not(target_arch = "wasm32"), feature = "http2"))]
+async fn read_all(request: &request::RequestBuilder) -> Result<Option<Vec<u8>>, HttpError> {
+    match request.body {
+        Some(ref body) => match body.as_bytes() {
+            Some(bytes) => Ok(Some(bytes.to_vec())),
+            None => {
+                let body = body.clone();
+                blocking(move || body.read_all().map(|body| body.into_owned())).await.map(Some)
+            }
+        },
+        None => Ok(None),
+    }
+}
+
+//wait for a free slot for a connection to the origin of the request
+#[cfg(not(target_arch = "wasm32"))]
+async fn acquire(request: &request::RequestBuilder) -> Result<Slot<Connection>, HttpError> {
+    let pool = &request.client.inner.async_pool;
+    let key = request.pool_key();
+    match pool.is_limited() {
+        //waiting for a connection to the origin to close blocks
+        true => {
+            let pool = pool.clone();
+            blocking(move || pool.acquire(&key)).await
+        }
+        false => pool.acquire(&key),
+    }
+}
+
+//return the addresses of `host` like the blocking client does, resolved by tokio unless the Client has a
+//resolver of its own, which is called on the blocking threads
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) async fn lookup(client: &crate::Client, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    if let Some(addrs) = client.cached(host, port) {
+        return addrs;
+    }
+    let addrs = match client.inner.resolver.clone() {
+        Some(resolver) => {
+            let name = host.to_owned();
+            tokio::task::spawn_blocking(move || dns::resolve(Some(&*resolver), &name, port)).await.map_err(io::Error::other)??
+        }
+        None => dns::found(host, tokio::net::lookup_host((host, port)).await?.collect())?,
+    };
+    client.resolved(host, port, addrs)
+}
+
+//connect to the first address accepting the connection, starting a new attempt every `ATTEMPT_DELAY` while
+//the previous ones are pending, like the blocking client does with threads
+#[cfg(not(target_arch = "wasm32"))]
+async fn connect_any(addrs: &[SocketAddr], options: &SocketOptions) -> io::Result<TcpStream> {
+    let mut addrs = conn::interleave(addrs).into_iter().peekable();
+    let mut attempts: Vec<Pin<Box<dyn Future<Output = io::Result<TcpStream>> + Send + '_>>> = Vec::new();
+    let mut delay = Box::pin(tokio::time::sleep(Duration::ZERO));
+    let mut last_err = None;
+    future::poll_fn(|cx| loop {
+        let mut i = 0;
+        while i < attempts.len() {
+            match attempts[i].as_mut().poll(cx) {
+                Poll::Ready(Ok(stream)) => return Poll::Ready(Ok(stream)),
+                Poll::Ready(Err(err)) => {
+                    last_err = Some(err);
+                    drop(attempts.swap_remove(i));
+                }
+                Poll::Pending => i += 1,
+            }
+        }
+        // a failed attempt starts the next one right away
+        if addrs.peek().is_some() && (attempts.is_empty() || delay.as_mut().poll(cx).is_ready()) {
+            attempts.extend(addrs.next().map(|addr| Box::pin(attempt(addr, options)) as Pin<Box<_>>));
+            delay.as_mut().reset(tokio::time::Instant::now() + conn::ATTEMPT_DELAY);
+            continue;
+        }
+        if attempts.is_empty() {
+            return Poll::Ready(Err(last_err.take().unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to connect to"))));
+        }
+        return Poll::Pending;
+    })
+    .await
+}
+
+//connect to `addr` with a socket set up with the options of the Client
+#[cfg(not(target_arch = "wasm32"))]
+async fn attempt(addr: SocketAddr, options: &SocketOptions) -> io::Result<TcpStream> {
+    let socket = conn::socket(addr, options)?;
+    socket.set_nonblocking(true)?;
+    TcpSocket::from_std_stream(socket.into()).connect(addr).await
+}
+
+//connect to the target, or to the proxy and through its tunnel for https, returning the connection and a
+//handle on its socket
+#[cfg(not(target_arch = "wasm32"))]
+async fn open_tcp(request: &request::RequestBuilder, started: Instant, timings: &mut Timings) -> Result<(TcpStream, net::TcpStream), HttpError> {
+    request.check_policy()?;
+    let proxy = request.proxy();
+    let (host, port) = request.dialed()?;
+    let addrs = lookup(&request.client, &host, port).await.map_err(request.fail(ErrorKind::Dns))?;
+    let addrs = if proxy.is_none() { request.allowed_addrs(addrs)? } else { addrs };
+    timings.dns = started.elapsed();
+    event!(debug, "resolved {}:{} to {} addresses in {:?}", host, port, addrs.len(), started.elapsed());
+    request.client.verbose('*', || request::describe_lookup(&host, port, &addrs));
+    let mut tcp = connect_any(&addrs, &request.client.inner.socket).await.map_err(request.fail(ErrorKind::Connect))?;
+    timings.connect = started.elapsed() - timings.dns;
+    event!(debug, "connected to {}:{} in {:?}", host, port, started.elapsed());
+    request.client.verbose('*', || request::describe_connection(&host, port, tcp.peer_addr()));
+    let socket = net::TcpStream::from(SockRef::from(&tcp).try_clone()?);
+    request.abort.register(&socket)?;
+
+    if let Some(proxy) = proxy.filter(|proxy| proxy.0.scheme != "http") {
+        let connect_header = request.connect_head(proxy);
+        tcp.write_all(connect_header.as_bytes()).await.map_err(request.fail(ErrorKind::Connect))?;
+        request.client.verbose('>', || &connect_header);
+
+        //read byte by byte, the bytes after the head belonging to the tunnel
+        let head = read_head(&mut tcp, request.client.inner.header_limits).await.map_err(request.fail(ErrorKind::Connect))?;
+        request.client.verbose('<', || String::from_utf8_lossy(&head));
+        request::tunnel_established(&head)?;
+        timings.connect = started.elapsed() - timings.dns;
+    }
+    Ok((tcp, socket))
+}
+
+//connect to the target, through the proxy if any, then set up TLS for https
+#[cfg(not(target_arch = "wasm32"))]
+async fn connect(request: &request::RequestBuilder, started: Instant, timings: &mut Timings) -> Result<Connection, HttpError> {
+    let (tcp, socket) = open_tcp(request, started, timings).await?;
+    match request.scheme.as_str() {
+        "https" => handshake(request, tcp, socket, request.client.tls_connector()?, started, timings).await,
+        _ => Ok(Connection { io: Io::Plain(tcp), socket }),
+    }
+}
+
+//set up TLS with the target over an open connection
+#[cfg(not(target_arch = "wasm32"))]
+async fn handshake(request: &request::RequestBuilder, tcp: TcpStream, socket: net::TcpStream, connector: &TlsConnector, started: Instant, timings: &mut Timings) -> Result<Connection, HttpError> {
+    let connector = tokio_native_tls::TlsConnector::from(connector.clone());
+    let handshake = connector.connect(request.tls_name(), tcp);
+    let tls = match request.client.inner.tls_handshake_timeout {
+        Some(limit) => match tokio::time::timeout(limit, handshake).await {
+            Ok(tls) => tls.map_err(HttpError::from),
+            Err(_) => Err(io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out").into()),
+        },
+        None => handshake.await.map_err(HttpError::from),
+    };
+    let tls = tls.map_err(request.fail(ErrorKind::Tls))?;
+    #[cfg(feature = "alpn")]
+    let alpn = tls.get_ref().negotiated_alpn().ok().flatten();
+    #[cfg(not(feature = "alpn"))]
+    let alpn: Option<Vec<u8>> = None;
+    request.handshake_done(started, timings, alpn.as_deref());
+    Ok(Connection { io: Io::Tls(Box::new(tls)), socket })
+}
+
+//write the request and read the response, waiting for `100 Continue` before the body if expected
+#[cfg(not(target_arch = "wasm32"))]
+async fn exchange(request: &request::RequestBuilder, stream: Connection, slot: Slot<Connection>, timings: &mut Timings) -> Result<Response, HttpError> {
+    #[cfg(feature = "alpn")]
+    let alpn = stream.alpn();
+    let mut stream = BufReader::new(stream);
+    let mut header = request.client.inner.buffers.take();
+    request.write_header(&mut header);
+    request.client.verbose('>', || &header);
+    let sent = Instant::now();
+    let written = stream.write_all(header.as_bytes()).await;
+    request.client.inner.buffers.put(header);
+    written.map_err(request.fail(ErrorKind::Write))?;
+
+    // interim heads, then the final response
+    let mut interim = Vec::new();
+    let mut head = None;
+    if let Some(ref body) = request.body {
+        let chunked = request.is_chunked();
+        if request.expects_continue() {
+            stream.flush().await.map_err(request.fail(ErrorKind::Write))?;
+            let limits = request.client.inner.header_limits;
+            let first = async {
+                loop {
+                    let head = read_head(&mut stream, limits).await?;
+                    match conn::head_status(&head) {
+                        Some(status) if status != 100 && conn::is_interim(status) => interim.push(head),
+                        _ => return Ok::<_, HttpError>(head),
+                    }
+                }
+            };
+            match tokio::time::timeout(CONTINUE_TIMEOUT, first).await {
+                Ok(Ok(first)) if conn::head_status(&first) == Some(100) => body.write_async(&mut stream, chunked).await.map_err(request.fail(ErrorKind::Write))?,
+                // the server answered before the body was sent, it won't be read
+                Ok(Ok(first)) => head = Some(first),
+                Ok(Err(err)) => return Err(err.context(ErrorKind::Read, &request.raw_url)),
+                // some servers ignore the expectation, the body is sent anyway
+                Err(_) => body.write_async(&mut stream, chunked).await.map_err(request.fail(ErrorKind::Write))?,
+            }
+        } else {
+            body.write_async(&mut stream, chunked).await.map_err(request.fail(ErrorKind::Write))?;
+        }
+    }
+    stream.flush().await.map_err(request.fail(ErrorKind::Write))?;
+    let (res, reusable) = receive(request, &mut stream, interim, head, sent, timings).await?;
+    #[cfg(feature = "alpn")]
+    let res = res.with_alpn(alpn);
+    if reusable && request.keeps_alive() && stream.buffer().is_empty() {
+        request.client.inner.async_pool.put(request.pool_key(), stream.into_inner(), slot);
+    }
+    Ok(res)
+}
+
+//read the response to the request, after the interim responses and the final head already read if any,
+//and return if the connection is left ready for another request
+#[cfg(not(target_arch = "wasm32"))]
+async fn receive<S>(request: &request::RequestBuilder, stream: &mut S, mut interim: Vec<Vec<u8>>, head: Option<Vec<u8>>, sent: Instant, timings: &mut Timings) -> Result<(Response, bool), HttpError>
+where
+    S: AsyncBufRead + Unpin,
+{
+    let head = match head {
+        Some(head) => head,
+        None => loop {
+            let head = read_head(stream, request.client.inner.header_limits).await.map_err(request.fail(ErrorKind::Read))?;
+            if !conn::head_status(&head).map(conn::is_interim).unwrap_or(false) {
+                break head;
+            }
+            interim.push(head);
+        },
+    };
+    timings.first_byte = sent.elapsed();
+    event!(debug, "response head received from {} after {:?}", request.host, timings.first_byte);
+
+    let limit = request.client.inner.max_response_size;
+    let framing = conn::framing(&head, matches!(request.method, Method::Head))?;
+    if request.discards_body(&head)? {
+        let drained = drain(stream, framing, request.client.inner.max_redirect_body).await;
+        return request.discarded(&interim, &head, drained);
+    }
+    conn::check_size(framing.length(), limit)?;
+    let mut copy: Box<dyn Write + Send> = match request.tee {
+        Some(ref tee) => Box::new(tee.clone()),
+        None => Box::new(io::sink()),
+    };
+    let (body, trailers, received, spilled) = match request.client.inner.spill {
+        Some(threshold) => {
+            let mut spill = Spill::new(threshold);
+            let trailers = copy_body(stream, framing, limit, &mut Split { body: &mut spill, copy: &mut copy }).await;
+            let received = spill.len();
+            let (body, spilled) = spill.finish()?;
+            (body, trailers.map_err(request.fail(ErrorKind::Read))?, received, spilled)
+        }
+        None => {
+            let mut body = Vec::with_capacity(conn::preallocation(framing.length(), limit));
+            let trailers = copy_body(stream, framing, limit, &mut Split { body: &mut body, copy: &mut copy }).await;
+            let received = body.len() as u64;
+            (body, trailers.map_err(request.fail(ErrorKind::Read))?, received, None)
+        }
+    };
+    if !request.client.inner.allow_incomplete {
+        conn::check_complete(framing.length(), received)?;
+    }
+    let reusable = conn::is_reusable(&head, framing, received);
+    Ok((request.parse_response(&interim, &head, body, trailers)?.with_spilled(spilled).with_framing(framing), reusable))
+}
+
+//read a response head without consuming any byte after it
+#[cfg(not(target_arch = "wasm32"))]
+async fn read_head<S>(stream: &mut S, limits: HeaderLimits) -> Result<Vec<u8>, HttpError>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut head = Head::new(limits);
+    let mut byte = [0u8; 1];
+    loop {
+        head.check_read(stream.read(&mut byte).await)?;
+        if head.push(byte[0])? {
+            return Ok(head.into_bytes());
+        }
+    }
+}
+
+//write a body to `out` as it arrives according to its framing, like the blocking client
+#[cfg(not(target_arch = "wasm32"))]
+async fn copy_body<S, W>(stream: &mut S, framing: Framing, limit: Option<u64>, out: &mut W) -> Result<HeaderMap, HttpError>
+where
+    S: AsyncBufRead + Unpin,
+    W: Write + ?Sized,
+{
+    let mut trailers = HeaderMap::new();
+    let max = limit.unwrap_or(u64::MAX);
+    let received = match framing {
+        // the size was checked against the limit before
+        Framing::Length(length) => copy(stream, length, out).await?,
+        Framing::Close => copy(stream, max.saturating_add(1), out).await?,
+        Framing::Chunked => {
+            let mut decoder = chunked::Decoder::new();
+            let (mut chunk, mut received) = (Vec::new(), 0);
+            while !decoder.is_done() && received <= max {
+                let input = stream.fill_buf().await?;
+                if input.is_empty() {
+                    return Err(HttpError::Parse("truncated chunked body"));
+                }
+                chunk.clear();
+                let used = decoder.decode(input, &mut chunk)?;
+                stream.consume(used);
+                out.write_all(&chunk)?;
+                received += chunk.len() as u64;
+            }
+            trailers = decoder.into_trailers();
+            received
+        }
+    };
+
+    match limit {
+        Some(limit) if received > limit => Err(HttpError::TooLarge { limit }),
+        _ => Ok(trailers),
+    }
+}
+
+//write at most `max` bytes of the stream to `out`, returning how many were written before it ended
+#[cfg(not(target_arch = "wasm32"))]
+async fn copy<S, W>(stream: &mut S, max: u64, out: &mut W) -> io::Result<u64>
+where
+    S: AsyncBufRead + Unpin,
+    W: Write + ?Sized,
+{
+    let mut copied = 0;
+    while copied < max {
+        let input = stream.fill_buf().await?;
+        if input.is_empty() {
+            break;
+        }
+        let n = input.len().min(usize::try_from(max - copied).unwrap_or(usize::MAX));
+        out.write_all(&input[..n])?;
+        stream.consume(n);
+        copied += n as u64;
+    }
+    Ok(copied)
+}
+
+//read and discard a body of at most `cap` bytes, returning if the connection is left at its end
+#[cfg(not(target_arch = "wasm32"))]
+async fn drain<S>(stream: &mut S, framing: Framing, cap: u64) -> bool
+where
+    S: AsyncBufRead + Unpin,
+{
+    match framing {
+        Framing::Length(length) if length <= cap => copy(stream, length, &mut io::sink()).await.is_ok_and(|copied| copied == length),
+        Framing::Chunked => copy_body(stream, framing, Some(cap), &mut io::sink()).await.is_ok(),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::CookieJar;
+    #[cfg(feature = "http2")]
+    use crate::h2;
+
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap().block_on(future)
+    }
+
+    #[test]
+    fn shared_pipeline() {
+        //a server accepting a single connection, answering a redirect setting a cookie, its target, then an upload
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut heads = Vec::new();
+            for response in [&b"HTTP/1.1 302 Found\r\nLocation: /b\r\nSet-Cookie: id=1\r\nContent-Length: 5\r\n\r\nmoved"[..], b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"] {
+                heads.push(String::from_utf8(conn::read_head(&mut stream, HeaderLimits::default()).unwrap()).unwrap());
+                stream.write_all(response).unwrap();
+            }
+            heads.push(String::from_utf8(conn::read_head(&mut stream, HeaderLimits::default()).unwrap()).unwrap());
+            let mut body = Vec::new();
+            let mut byte = [0u8; 1];
+            while !body.ends_with(b"0\r\n\r\n") {
+                stream.read_exact(&mut byte).unwrap();
+                body.push(byte[0]);
+            }
+            stream.write_all(b"HTTP/1.1 201 Created\r\nContent-Length: 0\r\n\r\n").unwrap();
+            (heads, chunked::decode(&body).unwrap().0)
+        });
+
+        let client = crate::Client::builder().default_header("Connection", "keep-alive").unwrap().timeout(5).redirects(1).cookie_store(CookieJar::new()).build().unwrap();
+        let client = Client::from(client);
+        let (res, upload) = block_on(async {
+            let res = client.get(&format!("http://127.0.0.1:{}/a", port)).unwrap().send().await.unwrap();
+            let upload = client.post(&format!("http://127.0.0.1:{}/upload", port)).unwrap().body(Body::reader(&b"streamed body"[..])).send().await.unwrap();
+            (res, upload)
+        });
+        assert_eq!((res.status_code(), res.text()), (200, "ok".to_owned()));
+        assert_eq!(res.url(), format!("http://127.0.0.1:{}/b", port));
+        assert_eq!(upload.status_code(), 201);
+
+        let (heads, body) = server.join().unwrap();
+        assert!(heads[1].starts_with("GET /b HTTP/1.1\r\n"));
+        assert!(heads[1].contains("Cookie: id=1\r\n"));
+        assert!(heads[2].contains("Transfer-Encoding: chunked\r\n"));
+        assert_eq!(body, b"streamed body");
+    }
+
+    #[test]
+    fn address_fallback() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let options = SocketOptions::default();
+        let stream = block_on(connect_any(&[closed, listener.local_addr().unwrap()], &options)).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+        assert!(block_on(connect_any(&[closed], &options)).is_err());
+    }
+
+    #[test]
+    fn spilled_body() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://127.0.0.1:{}/large", listener.local_addr().unwrap().port());
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            conn::read_head(&mut stream, HeaderLimits::default()).unwrap();
+            stream.write_all(format!("HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\n{}", "x".repeat(100)).as_bytes()).unwrap();
+        });
+
+        let client = Client::from(crate::Client::builder().timeout(5).spill_to_disk(64).build().unwrap());
+        let res = block_on(client.get(&url).unwrap().send()).unwrap();
+        assert!(res.bytes().is_empty());
+        assert_eq!(std::fs::read(res.body_path().unwrap()).unwrap(), "x".repeat(100).as_bytes());
+    }
+
+    #[test]
+    fn connector_requests() {
+        let mock = crate::testing::MockTransport::new();
+        mock.respond("GET", "http://example.com/", 200, "mocked");
+        let client = Client::from(crate::Client::builder().connector(mock.clone()).build().unwrap());
+        let res = block_on(client.get("http://example.com/").unwrap().send()).unwrap();
+        assert_eq!((res.status_code(), res.text()), (200, "mocked".to_owned()));
+        assert_eq!(mock.requests().len(), 1);
+    }
+
+    #[cfg(feature = "http2")]
+    #[test]
+    fn multiplexed_h2() {
+        //a server reading both requests before answering the second one first, over frames written by hand
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut preface = [0u8; 24];
+            stream.read_exact(&mut preface).unwrap();
+            let frame = |kind: u8, flags: u8, id: u32, payload: &[u8]| {
+                let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+                frame.extend_from_slice(&[kind, flags]);
+                frame.extend_from_slice(&id.to_be_bytes());
+                frame.extend_from_slice(payload);
+                frame
+            };
+            stream.write_all(&frame(0x4, 0, 0, &[])).unwrap();
+            let (mut ended, mut bodies) = (Vec::new(), Vec::new());
+            while ended.len() < 2 {
+                let mut header = [0u8; 9];
+                stream.read_exact(&mut header).unwrap();
+                let mut payload = vec![0; u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize];
+                stream.read_exact(&mut payload).unwrap();
+                let id = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+                if header[3] == 0x0 {
+                    bodies.push(payload);
+                }
+                if header[3] <= 0x1 && header[4] & 0x1 != 0 {
+                    ended.push(id);
+                }
+            }
+            let mut head = Vec::new();
+            crate::hpack::encode([(":status", "200")], &mut head);
+            for (id, body) in [(3, "second"), (1, "first")] {
+                stream.write_all(&frame(0x1, 0x4, id, &head)).unwrap();
+                stream.write_all(&frame(0x0, 0x1, id, body.as_bytes())).unwrap();
+            }
+            //the connection stays open until the client is done with it
+            let _ = stream.read_to_end(&mut Vec::new());
+            bodies
+        });
+
+        let client = crate::Client::new();
+        let (first, second) = block_on(async {
+            let tcp = TcpStream::connect(addr).await.unwrap();
+            let socket = net::TcpStream::from(SockRef::from(&tcp).try_clone().unwrap());
+            let connection = h2::AsyncConnection::handshake(Connection { io: Io::Plain(tcp), socket }).await.unwrap();
+            let send = |body: Option<&'static [u8]>| {
+                let (connection, request) = (connection.clone(), client.post(&format!("http://{}/", addr)).unwrap());
+                tokio::spawn(async move { request.send_async_h2(Some(connection), body, &mut Timings::default()).await })
+            };
+            let (first, second) = (send(Some(b"upload")), send(None));
+            (first.await.unwrap().unwrap().unwrap(), second.await.unwrap().unwrap().unwrap())
+        });
+        assert_eq!((first.status_code(), first.text()), (200, "first".to_owned()));
+        assert_eq!(second.text(), "second");
+        assert_eq!(server.join().unwrap(), [b"upload".to_vec()]);
+    }
+
+    #[test]
+    fn error_context() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://127.0.0.1:{}/", listener.local_addr().unwrap().port());
+        drop(listener);
+
+        let err = block_on(Client::new().get(&url).unwrap().send()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::Connect);
+        assert_eq!(err.url(), Some(url.as_str()));
+    }
+}
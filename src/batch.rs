@@ -0,0 +1,200 @@
+use super::ErrorKind;
+use super::HttpError;
+use super::Method;
+use super::RequestBuilder;
+use super::Response;
+use super::Timings;
+
+use std::io::{BufReader, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Instant;
+
+///send prepared requests concurrently, using at most `concurrency` threads
+///
+///The results are returned in the same order as `requests`.
+/// # Example
+/// ```no_run
+/// use smolhttp::Client;
+///
+/// let client = Client::new();
+/// let requests = ["https://docs.rs", "https://crates.io"]
+///     .into_iter()
+///     .map(|url| client.head(url).unwrap())
+///     .collect();
+///
+/// for res in smolhttp::send_all(requests, 4) {
+///     println!("{}", res.unwrap().status_code());
+/// }
+/// ```
+pub fn send_all(requests: Vec<RequestBuilder>, concurrency: usize) -> Vec<Result<Response, HttpError>> {
+    let workers = concurrency.max(1).min(requests.len());
+    let next = AtomicUsize::new(0);
+    let requests: Vec<Mutex<Option<RequestBuilder>>> = requests.into_iter().map(|req| Mutex::new(Some(req))).collect();
+    let results: Vec<Mutex<Option<Result<Response, HttpError>>>> = requests.iter().map(|_| Mutex::new(None)).collect();
+
+    thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let i = next.fetch_add(1, Ordering::SeqCst);
+                if i >= requests.len() {
+                    break;
+                }
+                let request = requests[i].lock().unwrap().take().expect("every request is taken once");
+                let res = request.send();
+                *results[i].lock().unwrap() = Some(res);
+            });
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|res| res.into_inner().unwrap().expect("every request is sent by a worker"))
+        .collect()
+}
+
+///send GET and HEAD requests to a single origin back to back over one connection, then read their
+///responses in order(HTTP/1.1 pipelining)
+///
+///The server must support pipelining. Redirects, retries and the cache don't apply to pipelined requests,
+///and the first failure ends the batch.
+/// # Example
+/// ```no_run
+/// use smolhttp::Client;
+///
+/// let client = Client::new();
+/// let requests = ["/sensors/1", "/sensors/2", "/sensors/3"]
+///     .into_iter()
+///     .map(|path| client.get(&format!("http://192.168.1.20{}", path)).unwrap())
+///     .collect();
+///
+/// for res in smolhttp::pipeline(requests).unwrap() {
+///     println!("{}", res.text());
+/// }
+/// ```
+pub fn pipeline(requests: Vec<RequestBuilder>) -> Result<Vec<Response>, HttpError> {
+    let origin = match requests.first() {
+        Some(first) => first.origin(),
+        None => return Ok(Vec::new()),
+    };
+    if requests.iter().any(|request| !matches!(request.method, Method::Get | Method::Head) || request.body.is_some()) {
+        return Err(HttpError::Config("only GET and HEAD requests without a body can be pipelined"));
+    }
+    if requests.iter().any(|request| request.origin() != origin) {
+        return Err(HttpError::Config("pipelined requests must share one origin"));
+    }
+    let requests = requests.iter().map(RequestBuilder::intercept).collect::<Result<Vec<_>, _>>()?;
+
+    //the connection is kept open until the last request
+    let mut head = String::new();
+    for (i, request) in requests.iter().enumerate() {
+        match i + 1 < requests.len() && !request.has_header("Connection") {
+            true => request.clone().header("Connection", "keep-alive")?.write_header(&mut head),
+            false => request.write_header(&mut head),
+        }
+    }
+
+    let first = &requests[0];
+    let (started, timeout) = (Instant::now(), first.effective_timeout());
+    let mut timings = Timings::default();
+    let pool = &first.client.inner.pool;
+    //the connection is counted against the limit of the origin until the last response is read
+    let (stream, _slot) = match pool.take(&first.pool_key()) {
+        Some((stream, slot)) => {
+            first.register_abort(&stream);
+            (stream, slot)
+        }
+        None => {
+            let slot = pool.acquire(&first.pool_key())?;
+            (first.connect(started, timeout, &mut timings)?, slot)
+        }
+    };
+    let mut stream = BufReader::new(stream);
+    stream.get_mut().set_read_timeout(Some(timeout))?;
+    first.client.verbose('>', || &head);
+    stream.get_mut().write_all(head.as_bytes()).map_err(first.fail(ErrorKind::Write))?;
+    stream.get_mut().flush().map_err(first.fail(ErrorKind::Write))?;
+
+    let sent = Instant::now();
+    requests
+        .iter()
+        .map(|request| {
+            let mut timings = timings;
+            let (res, _) = request.receive(&mut stream, Vec::new(), None, sent, &mut timings)?;
+            timings.total = started.elapsed();
+            request.intercept_response(res.with_url(&request.raw_url).with_timings(timings).with_elapsed(started.elapsed()))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    #[test]
+    fn send_all_returns_every_result() {
+        let mock = crate::testing::MockTransport::new();
+        for i in 0..8 {
+            mock.respond("GET", &format!("http://example.com/{}", i), 200, i.to_string());
+        }
+        let client = Client::with_connector(mock);
+        let mut requests: Vec<_> = (0..8).map(|i| client.get(&format!("http://example.com/{}", i)).unwrap()).collect();
+        requests.push(client.get("http://127.0.0.1:1/unrouted").unwrap());
+
+        //the results are in the order of the requests whatever the worker sending them
+        let results = send_all(requests, 3);
+        assert_eq!(results.len(), 9);
+        for (i, res) in results[..8].iter().enumerate() {
+            let res = res.as_ref().unwrap();
+            assert_eq!((res.url(), res.text()), (format!("http://example.com/{}", i).as_str(), i.to_string()));
+        }
+        assert!(results[8].is_err());
+    }
+
+    #[test]
+    fn send_all_empty() {
+        assert!(send_all(Vec::new(), 8).is_empty());
+    }
+
+    #[test]
+    fn pipelined_requests() {
+        let mock = crate::testing::MockTransport::new();
+        mock.respond("GET", "http://device.local/a", 200, "first");
+        mock.respond("HEAD", "http://device.local/b", 200, "");
+        mock.respond("GET", "http://device.local/c", 200, "third");
+        let client = Client::with_connector(mock.clone());
+
+        let requests = vec![client.get("http://device.local/a").unwrap(), client.head("http://device.local/b").unwrap(), client.get("http://device.local/c").unwrap()];
+        let responses = pipeline(requests).unwrap();
+        let bodies: Vec<String> = responses.iter().map(Response::text).collect();
+        assert_eq!(bodies, ["first", "", "third"]);
+        assert_eq!(responses[2].url(), "http://device.local/c");
+
+        let requests = mock.requests();
+        assert_eq!(requests[0].headers().get("Connection"), Some("keep-alive"));
+        assert_eq!(requests[2].headers().get("Connection"), Some("Close"));
+
+        let mixed = vec![client.get("http://device.local/a").unwrap(), client.get("http://other.local/").unwrap()];
+        assert!(matches!(pipeline(mixed), Err(HttpError::Config(_))));
+        assert!(pipeline(vec![client.post("http://device.local/a").unwrap()]).is_err());
+        assert!(pipeline(Vec::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn pipelined_redirect() {
+        let mock = crate::testing::MockTransport::new();
+        mock.respond("GET", "http://device.local/a", 200, "first");
+        mock.respond_raw("GET", "http://device.local/b", b"HTTP/1.1 302 Found\r\nLocation: /moved\r\nContent-Length: 5\r\n\r\nmoved".to_vec());
+        mock.respond("GET", "http://device.local/c", 200, "third");
+        let client = crate::ClientBuilder::new().connector(mock).redirects(5).build().unwrap();
+
+        //the body of the redirect in the middle is discarded without taking the next response with it
+        let requests = vec![client.get("http://device.local/a").unwrap(), client.get("http://device.local/b").unwrap(), client.get("http://device.local/c").unwrap()];
+        let responses = pipeline(requests).unwrap();
+        let statuses: Vec<_> = responses.iter().map(|res| (res.status_code(), res.text())).collect();
+        assert_eq!(statuses, [(200, "first".to_owned()), (302, String::new()), (200, "third".to_owned())]);
+        assert_eq!(responses[1].headers().get("Location"), Some("/moved"));
+    }
+}
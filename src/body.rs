@@ -0,0 +1,416 @@
+use super::chunked;
+use super::HttpError;
+
+use std::borrow::Cow;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+#[cfg(feature = "tokio")]
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+///body of a request: bytes in memory, a file or a reader, set with [`RequestBuilder::body`](crate::RequestBuilder::body).
+///
+///Bytes and files can be sent any number of times, by retries and redirects too, while a reader is
+///consumed by the first attempt: sending it again fails. Clones of a body share its bytes.
+///
+///A body whose length is unknown, a reader or chunks, is sent with `Transfer-Encoding: chunked` unless a
+///`Content-Length` header is set.
+/// # Example
+/// ```
+/// use smolhttp::{Body, Client};
+///
+/// let body = Body::chunks(["hello", " ", "world"]);
+/// assert_eq!(body.len(), None);
+/// let request = Client::new().post("http://example.com/").unwrap().body(body);
+/// assert!(String::from_utf8(request.preview()).unwrap().contains("\r\nTransfer-Encoding: chunked\r\n"));
+/// ```
+#[derive(Clone)]
+pub struct Body {
+    kind: Kind,
+}
+
+#[derive(Clone)]
+enum Kind {
+    Bytes(Arc<[u8]>),
+    File(PathBuf, u64),
+    //taken by the attempt sending it
+    Reader(Arc<Mutex<Option<Box<dyn Read + Send>>>>, Option<u64>),
+}
+
+impl fmt::Debug for Body {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            Kind::Bytes(ref bytes) => f.debug_tuple("Bytes").field(&bytes.len()).finish(),
+            Kind::File(ref path, len) => f.debug_tuple("File").field(path).field(&len).finish(),
+            Kind::Reader(_, len) => f.debug_tuple("Reader").field(&len).finish(),
+        }
+    }
+}
+
+impl Body {
+    ///return a body sending the content of the file at `path`, opened again by each attempt
+    pub fn file<P: AsRef<Path>>(path: P) -> Result<Self, HttpError> {
+        let path = path.as_ref();
+        let len = fs::metadata(path)?.len();
+        Ok(Self { kind: Kind::File(path.to_owned(), len) })
+    }
+
+    ///return a body sending everything read from `reader`, its length unknown
+    pub fn reader<R: Read + Send + 'static>(reader: R) -> Self {
+        Self { kind: Kind::Reader(Arc::new(Mutex::new(Some(Box::new(reader)))), None) }
+    }
+
+    ///return a body sending the first `len` bytes of `reader`
+    pub fn sized_reader<R: Read + Send + 'static>(reader: R, len: u64) -> Self {
+        Self { kind: Kind::Reader(Arc::new(Mutex::new(Some(Box::new(reader)))), Some(len)) }
+    }
+
+    ///return a body sending the chunks of `chunks` one after the other as they are produced, its length unknown
+    pub fn chunks<I, T>(chunks: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        I::IntoIter: Send + 'static,
+        T: Into<Vec<u8>> + 'static,
+    {
+        let chunks = chunks.into_iter().map(Into::into);
+        Self::reader(Chunks { chunks, chunk: Vec::new(), pos: 0 })
+    }
+
+    ///return a body serializing the items of `items` as a JSON array while it is sent, its length unknown
+    ///
+    ///An item failing to serialize fails the request while its body is written.
+    /// # Example
+    /// ```
+    /// use smolhttp::Body;
+    ///
+    /// let body = Body::json_array((0..1_000_000).map(|id| serde_json::json!({ "id": id })));
+    /// assert_eq!(body.len(), None);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn json_array<I>(items: I) -> Self
+    where
+        I: IntoIterator,
+        I::IntoIter: Send + 'static,
+        I::Item: serde::Serialize,
+    {
+        Self::reader(JsonItems { items: items.into_iter(), array: true, count: 0, done: false, buffer: Vec::new(), pos: 0 })
+    }
+
+    ///return a body serializing the items of `items` one per line(NDJSON) while it is sent, its length unknown
+    #[cfg(feature = "json")]
+    pub fn ndjson<I>(items: I) -> Self
+    where
+        I: IntoIterator,
+        I::IntoIter: Send + 'static,
+        I::Item: serde::Serialize,
+    {
+        Self::reader(JsonItems { items: items.into_iter(), array: false, count: 0, done: false, buffer: Vec::new(), pos: 0 })
+    }
+
+    ///return the length of the body in bytes, `None` when it is unknown until the body is sent
+    pub fn len(&self) -> Option<u64> {
+        match self.kind {
+            Kind::Bytes(ref bytes) => Some(bytes.len() as u64),
+            Kind::File(_, len) => Some(len),
+            Kind::Reader(_, len) => len,
+        }
+    }
+
+    ///return if the body is known to be empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+
+    ///return the body if it is held in memory
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self.kind {
+            Kind::Bytes(ref bytes) => Some(&bytes[..]),
+            _ => None,
+        }
+    }
+
+    //return if the body can be sent again, readers being consumed by the first attempt
+    pub(crate) fn is_replayable(&self) -> bool {
+        !matches!(self.kind, Kind::Reader(..))
+    }
+
+    //return the path of a file body
+    pub(crate) fn path(&self) -> Option<&Path> {
+        match self.kind {
+            Kind::File(ref path, _) => Some(path),
+            _ => None,
+        }
+    }
+
+    //return a reader of the content of the body, no longer than its length
+    fn open(&self) -> Result<Box<dyn Read + Send + '_>, HttpError> {
+        Ok(match self.kind {
+            Kind::Bytes(ref bytes) => Box::new(&bytes[..]),
+            Kind::File(ref path, len) => Box::new(File::open(path)?.take(len)),
+            Kind::Reader(ref reader, Some(len)) => Box::new(take(reader)?.take(len)),
+            Kind::Reader(ref reader, None) => take(reader)?,
+        })
+    }
+
+    //write the whole body to `out`, failing if a file or a reader is shorter than announced
+    pub(crate) fn write_to<W: Write + ?Sized>(&self, out: &mut W) -> Result<(), HttpError> {
+        let written = io::copy(&mut self.open()?, out)?;
+        if written < self.len().unwrap_or_default() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "request body is shorter than its length").into());
+        }
+        Ok(())
+    }
+
+    //write the whole body to `out` with the chunked transfer coding
+    pub(crate) fn write_chunked<W: Write + ?Sized>(&self, out: &mut W) -> Result<(), HttpError> {
+        let written = chunked::encode(&mut self.open()?, out)?;
+        if written < self.len().unwrap_or_default() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "request body is shorter than its length").into());
+        }
+        Ok(())
+    }
+
+    //write the whole body to `out`, with the chunked transfer coding if `chunked`, reading files and readers
+    //a chunk at a time on the blocking threads of tokio
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn write_async<W: AsyncWrite + Unpin>(&self, out: &mut W, chunked: bool) -> Result<(), HttpError> {
+        let mut written = 0;
+        let mut reader: Option<Box<dyn Read + Send>> = match self.kind {
+            Kind::Bytes(ref bytes) => {
+                write_chunk(out, bytes, chunked).await?;
+                written = bytes.len() as u64;
+                None
+            }
+            Kind::File(ref path, len) => {
+                let path = path.clone();
+                let file = tokio::task::spawn_blocking(move || File::open(path)).await.map_err(io::Error::other)??;
+                Some(Box::new(file.take(len)))
+            }
+            Kind::Reader(ref reader, Some(len)) => Some(Box::new(take(reader)?.take(len))),
+            Kind::Reader(ref reader, None) => Some(take(reader)?),
+        };
+        while let Some(mut source) = reader.take() {
+            let read = tokio::task::spawn_blocking(move || {
+                let mut chunk = vec![0; chunked::CHUNK_SIZE];
+                let len = loop {
+                    match source.read(&mut chunk) {
+                        Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                        len => break len,
+                    }
+                };
+                (source, len.map(|len| chunk[..len].to_vec()))
+            });
+            let (source, chunk) = read.await.map_err(io::Error::other)?;
+            let chunk = chunk?;
+            if !chunk.is_empty() {
+                write_chunk(out, &chunk, chunked).await?;
+                written += chunk.len() as u64;
+                reader = Some(source);
+            }
+        }
+        if chunked {
+            out.write_all(b"0\r\n\r\n").await?;
+        }
+        if written < self.len().unwrap_or_default() {
+            return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "request body is shorter than its length").into());
+        }
+        Ok(())
+    }
+
+    //return the whole body, reading it if it isn't held in memory
+    pub(crate) fn read_all(&self) -> Result<Cow<'_, [u8]>, HttpError> {
+        match self.kind {
+            Kind::Bytes(ref bytes) => Ok(Cow::Borrowed(&bytes[..])),
+            _ => {
+                let mut bytes = Vec::new();
+                self.write_to(&mut bytes)?;
+                Ok(Cow::Owned(bytes))
+            }
+        }
+    }
+}
+
+//write a part of a body, framed as a chunk if `chunked`
+#[cfg(feature = "tokio")]
+async fn write_chunk<W: AsyncWrite + Unpin>(out: &mut W, data: &[u8], chunked: bool) -> io::Result<()> {
+    if !chunked {
+        return out.write_all(data).await;
+    }
+    if !data.is_empty() {
+        out.write_all(format!("{:X}\r\n", data.len()).as_bytes()).await?;
+        out.write_all(data).await?;
+        out.write_all(b"\r\n").await?;
+    }
+    Ok(())
+}
+
+//take the reader of a body out for the attempt sending it
+fn take(reader: &Mutex<Option<Box<dyn Read + Send>>>) -> Result<Box<dyn Read + Send>, HttpError> {
+    reader.lock().unwrap().take().ok_or(HttpError::Config("the body reader was already sent"))
+}
+
+//reader over the chunks of an iterator
+struct Chunks<I> {
+    chunks: I,
+    chunk: Vec<u8>,
+    pos: usize,
+}
+
+impl<I: Iterator<Item = Vec<u8>>> Read for Chunks<I> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.chunk.len() {
+            match self.chunks.next() {
+                Some(chunk) => (self.chunk, self.pos) = (chunk, 0),
+                None => return Ok(0),
+            }
+        }
+        let len = buf.len().min(self.chunk.len() - self.pos);
+        buf[..len].copy_from_slice(&self.chunk[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+//reader serializing the items of an iterator as they are read, in a JSON array or one per line
+#[cfg(feature = "json")]
+struct JsonItems<I> {
+    items: I,
+    array: bool,
+    count: usize,
+    done: bool,
+    //serialized data not read yet, from its offset
+    buffer: Vec<u8>,
+    pos: usize,
+}
+
+#[cfg(feature = "json")]
+impl<I> Read for JsonItems<I>
+where
+    I: Iterator,
+    I::Item: serde::Serialize,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.buffer.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.buffer.clear();
+            self.pos = 0;
+            match self.items.next() {
+                Some(item) => {
+                    if self.array {
+                        self.buffer.push(if self.count == 0 { b'[' } else { b',' });
+                    }
+                    serde_json::to_writer(&mut self.buffer, &item)?;
+                    if !self.array {
+                        self.buffer.push(b'\n');
+                    }
+                    self.count += 1;
+                }
+                None => {
+                    self.done = true;
+                    if self.array {
+                        self.buffer.extend_from_slice(if self.count == 0 { b"[]" } else { b"]" });
+                    }
+                }
+            }
+        }
+        let len = buf.len().min(self.buffer.len() - self.pos);
+        buf[..len].copy_from_slice(&self.buffer[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+impl From<Vec<u8>> for Body {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self { kind: Kind::Bytes(bytes.into()) }
+    }
+}
+
+impl From<&[u8]> for Body {
+    fn from(bytes: &[u8]) -> Self {
+        bytes.to_vec().into()
+    }
+}
+
+impl From<String> for Body {
+    fn from(text: String) -> Self {
+        text.into_bytes().into()
+    }
+}
+
+impl From<&str> for Body {
+    fn from(text: &str) -> Self {
+        text.as_bytes().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_kinds() {
+        let mut out = Vec::new();
+        Body::from("abc").write_to(&mut out).unwrap();
+        assert_eq!(out, b"abc");
+
+        let reader = Body::sized_reader(io::Cursor::new(b"hello world".to_vec()), 5);
+        assert_eq!(reader.len(), Some(5));
+        assert!(reader.as_bytes().is_none());
+        assert_eq!(reader.clone().read_all().unwrap(), &b"hello"[..]);
+        assert!(matches!(reader.read_all(), Err(HttpError::Config(_))));
+
+        let short = Body::sized_reader(io::Cursor::new(b"hi".to_vec()), 5);
+        assert!(matches!(short.read_all(), Err(HttpError::IO(_))));
+
+        let chunks = Body::chunks(vec![b"ab".to_vec(), Vec::new(), b"c".to_vec()]);
+        let mut out = Vec::new();
+        chunks.write_chunked(&mut out).unwrap();
+        assert_eq!(out, b"2\r\nab\r\n1\r\nc\r\n0\r\n\r\n");
+        assert!(chunks.write_chunked(&mut out).is_err());
+
+        let path = std::env::temp_dir().join(format!("smolhttp-body-kinds-{}", std::process::id()));
+        fs::write(&path, b"file").unwrap();
+        let file = Body::file(&path).unwrap();
+        assert_eq!((file.len(), file.path()), (Some(4), Some(path.as_path())));
+        assert_eq!(file.read_all().unwrap(), &b"file"[..]);
+        assert_eq!(file.read_all().unwrap(), &b"file"[..]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "tokio")]
+    #[test]
+    fn async_parts() {
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+        let reader = Body::sized_reader(io::Cursor::new(vec![7; chunked::CHUNK_SIZE * 2]), chunked::CHUNK_SIZE as u64 + 3);
+        let mut out = Vec::new();
+        runtime.block_on(reader.write_async(&mut out, false)).unwrap();
+        assert_eq!(out, vec![7; chunked::CHUNK_SIZE + 3]);
+
+        let mut out = Vec::new();
+        runtime.block_on(Body::chunks(["hello", " world"]).write_async(&mut out, true)).unwrap();
+        assert_eq!(out, b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\n");
+
+        let short = Body::sized_reader(io::Cursor::new(b"hi".to_vec()), 5);
+        assert!(matches!(runtime.block_on(short.write_async(&mut Vec::new(), false)), Err(HttpError::IO(_))));
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_items() {
+        let read = |body: Body| String::from_utf8(body.read_all().unwrap().into_owned()).unwrap();
+        assert_eq!(read(Body::json_array(vec![serde_json::json!({"id": 1}), serde_json::json!("two")])), r#"[{"id":1},"two"]"#);
+        assert_eq!(read(Body::json_array(Vec::<u8>::new())), "[]");
+        assert_eq!(read(Body::ndjson([1, 2, 3])), "1\n2\n3\n");
+        assert_eq!(read(Body::ndjson(Vec::<u8>::new())), "");
+
+        //maps with non-string keys can't be serialized
+        let invalid = Body::json_array([std::collections::HashMap::from([((1, 2), 3)])]);
+        assert!(matches!(invalid.read_all(), Err(HttpError::IO(_))));
+    }
+}
@@ -0,0 +1,171 @@
+use super::ErrorKind;
+use super::HttpError;
+use super::Response;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Default)]
+struct Circuit {
+    //consecutive failures
+    failures: u32,
+    opened: Option<Instant>,
+    //a request was let through to probe a host whose cooldown elapsed
+    probing: bool,
+}
+
+///per host circuit breaker, failing the requests to a host at once with [`HttpError::CircuitOpen`] once it
+///failed too many times in a row, instead of waiting for it and retrying.
+///
+///Connection, TLS, timeout and transport errors and `5xx` responses are failures, any other response closes
+///the circuit again. Once the cooldown elapsed, a single request probes the host: the circuit closes if it
+///succeeds and stays open for another cooldown if it fails. Hosts are told apart by their name and port.
+///
+///The breaker is shared between its clones, so several Clients can protect the same hosts.
+/// # Example
+/// ```
+/// use smolhttp::{CircuitBreaker, Client, MaxRetries};
+/// use std::time::Duration;
+///
+/// let client = Client::builder()
+///     .retry(MaxRetries::new(3))
+///     .circuit_breaker(CircuitBreaker::new(5, Duration::from_secs(30)))
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    hosts: Arc<Mutex<HashMap<String, Circuit>>>,
+}
+
+impl CircuitBreaker {
+    ///return a breaker opening the circuit of a host after `threshold` failures in a row, for `cooldown`
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self { threshold: threshold.max(1), cooldown, hosts: Arc::default() }
+    }
+
+    ///return if the requests to `host` on `port` fail at once
+    /// # Example
+    /// ```
+    /// use smolhttp::CircuitBreaker;
+    /// use std::time::Duration;
+    ///
+    /// let breaker = CircuitBreaker::new(5, Duration::from_secs(30));
+    /// assert!(!breaker.is_open("example.com", 443));
+    /// ```
+    pub fn is_open(&self, host: &str, port: u16) -> bool {
+        let key = format!("{}:{}", host.to_ascii_lowercase(), port);
+        let hosts = self.hosts.lock().unwrap_or_else(|err| err.into_inner());
+        hosts.get(&key).and_then(|circuit| circuit.opened).is_some_and(|opened| opened.elapsed() < self.cooldown)
+    }
+
+    //let a request to `host:port` through, or fail while its circuit is open
+    pub(crate) fn check(&self, host: &str, port: u16) -> Result<(), HttpError> {
+        let key = format!("{}:{}", host, port);
+        let mut hosts = self.hosts.lock().unwrap_or_else(|err| err.into_inner());
+        let circuit = match hosts.get_mut(&key) {
+            Some(circuit) => circuit,
+            None => return Ok(()),
+        };
+        let opened = match circuit.opened {
+            Some(opened) => opened,
+            None => return Ok(()),
+        };
+        let elapsed = opened.elapsed();
+        if elapsed >= self.cooldown && !circuit.probing {
+            circuit.probing = true;
+            return Ok(());
+        }
+        Err(HttpError::CircuitOpen { host: key, retry_after: self.cooldown.saturating_sub(elapsed) })
+    }
+
+    //record the outcome of a request to `host:port`
+    pub(crate) fn record(&self, host: &str, port: u16, outcome: Result<&Response, &HttpError>) {
+        let key = format!("{}:{}", host, port);
+        let mut hosts = self.hosts.lock().unwrap_or_else(|err| err.into_inner());
+        let failed = match outcome {
+            Ok(res) => res.status().is_server_error(),
+            Err(err) => match err.kind() {
+                ErrorKind::Dns | ErrorKind::Connect | ErrorKind::Tls | ErrorKind::Write | ErrorKind::Read | ErrorKind::Timeout => true,
+                //a request that failed on its own, like an aborted one, says nothing of the host
+                _ => {
+                    if let Some(circuit) = hosts.get_mut(&key) {
+                        circuit.probing = false;
+                    }
+                    return;
+                }
+            },
+        };
+        if !failed {
+            hosts.remove(&key);
+            return;
+        }
+        let circuit = hosts.entry(key).or_default();
+        circuit.failures = circuit.failures.saturating_add(1);
+        circuit.probing = false;
+        if circuit.opened.is_some() || circuit.failures >= self.threshold {
+            circuit.opened = Some(Instant::now());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::{Client, MaxRetries};
+    use std::thread;
+
+    #[test]
+    fn open_and_probe() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(50));
+        let down = HttpError::IO(std::io::ErrorKind::ConnectionRefused.into());
+        let ok = Response::new(b"HTTP/1.1 204 No Content\r\n\r\n".to_vec()).unwrap();
+
+        breaker.record("example.com", 80, Err(&down));
+        assert!(breaker.check("example.com", 80).is_ok());
+        breaker.record("example.com", 80, Err(&down));
+        assert!(breaker.is_open("example.com", 80));
+        assert!(matches!(breaker.check("example.com", 80), Err(HttpError::CircuitOpen { ref host, .. }) if host == "example.com:80"));
+        assert!(breaker.check("example.com", 443).is_ok());
+
+        //a single probe once the cooldown elapsed, failing again
+        thread::sleep(Duration::from_millis(60));
+        assert!(breaker.check("example.com", 80).is_ok());
+        assert!(breaker.check("example.com", 80).is_err());
+        breaker.record("example.com", 80, Err(&down));
+        assert!(breaker.check("example.com", 80).is_err());
+
+        thread::sleep(Duration::from_millis(60));
+        assert!(breaker.check("example.com", 80).is_ok());
+        breaker.record("example.com", 80, Ok(&ok));
+        assert!(!breaker.is_open("example.com", 80));
+        assert!(breaker.check("example.com", 80).is_ok());
+    }
+
+    #[test]
+    fn short_circuits_retries() {
+        let mock = MockTransport::new();
+        mock.respond("GET", "http://down.example.com/", 503, "unavailable");
+        mock.respond("GET", "http://up.example.com/", 200, "ok");
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        let client = Client::builder()
+            .connector(mock.clone())
+            .retry(MaxRetries::new(10).delay(Duration::ZERO))
+            .circuit_breaker(breaker.clone())
+            .build()
+            .unwrap();
+
+        let err = client.get("http://down.example.com/").unwrap().send().unwrap_err();
+        assert!(matches!(err, HttpError::CircuitOpen { .. }));
+        assert!(err.is_connect());
+        assert_eq!(mock.requests().len(), 3);
+        assert!(client.get("http://down.example.com/").unwrap().send().is_err());
+        assert_eq!(mock.requests().len(), 3);
+        assert_eq!(client.get("http://up.example.com/").unwrap().send().unwrap().text(), "ok");
+        assert!(breaker.is_open("down.example.com", 80));
+    }
+}
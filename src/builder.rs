@@ -0,0 +1,1042 @@
+use super::conn::{BufferPool, ConnectionPool, HeaderLimits, PoolLimits, SocketOptions};
+use super::dns::{DnsCache, IpPreference};
+use super::netrc::Netrc;
+use super::no_proxy::NoProxy;
+use super::request::{parse_url, validate_header, ClientRef, HintsCallback, VerboseSink, DEFAULT_MAX_REDIRECT_BODY, DEFAULT_USER_AGENT};
+use super::CacheStore;
+use super::CircuitBreaker;
+use super::Client;
+use super::Connector;
+use super::CookieJar;
+use super::HarRecorder;
+use super::HeaderMap;
+use super::HstsStore;
+use super::HttpError;
+use super::MetricsSink;
+use super::Middleware;
+use super::Proxy;
+use super::Resolver;
+use super::RequestPolicy;
+use super::RetryPolicy;
+use super::Url;
+use super::Version;
+
+use std::net::IpAddr;
+use std::sync::{Arc, OnceLock, RwLock};
+use std::time::Duration;
+
+static DEFAULT_CLIENT: RwLock<Option<ClientBuilder>> = RwLock::new(None);
+
+///configuration of the Clients it builds: base url, default headers, timeout, proxy, TLS and retry options.
+/// # Example
+/// ```no_run
+/// use smolhttp::ClientBuilder;
+///
+/// let client = ClientBuilder::new()
+///     .base_url("https://api.github.com/users/octocat")
+///     .default_header("User-Agent", "my-tool/1.0").unwrap()
+///     .default_header("Accept", "application/json").unwrap()
+///     .timeout(5)
+///     .build()
+///     .unwrap();
+///
+/// let user = client.get("").unwrap().send().unwrap();
+/// let repos = client.get("repos").unwrap().send().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct ClientBuilder {
+    headers: HeaderMap,
+    user_agent: Option<String>,
+    timeout: Option<u64>,
+    proxy: Option<String>,
+    scheme_proxies: Vec<(String, String)>,
+    proxy_headers: HeaderMap,
+    no_proxy: NoProxy,
+    verify: bool,
+    retry: Option<Arc<dyn RetryPolicy>>,
+    breaker: Option<CircuitBreaker>,
+    policy: Option<RequestPolicy>,
+    cache: Option<Arc<dyn CacheStore>>,
+    hsts: Option<HstsStore>,
+    cookies: Option<CookieJar>,
+    redirects: Option<u32>,
+    base: Option<String>,
+    version: Version,
+    expect_continue: Option<usize>,
+    early_hints: Option<HintsCallback>,
+    max_response_size: Option<u64>,
+    max_redirect_body: u64,
+    tls_handshake_timeout: Option<Duration>,
+    host_header: Option<String>,
+    sni_hostname: Option<String>,
+    #[cfg(feature = "alpn")]
+    alpn: Vec<String>,
+    header_limits: HeaderLimits,
+    allow_incomplete: bool,
+    auto_encoding: bool,
+    strict_redirects: bool,
+    redirect_headers: Vec<String>,
+    spill: Option<u64>,
+    resolver: Option<Arc<dyn Resolver>>,
+    dns_cache: Option<(Duration, usize)>,
+    ip_version: IpPreference,
+    socket: SocketOptions,
+    pool: PoolLimits,
+    har: Option<HarRecorder>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    verbose: Option<VerboseSink>,
+    middleware: Vec<Arc<dyn Middleware>>,
+    connector: Option<Arc<dyn Connector>>,
+    netrc: bool,
+}
+
+impl Default for ClientBuilder {
+    fn default() -> Self {
+        Self {
+            headers: HeaderMap::new(),
+            user_agent: None,
+            timeout: None,
+            proxy: None,
+            scheme_proxies: Vec::new(),
+            proxy_headers: HeaderMap::new(),
+            no_proxy: NoProxy::default(),
+            verify: true,
+            retry: None,
+            breaker: None,
+            policy: None,
+            cache: None,
+            hsts: None,
+            cookies: None,
+            redirects: None,
+            base: None,
+            version: Version::Http11,
+            expect_continue: None,
+            early_hints: None,
+            max_response_size: None,
+            max_redirect_body: DEFAULT_MAX_REDIRECT_BODY,
+            tls_handshake_timeout: None,
+            host_header: None,
+            sni_hostname: None,
+            #[cfg(feature = "alpn")]
+            alpn: Vec::new(),
+            header_limits: HeaderLimits::default(),
+            allow_incomplete: false,
+            auto_encoding: true,
+            strict_redirects: false,
+            redirect_headers: Vec::new(),
+            spill: None,
+            resolver: None,
+            dns_cache: None,
+            ip_version: IpPreference::Auto,
+            socket: SocketOptions::default(),
+            pool: PoolLimits::default(),
+            har: None,
+            metrics: None,
+            verbose: None,
+            middleware: Vec::new(),
+            connector: None,
+            netrc: false,
+        }
+    }
+}
+
+impl ClientBuilder {
+    ///return a ClientBuilder without any default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///set the url that relative request urls are joined to, keeping its path, query and credentials
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let api = ClientBuilder::new().base_url("https://api.example.com/v2?key=abc").build().unwrap();
+    /// api.get("/users/1?fields=name").unwrap();
+    /// ```
+    pub fn base_url(&mut self, base: &str) -> &mut Self {
+        self.base = Some(base.to_owned());
+        self
+    }
+
+    ///set a header sent by every built Client, unless a request sets it too
+    pub fn default_header(&mut self, name: &str, value: &str) -> Result<&mut Self, HttpError> {
+        validate_header(name, value)?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    ///set the `Accept` header sent by every built Client, unless a request sets it too
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let client = Client::builder().accept("application/vnd.github+json").unwrap().build().unwrap();
+    /// ```
+    pub fn accept(&mut self, media_type: &str) -> Result<&mut Self, HttpError> {
+        self.default_header("Accept", media_type)
+    }
+
+    ///set the `User-Agent` header sent by every built Client, unless a request or a default header sets it
+    ///(default `smolhttp/<version>`)
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let client = Client::builder().user_agent("my-crawler/2.1 (+https://example.com/bot)").unwrap().build().unwrap();
+    /// ```
+    pub fn user_agent(&mut self, value: &str) -> Result<&mut Self, HttpError> {
+        validate_header("User-Agent", value)?;
+        self.user_agent = Some(value.to_owned());
+        Ok(self)
+    }
+
+    ///set the headers sent by every built Client, replacing the previous defaults
+    pub fn default_headers(&mut self, data: Vec<(String, String)>) -> Result<&mut Self, HttpError> {
+        for (name, value) in &data {
+            validate_header(name, value)?;
+        }
+        self.headers = data.into();
+        Ok(self)
+    }
+
+    ///set the read/write timeout(sec) of the built Clients
+    pub fn timeout(&mut self, time: u64) -> &mut Self {
+        self.timeout = Some(time);
+        self
+    }
+
+    ///set the proxy of the built Clients
+    pub fn proxy(&mut self, proxy: &str) -> &mut Self {
+        self.proxy = Some(proxy.to_owned());
+        self
+    }
+
+    ///set the proxy of the requests to the urls of `scheme` only, `http` or `https`, replacing the one set with
+    ///[`proxy`](ClientBuilder::proxy) for them
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new()
+    ///     .proxy_for("http", "http://cache.example.com:3128")
+    ///     .proxy_for("https", "http://proxy.example.com:3128")
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn proxy_for(&mut self, scheme: &str, proxy: &str) -> &mut Self {
+        let scheme = scheme.to_ascii_lowercase();
+        self.scheme_proxies.retain(|(name, _)| *name != scheme);
+        self.scheme_proxies.push((scheme, proxy.to_owned()));
+        self
+    }
+
+    ///set the proxies and the hosts reached directly from the environment variables, like curl does
+    ///
+    ///`http_proxy` is the proxy of the `http` urls, `HTTPS_PROXY` or `https_proxy` that of the `https` ones
+    ///and `ALL_PROXY` or `all_proxy` that of both when they aren't set. `NO_PROXY` or `no_proxy` lists the
+    ///[no proxy](ClientBuilder::no_proxy) rules, separated by commas. A proxy without a scheme is an `http` one.
+    ///
+    ///`HTTP_PROXY` is ignored, since a CGI program gets it from the `Proxy` header of the request it
+    ///answers(httpoxy). The shortcut functions like [`get`](crate::get) use these variables unless a
+    ///configuration is installed with [`set_default_client`].
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().proxy_from_env().unwrap().build().unwrap();
+    /// ```
+    pub fn proxy_from_env(&mut self) -> Result<&mut Self, HttpError> {
+        self.proxy_from_vars(|name| std::env::var(name).ok())
+    }
+
+    //set the proxies from the variables returned by `var`
+    fn proxy_from_vars<F: Fn(&str) -> Option<String>>(&mut self, var: F) -> Result<&mut Self, HttpError> {
+        let lookup = |names: &[&str]| names.iter().filter_map(|name| var(name)).map(|value| value.trim().to_owned()).find(|value| !value.is_empty());
+        let with_scheme = |proxy: String| match proxy.contains("://") {
+            true => proxy,
+            false => format!("http://{}", proxy),
+        };
+        if let Some(proxy) = lookup(&["ALL_PROXY", "all_proxy"]) {
+            self.proxy = Some(with_scheme(proxy));
+        }
+        if let Some(proxy) = lookup(&["http_proxy"]) {
+            self.proxy_for("http", &with_scheme(proxy));
+        }
+        if let Some(proxy) = lookup(&["HTTPS_PROXY", "https_proxy"]) {
+            self.proxy_for("https", &with_scheme(proxy));
+        }
+        if let Some(rules) = lookup(&["NO_PROXY", "no_proxy"]) {
+            self.no_proxy(rules.split(',').filter(|rule| !rule.trim().is_empty()))?;
+        }
+        Ok(self)
+    }
+
+    ///set a header sent to the proxy with the CONNECT requests opening the tunnels
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let client = Client::builder()
+    ///     .proxy("https://proxy.example.com:3128")
+    ///     .proxy_header("Proxy-Authorization", "Bearer token").unwrap()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn proxy_header(&mut self, name: &str, value: &str) -> Result<&mut Self, HttpError> {
+        validate_header(name, value)?;
+        self.proxy_headers.insert(name, value);
+        Ok(self)
+    }
+
+    ///add hosts the built Clients reach directly instead of through the proxy, like the `NO_PROXY` variable
+    ///
+    ///A rule is `*` for every host, a domain matching itself and its subdomains, `*.domain` matching only its
+    ///subdomains, an ip address or a CIDR block like `10.0.0.0/8`.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new()
+    ///     .proxy("http://proxy.example.com:3128")
+    ///     .no_proxy(["*.internal", "10.0.0.0/8", "localhost"]).unwrap()
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn no_proxy<I, S>(&mut self, rules: I) -> Result<&mut Self, HttpError>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for rule in rules {
+            self.no_proxy.push(rule.as_ref())?;
+        }
+        Ok(self)
+    }
+
+    ///set if the built Clients verify https certificates(default true)
+    pub fn verify(&mut self, verify: bool) -> &mut Self {
+        self.verify = verify;
+        self
+    }
+
+    ///set the maximum duration of the TLS handshakes of the built Clients, from the connection to the end of the
+    ///handshake(default only bounded by the read/write timeout of each socket call)
+    ///
+    ///A handshake taking longer is aborted and fails with a timeout error, even if the server keeps sending bytes.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let client = ClientBuilder::new().tls_handshake_timeout(Duration::from_secs(5)).build().unwrap();
+    /// ```
+    pub fn tls_handshake_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.tls_handshake_timeout = Some(timeout);
+        self
+    }
+
+    ///send `host` in the `Host` header of the requests of the built Clients instead of the host of their urls,
+    ///while connecting to the latter(default the host and port of the url)
+    ///
+    ///It reaches the virtual host `host` of the server of the url, like one behind a load balancer or sharing
+    ///an address with others. A `Host` header set on a request still replaces it.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().host_header("www.example.com").unwrap().build().unwrap();
+    /// let request = client.get("http://10.0.0.5/").unwrap();
+    /// assert!(String::from_utf8(request.preview()).unwrap().contains("\r\nHost: www.example.com\r\n"));
+    /// ```
+    pub fn host_header(&mut self, host: &str) -> Result<&mut Self, HttpError> {
+        validate_header("Host", host)?;
+        self.host_header = Some(host.to_owned());
+        Ok(self)
+    }
+
+    ///send `name` through SNI in the TLS handshakes of the built Clients, and check the certificates of the
+    ///servers against it, instead of the host of the url(default the host of the url)
+    ///
+    ///Along with [`host_header`](ClientBuilder::host_header), it reaches an origin server by its address while
+    ///presenting its public host name.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().sni_hostname("www.example.com").unwrap().host_header("www.example.com").unwrap().build().unwrap();
+    /// let res = client.get("https://origin-1.internal.example.net/").unwrap().send().unwrap();
+    /// ```
+    pub fn sni_hostname(&mut self, name: &str) -> Result<&mut Self, HttpError> {
+        if name.is_empty() || name.chars().any(|c| c.is_whitespace() || c.is_control() || "/:@[]".contains(c)) {
+            return Err(HttpError::Config("invalid SNI host name"));
+        }
+        self.sni_hostname = Some(name.to_ascii_lowercase());
+        Ok(self)
+    }
+
+    ///set the protocols the built Clients offer through ALPN in their TLS handshakes, in order of preference
+    ///(default none)
+    ///
+    ///The protocol selected by the server is returned by [`Response::alpn_protocol`](crate::Response::alpn_protocol).
+    ///The requests are still sent over HTTP/1.1, HTTP/2 being offered by [`http_version`](ClientBuilder::http_version).
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().alpn_protocols(&["http/1.1"]).build().unwrap();
+    /// ```
+    #[cfg(feature = "alpn")]
+    pub fn alpn_protocols(&mut self, protocols: &[&str]) -> &mut Self {
+        self.alpn = protocols.iter().map(|&protocol| protocol.to_owned()).collect();
+        self
+    }
+
+    ///set the http version of the requests sent by the built Clients(default HTTP/1.1)
+    ///
+    ///HTTP/1.0 helps talking to old embedded servers. With the `http2` feature, HTTP/2 is offered to https
+    ///servers through ALPN and falls back to HTTP/1.1 when they don't accept it, the requests to an origin
+    ///then share a single connection. With the experimental `http3` feature, HTTP/3 is used for every https
+    ///request not going through a proxy, the other Clients switching to it for the origins advertising it
+    ///in an `Alt-Svc` header.
+    /// # Example
+    /// ```
+    /// use smolhttp::{ClientBuilder, Version};
+    ///
+    /// let client = ClientBuilder::new().http_version(Version::Http10).unwrap().build().unwrap();
+    /// assert_eq!(ClientBuilder::new().http_version(Version::Http3).is_ok(), cfg!(feature = "http3"));
+    /// ```
+    pub fn http_version(&mut self, version: Version) -> Result<&mut Self, HttpError> {
+        match version {
+            Version::Http10 | Version::Http11 => self.version = version,
+            #[cfg(feature = "http2")]
+            Version::Http2 => self.version = version,
+            #[cfg(feature = "http3")]
+            Version::Http3 => self.version = version,
+            #[cfg(not(feature = "http3"))]
+            _ => return Err(HttpError::Config("Unsupported http version")),
+        }
+        Ok(self)
+    }
+
+    ///send `Expect: 100-continue` with bodies of at least `min_size` bytes(default never)
+    ///
+    ///The body is only sent once the server answered `100 Continue`, or after a short wait for servers
+    ///ignoring the expectation, so a request rejected with 401 or 413 doesn't upload it for nothing.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().expect_continue(1024 * 1024).build().unwrap();
+    /// ```
+    pub fn expect_continue(&mut self, min_size: usize) -> &mut Self {
+        self.expect_continue = Some(min_size);
+        self
+    }
+
+    ///set a callback receiving the headers of the `103 Early Hints` responses preceding final ones
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new()
+    ///     .early_hints(|headers| {
+    ///         for link in headers.get_all("Link") {
+    ///             println!("preload {}", link);
+    ///         }
+    ///     })
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn early_hints<F>(&mut self, callback: F) -> &mut Self
+    where
+        F: Fn(&HeaderMap) + Send + Sync + 'static,
+    {
+        self.early_hints = Some(HintsCallback(Arc::new(callback)));
+        self
+    }
+
+    ///print the wire-level details of the exchanges of the built Clients to stderr, like `curl -v`(default false)
+    ///
+    ///Connection events are prefixed by `*`, sent heads by `>` and received heads by `<`.
+    /// # Example
+    /// ```
+    /// let client = smolhttp::Client::builder().verbose(true).build().unwrap();
+    /// ```
+    pub fn verbose(&mut self, verbose: bool) -> &mut Self {
+        self.verbose = verbose.then(|| VerboseSink(Arc::new(|line: &str| eprintln!("{line}"))));
+        self
+    }
+
+    ///hand the lines printed by [`verbose`](ClientBuilder::verbose) to `sink` instead of stderr
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new()
+    ///     .verbose_with(|line| println!("[http] {line}"))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn verbose_with<F>(&mut self, sink: F) -> &mut Self
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.verbose = Some(VerboseSink(Arc::new(sink)));
+        self
+    }
+
+    ///set the maximum size in bytes of the response bodies received by the built Clients(default unlimited)
+    ///
+    ///Larger responses fail with [`HttpError::TooLarge`], before their body is read when they announce its length.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().max_response_size(10 * 1024 * 1024).build().unwrap();
+    /// ```
+    pub fn max_response_size(&mut self, bytes: u64) -> &mut Self {
+        self.max_response_size = Some(bytes);
+        self
+    }
+
+    ///set the maximum number of headers of the response heads received by the built Clients(default 100)
+    ///
+    ///A head with more headers fails with [`HttpError::TooManyHeaders`] as soon as the limit is crossed, the
+    ///interim responses and the head of a proxy tunnel being limited as well.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().max_response_headers(32).build().unwrap();
+    /// ```
+    pub fn max_response_headers(&mut self, headers: usize) -> &mut Self {
+        self.header_limits.max_headers = headers;
+        self
+    }
+
+    ///set the maximum size in bytes of the response heads received by the built Clients, the status line and
+    ///the headers(default 64 KiB)
+    ///
+    ///A larger head fails with [`HttpError::HeadersTooLarge`] as soon as the limit is crossed, before the rest of it
+    ///is read.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().max_header_bytes(16 * 1024).build().unwrap();
+    /// ```
+    pub fn max_header_bytes(&mut self, bytes: usize) -> &mut Self {
+        self.header_limits.max_bytes = bytes;
+        self
+    }
+
+    ///set if a body shorter than its `Content-Length` is returned as is(default false)
+    ///
+    ///By default a connection closed before the whole body was received fails with [`HttpError::IncompleteBody`].
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().allow_incomplete_body(true).build().unwrap();
+    /// ```
+    pub fn allow_incomplete_body(&mut self, allow: bool) -> &mut Self {
+        self.allow_incomplete = allow;
+        self
+    }
+
+    ///stop advertising the content codings the crate can decode in an `Accept-Encoding` header
+    ///
+    ///With the `decompress` feature, requests without an `Accept-Encoding` header ask for `gzip` or `deflate`
+    ///bodies, which are decoded before being returned. Without it no coding is ever advertised.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().no_auto_encoding().build().unwrap();
+    /// ```
+    pub fn no_auto_encoding(&mut self) -> &mut Self {
+        self.auto_encoding = false;
+        self
+    }
+
+    ///set the maximum number of redirects followed by the built Clients
+    pub fn redirects(&mut self, max: u32) -> &mut Self {
+        self.redirects = Some(max);
+        self
+    }
+
+    ///set the size in bytes up to which the bodies of the redirects followed by the built Clients are read and
+    ///discarded, for their connection to carry the next request(default 64 KiB)
+    ///
+    ///The connection of a larger body is closed instead, and the redirect followed over a new one.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().redirects(5).max_redirect_body(4096).build().unwrap();
+    /// ```
+    pub fn max_redirect_body(&mut self, bytes: u64) -> &mut Self {
+        self.max_redirect_body = bytes;
+        self
+    }
+
+    ///keep the method and body of the requests redirected with `301` or `302`, only `303` turning them into GET
+    ///
+    ///By default, like browsers and curl, a POST redirected with `301` or `302` is sent again as a GET without
+    ///its body. `307` and `308` always keep the method and body.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().redirects(5).strict_redirects().build().unwrap();
+    /// ```
+    pub fn strict_redirects(&mut self) -> &mut Self {
+        self.strict_redirects = true;
+        self
+    }
+
+    ///write the response bodies larger than `threshold` bytes to a temporary file instead of memory(default never)
+    ///
+    ///The file of a spilled body is returned by [`Response::body_path`](crate::Response::body_path) and read with
+    ///[`Response::into_reader`](crate::Response::into_reader), while [`Response::bytes`](crate::Response::bytes)
+    ///is empty. It is removed once the response is dropped. Bodies received over HTTP/2 or by the async
+    ///client stay in memory, and spilled bodies aren't decompressed.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::ClientBuilder;
+    /// use std::io;
+    ///
+    /// let client = ClientBuilder::new().spill_to_disk(64 * 1024 * 1024).build().unwrap();
+    /// let res = client.get("https://example.com/dataset.csv").unwrap().send().unwrap();
+    /// io::copy(&mut res.into_reader().unwrap(), &mut io::stdout()).unwrap();
+    /// ```
+    pub fn spill_to_disk(&mut self, threshold: u64) -> &mut Self {
+        self.spill = Some(threshold);
+        self
+    }
+
+    ///keep the sensitive headers named in `names` when a redirect leads to another origin
+    ///
+    ///By default the `Authorization`, `Cookie` and `Proxy-Authorization` headers of a request aren't sent
+    ///to the target of a redirect whose scheme, host or port differ, so credentials don't leak to third parties.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// //the api redirects to a mirror expecting the same token
+    /// let client = ClientBuilder::new().redirects(5).keep_on_redirect(&["Authorization"]).build().unwrap();
+    /// ```
+    pub fn keep_on_redirect(&mut self, names: &[&str]) -> &mut Self {
+        self.redirect_headers = names.iter().map(|name| name.to_string()).collect();
+        self
+    }
+
+    ///set the resolver turning host names into addresses for the built Clients(default [`SystemResolver`](crate::SystemResolver))
+    pub fn dns_resolver<R: Resolver + 'static>(&mut self, resolver: R) -> &mut Self {
+        self.resolver = Some(Arc::new(resolver));
+        self
+    }
+
+    ///cache the resolved addresses of up to `max_entries` hosts for `ttl`(default no cache)
+    ///
+    ///The cache is shared by the clones of a built Client, so repeated requests to a host skip its resolution.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let client = ClientBuilder::new().dns_cache(Duration::from_secs(60), 256).build().unwrap();
+    /// ```
+    pub fn dns_cache(&mut self, ttl: Duration, max_entries: usize) -> &mut Self {
+        self.dns_cache = Some((ttl, max_entries));
+        self
+    }
+
+    ///set the address families the built Clients connect to(default [`IpPreference::Auto`])
+    ///
+    ///Useful in containers and VPNs where one of the families is unreachable.
+    /// # Example
+    /// ```
+    /// use smolhttp::{ClientBuilder, IpPreference};
+    ///
+    /// let client = ClientBuilder::new().ip_version(IpPreference::V4Only).build().unwrap();
+    /// ```
+    pub fn ip_version(&mut self, preference: IpPreference) -> &mut Self {
+        self.ip_version = preference;
+        self
+    }
+
+    ///set the local address the connections of the built Clients leave from(default chosen by the system)
+    ///
+    ///Only the resolved addresses of the same family as `address` can be reached.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    /// use std::net::Ipv4Addr;
+    ///
+    /// let client = ClientBuilder::new().local_address(Ipv4Addr::new(192, 168, 1, 20).into()).build().unwrap();
+    /// ```
+    pub fn local_address(&mut self, address: IpAddr) -> &mut Self {
+        self.socket.local_address = Some(address);
+        self
+    }
+
+    ///set the network interface the connections of the built Clients are bound to, with `SO_BINDTODEVICE`
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    ///
+    /// let client = ClientBuilder::new().interface("eth0").build().unwrap();
+    /// ```
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub fn interface(&mut self, name: &str) -> &mut Self {
+        self.socket.interface = Some(name.to_owned());
+        self
+    }
+
+    ///set `TCP_NODELAY` on the connections of the built Clients, sending small writes without delay(default false)
+    pub fn tcp_nodelay(&mut self, nodelay: bool) -> &mut Self {
+        self.socket.nodelay = nodelay;
+        self
+    }
+
+    ///enable TCP keepalive on the connections of the built Clients, probing them after `idle` without traffic(default disabled)
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let client = ClientBuilder::new()
+    ///     .tcp_nodelay(true)
+    ///     .tcp_keepalive(Duration::from_secs(60))
+    ///     .recv_buffer_size(256 * 1024)
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn tcp_keepalive(&mut self, idle: Duration) -> &mut Self {
+        self.socket.keepalive = Some(idle);
+        self
+    }
+
+    ///set the size in bytes of the socket send buffer of the built Clients(default chosen by the system)
+    pub fn send_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.socket.send_buffer_size = Some(size);
+        self
+    }
+
+    ///set the size in bytes of the socket receive buffer of the built Clients(default chosen by the system)
+    pub fn recv_buffer_size(&mut self, size: usize) -> &mut Self {
+        self.socket.recv_buffer_size = Some(size);
+        self
+    }
+
+    ///set the maximum number of connections each built Client opens to an origin(default unlimited)
+    ///
+    ///Requests needing one more connection wait for another to close, up to the
+    ///[`pool_wait_timeout`](ClientBuilder::pool_wait_timeout), so threads sharing a Client can't open
+    ///unbounded sockets to one server. HTTP/2 connections and streamed responses aren't counted.
+    /// # Example
+    /// ```
+    /// use smolhttp::ClientBuilder;
+    /// use std::time::Duration;
+    ///
+    /// let client = ClientBuilder::new()
+    ///     .max_connections_per_host(8)
+    ///     .max_idle_per_host(2)
+    ///     .pool_wait_timeout(Duration::from_secs(5))
+    ///     .build()
+    ///     .unwrap();
+    /// ```
+    pub fn max_connections_per_host(&mut self, max: usize) -> &mut Self {
+        self.pool.max_connections = Some(max);
+        self
+    }
+
+    ///set the maximum number of open connections each built Client keeps waiting for a request to an origin(default unlimited)
+    pub fn max_idle_per_host(&mut self, max: usize) -> &mut Self {
+        self.pool.max_idle = Some(max);
+        self
+    }
+
+    ///set how long requests wait for a connection at the [`max_connections_per_host`](ClientBuilder::max_connections_per_host)
+    ///limit before failing with a timeout(default forever)
+    pub fn pool_wait_timeout(&mut self, wait: Duration) -> &mut Self {
+        self.pool.wait = Some(wait);
+        self
+    }
+
+    ///set how long the connections kept open by the built Clients stay idle before being closed(default 90 sec)
+    ///
+    ///Proxy tunnels are kept open, while direct HTTP/1.1 connections only are for requests sending
+    ///`Connection: keep-alive`, which [`default_header`](ClientBuilder::default_header) sets for all of them.
+    pub fn pool_idle_timeout(&mut self, timeout: Duration) -> &mut Self {
+        self.pool.idle_timeout = Some(timeout);
+        self
+    }
+
+    ///check that idle connections weren't closed by the server before reusing them(default false)
+    ///
+    ///Without the check, a request failing at once on a connection closed while idle is sent again
+    ///over a new one, unless its body is a reader.
+    pub fn pool_health_check(&mut self, check: bool) -> &mut Self {
+        self.pool.health_check = check;
+        self
+    }
+
+    ///set the retry policy of the built Clients
+    pub fn retry<P: RetryPolicy + 'static>(&mut self, policy: P) -> &mut Self {
+        self.retry = Some(Arc::new(policy));
+        self
+    }
+
+    ///set the circuit breaker shared by the built Clients, failing the requests to the hosts failing too often
+    pub fn circuit_breaker(&mut self, breaker: CircuitBreaker) -> &mut Self {
+        self.breaker = Some(breaker);
+        self
+    }
+
+    ///set the policy limiting the urls the built Clients request and the addresses they connect to, see
+    ///[`RequestPolicy`]
+    pub fn request_policy(&mut self, policy: RequestPolicy) -> &mut Self {
+        self.policy = Some(policy);
+        self
+    }
+
+    ///set the cache shared by the built Clients
+    pub fn cache<C: CacheStore + 'static>(&mut self, store: C) -> &mut Self {
+        self.cache = Some(Arc::new(store));
+        self
+    }
+
+    ///set the HSTS store shared by the built Clients
+    pub fn hsts(&mut self, store: HstsStore) -> &mut Self {
+        self.hsts = Some(store);
+        self
+    }
+
+    ///set the cookie jar shared by the built Clients, storing the received cookies and sending them back
+    pub fn cookie_store(&mut self, jar: CookieJar) -> &mut Self {
+        self.cookies = Some(jar);
+        self
+    }
+
+    ///set the sink receiving the statistics of the requests sent by the built Clients, see [`MetricsSink`]
+    pub fn metrics<M: MetricsSink + 'static>(&mut self, sink: M) -> &mut Self {
+        self.metrics = Some(Arc::new(sink));
+        self
+    }
+
+    ///add a middleware to the built Clients, run after the ones added before it
+    pub fn middleware<M: Middleware + 'static>(&mut self, middleware: M) -> &mut Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    ///send the credentials of the `.netrc` file as Basic auth to the hosts it lists, like `curl --netrc`(default false)
+    ///
+    ///The file named by the `NETRC` environment variable is read, or else `~/.netrc`, when the Client is built.
+    ///Credentials in the url and `Authorization` headers take precedence.
+    /// # Example
+    /// ```
+    /// let client = smolhttp::Client::builder().netrc(true).build().unwrap();
+    /// ```
+    pub fn netrc(&mut self, enabled: bool) -> &mut Self {
+        self.netrc = enabled;
+        self
+    }
+
+    ///open the connections of the built Clients with `connector` instead of TCP and native-tls
+    pub fn connector<C: Connector + 'static>(&mut self, connector: C) -> &mut Self {
+        self.connector = Some(Arc::new(connector));
+        self
+    }
+
+    ///record the exchanges of the built Clients in `recorder`, to export them as an HTTP Archive
+    /// # Example
+    /// ```
+    /// let recorder = smolhttp::HarRecorder::new();
+    /// let client = smolhttp::Client::builder().har(recorder.clone()).build().unwrap();
+    /// ```
+    pub fn har(&mut self, recorder: HarRecorder) -> &mut Self {
+        self.har = Some(recorder);
+        self
+    }
+
+    ///return a Client configured with this builder
+    pub fn build(&self) -> Result<Client, HttpError> {
+        if let Some(ref base) = self.base {
+            parse_url(base)?;
+        }
+        let inner = ClientRef {
+            default_headers: self.headers.clone(),
+            user_agent: self.user_agent.clone().unwrap_or_else(|| DEFAULT_USER_AGENT.to_owned()),
+            timeout: self.timeout.unwrap_or(30),
+            proxy: self.proxy.as_deref().map(Url::parse).transpose()?.map(Proxy),
+            scheme_proxies: self.scheme_proxies.iter().map(|(scheme, proxy)| Ok((scheme.clone(), Proxy(Url::parse(proxy)?)))).collect::<Result<_, HttpError>>()?,
+            proxy_headers: self.proxy_headers.clone(),
+            no_proxy: self.no_proxy.clone(),
+            verify: self.verify,
+            retry: self.retry.clone(),
+            breaker: self.breaker.clone(),
+            policy: self.policy.clone(),
+            cache: self.cache.clone(),
+            hsts: self.hsts.clone(),
+            cookies: self.cookies.clone(),
+            max_redirects: self.redirects.unwrap_or(0),
+            base: self.base.clone(),
+            version: self.version,
+            expect_continue: self.expect_continue,
+            early_hints: self.early_hints.clone(),
+            max_response_size: self.max_response_size,
+            max_redirect_body: self.max_redirect_body,
+            tls_handshake_timeout: self.tls_handshake_timeout,
+            host_header: self.host_header.clone(),
+            sni_hostname: self.sni_hostname.clone(),
+            #[cfg(feature = "alpn")]
+            alpn: self.alpn.clone(),
+            header_limits: self.header_limits,
+            allow_incomplete: self.allow_incomplete,
+            auto_encoding: self.auto_encoding,
+            strict_redirects: self.strict_redirects,
+            redirect_headers: self.redirect_headers.clone(),
+            spill: self.spill,
+            buffers: BufferPool::default(),
+            pool: ConnectionPool::new(self.pool),
+            #[cfg(feature = "tokio")]
+            async_pool: ConnectionPool::new(self.pool),
+            resolver: self.resolver.clone(),
+            dns_cache: self.dns_cache.map(|(ttl, max_entries)| DnsCache::new(ttl, max_entries)),
+            ip_version: self.ip_version,
+            socket: self.socket.clone(),
+            har: self.har.clone(),
+            metrics: self.metrics.clone(),
+            verbose: self.verbose.clone(),
+            middleware: self.middleware.clone(),
+            connector: self.connector.clone(),
+            netrc: if self.netrc { Netrc::load() } else { None },
+            tls: OnceLock::new(),
+            #[cfg(feature = "openssl")]
+            sessions: OnceLock::new(),
+            #[cfg(feature = "http2")]
+            h2: Default::default(),
+            #[cfg(feature = "http3")]
+            h3: Default::default(),
+        };
+        Ok(Client { inner: Arc::new(inner) })
+    }
+}
+
+///install the configuration used by the shortcut functions like [`get`](crate::get)
+///
+///Until one is installed they use the default configuration, with the proxies of the environment variables read
+///by [`ClientBuilder::proxy_from_env`].
+/// # Example
+/// ```no_run
+/// use smolhttp::ClientBuilder;
+///
+/// let mut builder = ClientBuilder::new();
+/// builder.default_header("User-Agent", "my-tool/1.0").unwrap().timeout(10);
+/// smolhttp::set_default_client(builder);
+///
+/// let content = smolhttp::get("https://www.rust-lang.org").unwrap().text();
+/// ```
+pub fn set_default_client(builder: ClientBuilder) {
+    *DEFAULT_CLIENT.write().unwrap() = Some(builder);
+}
+
+//return a copy of the installed configuration, or the default one with the proxies of the environment
+pub(crate) fn default_builder() -> Result<ClientBuilder, HttpError> {
+    if let Some(builder) = DEFAULT_CLIENT.read().unwrap().clone() {
+        return Ok(builder);
+    }
+    let mut builder = ClientBuilder::new();
+    builder.proxy_from_env()?;
+    Ok(builder)
+}
+
+//return a Client configured with the installed defaults, if any
+pub(crate) fn default_client() -> Result<Client, HttpError> {
+    default_builder()?.build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_inherited() {
+        let client = ClientBuilder::new()
+            .default_header("Accept", "application/json").unwrap()
+            .default_header("User-Agent", "tool/1.0").unwrap()
+            .timeout(5)
+            .build()
+            .unwrap();
+
+        let request = client.get("http://example.com/").unwrap().header("accept", "text/html").unwrap();
+
+        let head = request.build_header();
+        assert!(head.contains("\r\nUser-Agent: tool/1.0\r\n"));
+        assert!(head.contains("\r\naccept: text/html\r\n"));
+        assert!(!head.contains("application/json"));
+        assert_eq!(client.inner.timeout, 5);
+    }
+
+    #[test]
+    fn lazy_tls() {
+        //the fetch client on wasm32 has no TLS connector to build
+        let client = ClientBuilder::new().build().unwrap();
+        assert!(client.inner.tls.get().is_none());
+        client.tls_connector().unwrap();
+        assert!(client.inner.tls.get().is_some());
+    }
+
+    #[test]
+    fn relative_to_base() {
+        let client = ClientBuilder::new().base_url("https://api.example.com/v2?key=1").build().unwrap();
+        assert_eq!(client.get("/users?page=2").unwrap().raw_url, "https://api.example.com/v2/users?key=1&page=2");
+        assert_eq!(client.get("http://other.example/").unwrap().raw_url, "http://other.example/");
+        assert_eq!(client.get("//cdn.example/logo.png").unwrap().raw_url, "https://cdn.example/logo.png");
+        assert!(ClientBuilder::new().proxy("127.0.0.1:1080").build().is_err());
+    }
+
+    #[test]
+    fn user_agent() {
+        let head = Client::new().get("http://example.com/").unwrap().build_header();
+        assert!(head.contains(&format!("\r\nUser-Agent: smolhttp/{}\r\n", env!("CARGO_PKG_VERSION"))));
+
+        let client = ClientBuilder::new().user_agent("crawler/2.1").unwrap().build().unwrap();
+        let head = client.get("http://example.com/").unwrap().build_header();
+        assert_eq!(head.matches("User-Agent").count(), 1);
+        assert!(head.contains("\r\nUser-Agent: crawler/2.1\r\n"));
+
+        let head = client.get("http://example.com/").unwrap().header("user-agent", "once/1.0").unwrap().build_header();
+        assert_eq!(head.to_lowercase().matches("user-agent").count(), 1);
+        assert!(ClientBuilder::new().user_agent("bad\r\nX-Injected: 1").is_err());
+    }
+
+    #[test]
+    fn invalid_default_header() {
+        assert!(ClientBuilder::new().default_header("Bad Name", "x").is_err());
+    }
+
+    #[test]
+    fn environment_proxies() {
+        let vars = [("HTTP_PROXY", "http://injected:80"), ("https_proxy", "secure.example.com:3128"), ("all_proxy", ""), ("NO_PROXY", "localhost, .internal,")];
+        let client = ClientBuilder::new()
+            .proxy_from_vars(|name| vars.iter().find(|(var, _)| *var == name).map(|(_, value)| value.to_string()))
+            .unwrap()
+            .build()
+            .unwrap();
+        let proxy = |url: &str| client.get(url).unwrap().proxy().map(|proxy| format!("{}:{}", proxy.0.host, proxy.0.port));
+        assert_eq!(proxy("https://example.com/"), Some("secure.example.com:3128".to_owned()));
+        assert_eq!(proxy("wss://example.com/"), Some("secure.example.com:3128".to_owned()));
+        assert_eq!(proxy("http://example.com/"), None);
+        assert_eq!(proxy("https://db.internal/"), None);
+
+        let client = ClientBuilder::new().proxy("http://all.example.com:8080").proxy_for("HTTP", "http://cache.example.com:3128").build().unwrap();
+        assert_eq!(client.get("http://example.com/").unwrap().proxy().unwrap().0.host, "cache.example.com");
+        assert_eq!(client.get("https://example.com/").unwrap().proxy().unwrap().0.host, "all.example.com");
+        assert!(ClientBuilder::new().proxy_for("https", "proxy:3128").build().is_err());
+    }
+}
@@ -0,0 +1,163 @@
+use super::Response;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::Mutex;
+
+///storage of responses used to revalidate GET requests.
+///
+///When a Client has a cache, the stored `ETag`/`Last-Modified` validators of a url are sent
+///as `If-None-Match`/`If-Modified-Since`, and the stored response is returned on `304 Not Modified`.
+pub trait CacheStore: fmt::Debug + Send + Sync {
+    ///return the stored response for `url`
+    fn get(&self, url: &str) -> Option<Response>;
+    ///store the response for `url`, replacing any previous one
+    fn put(&self, url: &str, response: &Response);
+    ///forget the response for `url`
+    fn remove(&self, url: &str);
+}
+
+///in-memory [`CacheStore`], lost when dropped.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, Response>>,
+}
+
+impl MemoryCache {
+    ///return an empty MemoryCache
+    /// # Example
+    /// ```
+    /// use smolhttp::{Client, MemoryCache};
+    ///
+    /// let client = Client::builder().cache(MemoryCache::new()).build().unwrap();
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl CacheStore for MemoryCache {
+    fn get(&self, url: &str) -> Option<Response> {
+        self.entries.lock().unwrap().get(url).cloned()
+    }
+
+    fn put(&self, url: &str, response: &Response) {
+        self.entries.lock().unwrap().insert(url.to_owned(), response.clone());
+    }
+
+    fn remove(&self, url: &str) {
+        self.entries.lock().unwrap().remove(url);
+    }
+}
+
+///on-disk [`CacheStore`], one file per url in a directory.
+#[derive(Debug, Clone)]
+pub struct DiskCache {
+    dir: PathBuf,
+}
+
+impl DiskCache {
+    ///return a DiskCache storing its files in `dir`, which is created if needed
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::{Client, DiskCache};
+    ///
+    /// let client = Client::builder().cache(DiskCache::new("/tmp/smolhttp-cache")).build().unwrap();
+    /// ```
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        // FNV-1a, stable across runs and toolchains unlike the std hasher
+        let hash = url
+            .bytes()
+            .fold(0xcbf29ce484222325u64, |hash, b| (hash ^ b as u64).wrapping_mul(0x100000001b3));
+        self.dir.join(format!("{:016x}", hash))
+    }
+}
+
+impl CacheStore for DiskCache {
+    fn get(&self, url: &str) -> Option<Response> {
+        let data = fs::read(self.path(url)).ok()?;
+        let end = data.iter().position(|&b| b == b'\n')?;
+
+        // the url is stored first to tell hash collisions apart
+        if &data[..end] != url.as_bytes() {
+            return None;
+        }
+        Response::new(data[end + 1..].to_vec()).ok().map(|res| res.with_url(url))
+    }
+
+    fn put(&self, url: &str, response: &Response) {
+        if url.contains('\n') || fs::create_dir_all(&self.dir).is_err() {
+            return;
+        }
+
+        let mut data = format!("{}\n", url).into_bytes();
+        data.extend(response.to_raw());
+
+        let path = self.path(url);
+        let tmp = path.with_extension("tmp");
+        if fs::write(&tmp, data).is_ok() {
+            let _ = fs::rename(&tmp, &path);
+        }
+    }
+
+    fn remove(&self, url: &str) {
+        let _ = fs::remove_file(self.path(url));
+    }
+}
+
+//return if a response can be stored and revalidated later
+pub(crate) fn is_cacheable(res: &Response) -> bool {
+    let no_store = res.cache_control().map(|cc| cc.no_store()).unwrap_or(false);
+    res.status() == 200 && !no_store && (res.header("ETag").is_some() || res.header("Last-Modified").is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn response(headers: &str) -> Response {
+        let raw = format!("HTTP/1.1 200 OK\r\n{}Content-Length: 5\r\n\r\nhello", headers);
+        Response::new(raw.into_bytes()).unwrap()
+    }
+
+    #[test]
+    fn cacheable() {
+        assert!(is_cacheable(&response("ETag: \"v1\"\r\n")));
+        assert!(is_cacheable(&response("Last-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\n")));
+        assert!(!is_cacheable(&response("")));
+        assert!(!is_cacheable(&response("ETag: \"v1\"\r\nCache-Control: no-store\r\n")));
+    }
+
+    #[test]
+    fn memory_cache() {
+        let cache = MemoryCache::new();
+        cache.put("https://a.example/", &response("ETag: \"v1\"\r\n"));
+        assert_eq!(cache.get("https://a.example/").unwrap().header("ETag"), Some("\"v1\""));
+        assert!(cache.get("https://b.example/").is_none());
+        cache.remove("https://a.example/");
+        assert!(cache.get("https://a.example/").is_none());
+    }
+
+    #[test]
+    fn disk_cache() {
+        let dir = env::temp_dir().join(format!("smolhttp-cache-{}", std::process::id()));
+        let cache = DiskCache::new(&dir);
+        cache.put("https://a.example/x", &response("ETag: \"v2\"\r\n"));
+
+        let cached = cache.get("https://a.example/x").unwrap();
+        assert_eq!(cached.header("ETag"), Some("\"v2\""));
+        assert_eq!(cached.text(), "hello");
+        assert_eq!(cached.url(), "https://a.example/x");
+
+        cache.remove("https://a.example/x");
+        assert!(cache.get("https://a.example/x").is_none());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,117 @@
+use super::params::{split_list, split_params};
+
+use std::time::Duration;
+
+///parsed `Cache-Control` header directives.
+/// # Example
+/// ```
+/// use smolhttp::CacheControl;
+/// use std::time::Duration;
+///
+/// let cc = CacheControl::parse("public, max-age=3600, must-revalidate");
+/// assert!(cc.public());
+/// assert!(cc.must_revalidate());
+/// assert_eq!(cc.max_age(), Some(Duration::from_secs(3600)));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheControl {
+    directives: Vec<(String, Option<String>)>,
+}
+
+impl CacheControl {
+    ///parse a `Cache-Control` header value, unknown directives are kept as is
+    pub fn parse(value: &str) -> Self {
+        let directives = split_list(value)
+            .into_iter()
+            .flat_map(split_params)
+            .map(|(name, value)| {
+                let value = if value.is_empty() { None } else { Some(value) };
+                (name, value)
+            })
+            .collect();
+        Self { directives }
+    }
+
+    ///return if the directive `name` is present
+    pub fn contains(&self, name: &str) -> bool {
+        self.directives.iter().any(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+
+    ///return the argument of the directive `name`, if present with an argument
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.directives
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .and_then(|(_, v)| v.as_deref())
+    }
+
+    ///return the `max-age` directive
+    pub fn max_age(&self) -> Option<Duration> {
+        self.seconds("max-age")
+    }
+
+    ///return the `s-maxage` directive
+    pub fn s_maxage(&self) -> Option<Duration> {
+        self.seconds("s-maxage")
+    }
+
+    ///return the `stale-while-revalidate` directive
+    pub fn stale_while_revalidate(&self) -> Option<Duration> {
+        self.seconds("stale-while-revalidate")
+    }
+
+    ///return if the `no-cache` directive is present
+    pub fn no_cache(&self) -> bool {
+        self.contains("no-cache")
+    }
+
+    ///return if the `no-store` directive is present
+    pub fn no_store(&self) -> bool {
+        self.contains("no-store")
+    }
+
+    ///return if the `must-revalidate` directive is present
+    pub fn must_revalidate(&self) -> bool {
+        self.contains("must-revalidate")
+    }
+
+    ///return if the `public` directive is present
+    pub fn public(&self) -> bool {
+        self.contains("public")
+    }
+
+    ///return if the `private` directive is present
+    pub fn private(&self) -> bool {
+        self.contains("private")
+    }
+
+    ///return if the `immutable` directive is present
+    pub fn immutable(&self) -> bool {
+        self.contains("immutable")
+    }
+
+    fn seconds(&self, name: &str) -> Option<Duration> {
+        self.get(name)?.parse().ok().map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_directives() {
+        let cc = CacheControl::parse("no-cache, private=\"Set-Cookie, X-Id\", max-age=0, X-Ext");
+        assert!(cc.no_cache());
+        assert!(!cc.no_store());
+        assert_eq!(cc.get("private"), Some("Set-Cookie, X-Id"));
+        assert_eq!(cc.max_age(), Some(Duration::from_secs(0)));
+        assert!(cc.contains("x-ext"));
+    }
+
+    #[test]
+    fn invalid_seconds() {
+        assert_eq!(CacheControl::parse("max-age=soon").max_age(), None);
+        assert_eq!(CacheControl::parse("").max_age(), None);
+    }
+}
@@ -0,0 +1,99 @@
+//decoding of response bodies according to their charset.
+//
+//utf-8, us-ascii, iso-8859-1, windows-1252 and utf-16 are always supported, the
+//`charset` feature adds every encoding known to `encoding_rs`.
+
+//return the `charset` parameter of a Content-Type header value
+pub(crate) fn from_content_type(content_type: &str) -> Option<&str> {
+    content_type.split(';').skip(1).find_map(|param| {
+        let (name, value) = param.split_once('=')?;
+        if name.trim().eq_ignore_ascii_case("charset") {
+            Some(value.trim().trim_matches('"'))
+        } else {
+            None
+        }
+    })
+}
+
+//decode `body` as `charset`, unknown charsets are decoded as utf-8
+#[cfg(feature = "charset")]
+pub(crate) fn decode(body: &[u8], charset: &str) -> String {
+    let encoding = encoding_rs::Encoding::for_label(charset.trim().as_bytes()).unwrap_or(encoding_rs::UTF_8);
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+//decode `body` as `charset`, unknown charsets are decoded as utf-8
+#[cfg(not(feature = "charset"))]
+pub(crate) fn decode(body: &[u8], charset: &str) -> String {
+    if let Some(body) = body.strip_prefix(b"\xEF\xBB\xBF") {
+        return String::from_utf8_lossy(body).into_owned();
+    }
+    if let Some(body) = body.strip_prefix(b"\xFF\xFE") {
+        return decode_utf16(body, u16::from_le_bytes);
+    }
+    if let Some(body) = body.strip_prefix(b"\xFE\xFF") {
+        return decode_utf16(body, u16::from_be_bytes);
+    }
+
+    match charset.trim().to_ascii_lowercase().as_str() {
+        // like browsers (and encoding_rs), latin1 and ascii labels are decoded as windows-1252
+        "windows-1252" | "cp1252" | "iso-8859-1" | "iso8859-1" | "latin1" | "l1" | "us-ascii" | "ascii" => {
+            body.iter().map(|&b| windows_1252(b)).collect()
+        }
+        "utf-16" | "utf-16le" => decode_utf16(body, u16::from_le_bytes),
+        "utf-16be" => decode_utf16(body, u16::from_be_bytes),
+        _ => String::from_utf8_lossy(body).into_owned(),
+    }
+}
+
+#[cfg(not(feature = "charset"))]
+fn decode_utf16(body: &[u8], unit: fn([u8; 2]) -> u16) -> String {
+    let units = body.chunks(2).map(|c| if c.len() == 2 { unit([c[0], c[1]]) } else { 0xFFFD });
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+#[cfg(not(feature = "charset"))]
+fn windows_1252(byte: u8) -> char {
+    const HIGH: [char; 32] = [
+        '\u{20AC}', '\u{81}', '\u{201A}', '\u{0192}', '\u{201E}', '\u{2026}', '\u{2020}', '\u{2021}',
+        '\u{02C6}', '\u{2030}', '\u{0160}', '\u{2039}', '\u{0152}', '\u{8D}', '\u{017D}', '\u{8F}',
+        '\u{90}', '\u{2018}', '\u{2019}', '\u{201C}', '\u{201D}', '\u{2022}', '\u{2013}', '\u{2014}',
+        '\u{02DC}', '\u{2122}', '\u{0161}', '\u{203A}', '\u{0153}', '\u{9D}', '\u{017E}', '\u{0178}',
+    ];
+    match byte {
+        0x80..=0x9F => HIGH[(byte - 0x80) as usize],
+        _ => byte as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charset_param() {
+        assert_eq!(from_content_type("text/html; charset=ISO-8859-1"), Some("ISO-8859-1"));
+        assert_eq!(from_content_type("text/html;Charset=\"utf-8\""), Some("utf-8"));
+        assert_eq!(from_content_type("text/html"), None);
+    }
+
+    #[test]
+    fn decode_latin1() {
+        assert_eq!(decode(b"caf\xe9", "iso-8859-1"), "caf\u{e9}");
+        assert_eq!(decode(b"\x80 5", "windows-1252"), "\u{20AC} 5");
+    }
+
+    #[test]
+    fn decode_utf16_bom() {
+        assert_eq!(decode(b"\xFF\xFEh\x00i\x00", "utf-8"), "hi");
+        assert_eq!(decode(b"\x00h\x00i", "UTF-16BE"), "hi");
+    }
+
+    #[test]
+    fn decode_unknown_as_utf8() {
+        assert_eq!(decode("日本".as_bytes(), "x-unknown"), "日本");
+    }
+}
@@ -0,0 +1,125 @@
+use super::sha::{self, Hasher};
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::fmt;
+use std::fs::File;
+use std::io;
+
+///hash algorithm of a checksum, see [`RequestBuilder::expect_checksum`] and [`Response::digest`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    ///SHA-1, only for the sources still publishing it
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl Algorithm {
+    //size of the digests, in bytes
+    fn size(self) -> usize {
+        match self {
+            Algorithm::Sha1 => 20,
+            Algorithm::Sha256 => 32,
+            Algorithm::Sha512 => 64,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha512 => "sha512",
+        })
+    }
+}
+
+impl RequestBuilder {
+    ///fail with [`HttpError::Checksum`] when the body of a successful response doesn't hash to the `hex` digest
+    ///
+    ///The body is hashed once decoded, from its temporary file when it was spilled to disk. Responses with an
+    ///error status aren't checked, so that [`Response::error_for_status`] still reports them.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::{Algorithm, Client};
+    ///
+    /// let res = Client::new()
+    ///     .get("https://example.com/tool-1.0.tar.gz").unwrap()
+    ///     .expect_checksum(Algorithm::Sha256, "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855").unwrap()
+    ///     .send().unwrap();
+    /// ```
+    pub fn expect_checksum(mut self, algorithm: Algorithm, hex: &str) -> Result<Self, HttpError> {
+        if hex.len() != algorithm.size() * 2 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(HttpError::Config("Checksum is not a valid hex digest"));
+        }
+        self.checksum = Some((algorithm, hex.to_ascii_lowercase()));
+        Ok(self)
+    }
+}
+
+impl Response {
+    ///return the lowercase hex digest of the body, read from its temporary file when it was spilled to disk
+    /// # Example
+    /// ```
+    /// use smolhttp::{Algorithm, Response};
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nabc".to_vec()).unwrap();
+    /// assert_eq!(res.digest(Algorithm::Sha256).unwrap(), "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+    /// ```
+    pub fn digest(&self, algorithm: Algorithm) -> Result<String, HttpError> {
+        let mut hasher = Hasher::new(algorithm);
+        match self.body_path() {
+            Some(path) => {
+                io::copy(&mut File::open(path)?, &mut hasher)?;
+            }
+            None => hasher.update(self.bytes()),
+        }
+        Ok(sha::hex(&hasher.finish()))
+    }
+}
+
+//check the body of a successful response against the checksum expected, if any
+pub(crate) fn verify(checksum: Option<&(Algorithm, String)>, res: Response) -> Result<Response, HttpError> {
+    let Some(&(algorithm, ref expected)) = checksum else {
+        return Ok(res);
+    };
+    if !(200..300).contains(&res.status_code()) {
+        return Ok(res);
+    }
+    let actual = res.digest(algorithm)?;
+    if actual != *expected {
+        return Err(HttpError::Checksum { algorithm, expected: expected.clone(), actual });
+    }
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::Client;
+
+    #[test]
+    fn checksums() {
+        let abc = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        let mock = MockTransport::new();
+        mock.respond("GET", "http://example.com/abc", 200, "abc");
+        mock.respond("GET", "http://example.com/missing", 404, "not found");
+        let client = Client::builder().connector(mock).spill_to_disk(2).build().unwrap();
+
+        let res = client.get("http://example.com/abc").unwrap().expect_checksum(Algorithm::Sha256, &abc.to_uppercase()).unwrap().send().unwrap();
+        assert!(res.body_path().is_some());
+        assert_eq!(res.digest(Algorithm::Sha1).unwrap(), "a9993e364706816aba3e25717850c26c9cd0d89d");
+
+        let err = client.get("http://example.com/abc").unwrap().expect_checksum(Algorithm::Sha256, &"0".repeat(64)).unwrap().send().unwrap_err();
+        assert!(matches!(err, HttpError::Checksum { algorithm: Algorithm::Sha256, ref actual, .. } if actual == abc));
+        assert_eq!(err.kind(), crate::ErrorKind::Body);
+
+        let res = client.get("http://example.com/missing").unwrap().expect_checksum(Algorithm::Sha256, abc).unwrap().send().unwrap();
+        assert_eq!(res.status_code(), 404);
+        assert!(client.get("http://example.com/abc").unwrap().expect_checksum(Algorithm::Sha1, abc).is_err());
+    }
+}
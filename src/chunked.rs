@@ -0,0 +1,196 @@
+use super::HeaderMap;
+use super::HttpError;
+
+use std::io::{self, Read, Write};
+
+//longest chunk size or trailer line accepted
+const MAX_LINE: usize = 8 * 1024;
+
+//largest chunk written when encoding a body
+pub(crate) const CHUNK_SIZE: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Size,
+    Data(u64),
+    DataEnd,
+    Trailers,
+    Done,
+}
+
+//incremental decoder of a chunked body, fed with the bytes as they are received
+#[derive(Debug)]
+pub(crate) struct Decoder {
+    state: State,
+    line: Vec<u8>,
+    trailers: HeaderMap,
+}
+
+impl Decoder {
+    pub(crate) fn new() -> Self {
+        Self { state: State::Size, line: Vec::new(), trailers: HeaderMap::new() }
+    }
+
+    //decode `input` into `body`, returning how many bytes were used, all of them unless the body ended in it
+    pub(crate) fn decode(&mut self, input: &[u8], body: &mut Vec<u8>) -> Result<usize, HttpError> {
+        let mut used = 0;
+        while used < input.len() {
+            let rest = &input[used..];
+            match self.state {
+                State::Done => break,
+                State::Data(remaining) => {
+                    let n = rest.len().min(remaining.min(usize::MAX as u64) as usize);
+                    body.extend_from_slice(&rest[..n]);
+                    used += n;
+                    self.state = match remaining - n as u64 {
+                        0 => State::DataEnd,
+                        remaining => State::Data(remaining),
+                    };
+                }
+                State::Size | State::DataEnd | State::Trailers => {
+                    let (n, complete) = match rest.iter().position(|&b| b == b'\n') {
+                        Some(end) => (end + 1, true),
+                        None => (rest.len(), false),
+                    };
+                    self.line.extend_from_slice(&rest[..n]);
+                    used += n;
+                    if self.line.len() > MAX_LINE {
+                        return Err(HttpError::Parse("chunked body line too long"));
+                    }
+                    if complete {
+                        let line = std::mem::take(&mut self.line);
+                        self.line_end(line.strip_suffix(b"\n").unwrap_or(&line))?;
+                    }
+                }
+            }
+        }
+        Ok(used)
+    }
+
+    //return if the last chunk and the trailer section were decoded
+    pub(crate) fn is_done(&self) -> bool {
+        self.state == State::Done
+    }
+
+    //return the trailer fields following the last chunk
+    pub(crate) fn into_trailers(self) -> HeaderMap {
+        self.trailers
+    }
+
+    fn line_end(&mut self, line: &[u8]) -> Result<(), HttpError> {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        self.state = match self.state {
+            State::Size => {
+                let size = line.split(|&b| b == b';').next().unwrap_or_default();
+                let size = std::str::from_utf8(size)
+                    .ok()
+                    .and_then(|size| u64::from_str_radix(size.trim(), 16).ok())
+                    .ok_or(HttpError::Parse("invalid chunk size"))?;
+                match size {
+                    0 => State::Trailers,
+                    size => State::Data(size),
+                }
+            }
+            State::DataEnd if line.is_empty() => State::Size,
+            State::DataEnd => return Err(HttpError::Parse("missing chunk terminator")),
+            // the trailer section ends with an empty line
+            State::Trailers if line.is_empty() => State::Done,
+            State::Trailers => {
+                if let Some((name, value)) = String::from_utf8_lossy(line).split_once(':') {
+                    self.trailers.append(name.trim(), value.trim());
+                }
+                State::Trailers
+            }
+            state => state,
+        };
+        Ok(())
+    }
+}
+
+//decode a whole chunked body, returning the data and the trailer fields following the last chunk
+pub(crate) fn decode(data: &[u8]) -> Result<(Vec<u8>, HeaderMap), HttpError> {
+    let mut decoder = Decoder::new();
+    let mut body = Vec::new();
+    decoder.decode(data, &mut body)?;
+    // some servers close the connection without the empty line ending the trailer section
+    match decoder.state {
+        State::Done | State::Trailers if decoder.line.is_empty() => Ok((body, decoder.into_trailers())),
+        _ => Err(HttpError::Parse("truncated chunked body")),
+    }
+}
+
+//write everything read from `reader` to `out` as a chunked body, returning the number of bytes of data
+pub(crate) fn encode<R: Read + ?Sized, W: Write + ?Sized>(reader: &mut R, out: &mut W) -> io::Result<u64> {
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut written = 0;
+    loop {
+        let len = match reader.read(&mut chunk) {
+            Ok(0) => break,
+            Ok(len) => len,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        write!(out, "{:X}\r\n", len)?;
+        out.write_all(&chunk[..len])?;
+        out.write_all(b"\r\n")?;
+        written += len as u64;
+    }
+    out.write_all(b"0\r\n\r\n")?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_with_trailers() {
+        let (body, trailers) = decode(b"4;ext=1\r\nWiki\r\n5\r\npedia\r\n0\r\ngrpc-status: 0\r\nGrpc-Message: ok\r\n\r\n").unwrap();
+        assert_eq!(body, b"Wikipedia");
+        assert_eq!(trailers.get("Grpc-Status"), Some("0"));
+        assert_eq!(trailers.get("grpc-message"), Some("ok"));
+
+        let (body, trailers) = decode(b"A\r\n0123456789\r\n0\r\n").unwrap();
+        assert_eq!(body, b"0123456789");
+        assert!(trailers.is_empty());
+    }
+
+    #[test]
+    fn encode_roundtrip() {
+        let data: Vec<u8> = (0..40_000u32).map(|i| i as u8).collect();
+        let mut out = Vec::new();
+        assert_eq!(encode(&mut &data[..], &mut out).unwrap(), 40_000);
+        assert!(out.starts_with(b"4000\r\n"));
+        assert_eq!(decode(&out).unwrap().0, data);
+
+        let mut out = Vec::new();
+        encode(&mut io::empty(), &mut out).unwrap();
+        assert_eq!(out, b"0\r\n\r\n");
+    }
+
+    #[test]
+    fn decode_errors() {
+        assert!(decode(b"zz\r\n").is_err());
+        assert!(decode(b"5\r\nab").is_err());
+        assert!(decode(b"2\r\nabcd\r\n0\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn incremental_decoding() {
+        let data = b"4\r\nWiki\r\n5\r\npedia\r\n0\r\nExpires: never\r\n\r\nHTTP/1.1 200 OK";
+        let mut decoder = Decoder::new();
+        let mut body = Vec::new();
+        let mut used = 0;
+        // one byte at a time, as a slow server would send it
+        while !decoder.is_done() {
+            used += decoder.decode(&data[used..used + 1], &mut body).unwrap();
+        }
+        assert_eq!(body, b"Wikipedia");
+        assert_eq!(&data[used..], b"HTTP/1.1 200 OK");
+        assert_eq!(decoder.into_trailers().get("expires"), Some("never"));
+
+        let mut decoder = Decoder::new();
+        assert_eq!(decoder.decode(b"3\r\nabc\r\n0\r\n\r\nnext", &mut Vec::new()).unwrap(), 13);
+        assert!(decoder.is_done());
+    }
+}
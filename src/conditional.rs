@@ -0,0 +1,77 @@
+use super::date;
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::time::SystemTime;
+
+impl RequestBuilder {
+    ///send the request with an `If-None-Match` header, so the server answers `304 Not Modified` while the
+    ///resource still has the entity tag `etag`
+    ///
+    ///A tag without its quotes is quoted, and `*` matches any version of the resource.
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let request = Client::new().get("http://example.com/feed").unwrap().if_none_match("v1").unwrap();
+    /// assert!(String::from_utf8(request.preview()).unwrap().contains("\r\nIf-None-Match: \"v1\"\r\n"));
+    /// ```
+    pub fn if_none_match(self, etag: &str) -> Result<Self, HttpError> {
+        let etag = etag.trim();
+        if etag == "*" || etag.starts_with('"') || etag.starts_with("W/\"") {
+            self.header("If-None-Match", etag)
+        } else {
+            self.header("If-None-Match", &format!("\"{}\"", etag))
+        }
+    }
+
+    ///send the request with an `If-Modified-Since` header, so the server answers `304 Not Modified` while the
+    ///resource wasn't changed after `time`
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    /// use std::time::{Duration, UNIX_EPOCH};
+    ///
+    /// let request = Client::new().get("http://example.com/feed").unwrap().if_modified_since(UNIX_EPOCH + Duration::from_secs(784111777));
+    /// assert!(String::from_utf8(request.preview()).unwrap().contains("\r\nIf-Modified-Since: Sun, 06 Nov 1994 08:49:37 GMT\r\n"));
+    /// ```
+    pub fn if_modified_since(mut self, time: SystemTime) -> Self {
+        self.headers.insert("If-Modified-Since", date::fmt_http_date(time));
+        self
+    }
+}
+
+impl Response {
+    ///return if the response is `304 Not Modified`, the answer to a conditional request whose version is up to date
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let res = Client::new().get("https://example.com/feed").unwrap().if_none_match("\"v1\"").unwrap().send().unwrap();
+    /// if res.is_not_modified() {
+    ///     println!("still at v1");
+    /// }
+    /// ```
+    pub fn is_not_modified(&self) -> bool {
+        self.status_code() == 304
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::testing::MockTransport;
+    use crate::Client;
+
+    #[test]
+    fn conditional_requests() {
+        let mock = MockTransport::new();
+        mock.respond("GET", "http://example.com/feed", 304, "");
+        let client = Client::with_connector(mock.clone());
+
+        let res = client.get("http://example.com/feed").unwrap().if_none_match("W/\"v2\"").unwrap().send().unwrap();
+        assert!(res.is_not_modified());
+        assert_eq!(mock.requests()[0].headers().get("If-None-Match"), Some("W/\"v2\""));
+        assert!(client.get("http://example.com/feed").unwrap().if_none_match("v\r\n2").is_err());
+    }
+}
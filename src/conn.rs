@@ -0,0 +1,1114 @@
+use super::chunked;
+use super::params;
+use super::response;
+use super::ErrorKind;
+use super::HeaderMap;
+use super::HttpError;
+use super::Transport;
+use super::Version;
+
+#[cfg(feature = "openssl")]
+use super::session::SessionStream;
+
+use super::tls::TlsStream;
+
+#[cfg(not(target_arch = "wasm32"))]
+use socket2::{Domain, Protocol, Socket, TcpKeepalive, Type};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, Read, Write};
+use std::net::{IpAddr, Shutdown, SocketAddr, TcpStream};
+use std::sync::{mpsc, Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+//connection to a server or a proxy, over tls or not, or opened by a custom connector
+pub(crate) enum Stream {
+    Plain(TcpStream),
+    //TLS set up by native-tls, left to HTTP/2 and the async client when openssl is enabled
+    #[cfg_attr(all(feature = "openssl", not(feature = "http2")), allow(dead_code))]
+    Tls(Box<TlsStream<TcpStream>>),
+    //TLS set up by openssl, resuming sessions
+    #[cfg(feature = "openssl")]
+    Openssl(Box<SessionStream>),
+    Custom(Box<dyn Transport>),
+}
+
+impl Stream {
+    //set the read timeout of the underlying connection
+    pub(crate) fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.set_read_timeout(timeout),
+            Stream::Tls(stream) => stream.get_ref().set_read_timeout(timeout),
+            #[cfg(feature = "openssl")]
+            Stream::Openssl(stream) => stream.get_ref().set_read_timeout(timeout),
+            Stream::Custom(stream) => stream.set_read_timeout(timeout),
+        }
+    }
+
+    //return a function shutting the connection down from another thread, if it can be
+    pub(crate) fn shutdown_handle(&self) -> Option<Box<dyn Fn() + Send + Sync>> {
+        match self {
+            Stream::Plain(stream) => Transport::shutdown_handle(stream),
+            Stream::Tls(stream) => Transport::shutdown_handle(stream.get_ref()),
+            #[cfg(feature = "openssl")]
+            Stream::Openssl(stream) => Transport::shutdown_handle(stream.get_ref()),
+            Stream::Custom(stream) => stream.shutdown_handle(),
+        }
+    }
+
+    //return the protocol selected by the server through ALPN
+    #[cfg(feature = "alpn")]
+    pub(crate) fn alpn(&self) -> Option<String> {
+        match self {
+            Stream::Tls(stream) => stream.negotiated_alpn().ok().flatten().map(|alpn| String::from_utf8_lossy(&alpn).into_owned()),
+            #[cfg(feature = "openssl")]
+            Stream::Openssl(stream) => stream.ssl().selected_alpn_protocol().map(|alpn| String::from_utf8_lossy(alpn).into_owned()),
+            _ => None,
+        }
+    }
+
+}
+
+//connection that can wait in a ConnectionPool
+pub(crate) trait Pooled {
+    //return if the connection is still open with nothing sent by the server, peeking at it without blocking
+    fn is_alive(&self) -> bool;
+}
+
+impl Pooled for Stream {
+    //connections of a custom connector are assumed open
+    fn is_alive(&self) -> bool {
+        let tcp = match self {
+            Stream::Plain(stream) => stream,
+            Stream::Tls(stream) => stream.get_ref(),
+            #[cfg(feature = "openssl")]
+            Stream::Openssl(stream) => stream.get_ref(),
+            Stream::Custom(_) => return true,
+        };
+        if tcp.set_nonblocking(true).is_err() {
+            return false;
+        }
+        let idle = matches!(tcp.peek(&mut [0u8; 1]), Err(ref err) if err.kind() == io::ErrorKind::WouldBlock);
+        tcp.set_nonblocking(false).is_ok() && idle
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.read(buf),
+            Stream::Tls(stream) => stream.read(buf),
+            #[cfg(feature = "openssl")]
+            Stream::Openssl(stream) => stream.read(buf),
+            Stream::Custom(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Stream::Plain(stream) => stream.write(buf),
+            Stream::Tls(stream) => stream.write(buf),
+            #[cfg(feature = "openssl")]
+            Stream::Openssl(stream) => stream.write(buf),
+            Stream::Custom(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Stream::Plain(stream) => stream.flush(),
+            Stream::Tls(stream) => stream.flush(),
+            #[cfg(feature = "openssl")]
+            Stream::Openssl(stream) => stream.flush(),
+            Stream::Custom(stream) => stream.flush(),
+        }
+    }
+}
+
+//options applied to the sockets of a Client before they connect
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SocketOptions {
+    pub(crate) local_address: Option<IpAddr>,
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    pub(crate) interface: Option<String>,
+    pub(crate) nodelay: bool,
+    pub(crate) keepalive: Option<Duration>,
+    pub(crate) send_buffer_size: Option<usize>,
+    pub(crate) recv_buffer_size: Option<usize>,
+}
+
+//return an unconnected socket for `addr` with the options applied
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn socket(addr: SocketAddr, options: &SocketOptions) -> io::Result<Socket> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if let Some(local) = options.local_address {
+        if local.is_ipv4() != addr.is_ipv4() {
+            return Err(io::Error::new(io::ErrorKind::AddrNotAvailable, format!("local address {} can't reach {}", local, addr)));
+        }
+        socket.bind(&SocketAddr::new(local, 0).into())?;
+    }
+    #[cfg(any(target_os = "android", target_os = "fuchsia", target_os = "linux"))]
+    if let Some(ref interface) = options.interface {
+        socket.bind_device(Some(interface.as_bytes()))?;
+    }
+    socket.set_tcp_nodelay(options.nodelay)?;
+    if let Some(idle) = options.keepalive {
+        socket.set_tcp_keepalive(&TcpKeepalive::new().with_time(idle))?;
+    }
+    if let Some(size) = options.send_buffer_size {
+        socket.set_send_buffer_size(size)?;
+    }
+    if let Some(size) = options.recv_buffer_size {
+        socket.set_recv_buffer_size(size)?;
+    }
+    Ok(socket)
+}
+
+//delay before trying the next address while a connection attempt is pending(RFC 8305)
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+//order addresses alternating between families, starting with IPv6
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn interleave(addrs: &[SocketAddr]) -> Vec<SocketAddr> {
+    let (v6, v4): (Vec<_>, Vec<_>) = addrs.iter().partition(|addr| addr.is_ipv6());
+    let mut ordered = Vec::with_capacity(addrs.len());
+    for i in 0..v6.len().max(v4.len()) {
+        ordered.extend(v6.get(i));
+        ordered.extend(v4.get(i));
+    }
+    ordered
+}
+
+//connect to the first address accepting the connection, starting a new attempt every `ATTEMPT_DELAY`
+//while the previous ones are pending, so an unroutable address doesn't fail the whole connection
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn connect(addrs: &[SocketAddr], options: &SocketOptions, timeout: Duration) -> io::Result<TcpStream> {
+    let (sender, receiver) = mpsc::channel();
+    let mut last_err = None;
+    let mut pending = 0;
+    for addr in interleave(addrs) {
+        let sender = sender.clone();
+        let options = options.clone();
+        // the attempts losing the race are dropped once the receiver is gone
+        thread::spawn(move || {
+            let stream = socket(addr, &options).and_then(|socket| {
+                socket.connect_timeout(&addr.into(), timeout)?;
+                Ok(TcpStream::from(socket))
+            });
+            sender.send(stream)
+        });
+        pending += 1;
+
+        // a failed attempt starts the next one right away
+        match receiver.recv_timeout(ATTEMPT_DELAY) {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(err)) => {
+                last_err = Some(err);
+                pending -= 1;
+            }
+            Err(_) => {}
+        }
+    }
+    drop(sender);
+
+    while pending > 0 {
+        match receiver.recv() {
+            Ok(Ok(stream)) => return Ok(stream),
+            Ok(Err(err)) => last_err = Some(err),
+            Err(_) => break,
+        }
+        pending -= 1;
+    }
+    Err(last_err.unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no address to connect to")))
+}
+
+//wasm32 has no TCP sockets, the fetch API of the browser opens the connections
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn connect(_addrs: &[SocketAddr], _options: &SocketOptions, _timeout: Duration) -> io::Result<TcpStream> {
+    Err(io::Error::new(io::ErrorKind::Unsupported, "TCP connections aren't available on wasm32"))
+}
+
+//shuts a socket down once `timeout` elapsed unless finished before, bounding the whole of an exchange that
+//read and write timeouts only bound call by call, like a TLS handshake with a peer sending byte by byte
+pub(crate) struct Watchdog {
+    //set to `Some(fired)` once the watchdog finished or fired
+    state: Arc<Mutex<Option<bool>>>,
+    _stop: mpsc::Sender<()>,
+}
+
+impl Watchdog {
+    pub(crate) fn new(tcp: &TcpStream, timeout: Duration) -> io::Result<Self> {
+        let socket = tcp.try_clone()?;
+        let state = Arc::new(Mutex::new(None));
+        let (stop, stopped) = mpsc::channel::<()>();
+        let shared = state.clone();
+        thread::spawn(move || {
+            // the sender is dropped with the watchdog, ending the wait early
+            if stopped.recv_timeout(timeout) == Err(mpsc::RecvTimeoutError::Timeout) {
+                let mut state = shared.lock().unwrap();
+                if state.is_none() {
+                    *state = Some(true);
+                    let _ = socket.shutdown(Shutdown::Both);
+                }
+            }
+        });
+        Ok(Self { state, _stop: stop })
+    }
+
+    //stop the watchdog, and return if it fired and shut the socket down
+    pub(crate) fn finish(self) -> bool {
+        *self.state.lock().unwrap().get_or_insert(false)
+    }
+}
+
+//number of request head buffers kept by a Client, and the largest one kept
+const POOLED_BUFFERS: usize = 8;
+const MAX_POOLED_CAPACITY: usize = 64 * 1024;
+
+//request head buffers reused by the requests of a Client, so that repeated requests don't allocate them
+#[derive(Debug, Default)]
+pub(crate) struct BufferPool {
+    buffers: Mutex<Vec<String>>,
+}
+
+impl BufferPool {
+    //return an empty buffer, reusing a previous one if any
+    pub(crate) fn take(&self) -> String {
+        self.buffers.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    //give a buffer back to the pool, unless it is full or the buffer grew too large
+    pub(crate) fn put(&self, mut buffer: String) {
+        if buffer.capacity() > MAX_POOLED_CAPACITY {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < POOLED_BUFFERS {
+            buffers.push(buffer);
+        }
+    }
+}
+
+//how long a connection stays idle in the pool by default
+const IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
+//limits of the connections of a Client to each origin, unlimited by default
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct PoolLimits {
+    pub(crate) max_connections: Option<usize>,
+    pub(crate) max_idle: Option<usize>,
+    //how long to wait for a connection at the limit to close, forever by default
+    pub(crate) wait: Option<Duration>,
+    pub(crate) idle_timeout: Option<Duration>,
+    //peek at idle connections before reusing them
+    pub(crate) health_check: bool,
+}
+
+impl Default for PoolLimits {
+    fn default() -> Self {
+        Self { max_connections: None, max_idle: None, wait: None, idle_timeout: Some(IDLE_TIMEOUT), health_check: false }
+    }
+}
+
+//open connections waiting for a request by origin, and the number of connections open to each origin,
+//the connections of the blocking client by default
+pub(crate) struct ConnectionPool<S = Stream> {
+    shared: Arc<Shared<S>>,
+}
+
+struct Shared<S> {
+    state: Mutex<PoolState<S>>,
+    released: Condvar,
+    limits: PoolLimits,
+}
+
+struct PoolState<S> {
+    //with the time they were parked
+    idle: HashMap<String, Vec<(S, Instant)>>,
+    open: HashMap<String, usize>,
+}
+
+impl<S> Shared<S> {
+    fn lock(&self) -> MutexGuard<'_, PoolState<S>> {
+        self.state.lock().unwrap_or_else(|err| err.into_inner())
+    }
+}
+
+impl<S> PoolState<S> {
+    //forget a connection to `origin` that was closed
+    fn release(&mut self, origin: &str) {
+        if let Some(open) = self.open.get_mut(origin) {
+            *open -= 1;
+            if *open == 0 {
+                self.open.remove(origin);
+            }
+        }
+    }
+
+    //close the connections idle for longer than `timeout`, whatever their origin
+    fn evict(&mut self, timeout: Option<Duration>) {
+        let timeout = match timeout {
+            Some(timeout) => timeout,
+            None => return,
+        };
+        let mut closed = Vec::new();
+        self.idle.retain(|origin, streams| {
+            streams.retain(|(_, parked)| {
+                let expired = parked.elapsed() >= timeout;
+                if expired {
+                    closed.push(origin.clone());
+                }
+                !expired
+            });
+            !streams.is_empty()
+        });
+        for origin in closed {
+            self.release(&origin);
+        }
+    }
+}
+
+impl<S: Pooled> Default for ConnectionPool<S> {
+    fn default() -> Self {
+        Self::new(PoolLimits::default())
+    }
+}
+
+impl<S> Clone for ConnectionPool<S> {
+    fn clone(&self) -> Self {
+        Self { shared: self.shared.clone() }
+    }
+}
+
+impl<S> fmt::Debug for ConnectionPool<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.shared.lock();
+        let idle = state.idle.values().map(Vec::len).sum::<usize>();
+        let open = state.open.values().sum::<usize>();
+        f.debug_struct("ConnectionPool").field("idle", &idle).field("open", &open).field("limits", &self.shared.limits).finish()
+    }
+}
+
+//a connection counted against the limit of its origin until it is dropped, or parked in the pool
+pub(crate) struct Slot<S = Stream> {
+    shared: Arc<Shared<S>>,
+    origin: String,
+    parked: bool,
+}
+
+impl<S> Drop for Slot<S> {
+    fn drop(&mut self) {
+        if !self.parked {
+            self.shared.lock().release(&self.origin);
+            self.shared.released.notify_all();
+        }
+    }
+}
+
+impl<S: Pooled> ConnectionPool<S> {
+    pub(crate) fn new(limits: PoolLimits) -> Self {
+        let state = PoolState { idle: HashMap::new(), open: HashMap::new() };
+        Self { shared: Arc::new(Shared { state: Mutex::new(state), released: Condvar::new(), limits }) }
+    }
+
+    //return an open connection to `origin`, the last parked first, closing the expired ones and,
+    //with the health check, the ones closed by the server
+    pub(crate) fn take(&self, origin: &str) -> Option<(S, Slot<S>)> {
+        let limits = self.shared.limits;
+        let mut state = self.shared.lock();
+        state.evict(limits.idle_timeout);
+        loop {
+            let streams = state.idle.get_mut(origin)?;
+            let (stream, _) = streams.pop()?;
+            if streams.is_empty() {
+                state.idle.remove(origin);
+            }
+            if !limits.health_check || stream.is_alive() {
+                return Some((stream, Slot { shared: self.shared.clone(), origin: origin.to_owned(), parked: false }));
+            }
+            state.release(origin);
+        }
+    }
+
+    //return if new connections may have to wait for others to close
+    #[cfg(feature = "tokio")]
+    pub(crate) fn is_limited(&self) -> bool {
+        self.shared.limits.max_connections.is_some()
+    }
+
+    //count a new connection to `origin`, waiting for another one to close when the limit is reached
+    pub(crate) fn acquire(&self, origin: &str) -> Result<Slot<S>, HttpError> {
+        let limits = self.shared.limits;
+        let deadline = limits.wait.map(|wait| Instant::now() + wait);
+        let mut state = self.shared.lock();
+        state.evict(limits.idle_timeout);
+        while let Some(max) = limits.max_connections.filter(|&max| state.open.get(origin).copied().unwrap_or(0) >= max) {
+            //an idle connection is closed to make room
+            if state.idle.get_mut(origin).and_then(Vec::pop).is_some() {
+                state.release(origin);
+                continue;
+            }
+            state = match deadline {
+                None => self.shared.released.wait(state).unwrap_or_else(|err| err.into_inner()),
+                Some(deadline) => {
+                    let left = deadline.saturating_duration_since(Instant::now());
+                    if left.is_zero() {
+                        let message = format!("{} connections to {} already open", max, origin);
+                        return Err(io::Error::new(io::ErrorKind::TimedOut, message).into());
+                    }
+                    self.shared.released.wait_timeout(state, left).unwrap_or_else(|err| err.into_inner()).0
+                }
+            };
+        }
+        *state.open.entry(origin.to_owned()).or_default() += 1;
+        Ok(Slot { shared: self.shared.clone(), origin: origin.to_owned(), parked: false })
+    }
+
+    //park an open connection to `origin` until a request to it takes it, or close it if enough are idle
+    pub(crate) fn put(&self, origin: String, stream: S, mut slot: Slot<S>) {
+        let mut state = self.shared.lock();
+        let idle = state.idle.get(&origin).map_or(0, Vec::len);
+        if self.shared.limits.max_idle.is_some_and(|max| idle >= max) {
+            //the slot releases the connection once the pool is unlocked
+            drop(state);
+            return;
+        }
+        state.idle.entry(origin).or_default().push((stream, Instant::now()));
+        slot.parked = true;
+    }
+}
+
+//reason of the error of a connection closed before any byte of the response
+pub(crate) const CLOSED_BEFORE_RESPONSE: &str = "connection closed before the response";
+
+//return if a request failed because its reused connection was closed or reset by the server while idle,
+//before it read the request
+pub(crate) fn is_stale(err: &HttpError) -> bool {
+    err.kind() == ErrorKind::Write || matches!(err.cause(), HttpError::Parse(reason) if *reason == CLOSED_BEFORE_RESPONSE)
+}
+
+//return if an io error is the connection reset or aborted by the peer
+fn is_reset(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::ConnectionReset | io::ErrorKind::ConnectionAborted | io::ErrorKind::BrokenPipe)
+}
+
+//limits on the number of headers and the size of a response head, so a server can't make the client
+//buffer a head without end
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct HeaderLimits {
+    pub(crate) max_headers: usize,
+    pub(crate) max_bytes: usize,
+}
+
+impl Default for HeaderLimits {
+    fn default() -> Self {
+        Self { max_headers: 100, max_bytes: 64 * 1024 }
+    }
+}
+
+impl HeaderLimits {
+    //check a head of `len` bytes holding `headers` headers, while it is read or once it is whole
+    pub(crate) fn check(&self, len: usize, headers: usize) -> Result<(), HttpError> {
+        if len > self.max_bytes {
+            return Err(HttpError::HeadersTooLarge { limit: self.max_bytes });
+        }
+        if headers > self.max_headers {
+            return Err(HttpError::TooManyHeaders { limit: self.max_headers });
+        }
+        Ok(())
+    }
+}
+
+//response head read byte by byte, so that nothing after it is consumed, failing once it exceeds its limits
+pub(crate) struct Head {
+    bytes: Vec<u8>,
+    //lines ended so far, the status line included
+    lines: usize,
+    limits: HeaderLimits,
+}
+
+impl Head {
+    pub(crate) fn new(limits: HeaderLimits) -> Self {
+        Self { bytes: Vec::new(), lines: 0, limits }
+    }
+
+    //add the next byte of the head, and return if the head is complete
+    pub(crate) fn push(&mut self, byte: u8) -> Result<bool, HttpError> {
+        self.bytes.push(byte);
+        let done = self.bytes.ends_with(b"\r\n\r\n");
+        if byte == b'\n' && !done {
+            self.lines += 1;
+        }
+        self.limits.check(self.bytes.len(), self.lines.saturating_sub(1))?;
+        Ok(done)
+    }
+
+    //check the result of reading the next byte, failing when the connection was closed, a server dropping
+    //an idle connection maybe resetting it rather than closing it
+    pub(crate) fn check_read(&self, read: io::Result<usize>) -> Result<(), HttpError> {
+        let read = match read {
+            Err(err) if self.bytes.is_empty() && is_reset(&err) => 0,
+            read => read?,
+        };
+        if read == 0 {
+            return Err(HttpError::Parse(match self.bytes.is_empty() {
+                true => CLOSED_BEFORE_RESPONSE,
+                false => "connection closed before the end of the response head",
+            }));
+        }
+        Ok(())
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+//read a response head byte by byte, so that nothing after it is consumed, failing once it exceeds `limits`
+pub(crate) fn read_head<S: Read>(stream: &mut S, limits: HeaderLimits) -> Result<Vec<u8>, HttpError> {
+    let mut head = Head::new(limits);
+    let mut byte = [0u8; 1];
+    loop {
+        head.check_read(stream.read(&mut byte))?;
+        if head.push(byte[0])? {
+            return Ok(head.into_bytes());
+        }
+    }
+}
+
+//return the status code of a response head
+pub(crate) fn head_status(head: &[u8]) -> Option<u16> {
+    let line = head.split(|&b| b == b'\r').next()?;
+    let code = line.split(|&b| b == b' ').nth(1)?;
+    std::str::from_utf8(code).ok()?.parse().ok()
+}
+
+//return if a status is an interim response followed by the final one, `101 Switching Protocols` ends the exchange
+pub(crate) fn is_interim(status: u16) -> bool {
+    (100..200).contains(&status) && status != 101
+}
+
+//return if the server keeps the connection open after the response with this head(RFC 7230 section 6.3)
+pub(crate) fn keeps_alive(head: &[u8]) -> bool {
+    match response::parse_head(head) {
+        Ok((Version::Http11, headers)) => !headers.get_all("Connection").flat_map(params::split_list).any(|option| option.eq_ignore_ascii_case("close")),
+        _ => false,
+    }
+}
+
+//remove the interim responses preceding the final one, returning their heads
+pub(crate) fn take_interim(res: &mut Vec<u8>) -> Vec<Vec<u8>> {
+    let mut interim = Vec::new();
+    while head_status(res).map(is_interim).unwrap_or(false) {
+        let end = match res.windows(4).position(|w| w == b"\r\n\r\n") {
+            Some(end) => end + 4,
+            None => break,
+        };
+        interim.push(res.drain(..end).collect());
+    }
+    interim
+}
+
+//upper bound of the body buffer preallocated from an announced Content-Length
+const MAX_PREALLOCATION: u64 = 16 * 1024 * 1024;
+
+//how the end of a response body is found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Framing {
+    //the announced Content-Length
+    Length(u64),
+    //the last chunk of a chunked body
+    Chunked,
+    //the connection being closed
+    Close,
+}
+
+impl Framing {
+    //return the announced body length, if the body is framed by it
+    pub(crate) fn length(self) -> Option<u64> {
+        match self {
+            Framing::Length(length) => Some(length),
+            _ => None,
+        }
+    }
+}
+
+//return how the body following a response head is framed(RFC 9112 section 6.3), the body of a response
+//without `Content-Length` nor chunked coding lasting until the server closes the connection
+pub(crate) fn framing(head: &[u8], head_request: bool) -> Result<Framing, HttpError> {
+    if head_request || matches!(head_status(head), Some(100..=199 | 204 | 304)) {
+        return Ok(Framing::Length(0));
+    }
+    let headers = match response::parse_head(head) {
+        Ok((_, headers)) => headers,
+        Err(_) => return Ok(Framing::Close),
+    };
+    if headers.contains_key("Transfer-Encoding") {
+        return match is_chunked(&headers) {
+            true => Ok(Framing::Chunked),
+            false => Ok(Framing::Close),
+        };
+    }
+    if !headers.contains_key("Content-Length") {
+        return Ok(Framing::Close);
+    }
+    //repeated lengths are only accepted when they agree, anything else could split the response elsewhere
+    let parse = |length: &str| Some(length).filter(|length| length.bytes().all(|b| b.is_ascii_digit())).and_then(|length| length.parse::<u64>().ok());
+    let mut lengths = headers.get_all("Content-Length").flat_map(params::split_list).map(parse);
+    match lengths.next() {
+        Some(Some(length)) if lengths.all(|other| other == Some(length)) => Ok(Framing::Length(length)),
+        _ => Err(HttpError::Parse("invalid Content-Length")),
+    }
+}
+
+//return if chunked is the last transfer coding applied to a body
+pub(crate) fn is_chunked(headers: &HeaderMap) -> bool {
+    headers
+        .get_all("Transfer-Encoding")
+        .flat_map(params::split_list)
+        .last()
+        .map(|coding| coding.eq_ignore_ascii_case("chunked"))
+        .unwrap_or(false)
+}
+
+//fail early when the announced body size is over the limit
+pub(crate) fn check_size(length: Option<u64>, limit: Option<u64>) -> Result<(), HttpError> {
+    match (length, limit) {
+        (Some(length), Some(limit)) if length > limit => Err(HttpError::TooLarge { limit }),
+        _ => Ok(()),
+    }
+}
+
+//return how many body bytes to preallocate for an announced Content-Length
+pub(crate) fn preallocation(length: Option<u64>, limit: Option<u64>) -> usize {
+    let length = length.unwrap_or(0).min(limit.unwrap_or(u64::MAX)).min(MAX_PREALLOCATION);
+    length as usize
+}
+
+//fail when the connection was closed before the announced body length was received
+pub(crate) fn check_complete(length: Option<u64>, received: u64) -> Result<(), HttpError> {
+    match length {
+        Some(expected) if received < expected => Err(HttpError::IncompleteBody { expected, received }),
+        _ => Ok(()),
+    }
+}
+
+//write a body to `out` as it arrives according to its framing, leaving the stream right after it,
+//and return its trailer fields, failing once more than `limit` bytes were received
+pub(crate) fn copy_body<S: BufRead, W: Write + ?Sized>(stream: &mut S, framing: Framing, limit: Option<u64>, out: &mut W) -> Result<HeaderMap, HttpError> {
+    let mut trailers = HeaderMap::new();
+    let max = limit.unwrap_or(u64::MAX);
+    let received = match framing {
+        // the size was checked against the limit before
+        Framing::Length(length) => io::copy(&mut stream.take(length), out)?,
+        Framing::Close => io::copy(&mut stream.take(max.saturating_add(1)), out)?,
+        Framing::Chunked => {
+            let mut decoder = chunked::Decoder::new();
+            let (mut chunk, mut received) = (Vec::new(), 0);
+            while !decoder.is_done() && received <= max {
+                let input = stream.fill_buf()?;
+                if input.is_empty() {
+                    return Err(HttpError::Parse("truncated chunked body"));
+                }
+                chunk.clear();
+                let used = decoder.decode(input, &mut chunk)?;
+                stream.consume(used);
+                out.write_all(&chunk)?;
+                received += chunk.len() as u64;
+            }
+            trailers = decoder.into_trailers();
+            received
+        }
+    };
+
+    match limit {
+        Some(limit) if received > limit => Err(HttpError::TooLarge { limit }),
+        _ => Ok(trailers),
+    }
+}
+
+//return if the connection is left ready for another request once a body of `received` bytes was read
+pub(crate) fn is_reusable(head: &[u8], framing: Framing, received: u64) -> bool {
+    framing != Framing::Close && framing.length().is_none_or(|length| length == received) && keeps_alive(head)
+}
+
+//read and discard a body of at most `cap` bytes, returning if the connection is left at its end, ready for
+//another request
+pub(crate) fn drain<S: BufRead>(stream: &mut S, framing: Framing, cap: u64) -> bool {
+    match framing {
+        Framing::Length(length) if length <= cap => io::copy(&mut stream.take(length), &mut io::sink()).is_ok_and(|copied| copied == length),
+        Framing::Chunked => copy_body(stream, framing, Some(cap), &mut io::sink()).is_ok(),
+        _ => false,
+    }
+}
+
+//writer writing to a body and copying what it writes to another writer
+pub(crate) struct Split<'a, B: ?Sized, W: ?Sized> {
+    pub(crate) body: &'a mut B,
+    pub(crate) copy: &'a mut W,
+}
+
+impl<B: Write + ?Sized, W: Write + ?Sized> Write for Split<'_, B, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.copy.write_all(buf)?;
+        self.body.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.copy.flush()?;
+        self.body.flush()
+    }
+}
+
+//writer receiving a copy of the response bodies of a request as they arrive
+#[derive(Clone)]
+pub(crate) struct Tee(pub(crate) Arc<Mutex<Box<dyn Write + Send>>>);
+
+//the async client locks the tee write by write, as it can't hold the lock while waiting for the connection
+#[cfg(feature = "tokio")]
+impl Write for Tee {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner()).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap_or_else(|err| err.into_inner()).flush()
+    }
+}
+
+impl fmt::Debug for Tee {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Tee")
+    }
+}
+
+//reader copying what it reads to a tee
+pub(crate) struct TeeReader<R> {
+    pub(crate) reader: R,
+    pub(crate) tee: Tee,
+}
+
+impl<R: Read> Read for TeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.reader.read(buf)?;
+        self.tee.0.lock().unwrap_or_else(|err| err.into_inner()).write_all(&buf[..n])?;
+        Ok(n)
+    }
+}
+
+//reader of a body as it arrives, according to its framing
+pub(crate) struct BodyReader<S> {
+    stream: S,
+    state: BodyState,
+}
+
+enum BodyState {
+    Length(u64),
+    Close,
+    //decoded data not read yet, from its offset
+    Chunked(chunked::Decoder, Vec<u8>, usize),
+}
+
+impl<S: BufRead> BodyReader<S> {
+    pub(crate) fn new(stream: S, framing: Framing) -> Self {
+        let state = match framing {
+            Framing::Length(length) => BodyState::Length(length),
+            Framing::Close => BodyState::Close,
+            Framing::Chunked => BodyState::Chunked(chunked::Decoder::new(), Vec::new(), 0),
+        };
+        Self { stream, state }
+    }
+}
+
+impl<S: BufRead> Read for BodyReader<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let stream = &mut self.stream;
+        match self.state {
+            BodyState::Length(0) => Ok(0),
+            BodyState::Length(ref mut rest) => {
+                let max = (*rest).min(buf.len() as u64) as usize;
+                let n = stream.read(&mut buf[..max])?;
+                if n == 0 {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "connection closed before the end of the body"));
+                }
+                *rest -= n as u64;
+                Ok(n)
+            }
+            BodyState::Close => stream.read(buf),
+            BodyState::Chunked(ref mut decoder, ref mut pending, ref mut offset) => loop {
+                if *offset < pending.len() {
+                    let n = (pending.len() - *offset).min(buf.len());
+                    buf[..n].copy_from_slice(&pending[*offset..*offset + n]);
+                    *offset += n;
+                    return Ok(n);
+                }
+                pending.clear();
+                *offset = 0;
+                if decoder.is_done() {
+                    return Ok(0);
+                }
+                let input = stream.fill_buf()?;
+                if input.is_empty() {
+                    return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "truncated chunked body"));
+                }
+                let used = decoder.decode(input, pending).map_err(io::Error::other)?;
+                stream.consume(used);
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_body<S: BufRead>(stream: &mut S, framing: Framing, limit: Option<u64>) -> Result<(Vec<u8>, HeaderMap), HttpError> {
+        let mut body = Vec::new();
+        let trailers = copy_body(stream, framing, limit, &mut body)?;
+        Ok((body, trailers))
+    }
+
+    #[test]
+    fn body_reader() {
+        let read = |data: &'static [u8], framing| {
+            let mut body = String::new();
+            BodyReader::new(data, framing).read_to_string(&mut body).map(|_| body)
+        };
+        assert_eq!(read(b"hello world", Framing::Length(5)).unwrap(), "hello");
+        assert_eq!(read(b"hello", Framing::Close).unwrap(), "hello");
+        assert_eq!(read(b"5\r\nhello\r\n6\r\n world\r\n0\r\n\r\nnext", Framing::Chunked).unwrap(), "hello world");
+        assert!(read(b"hel", Framing::Length(5)).is_err());
+        assert!(read(b"5\r\nhello\r\n", Framing::Chunked).is_err());
+    }
+
+    #[test]
+    fn drained_bodies() {
+        let mut data: &[u8] = b"movedHTTP/1.1 200 OK";
+        assert!(drain(&mut data, Framing::Length(5), 8));
+        assert_eq!(data, b"HTTP/1.1 200 OK");
+        let mut chunked: &[u8] = b"5\r\nmoved\r\n0\r\n\r\nnext";
+        assert!(drain(&mut chunked, Framing::Chunked, 8));
+        assert_eq!(chunked, b"next");
+
+        assert!(!drain(&mut &b"moved"[..], Framing::Length(5), 4));
+        assert!(!drain(&mut &b"mov"[..], Framing::Length(5), 8));
+        assert!(!drain(&mut &b"5\r\nmoved\r\n5\r\nmoved\r\n0\r\n\r\n"[..], Framing::Chunked, 8));
+        assert!(!drain(&mut &b"moved"[..], Framing::Close, 8));
+    }
+
+    #[test]
+    fn head_is_read_alone() {
+        let mut data: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 200 OK\r\n\r\n";
+        let head = read_head(&mut data, HeaderLimits::default()).unwrap();
+        assert_eq!(head, b"HTTP/1.1 100 Continue\r\n\r\n");
+        assert_eq!(head_status(&head), Some(100));
+        assert_eq!(data, b"HTTP/1.1 200 OK\r\n\r\n");
+
+        let mut truncated: &[u8] = b"HTTP/1.1 200 OK\r\n";
+        assert!(read_head(&mut truncated, HeaderLimits::default()).is_err());
+        assert_eq!(head_status(b"garbage"), None);
+    }
+
+    #[test]
+    fn reset_before_response() {
+        //a connection reset after `sent` bytes of the head
+        struct Reset(&'static [u8]);
+        impl Read for Reset {
+            fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+                match self.0.read(buf)? {
+                    0 => Err(io::ErrorKind::ConnectionReset.into()),
+                    n => Ok(n),
+                }
+            }
+        }
+
+        let err = read_head(&mut Reset(b""), HeaderLimits::default()).unwrap_err();
+        assert!(is_stale(&err.context(ErrorKind::Read, "http://example.com/")));
+        let err = read_head(&mut Reset(b"HTTP/1.1 200"), HeaderLimits::default()).unwrap_err();
+        assert!(matches!(err, HttpError::IO(_)));
+        assert!(!is_stale(&err.context(ErrorKind::Read, "http://example.com/")));
+    }
+
+    #[test]
+    fn head_limits() {
+        let limits = HeaderLimits { max_headers: 2, max_bytes: 64 };
+        let mut two: &[u8] = b"HTTP/1.1 200 OK\r\nA: 1\r\nB: 2\r\n\r\n";
+        assert!(read_head(&mut two, limits).is_ok());
+
+        let mut three: &[u8] = b"HTTP/1.1 200 OK\r\nA: 1\r\nB: 2\r\nC: 3\r\n";
+        assert!(matches!(read_head(&mut three, limits), Err(HttpError::TooManyHeaders { limit: 2 })));
+        //the error comes as soon as the limit is crossed, before the rest of the head is read
+        let mut endless = std::io::repeat(b'a');
+        assert!(matches!(read_head(&mut endless, limits), Err(HttpError::HeadersTooLarge { limit: 64 })));
+    }
+
+    #[test]
+    fn address_fallback() {
+        let v4 = SocketAddr::from(([127, 0, 0, 1], 80));
+        let v6 = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 80));
+        assert_eq!(interleave(&[v4, v4, v6]), [v6, v4, v4]);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let closed = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap();
+        let options = SocketOptions::default();
+        let stream = connect(&[closed, listener.local_addr().unwrap()], &options, Duration::from_secs(5)).unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), listener.local_addr().unwrap());
+        assert!(connect(&[closed], &options, Duration::from_secs(5)).is_err());
+    }
+
+    #[test]
+    fn local_address() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut options = SocketOptions { local_address: Some(IpAddr::from([127, 0, 0, 1])), ..Default::default() };
+        let stream = connect(&[listener.local_addr().unwrap()], &options, Duration::from_secs(5)).unwrap();
+        assert_eq!(stream.local_addr().unwrap().ip(), IpAddr::from([127, 0, 0, 1]));
+
+        options.local_address = Some(IpAddr::from([0, 0, 0, 0, 0, 0, 0, 1]));
+        assert!(socket(listener.local_addr().unwrap(), &options).is_err());
+    }
+
+    #[test]
+    fn socket_options() {
+        let options = SocketOptions { nodelay: true, keepalive: Some(Duration::from_secs(30)), ..Default::default() };
+        let socket = socket(SocketAddr::from(([127, 0, 0, 1], 80)), &options).unwrap();
+        assert!(socket.tcp_nodelay().unwrap());
+        assert!(socket.keepalive().unwrap());
+    }
+
+    #[test]
+    fn buffers_are_reused() {
+        let pool = BufferPool::default();
+        let mut buffer = pool.take();
+        buffer.push_str("GET / HTTP/1.1\r\n\r\n");
+        let capacity = buffer.capacity();
+        pool.put(buffer);
+
+        let buffer = pool.take();
+        assert!(buffer.is_empty());
+        assert_eq!(buffer.capacity(), capacity);
+        assert_eq!(pool.take().capacity(), 0);
+
+        pool.put(String::with_capacity(MAX_POOLED_CAPACITY + 1));
+        assert_eq!(pool.take().capacity(), 0);
+    }
+
+    #[test]
+    fn connection_limits() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stream = || Stream::Plain(TcpStream::connect(addr).unwrap());
+        let limits = PoolLimits { max_connections: Some(2), max_idle: Some(1), wait: Some(Duration::from_millis(200)), ..PoolLimits::default() };
+        let pool = Arc::new(ConnectionPool::new(limits));
+
+        let first = pool.acquire("a").unwrap();
+        let second = pool.acquire("a").unwrap();
+        assert!(pool.acquire("a").err().unwrap().is_timeout());
+        drop(pool.acquire("b").unwrap());
+
+        //a parked connection stays counted, until it is closed to make room
+        pool.put("a".to_owned(), stream(), first);
+        pool.put("a".to_owned(), stream(), second);
+        assert_eq!(pool.shared.lock().idle["a"].len(), 1);
+        let third = pool.acquire("a").unwrap();
+        let _fourth = pool.acquire("a").unwrap();
+        assert!(pool.take("a").is_none());
+
+        //waiters go on once a connection is closed
+        let waiter = thread::spawn({
+            let pool = pool.clone();
+            move || pool.acquire("a").is_ok()
+        });
+        drop(third);
+        assert!(waiter.join().unwrap());
+    }
+
+    #[test]
+    fn idle_connections() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let stream = || Stream::Plain(TcpStream::connect(addr).unwrap());
+        let limits = PoolLimits { idle_timeout: Some(Duration::from_millis(50)), health_check: true, ..PoolLimits::default() };
+        let pool = ConnectionPool::new(limits);
+
+        pool.put("a".to_owned(), stream(), pool.acquire("a").unwrap());
+        thread::sleep(Duration::from_millis(60));
+        assert!(pool.take("a").is_none());
+        assert!(pool.shared.lock().open.is_empty());
+
+        //the last parked connection is closed by the server while idle
+        pool.put("a".to_owned(), stream(), pool.acquire("a").unwrap());
+        pool.put("a".to_owned(), stream(), pool.acquire("a").unwrap());
+        let mut accepted: Vec<_> = (0..3).map(|_| listener.accept().unwrap().0).collect();
+        drop(accepted.pop());
+        thread::sleep(Duration::from_millis(10));
+        assert!(pool.take("a").is_some());
+        assert!(pool.take("a").is_none());
+    }
+
+    #[test]
+    fn size_limit() {
+        let head = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n";
+        assert_eq!(framing(head, false).unwrap(), Framing::Length(10));
+        assert!(check_size(Some(10), Some(10)).is_ok());
+        assert!(matches!(check_size(Some(11), Some(10)), Err(HttpError::TooLarge { limit: 10 })));
+        assert_eq!(preallocation(Some(10), None), 10);
+        assert_eq!(preallocation(Some(u64::MAX), None), MAX_PREALLOCATION as usize);
+
+        let (body, _) = read_body(&mut &b"0123456789"[..], Framing::Close, Some(10)).unwrap();
+        assert_eq!(body, b"0123456789");
+        assert!(read_body(&mut &b"0123456789A"[..], Framing::Close, Some(10)).is_err());
+        assert!(read_body(&mut &b"B\r\n0123456789A\r\n0\r\n\r\n"[..], Framing::Chunked, Some(10)).is_err());
+    }
+
+    #[test]
+    fn incomplete_body() {
+        let head = b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\n";
+        assert_eq!(framing(head, true).unwrap(), Framing::Length(0));
+        assert_eq!(framing(b"HTTP/1.1 304 Not Modified\r\nContent-Length: 10\r\n\r\n", false).unwrap(), Framing::Length(0));
+        assert_eq!(framing(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nContent-Length: 10\r\n\r\n", false).unwrap(), Framing::Chunked);
+        assert_eq!(framing(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: gzip\r\n\r\n", false).unwrap(), Framing::Close);
+        assert_eq!(framing(b"HTTP/1.1 200 OK\r\n\r\n", false).unwrap(), Framing::Close);
+        assert_eq!(framing(b"HTTP/1.0 200 OK\r\nContent-Length: 4, 4\r\nContent-Length: 4\r\n\r\n", false).unwrap(), Framing::Length(4));
+        for length in ["4, 5", "-1", "+4", "0x10", ""] {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", length);
+            assert!(matches!(framing(head.as_bytes(), false), Err(HttpError::Parse("invalid Content-Length"))), "{}", length);
+        }
+
+        assert!(check_complete(Some(10), 10).is_ok());
+        assert!(check_complete(None, 0).is_ok());
+        assert!(matches!(check_complete(Some(10), 4), Err(HttpError::IncompleteBody { expected: 10, received: 4 })));
+    }
+
+    #[test]
+    fn framed_bodies() {
+        // the stream is left right after the body, ready for the next response
+        let mut stream: &[u8] = b"helloHTTP/1.1";
+        let (body, _) = read_body(&mut stream, Framing::Length(5), None).unwrap();
+        assert_eq!((body.as_slice(), stream), (&b"hello"[..], &b"HTTP/1.1"[..]));
+
+        let mut stream: &[u8] = b"5\r\nhello\r\n0\r\nDigest: x\r\n\r\nHTTP/1.1";
+        let (body, trailers) = read_body(&mut stream, Framing::Chunked, None).unwrap();
+        assert_eq!((body.as_slice(), stream), (&b"hello"[..], &b"HTTP/1.1"[..]));
+        assert_eq!(trailers.get("digest"), Some("x"));
+        assert!(read_body(&mut &b"5\r\nhel"[..], Framing::Chunked, None).is_err());
+    }
+
+    #[test]
+    fn interim_responses() {
+        let mut res = b"HTTP/1.1 100 Continue\r\n\r\nHTTP/1.1 103 Early Hints\r\nLink: </a.css>\r\n\r\nHTTP/1.1 200 OK\r\n\r\nbody".to_vec();
+        let interim = take_interim(&mut res);
+        assert_eq!(interim.len(), 2);
+        assert_eq!(head_status(&interim[1]), Some(103));
+        assert_eq!(res, b"HTTP/1.1 200 OK\r\n\r\nbody");
+
+        let mut upgrade = b"HTTP/1.1 101 Switching Protocols\r\n\r\n".to_vec();
+        assert!(take_interim(&mut upgrade).is_empty());
+    }
+}
@@ -0,0 +1,555 @@
+use super::date::parse_http_date;
+use super::har::quote;
+use super::url::Url;
+
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cookie {
+    name: String,
+    value: String,
+    //lowercase, without a leading dot
+    domain: String,
+    host_only: bool,
+    path: String,
+    secure: bool,
+    http_only: bool,
+    //`None` for session cookies
+    expires: Option<SystemTime>,
+}
+
+impl Cookie {
+    //parse a `Set-Cookie` header value received from `url`(RFC 6265 section 5.2)
+    fn parse(url: &Url, value: &str) -> Option<Cookie> {
+        let mut parts = value.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let (name, value) = (name.trim(), value.trim());
+        if name.is_empty() {
+            return None;
+        }
+
+        let host = url.host.to_ascii_lowercase();
+        let mut cookie = Cookie {
+            name: name.to_owned(),
+            value: value.to_owned(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(&url.path),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+
+        let mut max_age = None;
+        for attribute in parts {
+            let (key, value) = match attribute.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (attribute.trim(), ""),
+            };
+            match key.to_ascii_lowercase().as_str() {
+                "expires" => {
+                    if let Some(expires) = parse_http_date(value) {
+                        cookie.expires = Some(expires);
+                    }
+                }
+                "max-age" => {
+                    if let Ok(seconds) = value.parse::<i64>() {
+                        max_age = Some(seconds);
+                    }
+                }
+                "domain" if !value.is_empty() => {
+                    let domain = value.trim_start_matches('.').to_ascii_lowercase();
+                    // a host can only set cookies for itself and its parent domains
+                    if !domain_matches(&host, &domain) {
+                        return None;
+                    }
+                    // nor for a public suffix, unless it is the host itself(RFC 6265 section 5.3)
+                    if is_public_suffix(&domain) && domain != host {
+                        return None;
+                    }
+                    cookie.host_only = host.parse::<IpAddr>().is_ok() || is_public_suffix(&domain);
+                    cookie.domain = domain;
+                }
+                "path" if value.starts_with('/') => cookie.path = value.to_owned(),
+                "secure" => cookie.secure = true,
+                "httponly" => cookie.http_only = true,
+                _ => {}
+            }
+        }
+
+        // Max-Age takes precedence over Expires
+        match max_age {
+            Some(seconds) if seconds <= 0 => cookie.expires = Some(UNIX_EPOCH),
+            Some(seconds) => cookie.expires = Some(SystemTime::now() + Duration::from_secs(seconds as u64)),
+            None => {}
+        }
+        Some(cookie)
+    }
+
+    fn is_expired(&self, now: SystemTime) -> bool {
+        self.expires.map(|expires| expires <= now).unwrap_or(false)
+    }
+
+    //return if the cookie is sent with requests to `url`
+    fn matches(&self, url: &Url, now: SystemTime) -> bool {
+        let host = url.host.to_ascii_lowercase();
+        let domain = match self.host_only {
+            true => host == self.domain,
+            false => domain_matches(&host, &self.domain),
+        };
+        domain && path_matches(&url.path, &self.path) && (!self.secure || matches!(url.scheme.as_str(), "https" | "wss")) && !self.is_expired(now)
+    }
+
+    fn expires_secs(&self) -> u64 {
+        self.expires.and_then(|expires| expires.duration_since(UNIX_EPOCH).ok()).map(|expires| expires.as_secs()).unwrap_or(0)
+    }
+}
+
+//return if `host` is `domain` or one of its subdomains(RFC 6265 section 5.1.3)
+fn domain_matches(host: &str, domain: &str) -> bool {
+    if host == domain {
+        return true;
+    }
+    host.parse::<IpAddr>().is_err() && host.strip_suffix(domain).map(|rest| rest.ends_with('.')).unwrap_or(false)
+}
+
+#[cfg(feature = "psl")]
+use super::psl::is_public_suffix;
+
+//without the public suffix list, any domain can be set by its subdomains
+#[cfg(not(feature = "psl"))]
+fn is_public_suffix(_domain: &str) -> bool {
+    false
+}
+
+//return the path of the request up to its last segment(RFC 6265 section 5.1.4)
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_owned(),
+        Some(i) => path[..i].to_owned(),
+    }
+}
+
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    match path.strip_prefix(cookie_path) {
+        Some(rest) => cookie_path.ends_with('/') || rest.is_empty() || rest.starts_with('/'),
+        None => false,
+    }
+}
+
+///store of the cookies received in `Set-Cookie` headers, sent back in the `Cookie` header of later requests.
+///
+///The jar is shared between its clones, so a Client can update it while the
+///application keeps a handle to [`save`](CookieJar::save) it later, like `curl -b/-c` do.
+#[derive(Debug, Clone, Default)]
+pub struct CookieJar {
+    cookies: Arc<Mutex<Vec<Cookie>>>,
+}
+
+impl CookieJar {
+    ///return an empty CookieJar
+    /// # Example
+    /// ```
+    /// use smolhttp::{Client, CookieJar};
+    ///
+    /// let jar = CookieJar::new();
+    /// let client = Client::builder().cookie_store(jar.clone()).build().unwrap();
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///load a jar from a Netscape `cookies.txt` file, or a JSON file written by [`save`](CookieJar::save)
+    ///
+    ///The format is guessed from the content of the file.
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let data = fs::read_to_string(path)?;
+        let cookies = match data.trim_start().starts_with('[') {
+            true => parse_json(&data).ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "invalid JSON cookie file"))?,
+            false => parse_netscape(&data),
+        };
+        let now = SystemTime::now();
+        let cookies = cookies.into_iter().filter(|cookie| !cookie.is_expired(now)).collect();
+        Ok(Self { cookies: Arc::new(Mutex::new(cookies)) })
+    }
+
+    ///write the unexpired cookies, session ones included, to `path`
+    ///
+    ///Paths ending in `.json` get a JSON array, other ones the Netscape `cookies.txt` format read by curl and wget.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::{Client, CookieJar};
+    ///
+    /// let jar = CookieJar::load("cookies.txt").unwrap_or_default();
+    /// let client = Client::builder().cookie_store(jar.clone()).build().unwrap();
+    /// client.get("https://example.com/login").unwrap().send().unwrap();
+    /// jar.save("cookies.txt").unwrap();
+    /// ```
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let now = SystemTime::now();
+        let cookies = self.cookies.lock().unwrap();
+        let cookies = cookies.iter().filter(|cookie| !cookie.is_expired(now));
+        let data = match path.extension().map(|ext| ext.eq_ignore_ascii_case("json")).unwrap_or(false) {
+            true => write_json(cookies),
+            false => write_netscape(cookies),
+        };
+        fs::write(path, data)
+    }
+
+    ///record the `Set-Cookie` header value received from `url`
+    pub fn record(&self, url: &Url, value: &str) {
+        let cookie = match Cookie::parse(url, value) {
+            Some(cookie) => cookie,
+            None => return,
+        };
+
+        let mut cookies = self.cookies.lock().unwrap();
+        cookies.retain(|old| (&old.name, &old.domain, &old.path) != (&cookie.name, &cookie.domain, &cookie.path));
+        if !cookie.is_expired(SystemTime::now()) {
+            cookies.push(cookie);
+        }
+    }
+
+    ///return the value of the `Cookie` header sent with requests to `url`, if any cookie matches it
+    /// # Example
+    /// ```
+    /// use smolhttp::{CookieJar, Url};
+    ///
+    /// let jar = CookieJar::new();
+    /// jar.record(&Url::parse("https://example.com/login").unwrap(), "id=42; Domain=example.com; Path=/");
+    /// assert_eq!(jar.header(&Url::parse("https://www.example.com/").unwrap()).as_deref(), Some("id=42"));
+    /// assert_eq!(jar.header(&Url::parse("https://example.org/").unwrap()), None);
+    /// ```
+    pub fn header(&self, url: &Url) -> Option<String> {
+        let now = SystemTime::now();
+        let cookies = self.cookies.lock().unwrap();
+        let mut matching: Vec<&Cookie> = cookies.iter().filter(|cookie| cookie.matches(url, now)).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        // cookies with longer paths are listed first(RFC 6265 section 5.4)
+        matching.sort_by_key(|cookie| std::cmp::Reverse(cookie.path.len()));
+        let pairs: Vec<String> = matching.iter().map(|cookie| format!("{}={}", cookie.name, cookie.value)).collect();
+        Some(pairs.join("; "))
+    }
+
+    ///return the number of cookies in the jar
+    pub fn len(&self) -> usize {
+        self.cookies.lock().unwrap().len()
+    }
+
+    ///return if the jar has no cookies
+    pub fn is_empty(&self) -> bool {
+        self.cookies.lock().unwrap().is_empty()
+    }
+
+    ///remove every cookie
+    pub fn clear(&self) {
+        self.cookies.lock().unwrap().clear();
+    }
+}
+
+//write the cookies in the Netscape format, one tab separated line each
+fn write_netscape<'a>(cookies: impl Iterator<Item = &'a Cookie>) -> String {
+    let mut out = String::from("# Netscape HTTP Cookie File\n");
+    let flag = |set: bool| if set { "TRUE" } else { "FALSE" };
+    for cookie in cookies {
+        out.push_str(&format!(
+            "{}{}{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+            if cookie.http_only { "#HttpOnly_" } else { "" },
+            if cookie.host_only { "" } else { "." },
+            cookie.domain,
+            flag(!cookie.host_only),
+            cookie.path,
+            flag(cookie.secure),
+            cookie.expires_secs(),
+            cookie.name,
+            cookie.value,
+        ));
+    }
+    out
+}
+
+fn parse_netscape(data: &str) -> Vec<Cookie> {
+    let mut cookies = Vec::new();
+    for line in data.lines() {
+        // curl marks HttpOnly cookies with a prefix making the line look like a comment
+        let (line, http_only) = match line.strip_prefix("#HttpOnly_") {
+            Some(line) => (line, true),
+            None => (line, false),
+        };
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() != 7 {
+            continue;
+        }
+        let expires = match fields[4].parse::<u64>() {
+            Ok(0) => None,
+            Ok(secs) => Some(UNIX_EPOCH + Duration::from_secs(secs)),
+            Err(_) => continue,
+        };
+        cookies.push(Cookie {
+            name: fields[5].to_owned(),
+            value: fields[6].to_owned(),
+            domain: fields[0].trim_start_matches('.').to_ascii_lowercase(),
+            host_only: fields[1] != "TRUE",
+            path: fields[2].to_owned(),
+            secure: fields[3] == "TRUE",
+            http_only,
+            expires,
+        });
+    }
+    cookies
+}
+
+//write the cookies as a JSON array of objects, `expires` being null for session cookies
+fn write_json<'a>(cookies: impl Iterator<Item = &'a Cookie>) -> String {
+    let cookies: Vec<String> = cookies
+        .map(|cookie| {
+            format!(
+                r#"{{"name":{},"value":{},"domain":{},"hostOnly":{},"path":{},"secure":{},"httpOnly":{},"expires":{}}}"#,
+                quote(&cookie.name),
+                quote(&cookie.value),
+                quote(&cookie.domain),
+                cookie.host_only,
+                quote(&cookie.path),
+                cookie.secure,
+                cookie.http_only,
+                cookie.expires.map(|_| cookie.expires_secs().to_string()).unwrap_or_else(|| "null".to_owned()),
+            )
+        })
+        .collect();
+    format!("[{}]\n", cookies.join(",\n"))
+}
+
+//JSON value of a cookie field, the JSON files only holding an array of flat objects
+#[derive(Debug, PartialEq)]
+enum Value {
+    String(String),
+    Number(u64),
+    Bool(bool),
+    Null,
+}
+
+fn parse_json(data: &str) -> Option<Vec<Cookie>> {
+    let mut parser = Parser { rest: data };
+    let mut cookies = Vec::new();
+    parser.expect('[')?;
+    if parser.eat(']') {
+        return Some(cookies);
+    }
+    loop {
+        let mut cookie = Cookie {
+            name: String::new(),
+            value: String::new(),
+            domain: String::new(),
+            host_only: true,
+            path: "/".to_owned(),
+            secure: false,
+            http_only: false,
+            expires: None,
+        };
+        parser.expect('{')?;
+        if !parser.eat('}') {
+            loop {
+                let key = parser.string()?;
+                parser.expect(':')?;
+                match (key.as_str(), parser.value()?) {
+                    ("name", Value::String(name)) => cookie.name = name,
+                    ("value", Value::String(value)) => cookie.value = value,
+                    ("domain", Value::String(domain)) => cookie.domain = domain.trim_start_matches('.').to_ascii_lowercase(),
+                    ("hostOnly", Value::Bool(host_only)) => cookie.host_only = host_only,
+                    ("path", Value::String(path)) => cookie.path = path,
+                    ("secure", Value::Bool(secure)) => cookie.secure = secure,
+                    ("httpOnly", Value::Bool(http_only)) => cookie.http_only = http_only,
+                    ("expires", Value::Number(secs)) => cookie.expires = Some(UNIX_EPOCH + Duration::from_secs(secs)),
+                    _ => {}
+                }
+                if !parser.eat(',') {
+                    break;
+                }
+            }
+            parser.expect('}')?;
+        }
+        if !cookie.name.is_empty() && !cookie.domain.is_empty() {
+            cookies.push(cookie);
+        }
+        if !parser.eat(',') {
+            break;
+        }
+    }
+    parser.expect(']')?;
+    Some(cookies)
+}
+
+struct Parser<'a> {
+    rest: &'a str,
+}
+
+impl Parser<'_> {
+    //skip the whitespace and consume `c` if it comes next
+    fn eat(&mut self, c: char) -> bool {
+        self.rest = self.rest.trim_start();
+        match self.rest.strip_prefix(c) {
+            Some(rest) => {
+                self.rest = rest;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Option<()> {
+        self.eat(c).then_some(())
+    }
+
+    fn string(&mut self) -> Option<String> {
+        self.expect('"')?;
+        let mut out = String::new();
+        let mut chars = self.rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.rest = &self.rest[i + 1..];
+                    return Some(out);
+                }
+                '\\' => match chars.next()?.1 {
+                    'n' => out.push('\n'),
+                    'r' => out.push('\r'),
+                    't' => out.push('\t'),
+                    'u' => {
+                        let hex: String = (0..4).filter_map(|_| chars.next().map(|(_, c)| c)).collect();
+                        out.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                    }
+                    c => out.push(c),
+                },
+                c => out.push(c),
+            }
+        }
+        None
+    }
+
+    fn value(&mut self) -> Option<Value> {
+        self.rest = self.rest.trim_start();
+        if self.rest.starts_with('"') {
+            return self.string().map(Value::String);
+        }
+        let end = self.rest.find(|c: char| !c.is_ascii_alphanumeric()).unwrap_or(self.rest.len());
+        let (word, rest) = self.rest.split_at(end);
+        self.rest = rest;
+        match word {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            "null" => Some(Value::Null),
+            word => word.parse().ok().map(Value::Number),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::ClientBuilder;
+    use std::env;
+
+    fn url(url: &str) -> Url {
+        Url::parse(url).unwrap()
+    }
+
+    #[test]
+    fn record_and_match() {
+        let jar = CookieJar::new();
+        let login = url("https://example.com/account/login");
+        jar.record(&login, "session=abc; Secure; HttpOnly");
+        jar.record(&login, "theme=dark; Domain=.Example.com; Path=/");
+        jar.record(&login, "tracker=1; Domain=example.org");
+        jar.record(&login, "gone=1; Max-Age=0");
+
+        assert_eq!(jar.len(), 2);
+        assert_eq!(jar.header(&url("https://example.com/account/settings")).as_deref(), Some("session=abc; theme=dark"));
+        assert_eq!(jar.header(&url("http://example.com/account/settings")).as_deref(), Some("theme=dark"));
+        assert_eq!(jar.header(&url("https://www.example.com/account")).as_deref(), Some("theme=dark"));
+        assert_eq!(jar.header(&url("https://example.com/accounts")).as_deref(), Some("theme=dark"));
+
+        jar.record(&login, "theme=light; Domain=example.com; Path=/");
+        assert_eq!(jar.header(&url("https://example.com/")).as_deref(), Some("theme=light"));
+        jar.record(&login, "theme=; Domain=example.com; Path=/; Expires=Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(jar.header(&url("https://example.com/")), None);
+    }
+
+    #[cfg(feature = "psl")]
+    #[test]
+    fn public_suffix_domains() {
+        let jar = CookieJar::new();
+        jar.record(&url("https://evil.github.io/"), "a=1; Domain=github.io");
+        jar.record(&url("https://example.co.uk/"), "b=2; Domain=co.uk");
+        jar.record(&url("https://a.eu.org/"), "e=5; Domain=eu.org");
+        assert!(jar.is_empty());
+
+        jar.record(&url("https://evil.github.io/"), "c=3; Domain=evil.github.io");
+        jar.record(&url("https://github.io/"), "d=4; Domain=github.io");
+        assert_eq!(jar.header(&url("https://evil.github.io/")).as_deref(), Some("c=3"));
+        assert_eq!(jar.header(&url("https://github.io/")).as_deref(), Some("d=4"));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let jar = CookieJar::new();
+        let login = url("https://example.com/login");
+        jar.record(&login, "session=a\"b; HttpOnly; Secure");
+        jar.record(&login, "theme=dark; Domain=example.com; Max-Age=3600");
+
+        for ext in ["txt", "json"] {
+            let path = env::temp_dir().join(format!("smolhttp-cookies-{}.{}", std::process::id(), ext));
+            jar.save(&path).unwrap();
+            let loaded = CookieJar::load(&path).unwrap();
+            let _ = fs::remove_file(&path);
+
+            let mut saved = jar.cookies.lock().unwrap().clone();
+            let mut loaded = loaded.cookies.lock().unwrap().clone();
+            // the files keep whole seconds
+            for cookie in saved.iter_mut().chain(loaded.iter_mut()) {
+                cookie.expires = cookie.expires.map(|_| UNIX_EPOCH + Duration::from_secs(cookie.expires_secs()));
+            }
+            saved.sort_by(|a, b| a.name.cmp(&b.name));
+            loaded.sort_by(|a, b| a.name.cmp(&b.name));
+            assert_eq!(saved, loaded, "{}", ext);
+        }
+    }
+
+    #[test]
+    fn curl_cookie_file() {
+        let data = "# Netscape HTTP Cookie File\n\n.example.com\tTRUE\t/\tFALSE\t0\ta\t1\n#HttpOnly_example.com\tFALSE\t/app\tTRUE\t4102444800\tb\t2\nexample.com\tFALSE\t/\tFALSE\t1\told\t3\n";
+        let cookies = parse_netscape(data);
+        assert_eq!(cookies.len(), 3);
+        assert!(!cookies[0].host_only && cookies[0].expires.is_none());
+        assert!(cookies[1].host_only && cookies[1].http_only && cookies[1].secure);
+        assert!(cookies[2].is_expired(SystemTime::now()));
+    }
+
+    #[test]
+    fn client_cookies() {
+        let mock = MockTransport::new();
+        mock.respond_raw("POST", "https://example.com/login", b"HTTP/1.1 303 See Other\r\nSet-Cookie: session=abc; Path=/; Secure\r\nLocation: /home\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond("GET", "*", 200, "");
+        let jar = CookieJar::new();
+        let client = ClientBuilder::new().connector(mock.clone()).redirects(5).cookie_store(jar.clone()).build().unwrap();
+
+        client.post("https://example.com/login").unwrap().send().unwrap();
+        client.get("http://example.com/home").unwrap().send().unwrap();
+        client.get("https://example.com/home").unwrap().header("Cookie", "mine=1").unwrap().send().unwrap();
+        let requests = mock.requests();
+        let cookies: Vec<Option<&str>> = requests.iter().map(|request| request.headers().get("Cookie")).collect();
+        assert_eq!(cookies, [None, Some("session=abc"), None, Some("mine=1")]);
+        assert_eq!(jar.len(), 1);
+    }
+}
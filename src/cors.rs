@@ -0,0 +1,166 @@
+use super::params;
+use super::Client;
+use super::HttpError;
+use super::Method;
+use super::Response;
+use super::StatusCode;
+
+use std::time::Duration;
+
+///what a server lets a cross-origin request do, from its answer to a CORS preflight, returned by
+///[`Client::preflight`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Preflight {
+    status: StatusCode,
+    origin: String,
+    method: Method,
+    headers: Vec<String>,
+    allow_origin: Option<String>,
+    allow_methods: Vec<Method>,
+    allow_headers: Vec<String>,
+    allow_credentials: bool,
+    max_age: Option<Duration>,
+}
+
+impl Preflight {
+    ///return the status of the preflight response, which must be a 2xx for the request to be allowed
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    ///return the `Access-Control-Allow-Origin` header, an origin or `*`
+    pub fn allow_origin(&self) -> Option<&str> {
+        self.allow_origin.as_deref()
+    }
+
+    ///return the methods of `Access-Control-Allow-Methods`
+    pub fn allow_methods(&self) -> &[Method] {
+        &self.allow_methods
+    }
+
+    ///return the lowercased header names of `Access-Control-Allow-Headers`
+    pub fn allow_headers(&self) -> &[String] {
+        &self.allow_headers
+    }
+
+    ///return if `Access-Control-Allow-Credentials` is `true`
+    pub fn allow_credentials(&self) -> bool {
+        self.allow_credentials
+    }
+
+    ///return how long the preflight may be cached, from `Access-Control-Max-Age`
+    pub fn max_age(&self) -> Option<Duration> {
+        self.max_age
+    }
+
+    ///return if a browser would send the request described by the preflight, without credentials
+    ///
+    ///The status must be a 2xx and the origin allowed, the method must be `GET`, `HEAD`, `POST` or allowed,
+    ///and each header asked for must be allowed, `*` standing for any origin, method or header.
+    pub fn is_allowed(&self) -> bool {
+        let wildcard = |value: &str| value == "*";
+        let origin = self.allow_origin.as_deref().is_some_and(|origin| wildcard(origin) || origin == self.origin);
+        let method = matches!(self.method, Method::Get | Method::Head | Method::Post)
+            || self.allow_methods.iter().any(|method| *method == self.method || wildcard(method.as_str()));
+        let headers = self.headers.iter().all(|name| self.allow_headers.iter().any(|allowed| allowed == name || wildcard(allowed)));
+        self.status.is_success() && origin && method && headers
+    }
+}
+
+impl Client {
+    ///send the CORS preflight a browser at `origin` would send before a `method` request to `url` with the
+    ///`headers`, and return what the server allows
+    ///
+    ///The `OPTIONS` request holds the `Origin`, `Access-Control-Request-Method` and, when there are headers,
+    ///`Access-Control-Request-Headers` headers. An error status doesn't fail, it is reported by the summary.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let preflight = Client::new().preflight("https://api.example.com/items", "https://app.example.com", "PUT", &["Content-Type"]).unwrap();
+    /// if !preflight.is_allowed() {
+    ///     println!("blocked: allows {:?} with {:?}", preflight.allow_methods(), preflight.allow_headers());
+    /// }
+    /// ```
+    pub fn preflight(&self, url: &str, origin: &str, method: &str, headers: &[&str]) -> Result<Preflight, HttpError> {
+        let method: Method = method.parse()?;
+        let mut headers: Vec<String> = headers.iter().map(|name| name.to_ascii_lowercase()).collect();
+        headers.sort();
+        headers.dedup();
+
+        let mut request = self.options(url)?.header("Origin", origin)?.header("Access-Control-Request-Method", method.as_str())?;
+        if !headers.is_empty() {
+            request = request.header("Access-Control-Request-Headers", &headers.join(","))?;
+        }
+        let res = request.send()?;
+
+        let list = |name: &str| res.headers().get_all(name).flat_map(params::split_list).map(str::to_owned).collect::<Vec<_>>();
+        Ok(Preflight {
+            status: res.status(),
+            origin: origin.to_owned(),
+            allow_origin: res.header("Access-Control-Allow-Origin").map(|origin| origin.trim().to_owned()),
+            allow_methods: list("Access-Control-Allow-Methods").iter().filter_map(|method| method.parse().ok()).collect(),
+            allow_headers: list("Access-Control-Allow-Headers").iter().map(|name| name.to_ascii_lowercase()).collect(),
+            allow_credentials: res.header("Access-Control-Allow-Credentials").is_some_and(|value| value.trim() == "true"),
+            max_age: res.header("Access-Control-Max-Age").and_then(|age| age.trim().parse().ok()).map(Duration::from_secs),
+            method,
+            headers,
+        })
+    }
+}
+
+impl Response {
+    ///return the methods of the `Allow` header, sent with `405 Method Not Allowed` or in answer to `OPTIONS`,
+    ///skipping the invalid ones
+    /// # Example
+    /// ```
+    /// use smolhttp::{Method, Response};
+    ///
+    /// let res = Response::new(b"HTTP/1.1 405 Method Not Allowed\r\nAllow: GET, HEAD\r\nAllow: PROPFIND\r\n\r\n".to_vec()).unwrap();
+    /// assert_eq!(res.allowed_methods(), [Method::Get, Method::Head, Method::Custom("PROPFIND".to_owned())]);
+    /// ```
+    pub fn allowed_methods(&self) -> Vec<Method> {
+        self.headers().get_all("Allow").flat_map(params::split_list).filter_map(|method| method.parse().ok()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+
+    #[test]
+    fn preflight_summary() {
+        let mock = MockTransport::new();
+        mock.respond_raw(
+            "OPTIONS",
+            "http://api.example.com/items",
+            b"HTTP/1.1 204 No Content\r\n\
+              Access-Control-Allow-Origin: https://app.example.com\r\n\
+              Access-Control-Allow-Methods: GET, PUT\r\n\
+              Access-Control-Allow-Headers: Content-Type, X-Trace\r\n\
+              Access-Control-Max-Age: 600\r\n\r\n"
+                .to_vec(),
+        );
+        let client = Client::builder().connector(mock.clone()).build().unwrap();
+
+        let preflight = client.preflight("http://api.example.com/items", "https://app.example.com", "PUT", &["X-Trace", "content-type"]).unwrap();
+        assert!(preflight.is_allowed());
+        assert_eq!(preflight.allow_methods(), [Method::Get, Method::Put]);
+        assert_eq!(preflight.allow_headers(), ["content-type", "x-trace"]);
+        assert_eq!(preflight.max_age(), Some(Duration::from_secs(600)));
+        assert!(!preflight.allow_credentials());
+
+        let request = &mock.requests()[0];
+        assert_eq!(request.method(), "OPTIONS");
+        assert_eq!(request.headers().get("Origin"), Some("https://app.example.com"));
+        assert_eq!(request.headers().get("Access-Control-Request-Method"), Some("PUT"));
+        assert_eq!(request.headers().get("Access-Control-Request-Headers"), Some("content-type,x-trace"));
+
+        let denied = |origin, method, headers: &[&str]| !client.preflight("http://api.example.com/items", origin, method, headers).unwrap().is_allowed();
+        assert!(denied("https://evil.example", "PUT", &[]));
+        assert!(denied("https://app.example.com", "DELETE", &[]));
+        assert!(denied("https://app.example.com", "PUT", &["Authorization"]));
+        assert!(!denied("https://app.example.com", "POST", &[]));
+    }
+}
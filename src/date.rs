@@ -0,0 +1,144 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+const WEEKDAYS: [&str; 7] = ["Sun", "Mon", "Tue", "Wed", "Thu", "Fri", "Sat"];
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+
+///parse an http date in any of the three RFC 7231 formats
+/// # Example
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let date = UNIX_EPOCH + Duration::from_secs(784111777);
+/// assert_eq!(smolhttp::parse_http_date("Sun, 06 Nov 1994 08:49:37 GMT"), Some(date));
+/// assert_eq!(smolhttp::parse_http_date("Sunday, 06-Nov-94 08:49:37 GMT"), Some(date));
+/// assert_eq!(smolhttp::parse_http_date("Sun Nov  6 08:49:37 1994"), Some(date));
+/// ```
+pub fn parse_http_date(value: &str) -> Option<SystemTime> {
+    let value = value.trim();
+    let (day, month, year, time) = match value.split_once(',') {
+        Some((_, rest)) => {
+            let fields: Vec<&str> = rest.split_whitespace().collect();
+            match fields[..] {
+                // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+                [day, month, year, time, "GMT"] => (day, month, year.parse().ok()?, time),
+                // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+                [date, time, "GMT"] => {
+                    let mut date = date.split('-');
+                    let (day, month, year) = (date.next()?, date.next()?, date.next()?);
+                    let year: i64 = year.parse().ok()?;
+                    let year = if year < 70 { 2000 + year } else { 1900 + year };
+                    (day, month, year, time)
+                }
+                _ => return None,
+            }
+        }
+        None => match value.split_whitespace().collect::<Vec<_>>()[..] {
+            // asctime: Sun Nov  6 08:49:37 1994
+            [_, month, day, time, year] => (day, month, year.parse().ok()?, time),
+            _ => return None,
+        },
+    };
+
+    let day: i64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|m| *m == month)? as i64 + 1;
+    let mut time = time.split(':').map(|t| t.parse::<i64>().ok());
+    let (hour, min, sec) = (time.next()??, time.next()??, time.next()??);
+
+    if !(1..=31).contains(&day) || hour > 23 || min > 59 || sec > 60 {
+        return None;
+    }
+
+    let secs = days_from_civil(year, month, day) * 86400 + hour * 3600 + min * 60 + sec;
+    if secs >= 0 {
+        UNIX_EPOCH.checked_add(Duration::from_secs(secs as u64))
+    } else {
+        UNIX_EPOCH.checked_sub(Duration::from_secs(secs.unsigned_abs()))
+    }
+}
+
+///format a time as an IMF-fixdate, the preferred http date format
+/// # Example
+/// ```
+/// use std::time::{Duration, UNIX_EPOCH};
+///
+/// let date = UNIX_EPOCH + Duration::from_secs(784111777);
+/// assert_eq!(smolhttp::fmt_http_date(date), "Sun, 06 Nov 1994 08:49:37 GMT");
+/// ```
+pub fn fmt_http_date(time: SystemTime) -> String {
+    let secs = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64),
+    };
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!("{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        WEEKDAYS[(days + 4).rem_euclid(7) as usize],
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60)
+}
+
+//format `time` as an ISO 8601 UTC date with milliseconds, like `1994-11-06T08:49:37.000Z`
+pub(crate) fn fmt_iso_date(time: SystemTime) -> String {
+    let millis = match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_millis() as i64,
+        Err(e) => -(e.duration().as_millis() as i64),
+    };
+    let secs = millis.div_euclid(1000);
+    let (days, rem) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (year, month, day) = civil_from_days(days);
+
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year, month, day, rem / 3600, rem % 3600 / 60, rem % 60, millis.rem_euclid(1000))
+}
+
+//days since 1970-01-01 of a proleptic gregorian date
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146097 + doe - 719468
+}
+
+//proleptic gregorian date of a number of days since 1970-01-01
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let doe = days - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        for secs in [0, 951782400, 1709164800, 4102444799] {
+            let time = UNIX_EPOCH + Duration::from_secs(secs);
+            assert_eq!(parse_http_date(&fmt_http_date(time)), Some(time));
+        }
+        assert_eq!(fmt_http_date(UNIX_EPOCH + Duration::from_secs(951782400)), "Tue, 29 Feb 2000 00:00:00 GMT");
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        assert_eq!(parse_http_date("yesterday"), None);
+        assert_eq!(parse_http_date("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 25:49:37 GMT"), None);
+        assert_eq!(parse_http_date("Sun, 06 Nov 1994 08:49:37 PST"), None);
+        assert_eq!(parse_http_date("0"), None);
+    }
+}
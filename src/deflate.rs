@@ -0,0 +1,488 @@
+//DEFLATE compression(RFC 1951) with the fixed Huffman codes, and its gzip(RFC 1952) and zlib(RFC 1950) wrappers.
+//Decompression of all the block types comes with the `decompress` feature.
+
+#[cfg(feature = "decompress")]
+use super::HttpError;
+
+//distance back a match can reach
+const WINDOW: usize = 32 * 1024;
+//shortest and longest match
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+//candidates tried for each match, trading speed for size
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+const LENGTH_BASE: [u16; 29] = [3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131, 163, 195, 227, 258];
+const LENGTH_EXTRA: [u8; 29] = [0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537, 2049, 3073, 4097, 6145, 8193, 12289, 16385,
+    24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13, 13];
+
+//writer of bits, least significant first
+struct Bits {
+    out: Vec<u8>,
+    acc: u32,
+    len: u32,
+}
+
+impl Bits {
+    fn write(&mut self, value: u32, len: u32) {
+        self.acc |= value << self.len;
+        self.len += len;
+        while self.len >= 8 {
+            self.out.push(self.acc as u8);
+            self.acc >>= 8;
+            self.len -= 8;
+        }
+    }
+
+    //write a Huffman code, sent most significant bit first
+    fn code(&mut self, code: u32, len: u32) {
+        self.write(code.reverse_bits() >> (32 - len), len);
+    }
+
+    //write a literal/length symbol with its fixed code
+    fn symbol(&mut self, symbol: u16) {
+        let symbol = u32::from(symbol);
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xC0 + symbol - 280, 8),
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.out.push(self.acc as u8);
+        }
+        self.out
+    }
+}
+
+//return the index of the last entry of `base` not above `value`
+fn bucket(base: &[u16], value: usize) -> usize {
+    base.iter().rposition(|&b| usize::from(b) <= value).unwrap_or(0)
+}
+
+//index of the earlier positions of the data by the hash of their first bytes
+struct Matcher<'a> {
+    data: &'a [u8],
+    //most recent position of each hash, and the previous position with the same hash
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+impl<'a> Matcher<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, head: vec![usize::MAX; 1 << HASH_BITS], prev: vec![usize::MAX; WINDOW] }
+    }
+
+    fn hash(&self, pos: usize) -> usize {
+        let value = u32::from(self.data[pos]) << 16 | u32::from(self.data[pos + 1]) << 8 | u32::from(self.data[pos + 2]);
+        (value.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+    }
+
+    fn insert(&mut self, pos: usize) {
+        if pos + MIN_MATCH <= self.data.len() {
+            let hash = self.hash(pos);
+            self.prev[pos % WINDOW] = self.head[hash];
+            self.head[hash] = pos;
+        }
+    }
+
+    //return the length and distance of the longest earlier match of the data at `pos`, if any
+    fn longest(&self, pos: usize) -> Option<(usize, usize)> {
+        if pos + MIN_MATCH > self.data.len() {
+            return None;
+        }
+        let max = (self.data.len() - pos).min(MAX_MATCH);
+        let mut best = (0, 0);
+        let mut candidate = self.head[self.hash(pos)];
+        for _ in 0..MAX_CHAIN {
+            //positions older than the window were overwritten in `prev`
+            if candidate == usize::MAX || candidate >= pos || pos - candidate >= WINDOW {
+                break;
+            }
+            let len = self.data[candidate..].iter().zip(&self.data[pos..pos + max]).take_while(|(a, b)| a == b).count();
+            if len > best.0 {
+                best = (len, pos - candidate);
+                if len == max {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate % WINDOW];
+        }
+        Some(best).filter(|&(len, _)| len >= MIN_MATCH)
+    }
+}
+
+//compress `data` into a raw DEFLATE stream of one block
+pub(crate) fn compress(data: &[u8]) -> Vec<u8> {
+    let mut bits = Bits { out: Vec::with_capacity(data.len() / 2 + 16), acc: 0, len: 0 };
+    //last block, fixed Huffman codes
+    bits.write(1, 1);
+    bits.write(1, 2);
+
+    let mut matcher = Matcher::new(data);
+    let mut pos = 0;
+    while pos < data.len() {
+        match matcher.longest(pos) {
+            Some((len, distance)) => {
+                let i = bucket(&LENGTH_BASE, len);
+                bits.symbol(257 + i as u16);
+                bits.write((len - usize::from(LENGTH_BASE[i])) as u32, u32::from(LENGTH_EXTRA[i]));
+                let i = bucket(&DISTANCE_BASE, distance);
+                bits.code(i as u32, 5);
+                bits.write((distance - usize::from(DISTANCE_BASE[i])) as u32, u32::from(DISTANCE_EXTRA[i]));
+                for at in pos..pos + len {
+                    matcher.insert(at);
+                }
+                pos += len;
+            }
+            None => {
+                bits.symbol(u16::from(data[pos]));
+                matcher.insert(pos);
+                pos += 1;
+            }
+        }
+    }
+    bits.symbol(256);
+    bits.finish()
+}
+
+//CRC-32 of `data`, as used by gzip
+pub(crate) fn crc32(data: &[u8]) -> u32 {
+    !data.iter().fold(!0u32, |crc, &byte| {
+        (0..8).fold(crc ^ u32::from(byte), |crc, _| if crc & 1 == 1 { (crc >> 1) ^ 0xEDB8_8320 } else { crc >> 1 })
+    })
+}
+
+//Adler-32 of `data`, as used by zlib
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (a, b) = data.iter().fold((1u32, 0u32), |(a, b), &byte| {
+        let a = (a + u32::from(byte)) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
+
+//compress `data` into a gzip member
+pub(crate) fn gzip(data: &[u8]) -> Vec<u8> {
+    //no name nor modification time, unknown OS
+    let mut out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+    out.extend_from_slice(&compress(data));
+    out.extend_from_slice(&crc32(data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+//compress `data` into a zlib stream, the `deflate` content coding of HTTP
+pub(crate) fn zlib(data: &[u8]) -> Vec<u8> {
+    //32K window, header check bits
+    let mut out = vec![0x78, 0x01];
+    out.extend_from_slice(&compress(data));
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+    out
+}
+
+//order of the code length codes in a dynamic block header
+#[cfg(feature = "decompress")]
+const CODE_LENGTH_ORDER: [usize; 19] = [16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15];
+
+//reader of bits, least significant first
+#[cfg(feature = "decompress")]
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    acc: u32,
+    len: u32,
+}
+
+#[cfg(feature = "decompress")]
+impl BitReader<'_> {
+    fn bits(&mut self, len: u32) -> Result<u32, HttpError> {
+        while self.len < len {
+            let byte = *self.data.get(self.pos).ok_or(HttpError::Parse("truncated deflate stream"))?;
+            self.acc |= u32::from(byte) << self.len;
+            self.pos += 1;
+            self.len += 8;
+        }
+        let value = self.acc & ((1u64 << len) - 1) as u32;
+        self.acc >>= len;
+        self.len -= len;
+        Ok(value)
+    }
+
+    //skip to the next byte boundary
+    fn align(&mut self) {
+        self.acc = 0;
+        self.len = 0;
+    }
+
+    //decode a symbol, reading its code one bit at a time
+    fn symbol(&mut self, huffman: &Huffman) -> Result<u16, HttpError> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for &count in &huffman.counts[1..] {
+            code |= self.bits(1)? as i32;
+            let count = i32::from(count);
+            if code - count < first {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err(HttpError::Parse("invalid deflate code"))
+    }
+}
+
+//canonical Huffman code: the number of codes of each length, and the symbols ordered by code
+#[cfg(feature = "decompress")]
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+#[cfg(feature = "decompress")]
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, HttpError> {
+        let mut counts = [0u16; 16];
+        for &len in lengths {
+            counts[usize::from(len)] += 1;
+        }
+        counts[0] = 0;
+        //over-subscribed codes can't be decoded, incomplete ones are allowed
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(HttpError::Parse("invalid deflate code lengths"));
+            }
+        }
+        let mut offsets = [0u16; 16];
+        for len in 1..15 {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[usize::from(offsets[usize::from(len)])] = symbol as u16;
+                offsets[usize::from(len)] += 1;
+            }
+        }
+        Ok(Self { counts, symbols })
+    }
+}
+
+//decompress a raw DEFLATE stream, returning the data and the number of bytes of the stream used
+#[cfg(feature = "decompress")]
+pub(crate) fn decompress(data: &[u8], limit: Option<u64>) -> Result<(Vec<u8>, usize), HttpError> {
+    let mut bits = BitReader { data, pos: 0, acc: 0, len: 0 };
+    let mut out = Vec::with_capacity(data.len() * 3);
+    let (fixed_literals, fixed_distances) = {
+        let lengths: Vec<u8> = (0..288).map(|symbol| match symbol {
+            0..=143 => 8,
+            144..=255 => 9,
+            256..=279 => 7,
+            _ => 8,
+        }).collect();
+        (Huffman::new(&lengths)?, Huffman::new(&[5; 30])?)
+    };
+
+    loop {
+        let last = bits.bits(1)? == 1;
+        match bits.bits(2)? {
+            0 => {
+                bits.align();
+                let header = data.get(bits.pos..bits.pos + 4).ok_or(HttpError::Parse("truncated deflate stream"))?;
+                let len = usize::from(u16::from_le_bytes([header[0], header[1]]));
+                if len != usize::from(!u16::from_le_bytes([header[2], header[3]])) {
+                    return Err(HttpError::Parse("invalid deflate stored block"));
+                }
+                let stored = data.get(bits.pos + 4..bits.pos + 4 + len).ok_or(HttpError::Parse("truncated deflate stream"))?;
+                out.extend_from_slice(stored);
+                bits.pos += 4 + len;
+            }
+            1 => inflate_block(&mut bits, &mut out, &fixed_literals, &fixed_distances, limit)?,
+            2 => {
+                let literals = bits.bits(5)? as usize + 257;
+                let distances = bits.bits(5)? as usize + 1;
+                let codes = bits.bits(4)? as usize + 4;
+                let mut lengths = [0u8; 19];
+                for &i in &CODE_LENGTH_ORDER[..codes] {
+                    lengths[i] = bits.bits(3)? as u8;
+                }
+                let code_lengths = Huffman::new(&lengths)?;
+
+                let mut lengths = Vec::with_capacity(literals + distances);
+                while lengths.len() < literals + distances {
+                    let (value, repeat) = match bits.symbol(&code_lengths)? {
+                        len @ 0..=15 => (len as u8, 1),
+                        16 => (*lengths.last().ok_or(HttpError::Parse("invalid deflate code lengths"))?, 3 + bits.bits(2)?),
+                        17 => (0, 3 + bits.bits(3)?),
+                        _ => (0, 11 + bits.bits(7)?),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() > literals + distances || lengths[256] == 0 {
+                    return Err(HttpError::Parse("invalid deflate code lengths"));
+                }
+                let (literal_lengths, distance_lengths) = lengths.split_at(literals);
+                inflate_block(&mut bits, &mut out, &Huffman::new(literal_lengths)?, &Huffman::new(distance_lengths)?, limit)?;
+            }
+            _ => return Err(HttpError::Parse("invalid deflate block type")),
+        }
+        if limit.map(|limit| out.len() as u64 > limit).unwrap_or(false) {
+            return Err(HttpError::TooLarge { limit: limit.unwrap_or_default() });
+        }
+        if last {
+            return Ok((out, bits.pos));
+        }
+    }
+}
+
+//decode the symbols of a compressed block until its end
+#[cfg(feature = "decompress")]
+fn inflate_block(bits: &mut BitReader, out: &mut Vec<u8>, literals: &Huffman, distances: &Huffman, limit: Option<u64>) -> Result<(), HttpError> {
+    loop {
+        let symbol = bits.symbol(literals)?;
+        match symbol {
+            0..=255 => out.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let i = usize::from(symbol - 257);
+                let (&base, &extra) = LENGTH_BASE.get(i).zip(LENGTH_EXTRA.get(i)).ok_or(HttpError::Parse("invalid deflate length"))?;
+                let len = usize::from(base) + bits.bits(u32::from(extra))? as usize;
+                let i = usize::from(bits.symbol(distances)?);
+                let (&base, &extra) = DISTANCE_BASE.get(i).zip(DISTANCE_EXTRA.get(i)).ok_or(HttpError::Parse("invalid deflate distance"))?;
+                let distance = usize::from(base) + bits.bits(u32::from(extra))? as usize;
+                if distance > out.len() {
+                    return Err(HttpError::Parse("invalid deflate distance"));
+                }
+                //the match can overlap the bytes it produces
+                let start = out.len() - distance;
+                for i in 0..len {
+                    out.push(out[start + i]);
+                }
+                if limit.map(|limit| out.len() as u64 > limit).unwrap_or(false) {
+                    return Err(HttpError::TooLarge { limit: limit.unwrap_or_default() });
+                }
+            }
+        }
+    }
+}
+
+//decompress the gzip members of `data`
+#[cfg(feature = "decompress")]
+pub(crate) fn gunzip(mut data: &[u8], limit: Option<u64>) -> Result<Vec<u8>, HttpError> {
+    let invalid = HttpError::Parse("invalid gzip header");
+    let mut out = Vec::new();
+    while !data.is_empty() {
+        if data.len() < 18 || data[..3] != [0x1f, 0x8b, 8] {
+            return Err(invalid);
+        }
+        let flags = data[3];
+        let mut pos = 10;
+        if flags & 4 != 0 {
+            let extra = data.get(pos..pos + 2).ok_or(HttpError::Parse("invalid gzip header"))?;
+            pos += 2 + usize::from(u16::from_le_bytes([extra[0], extra[1]]));
+        }
+        //zero terminated name and comment
+        for flag in [8, 16] {
+            if flags & flag != 0 {
+                pos += data.get(pos..).and_then(|rest| rest.iter().position(|&b| b == 0)).ok_or(HttpError::Parse("invalid gzip header"))? + 1;
+            }
+        }
+        if flags & 2 != 0 {
+            pos += 2;
+        }
+
+        let (member, used) = decompress(data.get(pos..).ok_or(HttpError::Parse("invalid gzip header"))?, limit.map(|limit| limit - out.len() as u64))?;
+        let trailer = data.get(pos + used..pos + used + 8).ok_or(HttpError::Parse("truncated gzip stream"))?;
+        if trailer[..4] != crc32(&member).to_le_bytes() || trailer[4..] != (member.len() as u32).to_le_bytes() {
+            return Err(HttpError::Parse("gzip checksum mismatch"));
+        }
+        out.extend_from_slice(&member);
+        data = &data[pos + used + 8..];
+    }
+    Ok(out)
+}
+
+//decompress a zlib stream, or a raw DEFLATE stream as sent by some servers for the `deflate` coding
+#[cfg(feature = "decompress")]
+pub(crate) fn unzlib(data: &[u8], limit: Option<u64>) -> Result<Vec<u8>, HttpError> {
+    let zlib = data.len() >= 6 && data[0] & 0x0f == 8 && u16::from_be_bytes([data[0], data[1]]).is_multiple_of(31);
+    if !zlib {
+        return Ok(decompress(data, limit)?.0);
+    }
+    if data[1] & 0x20 != 0 {
+        return Err(HttpError::Parse("zlib preset dictionaries are not supported"));
+    }
+    let (out, used) = decompress(&data[2..], limit)?;
+    match data.get(2 + used..2 + used + 4) {
+        Some(checksum) if checksum == adler32(&out).to_be_bytes() => Ok(out),
+        _ => Err(HttpError::Parse("zlib checksum mismatch")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(b""), 0);
+        assert_eq!(adler32(b"Wikipedia"), 0x11E6_0398);
+    }
+
+    #[test]
+    fn fixed_blocks() {
+        //an empty block, then a literal followed by a match of length 5 at distance 1
+        assert_eq!(compress(b""), [0x03, 0x00]);
+        assert_eq!(compress(b"aaaaaa"), [0x4b, 0x04, 0x03, 0x00]);
+
+        let data = b"log line: request served\n".repeat(100);
+        let gz = gzip(&data);
+        assert!(gz.len() < data.len() / 10);
+        assert_eq!(gz[..3], [0x1f, 0x8b, 8]);
+        assert_eq!(gz[gz.len() - 4..], (data.len() as u32).to_le_bytes());
+        assert_eq!(u16::from_be_bytes([zlib(&data)[0], zlib(&data)[1]]) % 31, 0);
+    }
+
+    #[cfg(feature = "decompress")]
+    fn hex(text: &str) -> Vec<u8> {
+        (0..text.len()).step_by(2).map(|i| u8::from_str_radix(&text[i..i + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    #[cfg(feature = "decompress")]
+    fn inflate() {
+        //stored, then dynamic blocks as produced by zlib
+        assert_eq!(unzlib(&hex("7801010500faff68656c6c6f062c0215"), None).unwrap(), b"hello");
+        let lorem = hex(concat!(
+            "78da25ccd10903310c04d156b6802395a409c512c78265fb2ca9ff18ee7b78f39ddb1c5c510e9d7d6e0413e29617da1c612d",
+            "2d6b43948bd1386e58e789617a008c153e1569be0ee668546a8d4425bafcce1e96efdae0720f81743e259f3f125d2d87"
+        ));
+        let text = unzlib(&lorem, None).unwrap();
+        assert!(text.starts_with(b"Lorem ipsum") && text.ends_with(b"magna aliqua."));
+        assert!(matches!(unzlib(&lorem, Some(10)), Err(HttpError::TooLarge { limit: 10 })));
+
+        let data = b"log line: request served\n".repeat(100);
+        assert_eq!(gunzip(&gzip(&data), None).unwrap(), data);
+        assert_eq!(unzlib(&zlib(&data), None).unwrap(), data);
+        assert_eq!(unzlib(&compress(&data), None).unwrap(), data);
+        let mut twice = gzip(b"ab");
+        twice.extend(gzip(b"cd"));
+        assert_eq!(gunzip(&twice, None).unwrap(), b"abcd");
+
+        let mut corrupted = gzip(&data);
+        let len = corrupted.len();
+        corrupted[len - 5] ^= 1;
+        assert!(gunzip(&corrupted, None).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//parsing of the Content-Disposition header (RFC 6266).
+
+use super::params::split_params;
+
+//return the file name of a Content-Disposition value, without any directory component
+pub(crate) fn filename(value: &str) -> Option<String> {
+    let params = split_params(value);
+
+    let extended = params
+        .iter()
+        .find(|(name, _)| name == "filename*")
+        .and_then(|(_, value)| decode_ext_value(value));
+    let name = extended.or_else(|| {
+        params
+            .iter()
+            .find(|(name, _)| name == "filename")
+            .map(|(_, value)| value.clone())
+    })?;
+
+    let name = name.rsplit(['/', '\\']).next().unwrap_or_default().trim();
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(name.to_owned())
+    }
+}
+
+//decode a RFC 5987 `charset'language'percent-encoded` value
+fn decode_ext_value(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let charset = parts.next()?;
+    let _language = parts.next()?;
+    let encoded = parts.next()?;
+
+    let bytes = percent_decode(encoded)?;
+    if charset.eq_ignore_ascii_case("utf-8") {
+        String::from_utf8(bytes).ok()
+    } else if charset.eq_ignore_ascii_case("iso-8859-1") {
+        Some(bytes.iter().map(|&b| b as char).collect())
+    } else {
+        None
+    }
+}
+
+pub(crate) fn percent_decode(value: &str) -> Option<Vec<u8>> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = value.get(i + 1..i + 3)?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_filename() {
+        assert_eq!(filename("attachment; filename=report.pdf"), Some("report.pdf".to_owned()));
+        assert_eq!(filename("attachment; filename=\"a; \\\"b\\\".txt\""), Some("a; \"b\".txt".to_owned()));
+        assert_eq!(filename("inline"), None);
+    }
+
+    #[test]
+    fn extended_filename_wins() {
+        let value = "attachment; filename=\"EURO rates\"; filename*=utf-8''%e2%82%ac%20rates";
+        assert_eq!(filename(value), Some("\u{20ac} rates".to_owned()));
+        assert_eq!(filename("attachment; filename*=iso-8859-1'en'caf%E9"), Some("caf\u{e9}".to_owned()));
+    }
+
+    #[test]
+    fn strips_directories() {
+        assert_eq!(filename("attachment; filename=\"../../etc/passwd\""), Some("passwd".to_owned()));
+        assert_eq!(filename("attachment; filename=\"..\""), None);
+    }
+}
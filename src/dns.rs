@@ -0,0 +1,184 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+///turns a host name into the socket addresses a Client connects to.
+///
+///Addresses are tried in the returned order, so a resolver can also decide which one is preferred.
+/// # Example
+/// ```
+/// use smolhttp::{Client, Resolver};
+/// use std::io;
+/// use std::net::SocketAddr;
+///
+/// // send every request for `api.internal` to a fixed address
+/// #[derive(Debug)]
+/// struct Internal;
+///
+/// impl Resolver for Internal {
+///     fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+///         match host {
+///             "api.internal" => Ok(vec![SocketAddr::from(([10, 0, 0, 7], port))]),
+///             _ => Err(io::Error::new(io::ErrorKind::NotFound, "unknown host")),
+///         }
+///     }
+/// }
+///
+/// let client = Client::builder().dns_resolver(Internal).build().unwrap();
+/// ```
+pub trait Resolver: fmt::Debug + Send + Sync {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>>;
+}
+
+///resolver using the system configuration through [`ToSocketAddrs`], the default one.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemResolver;
+
+impl Resolver for SystemResolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        Ok((host, port).to_socket_addrs()?.collect())
+    }
+}
+
+///address families a Client connects to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IpPreference {
+    ///any resolved address, IPv6 first
+    #[default]
+    Auto,
+    ///IPv4 addresses only
+    V4Only,
+    ///IPv6 addresses only
+    V6Only,
+}
+
+impl IpPreference {
+    //keep the addresses of the allowed families, failing when none is left
+    pub(crate) fn filter(self, host: &str, mut addrs: Vec<SocketAddr>) -> io::Result<Vec<SocketAddr>> {
+        let family = match self {
+            IpPreference::Auto => return Ok(addrs),
+            IpPreference::V4Only => "IPv4",
+            IpPreference::V6Only => "IPv6",
+        };
+        addrs.retain(|addr| addr.is_ipv4() == (self == IpPreference::V4Only));
+        if addrs.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::NotFound, format!("no {} address found for {}", family, host)));
+        }
+        Ok(addrs)
+    }
+}
+
+//resolve `host` with the resolver, or the system one, failing when no address is found
+pub(crate) fn resolve(resolver: Option<&dyn Resolver>, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+    let addrs = match resolver {
+        Some(resolver) => resolver.resolve(host, port)?,
+        None => SystemResolver.resolve(host, port)?,
+    };
+    found(host, addrs)
+}
+
+//fail when no address was found for `host`
+pub(crate) fn found(host: &str, addrs: Vec<SocketAddr>) -> io::Result<Vec<SocketAddr>> {
+    if addrs.is_empty() {
+        return Err(io::Error::new(io::ErrorKind::NotFound, format!("no address found for {}", host)));
+    }
+    Ok(addrs)
+}
+
+//(host, port) -> when it was resolved and its addresses
+type Entries = HashMap<(String, u16), (Instant, Vec<SocketAddr>)>;
+
+//resolved addresses kept for `ttl`, shared by the clones of a Client
+#[derive(Debug)]
+pub(crate) struct DnsCache {
+    ttl: Duration,
+    max_entries: usize,
+    entries: Mutex<Entries>,
+}
+
+impl DnsCache {
+    pub(crate) fn new(ttl: Duration, max_entries: usize) -> Self {
+        Self { ttl, max_entries, entries: Mutex::new(HashMap::new()) }
+    }
+
+    //return the addresses of `host` if they were resolved less than `ttl` ago
+    pub(crate) fn get(&self, host: &str, port: u16) -> Option<Vec<SocketAddr>> {
+        let entries = self.entries.lock().unwrap();
+        match entries.get(&(host.to_ascii_lowercase(), port)) {
+            Some((resolved, addrs)) if resolved.elapsed() < self.ttl => Some(addrs.clone()),
+            _ => None,
+        }
+    }
+
+    //store the addresses of `host`, making room by dropping expired entries then the oldest one
+    pub(crate) fn put(&self, host: &str, port: u16, addrs: &[SocketAddr]) {
+        if self.max_entries == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        let key = (host.to_ascii_lowercase(), port);
+        if !entries.contains_key(&key) && entries.len() >= self.max_entries {
+            entries.retain(|_, (resolved, _)| resolved.elapsed() < self.ttl);
+            if entries.len() >= self.max_entries {
+                let oldest = entries.iter().min_by_key(|(_, (resolved, _))| *resolved).map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+        }
+        entries.insert(key, (Instant::now(), addrs.to_vec()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug)]
+    struct Nowhere;
+
+    impl Resolver for Nowhere {
+        fn resolve(&self, _host: &str, _port: u16) -> io::Result<Vec<SocketAddr>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn system_resolver() {
+        let addrs = resolve(None, "127.0.0.1", 8080).unwrap();
+        assert_eq!(addrs, [SocketAddr::from(([127, 0, 0, 1], 8080))]);
+        assert!(resolve(Some(&Nowhere), "example.com", 80).is_err());
+    }
+
+    #[test]
+    fn ip_preference() {
+        let v4 = SocketAddr::from(([127, 0, 0, 1], 80));
+        let v6 = SocketAddr::from(([0, 0, 0, 0, 0, 0, 0, 1], 80));
+        assert_eq!(IpPreference::Auto.filter("localhost", vec![v4, v6]).unwrap(), [v4, v6]);
+        assert_eq!(IpPreference::V4Only.filter("localhost", vec![v4, v6]).unwrap(), [v4]);
+        assert_eq!(IpPreference::V6Only.filter("localhost", vec![v4, v6]).unwrap(), [v6]);
+        assert!(IpPreference::V6Only.filter("localhost", vec![v4]).is_err());
+    }
+
+    #[test]
+    fn dns_cache() {
+        let addr = [SocketAddr::from(([10, 0, 0, 1], 80))];
+        let cache = DnsCache::new(Duration::from_secs(60), 2);
+        cache.put("a.example", 80, &addr);
+        cache.put("b.example", 80, &addr);
+        assert_eq!(cache.get("A.example", 80).unwrap(), addr);
+        assert!(cache.get("a.example", 443).is_none());
+
+        // the oldest entry makes room for a new host
+        cache.put("c.example", 80, &addr);
+        assert!(cache.get("a.example", 80).is_none());
+        assert!(cache.get("c.example", 80).is_some());
+
+        let expired = DnsCache::new(Duration::ZERO, 2);
+        expired.put("a.example", 80, &addr);
+        assert!(expired.get("a.example", 80).is_none());
+    }
+}
@@ -0,0 +1,171 @@
+use super::Client;
+use super::HttpError;
+use super::Resolver;
+
+use std::fmt;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+//dns record types
+const TYPE_A: u16 = 1;
+const TYPE_AAAA: u16 = 28;
+
+///resolver sending DNS queries over HTTPS(RFC 8484), available with the `doh` feature.
+///
+///The endpoint host itself is resolved by the system, so it's best given as an address or a stable name.
+/// # Example
+/// ```no_run
+/// use smolhttp::{Client, DohResolver};
+///
+/// let client = Client::builder()
+///     .dns_resolver(DohResolver::new("https://1.1.1.1/dns-query"))
+///     .build()
+///     .unwrap();
+/// let res = client.get("https://www.rust-lang.org").unwrap().send().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct DohResolver {
+    endpoint: String,
+    client: Client,
+}
+
+impl fmt::Debug for DohResolver {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("DohResolver").field("endpoint", &self.endpoint).finish()
+    }
+}
+
+impl DohResolver {
+    ///return a resolver querying `endpoint`, like `https://dns.google/dns-query`
+    pub fn new(endpoint: &str) -> Self {
+        Self::with_client(endpoint, Client::new())
+    }
+
+    ///return a resolver querying `endpoint` with `client`, to set its timeout or proxy
+    pub fn with_client(endpoint: &str, client: Client) -> Self {
+        Self { endpoint: endpoint.to_owned(), client }
+    }
+
+    //send one query and return the addresses of its answer
+    fn query(&self, host: &str, record: u16) -> Result<Vec<IpAddr>, HttpError> {
+        let res = self.client
+            .post(&self.endpoint)?
+            .header("Content-Type", "application/dns-message")?
+            .header("Accept", "application/dns-message")?
+            .body(encode_query(host, record)?)
+            .send()?
+            .error_for_status()?;
+        decode_answer(res.bytes())
+    }
+}
+
+impl Resolver for DohResolver {
+    fn resolve(&self, host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+        if let Ok(ip) = host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            return Ok(vec![SocketAddr::new(ip, port)]);
+        }
+
+        let mut addrs = Vec::new();
+        for record in [TYPE_AAAA, TYPE_A] {
+            let ips = self.query(host, record).map_err(io::Error::other)?;
+            addrs.extend(ips.into_iter().map(|ip| SocketAddr::new(ip, port)));
+        }
+        Ok(addrs)
+    }
+}
+
+//build a recursive query for the `record` addresses of `host`, with the id 0 advised for caching
+fn encode_query(host: &str, record: u16) -> Result<Vec<u8>, HttpError> {
+    let mut query = vec![0, 0, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in host.trim_end_matches('.').split('.') {
+        if label.is_empty() || label.len() > 63 {
+            return Err(HttpError::Parse("invalid host name label"));
+        }
+        query.push(label.len() as u8);
+        query.extend_from_slice(label.as_bytes());
+    }
+    query.push(0);
+    query.extend_from_slice(&record.to_be_bytes());
+    query.extend_from_slice(&1u16.to_be_bytes());
+    Ok(query)
+}
+
+//return the A and AAAA addresses found in the answer section of a dns message
+fn decode_answer(message: &[u8]) -> Result<Vec<IpAddr>, HttpError> {
+    const TRUNCATED: HttpError = HttpError::Parse("truncated dns message");
+
+    let u16_at = |pos: usize| -> Result<u16, HttpError> {
+        match message.get(pos..pos + 2) {
+            Some(bytes) => Ok(u16::from_be_bytes([bytes[0], bytes[1]])),
+            None => Err(TRUNCATED),
+        }
+    };
+    // skip a possibly compressed name
+    let skip_name = |mut pos: usize| -> Result<usize, HttpError> {
+        loop {
+            let len = *message.get(pos).ok_or(TRUNCATED)?;
+            match len {
+                0 => return Ok(pos + 1),
+                len if len & 0xC0 == 0xC0 => return Ok(pos + 2),
+                len => pos += 1 + len as usize,
+            }
+        }
+    };
+
+    match u16_at(2)? & 0x000F {
+        0 | 3 => {}
+        _ => return Err(HttpError::Parse("dns server failed to answer the query")),
+    }
+
+    let mut pos = 12;
+    for _ in 0..u16_at(4)? {
+        pos = skip_name(pos)? + 4;
+    }
+
+    let mut ips = Vec::new();
+    for _ in 0..u16_at(6)? {
+        pos = skip_name(pos)?;
+        let record = u16_at(pos)?;
+        let len = u16_at(pos + 8)? as usize;
+        let data = message.get(pos + 10..pos + 10 + len).ok_or(TRUNCATED)?;
+        match (record, len) {
+            (TYPE_A, 4) => ips.push(IpAddr::V4(Ipv4Addr::new(data[0], data[1], data[2], data[3]))),
+            (TYPE_AAAA, 16) => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(data);
+                ips.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            // CNAME and other records, the addresses of the alias follow them
+            _ => {}
+        }
+        pos += 10 + len;
+    }
+    Ok(ips)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn query_encoding() {
+        let query = encode_query("example.com.", TYPE_A).unwrap();
+        assert_eq!(&query[..12], [0, 0, 1, 0, 0, 1, 0, 0, 0, 0, 0, 0]);
+        assert_eq!(&query[12..], b"\x07example\x03com\x00\x00\x01\x00\x01");
+        assert!(encode_query("a..b", TYPE_A).is_err());
+    }
+
+    #[test]
+    fn answer_decoding() {
+        let mut message = vec![0, 0, 0x81, 0x80, 0, 1, 0, 2, 0, 0, 0, 0];
+        message.extend_from_slice(b"\x07example\x03com\x00\x00\x01\x00\x01");
+        // CNAME to a compressed name, then the A record
+        message.extend_from_slice(&[0xC0, 12, 0, 5, 0, 1, 0, 0, 0, 60, 0, 2, 0xC0, 12]);
+        message.extend_from_slice(&[0xC0, 12, 0, 1, 0, 1, 0, 0, 0, 60, 0, 4, 93, 184, 216, 34]);
+        assert_eq!(decode_answer(&message).unwrap(), [IpAddr::V4(Ipv4Addr::new(93, 184, 216, 34))]);
+
+        assert!(decode_answer(&message[..message.len() - 2]).is_err());
+        message[3] = 0x82;
+        assert!(decode_answer(&message).is_err());
+    }
+}
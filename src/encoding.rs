@@ -0,0 +1,154 @@
+use super::deflate;
+use super::Body;
+use super::HttpError;
+use super::RequestBuilder;
+
+use std::fmt;
+
+///content coding of a body, sent in the `Content-Encoding` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    ///gzip format(RFC 1952)
+    Gzip,
+    ///zlib format(RFC 1950)
+    Deflate,
+}
+
+impl Encoding {
+    ///return the name of the coding in http headers
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+
+    //return the coding named `name` in a `Content-Encoding` header
+    #[cfg(feature = "decompress")]
+    pub(crate) fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Encoding::Gzip),
+            "deflate" => Some(Encoding::Deflate),
+            _ => None,
+        }
+    }
+
+    //return the codings that can be decoded, as advertised in an `Accept-Encoding` header
+    pub(crate) fn decodable() -> Option<&'static str> {
+        match cfg!(feature = "decompress") {
+            true => Some("gzip, deflate"),
+            false => None,
+        }
+    }
+
+    //compress `data` with the coding
+    pub(crate) fn encode(&self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Encoding::Gzip => deflate::gzip(data),
+            Encoding::Deflate => deflate::zlib(data),
+        }
+    }
+
+    //decompress `data` encoded with the coding, failing once more than `limit` bytes are produced
+    #[cfg(feature = "decompress")]
+    pub(crate) fn decode(&self, data: &[u8], limit: Option<u64>) -> Result<Vec<u8>, HttpError> {
+        match self {
+            Encoding::Gzip => deflate::gunzip(data, limit),
+            Encoding::Deflate => deflate::unzlib(data, limit),
+        }
+    }
+}
+
+impl fmt::Display for Encoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl RequestBuilder {
+    ///compress the body set so far with `encoding` and set the `Content-Encoding` header
+    ///
+    ///The whole body is compressed in memory, a file or a reader being read to its end, and only servers
+    ///accepting compressed requests can read it.
+    /// # Example
+    /// ```
+    /// use smolhttp::{Client, Encoding};
+    ///
+    /// let logs = "GET /index.html 200\n".repeat(1000);
+    /// let request = Client::new().post("http://example.com/logs").unwrap().body(logs).compress_body(Encoding::Gzip).unwrap();
+    /// let bytes = request.preview();
+    /// assert!(String::from_utf8_lossy(&bytes).contains("\r\nContent-Encoding: gzip\r\n"));
+    /// assert!(bytes.len() < 1000);
+    /// ```
+    pub fn compress_body(mut self, encoding: Encoding) -> Result<Self, HttpError> {
+        if let Some(body) = self.body.take() {
+            self.body = Some(Body::from(encoding.encode(&body.read_all()?)));
+            self.headers.insert("Content-Encoding", encoding.as_str());
+        }
+        Ok(self)
+    }
+
+    ///keep the response body exactly as received, without decoding its `Content-Encoding`
+    ///
+    ///The codings the crate can decode are still advertised, so a mirror or a proxy can store the compressed
+    ///body along with its `Content-Encoding` and `Content-Length` headers.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let res = Client::new().get("https://www.rust-lang.org").unwrap().raw().send().unwrap();
+    /// std::fs::write("index.html.gz", res.bytes()).unwrap();
+    /// ```
+    pub fn raw(mut self) -> Self {
+        self.raw = true;
+        self
+    }
+}
+
+#[cfg(all(test, feature = "decompress"))]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::{Client, ClientBuilder};
+
+    #[test]
+    fn automatic_decoding() {
+        let body = deflate::gzip(b"compressed page");
+        let mut raw = format!("HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes();
+        raw.extend(body);
+        let mock = MockTransport::new();
+        mock.respond_raw("GET", "http://example.com/", raw);
+
+        let res = Client::with_connector(mock.clone()).get("http://example.com/").unwrap().send().unwrap();
+        assert_eq!(res.text(), "compressed page");
+        assert_eq!(res.headers().get("Content-Encoding"), None);
+        assert_eq!(mock.requests()[0].headers().get("Accept-Encoding"), Some("gzip, deflate"));
+
+        let client = ClientBuilder::new().connector(mock.clone()).no_auto_encoding().build().unwrap();
+        let res = client.get("http://example.com/").unwrap().header("Accept-Encoding", "br").unwrap().send().unwrap();
+        assert_eq!(res.headers().get("Content-Encoding"), Some("gzip"));
+        assert_eq!(mock.requests()[1].headers().get("Accept-Encoding"), Some("br"));
+        client.get("http://example.com/").unwrap().send().unwrap();
+        assert_eq!(mock.requests()[2].headers().get("Accept-Encoding"), None);
+    }
+
+    #[test]
+    fn transfer_and_decoded_sizes() {
+        let body = deflate::gzip(&b"a".repeat(1000));
+        let length = body.len() as u64;
+        let mut raw = format!("HTTP/1.1 200 OK\r\nContent-Encoding: gzip\r\nContent-Length: {}\r\n\r\n", length).into_bytes();
+        raw.extend(body);
+        let mock = MockTransport::new();
+        mock.respond_raw("GET", "http://example.com/", raw);
+        let client = Client::with_connector(mock.clone());
+
+        let res = client.get("http://example.com/").unwrap().send().unwrap();
+        assert_eq!((res.transfer_size(), res.decoded_size(), res.content_length()), (length, 1000, None));
+
+        let res = client.get("http://example.com/").unwrap().raw().send().unwrap();
+        assert_eq!((res.transfer_size(), res.decoded_size(), res.content_length()), (length, length, Some(length)));
+        assert_eq!(res.headers().get("Content-Encoding"), Some("gzip"));
+        assert_eq!(deflate::gunzip(res.bytes(), None).unwrap(), b"a".repeat(1000));
+        assert_eq!(mock.requests()[1].headers().get("Accept-Encoding"), Some("gzip, deflate"));
+    }
+}
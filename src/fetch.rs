@@ -0,0 +1,80 @@
+use super::request::RequestBuilder;
+use super::ErrorKind;
+use super::HeaderMap;
+use super::HttpError;
+use super::Response;
+use super::StatusCode;
+use super::Version;
+
+use std::io;
+
+use js_sys::{Array, Uint8Array};
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{AbortSignal, Headers, RequestInit, Window, WorkerGlobalScope};
+
+//send the request with the fetch API, going through the middlewares. The browser follows the redirects, keeps
+//the cookies, the HSTS policies and the cache, and pools the connections itself
+pub(crate) async fn send(request: RequestBuilder) -> Result<Response, HttpError> {
+    if request.abort.is_aborted() {
+        return Err(HttpError::Aborted);
+    }
+    if request.client.inner.middleware.is_empty() && request.signer.is_none() {
+        return fetch(&request).await;
+    }
+    let request = request.intercept()?;
+    let res = fetch(&request).await?;
+    request.intercept_response(res)
+}
+
+async fn fetch(request: &RequestBuilder) -> Result<Response, HttpError> {
+    let url = request.raw_url.as_str();
+    let headers = Headers::new().map_err(|err| js_error(err, ErrorKind::Config, url))?;
+    let defaults = request.client.inner.default_headers.iter().filter(|(name, _)| !request.headers.contains_key(name));
+    for (name, value) in defaults.chain(&request.headers) {
+        headers.append(name, value).map_err(|err| js_error(err, ErrorKind::Config, url))?;
+    }
+    let init = RequestInit::new();
+    init.set_method(request.method.as_str());
+    init.set_headers(&headers);
+    if let Some(ref body) = request.body {
+        init.set_body(&Uint8Array::from(&*body.read_all()?).into());
+    }
+    let timeout = u32::try_from(request.effective_timeout().as_millis()).unwrap_or(u32::MAX);
+    init.set_signal(Some(&AbortSignal::timeout_with_u32(timeout)));
+    let fetched = web_sys::Request::new_with_str_and_init(url, &init).map_err(|err| js_error(err, ErrorKind::Config, url))?;
+
+    //in a web worker, the global object isn't a Window
+    let global = js_sys::global();
+    let promise = match global.dyn_ref::<Window>() {
+        Some(window) => window.fetch_with_request(&fetched),
+        None => global.unchecked_into::<WorkerGlobalScope>().fetch_with_request(&fetched),
+    };
+    let res: web_sys::Response = JsFuture::from(promise).await.map_err(|err| js_error(err, ErrorKind::Connect, url))?.unchecked_into();
+    let body = res.array_buffer().map_err(|err| js_error(err, ErrorKind::Read, url))?;
+    let body = JsFuture::from(body).await.map_err(|err| js_error(err, ErrorKind::Read, url))?;
+
+    let mut received = HeaderMap::new();
+    let entries = js_sys::try_iter(&res.headers()).map_err(|err| js_error(err, ErrorKind::Read, url))?;
+    for entry in entries.into_iter().flatten() {
+        let entry: Array = entry.map_err(|err| js_error(err, ErrorKind::Read, url))?.unchecked_into();
+        received.append(entry.get(0).as_string().unwrap_or_default(), entry.get(1).as_string().unwrap_or_default());
+    }
+    //the fetch API doesn't tell the version used, and opaque responses have the status 0
+    let status = StatusCode::from_u16(res.status())?;
+    let res = Response::from_fields(Version::Http11, status, &res.status_text(), &received, Uint8Array::new(&body).to_vec())?.with_url(&res.url());
+    Ok(res)
+}
+
+//convert a rejection of the fetch API, the timeout of its signal being reported as such
+fn js_error(err: JsValue, kind: ErrorKind, url: &str) -> HttpError {
+    let (name, message) = match err.dyn_ref::<js_sys::Error>() {
+        Some(err) => (String::from(err.name()), String::from(err.message())),
+        None => (String::new(), err.as_string().unwrap_or_else(|| format!("{:?}", err))),
+    };
+    let cause = match name.as_str() {
+        "TimeoutError" => io::ErrorKind::TimedOut,
+        _ => io::ErrorKind::Other,
+    };
+    HttpError::from(io::Error::new(cause, message)).context(kind, url)
+}
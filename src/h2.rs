@@ -0,0 +1,1032 @@
+use super::conn::{self, Stream};
+use super::AbortHandle;
+use super::Body;
+use super::hpack;
+use super::Client;
+use super::HeaderMap;
+use super::HttpError;
+use super::Method;
+use super::RequestBuilder;
+use super::Response;
+use super::Timings;
+
+use super::tls::TlsConnector;
+
+#[cfg(feature = "tokio")]
+use super::r#async;
+#[cfg(feature = "tokio")]
+use tokio::io::{ReadHalf, WriteHalf};
+#[cfg(feature = "tokio")]
+use tokio::sync::watch;
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
+#[cfg(feature = "tokio")]
+use std::sync::Weak;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+//frame types
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PUSH_PROMISE: u8 = 0x5;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+//frame flags
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+//settings
+const HEADER_TABLE_SIZE: u16 = 0x1;
+const ENABLE_PUSH: u16 = 0x2;
+const MAX_CONCURRENT_STREAMS: u16 = 0x3;
+const INITIAL_WINDOW_SIZE: u16 = 0x4;
+const MAX_FRAME_SIZE: u16 = 0x5;
+
+//error codes
+const REFUSED_STREAM: u32 = 0x7;
+const CANCEL: u32 = 0x8;
+
+//largest frame accepted, the default one
+const FRAME_SIZE: usize = 16_384;
+//flow control window of each stream and of the whole connection announced to the server
+const STREAM_WINDOW: u32 = 1 << 20;
+const CONNECTION_WINDOW: u32 = 1 << 24;
+//wait for the next frame before letting other requests use the connection
+const POLL: Duration = Duration::from_millis(10);
+//window of the server until its settings say otherwise
+const DEFAULT_WINDOW: i64 = 65_535;
+
+//headers specific to HTTP/1.1 connections, which are malformed in HTTP/2(RFC 9113 8.2.2), and `Expect`
+//since the body is sent right away
+const CONNECTION_HEADERS: [&str; 6] = ["connection", "keep-alive", "proxy-connection", "transfer-encoding", "upgrade", "expect"];
+
+//HTTP/2 connections shared by the clones of a Client, one per origin
+#[derive(Default)]
+pub(crate) struct Pool {
+    tls: OnceLock<TlsConnector>,
+    connections: Connections<Connection>,
+    //connections driven by the tasks of the async client
+    #[cfg(feature = "tokio")]
+    async_connections: Connections<AsyncConnection>,
+}
+
+impl fmt::Debug for Pool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let connections = self.connections.0.lock().map(|connections| connections.len()).unwrap_or_default();
+        f.debug_struct("Pool").field("connections", &connections).finish_non_exhaustive()
+    }
+}
+
+impl Pool {
+    //return the TLS connector offering HTTP/2 through ALPN, built on first use
+    pub(crate) fn tls_connector(&self, client: &Client) -> Result<&TlsConnector, HttpError> {
+        if let Some(connector) = self.tls.get() {
+            return Ok(connector);
+        }
+        let connector = client.tls_builder().request_alpns(&["h2", "http/1.1"]).build()?;
+        Ok(self.tls.get_or_init(|| connector))
+    }
+}
+
+//connection multiplexing requests, which can be closed to new streams
+trait Multiplexed {
+    fn is_open(&self) -> bool;
+}
+
+//connections of a kind, by origin
+struct Connections<C>(Mutex<HashMap<String, Arc<C>>>);
+
+impl<C> Default for Connections<C> {
+    fn default() -> Self {
+        Self(Mutex::default())
+    }
+}
+
+impl<C: Multiplexed> Connections<C> {
+    //return the connection to `origin` if it can still start streams
+    fn get(&self, origin: &str) -> Option<Arc<C>> {
+        let mut connections = self.0.lock().unwrap_or_else(|err| err.into_inner());
+        match connections.get(origin) {
+            Some(connection) if connection.is_open() => Some(connection.clone()),
+            Some(_) => {
+                connections.remove(origin);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn insert(&self, origin: String, connection: Arc<C>) {
+        self.0.lock().unwrap_or_else(|err| err.into_inner()).insert(origin, connection);
+    }
+}
+
+//request sent over a connection, filled as its frames are received
+#[derive(Default)]
+struct Exchange {
+    interim: Vec<Vec<(String, String)>>,
+    head: Option<Vec<(String, String)>>,
+    body: Vec<u8>,
+    trailers: Vec<(String, String)>,
+    //body of the request and how much of it was sent, and the window for the data sent on the stream
+    upload: Vec<u8>,
+    uploaded: usize,
+    window: i64,
+    limit: Option<u64>,
+    first_byte: Option<Instant>,
+    done: bool,
+    too_large: bool,
+    reset: Option<u32>,
+}
+
+//parts of a response received over HTTP/2
+pub(crate) struct Reply {
+    interim: Vec<Vec<(String, String)>>,
+    head: Vec<(String, String)>,
+    body: Vec<u8>,
+    trailers: Vec<(String, String)>,
+    first_byte: Option<Instant>,
+}
+
+//state of a HTTP/2 connection, without its IO
+//
+//The frames to write are queued, and the frames read are applied to the streams, so the blocking and the async
+//connections only move bytes.
+struct Core {
+    decoder: hpack::Decoder,
+    streams: HashMap<u32, Exchange>,
+    next_id: u32,
+    //window for the data sent on the connection, and initial one of the streams
+    window: i64,
+    initial_window: i64,
+    max_frame: usize,
+    max_streams: usize,
+    //last stream processed by the server once it sent GOAWAY
+    goaway: Option<u32>,
+    broken: bool,
+    //header block continued in CONTINUATION frames: stream, flags of the HEADERS frame and fragments so far
+    continuation: Option<(u32, u8, Vec<u8>)>,
+    //frames waiting to be written
+    out: Vec<u8>,
+}
+
+impl Core {
+    //start the connection with the preface, the settings of the client and its connection window
+    fn new() -> Self {
+        let mut core = Self {
+            decoder: hpack::Decoder::new(),
+            streams: HashMap::new(),
+            next_id: 1,
+            window: DEFAULT_WINDOW,
+            initial_window: DEFAULT_WINDOW,
+            max_frame: FRAME_SIZE,
+            max_streams: usize::MAX,
+            goaway: None,
+            broken: false,
+            continuation: None,
+            out: PREFACE.to_vec(),
+        };
+        let mut settings = Vec::new();
+        for (id, value) in [(HEADER_TABLE_SIZE, hpack::TABLE_SIZE as u32), (ENABLE_PUSH, 0), (INITIAL_WINDOW_SIZE, STREAM_WINDOW)] {
+            settings.extend_from_slice(&id.to_be_bytes());
+            settings.extend_from_slice(&value.to_be_bytes());
+        }
+        core.queue_frame(SETTINGS, 0, 0, &settings);
+        core.queue_frame(WINDOW_UPDATE, 0, 0, &(CONNECTION_WINDOW - DEFAULT_WINDOW as u32).to_be_bytes());
+        core
+    }
+
+    fn queue_frame(&mut self, kind: u8, flags: u8, id: u32, payload: &[u8]) {
+        self.out.extend_from_slice(&(payload.len() as u32).to_be_bytes()[1..]);
+        self.out.push(kind);
+        self.out.push(flags);
+        self.out.extend_from_slice(&id.to_be_bytes());
+        self.out.extend_from_slice(payload);
+    }
+
+    //return if no new stream can be started
+    fn is_closed(&self) -> bool {
+        self.goaway.is_some() || self.broken
+    }
+
+    //start a stream for a request, queueing its header block and as much of its body as the windows allow,
+    //`None` if the connection can't take it
+    fn start(&mut self, block: &[u8], body: Option<&[u8]>, limit: Option<u64>) -> Option<u32> {
+        if self.is_closed() || self.streams.len() >= self.max_streams || self.next_id > i32::MAX as u32 {
+            return None;
+        }
+        let id = self.next_id;
+        self.next_id += 2;
+        let upload = body.unwrap_or_default().to_vec();
+        let mut fragments = block.chunks(self.max_frame).peekable();
+        let mut kind = HEADERS;
+        loop {
+            let fragment = fragments.next().unwrap_or_default();
+            let mut flags = if fragments.peek().is_none() { END_HEADERS } else { 0 };
+            if kind == HEADERS && upload.is_empty() {
+                flags |= END_STREAM;
+            }
+            self.queue_frame(kind, flags, id, fragment);
+            if flags & END_HEADERS != 0 {
+                break;
+            }
+            kind = CONTINUATION;
+        }
+        self.streams.insert(id, Exchange { upload, window: self.initial_window, limit, ..Default::default() });
+        self.send_pending();
+        Some(id)
+    }
+
+    //queue the bodies left to send, as the flow control windows allow
+    fn send_pending(&mut self) {
+        let mut ids = self.streams.iter().filter(|(_, exchange)| exchange.uploaded < exchange.upload.len()).map(|(&id, _)| id).collect::<Vec<_>>();
+        ids.sort_unstable();
+        for id in ids {
+            let Some(exchange) = self.streams.get_mut(&id) else { continue };
+            // the server answered or gave up before the whole body was sent
+            if exchange.done || exchange.reset.is_some() {
+                exchange.upload = Vec::new();
+                continue;
+            }
+            let (upload, mut sent, mut window) = (std::mem::take(&mut exchange.upload), exchange.uploaded, exchange.window);
+            while sent < upload.len() {
+                let available = self.window.min(window).min(self.max_frame as i64);
+                if available <= 0 {
+                    break;
+                }
+                let n = (upload.len() - sent).min(available as usize);
+                let flags = if sent + n == upload.len() { END_STREAM } else { 0 };
+                self.queue_frame(DATA, flags, id, &upload[sent..sent + n]);
+                sent += n;
+                self.window -= n as i64;
+                window -= n as i64;
+            }
+            if let Some(exchange) = self.streams.get_mut(&id) {
+                exchange.window = window;
+                exchange.uploaded = sent;
+                if sent < upload.len() {
+                    exchange.upload = upload;
+                }
+            }
+        }
+    }
+
+    //apply a frame read from the server to the connection or to its stream
+    fn apply(&mut self, kind: u8, flags: u8, id: u32, payload: &[u8]) -> Result<(), HttpError> {
+        if self.continuation.is_some() && kind != CONTINUATION {
+            return Err(HttpError::Parse("HTTP/2 header block interrupted"));
+        }
+
+        match kind {
+            DATA => {
+                let data = unpad(flags, payload)?;
+                let mut ended = flags & END_STREAM != 0;
+                let mut cancel = false;
+                if let Some(exchange) = self.streams.get_mut(&id) {
+                    exchange.body.extend_from_slice(data);
+                    if exchange.limit.map(|limit| exchange.body.len() as u64 > limit).unwrap_or(false) {
+                        exchange.too_large = true;
+                        exchange.done = true;
+                        ended = true;
+                        cancel = true;
+                    }
+                    exchange.done |= flags & END_STREAM != 0;
+                }
+                // the data is buffered right away, so the windows are given back at once
+                if !payload.is_empty() {
+                    self.queue_frame(WINDOW_UPDATE, 0, 0, &(payload.len() as u32).to_be_bytes());
+                    if !ended {
+                        self.queue_frame(WINDOW_UPDATE, 0, id, &(payload.len() as u32).to_be_bytes());
+                    }
+                }
+                if cancel {
+                    self.queue_frame(RST_STREAM, 0, id, &CANCEL.to_be_bytes());
+                }
+            }
+            HEADERS => {
+                let mut block = unpad(flags, payload)?;
+                if flags & PRIORITY != 0 {
+                    block = block.get(5..).ok_or(HttpError::Parse("invalid HTTP/2 HEADERS frame"))?;
+                }
+                match flags & END_HEADERS {
+                    0 => self.continuation = Some((id, flags, block.to_vec())),
+                    _ => self.headers(id, flags, block)?,
+                }
+            }
+            CONTINUATION => {
+                let (stream, first_flags, mut block) = self.continuation.take().ok_or(HttpError::Parse("unexpected HTTP/2 CONTINUATION frame"))?;
+                if stream != id {
+                    return Err(HttpError::Parse("HTTP/2 header block interrupted"));
+                }
+                block.extend_from_slice(payload);
+                match flags & END_HEADERS {
+                    0 => self.continuation = Some((stream, first_flags, block)),
+                    _ => self.headers(id, first_flags, &block)?,
+                }
+            }
+            RST_STREAM => {
+                let code = payload.get(..4).map(|code| u32::from_be_bytes([code[0], code[1], code[2], code[3]]));
+                if let Some(exchange) = self.streams.get_mut(&id) {
+                    exchange.reset = Some(code.unwrap_or_default());
+                }
+            }
+            SETTINGS if flags & ACK == 0 => {
+                for setting in payload.chunks_exact(6) {
+                    let value = u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+                    match u16::from_be_bytes([setting[0], setting[1]]) {
+                        MAX_CONCURRENT_STREAMS => self.max_streams = value as usize,
+                        INITIAL_WINDOW_SIZE => {
+                            let delta = value as i64 - self.initial_window;
+                            self.initial_window = value as i64;
+                            for exchange in self.streams.values_mut() {
+                                exchange.window += delta;
+                            }
+                        }
+                        MAX_FRAME_SIZE => self.max_frame = value as usize,
+                        _ => (),
+                    }
+                }
+                self.queue_frame(SETTINGS, ACK, 0, &[]);
+                self.send_pending();
+            }
+            PING if flags & ACK == 0 => self.queue_frame(PING, ACK, 0, payload),
+            GOAWAY => {
+                let last = payload.get(..4).ok_or(HttpError::Parse("invalid HTTP/2 GOAWAY frame"))?;
+                self.goaway = Some(u32::from_be_bytes([last[0], last[1], last[2], last[3]]) & 0x7FFF_FFFF);
+            }
+            WINDOW_UPDATE => {
+                let increment = payload.get(..4).ok_or(HttpError::Parse("invalid HTTP/2 WINDOW_UPDATE frame"))?;
+                let increment = (u32::from_be_bytes([increment[0], increment[1], increment[2], increment[3]]) & 0x7FFF_FFFF) as i64;
+                match id {
+                    0 => self.window += increment,
+                    id => {
+                        if let Some(exchange) = self.streams.get_mut(&id) {
+                            exchange.window += increment;
+                        }
+                    }
+                }
+                self.send_pending();
+            }
+            PUSH_PROMISE => return Err(HttpError::Parse("HTTP/2 push promise while disabled")),
+            // PRIORITY, acknowledgements and unknown frames
+            _ => (),
+        }
+        Ok(())
+    }
+
+    //decode a complete header block, kept as the head, an interim head or the trailers of its stream
+    fn headers(&mut self, id: u32, flags: u8, block: &[u8]) -> Result<(), HttpError> {
+        // the block is decoded even if the stream is gone, to keep the table in sync with the server
+        let fields = self.decoder.decode(block)?;
+        if let Some(exchange) = self.streams.get_mut(&id) {
+            if exchange.head.is_some() {
+                exchange.trailers = fields;
+            } else if status(&fields).map(conn::is_interim).unwrap_or(false) {
+                exchange.interim.push(fields);
+            } else {
+                exchange.first_byte = Some(Instant::now());
+                exchange.head = Some(fields);
+            }
+            exchange.done |= flags & END_STREAM != 0;
+        }
+        Ok(())
+    }
+
+    //return the outcome of the stream once it is over, `Ok(None)` if it was refused without being processed
+    fn finish(&mut self, id: u32) -> Option<Result<Option<Reply>, HttpError>> {
+        let exchange = self.streams.get(&id)?;
+        let over = exchange.done || exchange.reset.is_some() || self.goaway.map(|last| id > last).unwrap_or(false) || self.broken;
+        if !over {
+            return None;
+        }
+        let exchange = self.streams.remove(&id).unwrap_or_default();
+        if exchange.too_large {
+            return Some(Err(HttpError::TooLarge { limit: exchange.limit.unwrap_or_default() }));
+        }
+        Some(match (exchange.done, exchange.reset, exchange.head) {
+            (true, _, Some(head)) => Ok(Some(Reply {
+                interim: exchange.interim,
+                head,
+                body: exchange.body,
+                trailers: exchange.trailers,
+                first_byte: exchange.first_byte,
+            })),
+            (true, _, None) => Err(HttpError::Parse("HTTP/2 stream ended without a response")),
+            (_, Some(REFUSED_STREAM), _) => Ok(None),
+            (_, Some(_), _) => Err(HttpError::Parse("HTTP/2 stream reset by the server")),
+            _ if self.broken => Err(HttpError::Parse("HTTP/2 connection lost")),
+            _ => Ok(None),
+        })
+    }
+
+    //give up on a stream, the others going on
+    fn cancel(&mut self, id: u32) {
+        if self.streams.remove(&id).is_some() && !self.broken {
+            self.queue_frame(RST_STREAM, 0, id, &CANCEL.to_be_bytes());
+        }
+    }
+}
+
+//return the length of the payload following a frame header
+fn payload_len(header: &[u8; 9]) -> Result<usize, HttpError> {
+    let len = u32::from_be_bytes([0, header[0], header[1], header[2]]) as usize;
+    match len > FRAME_SIZE {
+        true => Err(HttpError::Parse("HTTP/2 frame too large")),
+        false => Ok(len),
+    }
+}
+
+//return the type, the flags and the stream of a frame header
+fn frame_kind(header: &[u8; 9]) -> (u8, u8, u32) {
+    (header[3], header[4], u32::from_be_bytes([header[5], header[6], header[7], header[8]]) & 0x7FFF_FFFF)
+}
+
+//HTTP/2 connection multiplexing the requests of a Client to an origin
+//
+//There is no background thread: the requests waiting for a response take turns polling for the next frame
+//and hand it to the stream it belongs to.
+pub(crate) struct Connection {
+    inner: Mutex<Inner>,
+    open: AtomicBool,
+    timeout: Duration,
+}
+
+impl fmt::Debug for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Connection").field("open", &self.is_open()).finish_non_exhaustive()
+    }
+}
+
+impl Multiplexed for Connection {
+    fn is_open(&self) -> bool {
+        self.is_open()
+    }
+}
+
+struct Inner {
+    stream: BufReader<Stream>,
+    core: Core,
+}
+
+impl Connection {
+    //start HTTP/2 over a connection which negotiated it
+    pub(crate) fn handshake(stream: Stream, timeout: Duration) -> Result<Self, HttpError> {
+        let mut inner = Inner { stream: BufReader::new(stream), core: Core::new() };
+        inner.flush()?;
+        Ok(Self { inner: Mutex::new(inner), open: AtomicBool::new(true), timeout })
+    }
+
+    //return if new streams can be started on the connection
+    pub(crate) fn is_open(&self) -> bool {
+        self.open.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Inner> {
+        self.inner.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    //send a request and wait for its response, `None` if it was refused without being processed, the stream
+    //being cancelled once `abort` is
+    fn send(&self, block: &[u8], body: Option<&[u8]>, limit: Option<u64>, abort: &AbortHandle) -> Result<Option<Reply>, HttpError> {
+        let id = {
+            let mut inner = self.lock();
+            let Some(id) = inner.core.start(block, body, limit) else { return Ok(None) };
+            if let Err(err) = inner.flush() {
+                inner.core.streams.remove(&id);
+                inner.core.broken = true;
+                self.open.store(false, Ordering::Relaxed);
+                return Err(err);
+            }
+            id
+        };
+
+        let mut deadline = Instant::now() + self.timeout;
+        loop {
+            let mut inner = self.lock();
+            if abort.is_aborted() {
+                inner.core.cancel(id);
+                //the other streams go on, a connection failing to write is found out by them
+                let _ = inner.flush();
+                return Err(HttpError::Aborted);
+            }
+            if let Some(outcome) = inner.core.finish(id) {
+                return outcome;
+            }
+            let polled = inner.poll_frame(self.timeout);
+            if inner.core.goaway.is_some() {
+                self.open.store(false, Ordering::Relaxed);
+            }
+            match polled {
+                Ok(true) => deadline = Instant::now() + self.timeout,
+                Ok(false) if Instant::now() > deadline => {
+                    inner.core.streams.remove(&id);
+                    return Err(io::Error::from(io::ErrorKind::TimedOut).into());
+                }
+                // let the other requests write theirs
+                Ok(false) => {
+                    drop(inner);
+                    thread::yield_now();
+                }
+                Err(err) => {
+                    inner.core.streams.remove(&id);
+                    inner.core.broken = true;
+                    self.open.store(false, Ordering::Relaxed);
+                    return Err(err);
+                }
+            }
+        }
+    }
+}
+
+impl Inner {
+    //read the next frame if one starts arriving before a short delay, so the connection isn't held while idle
+    fn poll_frame(&mut self, timeout: Duration) -> Result<bool, HttpError> {
+        if self.stream.buffer().is_empty() {
+            self.stream.get_mut().set_read_timeout(Some(POLL))?;
+            let polled = self.stream.fill_buf().map(|buf| buf.is_empty());
+            self.stream.get_mut().set_read_timeout(Some(timeout))?;
+            match polled {
+                Ok(false) => (),
+                Ok(true) => return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into()),
+                Err(err) if matches!(err.kind(), io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut) => return Ok(false),
+                Err(err) => return Err(err.into()),
+            }
+        }
+        self.read_frame()?;
+        Ok(true)
+    }
+
+    //write the queued frames
+    fn flush(&mut self) -> Result<(), HttpError> {
+        if !self.core.out.is_empty() {
+            let out = std::mem::take(&mut self.core.out);
+            self.stream.get_mut().write_all(&out)?;
+            self.stream.get_mut().flush()?;
+        }
+        Ok(())
+    }
+
+    //read the next frame, apply it and write the frames it calls for
+    fn read_frame(&mut self) -> Result<(), HttpError> {
+        let mut header = [0; 9];
+        self.stream.read_exact(&mut header)?;
+        let mut payload = vec![0; payload_len(&header)?];
+        self.stream.read_exact(&mut payload)?;
+        let (kind, flags, id) = frame_kind(&header);
+        self.core.apply(kind, flags, id, &payload)?;
+        self.flush()
+    }
+}
+
+//HTTP/2 connection of the async client
+//
+//A task reads the frames and applies them, waking the requests waiting on their stream. The frames are queued
+//by the requests and the task, and written by whichever holds the writer, in the order they were queued.
+#[cfg(feature = "tokio")]
+pub(crate) struct AsyncConnection {
+    core: Mutex<Core>,
+    writer: tokio::sync::Mutex<WriteHalf<r#async::Connection>>,
+    events: watch::Sender<()>,
+    open: AtomicBool,
+    reader: OnceLock<tokio::task::AbortHandle>,
+}
+
+#[cfg(feature = "tokio")]
+impl fmt::Debug for AsyncConnection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AsyncConnection").field("open", &self.is_open()).finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Multiplexed for AsyncConnection {
+    fn is_open(&self) -> bool {
+        self.is_open()
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl Drop for AsyncConnection {
+    fn drop(&mut self) {
+        if let Some(reader) = self.reader.get() {
+            reader.abort();
+        }
+    }
+}
+
+//cancels the stream of a request given up, by a timeout or a dropped future
+#[cfg(feature = "tokio")]
+struct Cancel<'a>(&'a AsyncConnection, Option<u32>);
+
+#[cfg(feature = "tokio")]
+impl Drop for Cancel<'_> {
+    fn drop(&mut self) {
+        if let Some(id) = self.1 {
+            //the reset goes out with the next frames written on the connection
+            self.0.lock().cancel(id);
+        }
+    }
+}
+
+#[cfg(feature = "tokio")]
+impl AsyncConnection {
+    //start HTTP/2 over a connection which negotiated it, its frames being read by a task of the runtime
+    pub(crate) async fn handshake(stream: r#async::Connection) -> Result<Arc<Self>, HttpError> {
+        let (reader, writer) = tokio::io::split(stream);
+        let connection = Arc::new(Self {
+            core: Mutex::new(Core::new()),
+            writer: tokio::sync::Mutex::new(writer),
+            events: watch::Sender::new(()),
+            open: AtomicBool::new(true),
+            reader: OnceLock::new(),
+        });
+        connection.flush().await?;
+        let task = tokio::spawn(Self::read(Arc::downgrade(&connection), tokio::io::BufReader::new(reader)));
+        let _ = connection.reader.set(task.abort_handle());
+        Ok(connection)
+    }
+
+    //return if new streams can be started on the connection
+    pub(crate) fn is_open(&self) -> bool {
+        self.open.load(Ordering::Relaxed)
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Core> {
+        self.core.lock().unwrap_or_else(|err| err.into_inner())
+    }
+
+    //read the frames until the connection fails or is dropped
+    async fn read(connection: Weak<Self>, mut stream: tokio::io::BufReader<ReadHalf<r#async::Connection>>) {
+        use tokio::io::AsyncReadExt;
+        loop {
+            let frame = async {
+                let mut header = [0; 9];
+                stream.read_exact(&mut header).await?;
+                let mut payload = vec![0; payload_len(&header)?];
+                stream.read_exact(&mut payload).await?;
+                Ok::<_, HttpError>((header, payload))
+            }
+            .await;
+            let Some(connection) = connection.upgrade() else { return };
+            let applied = frame.and_then(|(header, payload)| {
+                let (kind, flags, id) = frame_kind(&header);
+                connection.lock().apply(kind, flags, id, &payload)
+            });
+            let applied = match applied {
+                Ok(()) => connection.flush().await,
+                Err(err) => Err(err),
+            };
+            let broken = {
+                let mut core = connection.lock();
+                core.broken |= applied.is_err();
+                if core.is_closed() {
+                    connection.open.store(false, Ordering::Relaxed);
+                }
+                core.broken
+            };
+            connection.events.send_replace(());
+            if broken {
+                return;
+            }
+        }
+    }
+
+    //write the queued frames
+    async fn flush(&self) -> Result<(), HttpError> {
+        use tokio::io::AsyncWriteExt;
+        let mut writer = self.writer.lock().await;
+        let out = std::mem::take(&mut self.lock().out);
+        if !out.is_empty() {
+            writer.write_all(&out).await?;
+            writer.flush().await?;
+        }
+        Ok(())
+    }
+
+    //send a request and wait for its response, `None` if it was refused without being processed, the stream
+    //being cancelled once `abort` is
+    async fn send(self: &Arc<Self>, block: &[u8], body: Option<&[u8]>, limit: Option<u64>, abort: &AbortHandle) -> Result<Option<Reply>, HttpError> {
+        let mut events = self.events.subscribe();
+        let Some(id) = self.lock().start(block, body, limit) else { return Ok(None) };
+        let mut stream = Cancel(self, Some(id));
+        if let Err(err) = self.flush().await {
+            self.lock().broken = true;
+            self.open.store(false, Ordering::Relaxed);
+            return Err(err);
+        }
+        let connection = Arc::downgrade(self);
+        abort.register_with(Box::new(move || {
+            if let Some(connection) = connection.upgrade() {
+                connection.events.send_replace(());
+            }
+        }));
+        loop {
+            if abort.is_aborted() {
+                drop(stream);
+                //the other streams go on, a connection failing to write is found out by them
+                let _ = self.flush().await;
+                return Err(HttpError::Aborted);
+            }
+            if let Some(outcome) = self.lock().finish(id) {
+                stream.1 = None;
+                return outcome;
+            }
+            if events.changed().await.is_err() {
+                return Err(HttpError::Parse("HTTP/2 connection lost"));
+            }
+        }
+    }
+}
+
+//return the payload of a frame without its padding
+fn unpad(flags: u8, payload: &[u8]) -> Result<&[u8], HttpError> {
+    if flags & PADDED == 0 {
+        return Ok(payload);
+    }
+    let (&padding, rest) = payload.split_first().ok_or(HttpError::Parse("invalid HTTP/2 padding"))?;
+    rest.len()
+        .checked_sub(padding as usize)
+        .map(|end| &rest[..end])
+        .ok_or(HttpError::Parse("invalid HTTP/2 padding"))
+}
+
+fn status(fields: &[(String, String)]) -> Option<u16> {
+    fields.iter().find(|(name, _)| name == ":status").and_then(|(_, value)| value.parse().ok())
+}
+
+//write a HTTP/2 head in the HTTP/1.1 format the responses are parsed from
+fn head(fields: &[(String, String)]) -> Result<String, HttpError> {
+    let status = status(fields).ok_or(HttpError::Parse("HTTP/2 response without status"))?;
+    let mut head = format!("HTTP/2 {}\r\n", status);
+    for (name, value) in fields.iter().filter(|(name, _)| !name.starts_with(':')) {
+        head.push_str(name);
+        head.push_str(": ");
+        head.push_str(value);
+        head.push_str("\r\n");
+    }
+    head.push_str("\r\n");
+    Ok(head)
+}
+
+impl RequestBuilder {
+    //send the request over the HTTP/2 connection to its origin, opening one if there is none yet and falling
+    //back to HTTP/1.1 when the server doesn't negotiate HTTP/2
+    pub(crate) fn send_h2(&self, started: Instant, timeout: Duration, timings: &mut Timings) -> Result<Response, HttpError> {
+        let pool = &self.client.inner.h2;
+        let origin = format!("{}:{}", self.host, self.port);
+        let block = self.h2_block();
+        let body = self.body.as_ref().map(Body::read_all).transpose()?;
+        let limit = self.client.inner.max_response_size;
+        let sent = Instant::now();
+        let reply = match pool.connections.get(&origin) {
+            Some(connection) => connection.send(&block, body.as_deref(), limit, &self.abort)?,
+            None => None,
+        };
+        let reply = match reply {
+            Some(reply) => reply,
+            None => match self.open_h2(started, timeout, timings)? {
+                Ok(connection) => connection.send(&block, body.as_deref(), limit, &self.abort)?.ok_or(HttpError::Parse("HTTP/2 stream refused"))?,
+                Err(stream) => {
+                    let slot = self.client.inner.pool.acquire(&self.pool_key())?;
+                    return self.exchange(stream, slot, Some(timeout), timings);
+                }
+            },
+        };
+        self.h2_response(reply, sent, timings)
+    }
+
+    //send the request over the HTTP/2 connection to its origin from the async client, `None` if there is none
+    //or it refused the stream
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn send_async_h2(&self, connection: Option<Arc<AsyncConnection>>, body: Option<&[u8]>, timings: &mut Timings) -> Result<Option<Response>, HttpError> {
+        let pool = &self.client.inner.h2;
+        let Some(connection) = connection.or_else(|| pool.async_connections.get(&format!("{}:{}", self.host, self.port))) else { return Ok(None) };
+        let block = self.h2_block();
+        let sent = Instant::now();
+        match connection.send(&block, body, self.client.inner.max_response_size, &self.abort).await? {
+            Some(reply) => self.h2_response(reply, sent, timings).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    //encode the header block of the request
+    fn h2_block(&self) -> Vec<u8> {
+        let fields = self.fields();
+        let mut block = Vec::new();
+        hpack::encode(fields.iter().map(|(name, value)| (name.as_str(), value.as_str())), &mut block);
+        self.client.verbose('>', || fields.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect::<String>());
+        block
+    }
+
+    //build the response to the request from the reply received over HTTP/2
+    fn h2_response(&self, reply: Reply, sent: Instant, timings: &mut Timings) -> Result<Response, HttpError> {
+        timings.first_byte = reply.first_byte.map(|at| at.saturating_duration_since(sent)).unwrap_or_default();
+
+        let interim = reply.interim.iter().map(|fields| head(fields).map(String::into_bytes)).collect::<Result<Vec<_>, _>>()?;
+        let head = head(&reply.head)?;
+        for (fields, len) in reply.interim.iter().zip(interim.iter().map(Vec::len)).chain([(&reply.head, head.len())]) {
+            let headers = fields.iter().filter(|(name, _)| !name.starts_with(':')).count();
+            self.client.inner.header_limits.check(len, headers)?;
+        }
+        let length = reply.head.iter().find(|(name, _)| name == "content-length").and_then(|(_, value)| value.parse().ok());
+        if !self.client.inner.allow_incomplete && !matches!(self.method, Method::Head) {
+            conn::check_complete(length, reply.body.len() as u64)?;
+        }
+        let trailers = reply.trailers.into_iter().collect::<HeaderMap>();
+        self.parse_response(&interim, head.as_bytes(), reply.body, trailers).map(|res| res.with_alpn(Some("h2".to_owned())))
+    }
+
+    //open a connection to the origin and add it to the pool if the server negotiates HTTP/2, or else
+    //return it for HTTP/1.1
+    pub(crate) fn open_h2(&self, started: Instant, timeout: Duration, timings: &mut Timings) -> Result<Result<Arc<Connection>, Stream>, HttpError> {
+        let pool = &self.client.inner.h2;
+        let tcp = self.open_tcp(started, timeout, timings)?;
+        let tls = self.handshake(tcp, pool.tls_connector(&self.client)?, started, timings)?;
+        if tls.negotiated_alpn().ok().flatten().as_deref() != Some(b"h2") {
+            return Ok(Err(Stream::Tls(Box::new(tls))));
+        }
+        self.client.verbose('*', || "Using HTTP/2");
+        //the connection is shared, aborting the request only cancels its stream
+        self.abort.clear();
+        let connection = Arc::new(Connection::handshake(Stream::Tls(Box::new(tls)), timeout)?);
+        pool.connections.insert(format!("{}:{}", self.host, self.port), connection.clone());
+        Ok(Ok(connection))
+    }
+
+    //share a connection of the async client which negotiated HTTP/2
+    #[cfg(feature = "tokio")]
+    pub(crate) async fn open_async_h2(&self, stream: r#async::Connection) -> Result<Arc<AsyncConnection>, HttpError> {
+        self.client.verbose('*', || "Using HTTP/2");
+        self.abort.clear();
+        let connection = AsyncConnection::handshake(stream).await?;
+        self.client.inner.h2.async_connections.insert(format!("{}:{}", self.host, self.port), connection.clone());
+        Ok(connection)
+    }
+
+    //return the pseudo headers and the headers of the request, as written for HTTP/1.1 without those
+    //specific to its connections
+    pub(crate) fn fields(&self) -> Vec<(String, String)> {
+        let mut head = String::new();
+        self.write_header(&mut head);
+        let mut fields = vec![
+            (":method".to_owned(), self.method.to_string()),
+            (":scheme".to_owned(), self.scheme.clone()),
+            (":authority".to_owned(), self.authority(false)),
+            (":path".to_owned(), self.target()),
+        ];
+        for (name, value) in head.split("\r\n").skip(1).filter_map(|line| line.split_once(':')) {
+            let name = name.trim().to_ascii_lowercase();
+            let value = value.trim();
+            match name.as_str() {
+                "host" => fields[2].1 = value.to_owned(),
+                "te" if !value.eq_ignore_ascii_case("trailers") => (),
+                name if CONNECTION_HEADERS.contains(&name) => (),
+                _ => fields.push((name, value.to_owned())),
+            }
+        }
+        fields
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    //connection replaying frames from the server and keeping what is written to it
+    struct Server(Cursor<Vec<u8>>, Arc<Mutex<Vec<u8>>>);
+
+    impl Read for Server {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for Server {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.1.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl crate::Transport for Server {}
+
+    fn frame(kind: u8, flags: u8, id: u32, payload: &[u8]) -> Vec<u8> {
+        let mut frame = (payload.len() as u32).to_be_bytes()[1..].to_vec();
+        frame.extend_from_slice(&[kind, flags]);
+        frame.extend_from_slice(&id.to_be_bytes());
+        frame.extend_from_slice(payload);
+        frame
+    }
+
+    //split the frames written by the client after its preface
+    fn frames(mut written: &[u8]) -> Vec<(u8, u8, u32, Vec<u8>)> {
+        written = &written[PREFACE.len()..];
+        let mut frames = Vec::new();
+        while written.len() >= 9 {
+            let len = u32::from_be_bytes([0, written[0], written[1], written[2]]) as usize;
+            let id = u32::from_be_bytes([written[5], written[6], written[7], written[8]]);
+            frames.push((written[3], written[4], id, written[9..9 + len].to_vec()));
+            written = &written[9 + len..];
+        }
+        frames
+    }
+
+    #[test]
+    fn multiplexed_streams() {
+        let mut headers = Vec::new();
+        hpack::encode([(":status", "200"), ("content-type", "text/plain")], &mut headers);
+        let mut trailers = Vec::new();
+        hpack::encode([("grpc-status", "0")], &mut trailers);
+        // responses to the streams 1 and 3 interleaved, the head of the first one split by a CONTINUATION
+        let replies = [
+            frame(SETTINGS, 0, 0, &[0, 3, 0, 0, 0, 100]),
+            frame(HEADERS, 0, 1, &headers[..2]),
+            frame(CONTINUATION, END_HEADERS, 1, &headers[2..]),
+            frame(HEADERS, END_HEADERS, 3, &headers),
+            frame(DATA, PADDED, 1, b"\x02hello\0\0"),
+            frame(PING, 0, 0, b"12345678"),
+            frame(DATA, END_STREAM, 3, b"second"),
+            frame(HEADERS, END_HEADERS | END_STREAM, 1, &trailers),
+        ]
+        .concat();
+
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let stream = Stream::Custom(Box::new(Server(Cursor::new(replies), written.clone())));
+        let connection = Connection::handshake(stream, Duration::from_secs(1)).unwrap();
+
+        let mut block = Vec::new();
+        hpack::encode([(":method", "POST"), (":path", "/")], &mut block);
+        // the first stream is left in flight while the second one is sent and received
+        let mut inner = connection.lock();
+        assert_eq!(inner.core.start(&block, Some(b"body"), None), Some(1));
+        inner.flush().unwrap();
+        drop(inner);
+        let second = connection.send(&block, None, None, &AbortHandle::default()).unwrap().unwrap();
+        assert_eq!(second.head, [(":status".to_owned(), "200".to_owned()), ("content-type".to_owned(), "text/plain".to_owned())]);
+        assert_eq!(second.body, b"second");
+
+        // the trailers ending the first stream are still to be read
+        let mut inner = connection.lock();
+        inner.read_frame().unwrap();
+        let exchange = &inner.core.streams[&1];
+        assert!(exchange.done);
+        assert_eq!(exchange.body, b"hello");
+        assert_eq!(exchange.trailers, [("grpc-status".to_owned(), "0".to_owned())]);
+        assert_eq!(inner.core.max_streams, 100);
+        drop(inner);
+
+        let frames = frames(&written.lock().unwrap());
+        let kinds = frames.iter().map(|frame| (frame.0, frame.1, frame.2)).collect::<Vec<_>>();
+        assert_eq!(kinds[..5], [(SETTINGS, 0, 0), (WINDOW_UPDATE, 0, 0), (HEADERS, END_HEADERS, 1), (DATA, END_STREAM, 1), (HEADERS, END_HEADERS | END_STREAM, 3)]);
+        assert!(kinds.contains(&(SETTINGS, ACK, 0)));
+        assert!(frames.contains(&(PING, ACK, 0, b"12345678".to_vec())));
+        assert!(frames.contains(&(WINDOW_UPDATE, 0, 1, 8u32.to_be_bytes().to_vec())));
+    }
+
+    #[test]
+    fn aborted_stream() {
+        let replies = frame(SETTINGS, 0, 0, &[]);
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let stream = Stream::Custom(Box::new(Server(Cursor::new(replies), written.clone())));
+        let connection = Connection::handshake(stream, Duration::from_secs(1)).unwrap();
+
+        let mut block = Vec::new();
+        hpack::encode([(":method", "GET"), (":path", "/")], &mut block);
+        let abort = AbortHandle::default();
+        abort.abort();
+        assert!(matches!(connection.send(&block, None, None, &abort), Err(HttpError::Aborted)));
+        // only the stream is cancelled, the connection stays open for the others
+        assert!(connection.is_open());
+        assert!(connection.lock().core.streams.is_empty());
+        let frames = frames(&written.lock().unwrap());
+        assert_eq!(frames.last().unwrap(), &(RST_STREAM, 0, 1, CANCEL.to_be_bytes().to_vec()));
+    }
+
+    #[test]
+    fn request_fields() {
+        let request = Client::new().post("https://example.com:8443/a?b=1").unwrap().header("TE", "gzip").unwrap().body(b"hi".to_vec());
+        let fields = request.fields();
+        let fields = fields.iter().map(|(name, value)| (name.as_str(), value.as_str())).collect::<Vec<_>>();
+        assert_eq!(fields[..4], [(":method", "POST"), (":scheme", "https"), (":authority", "example.com:8443"), (":path", "/a?b=1")]);
+        assert!(fields.contains(&("content-length", "2")));
+        assert!(!fields.iter().any(|(name, _)| ["connection", "host", "te"].contains(name)));
+    }
+}
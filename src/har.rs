@@ -0,0 +1,233 @@
+use super::date;
+use super::Body;
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+use super::Timings;
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+///recorder of the exchanges of the Clients it's set on, exported as an HTTP Archive(HAR 1.2).
+///
+///Every exchange is recorded, including redirects and retries, so the archive can be opened in browser devtools.
+/// # Example
+/// ```no_run
+/// use smolhttp::{Client, HarRecorder};
+///
+/// let recorder = HarRecorder::new();
+/// let client = Client::builder().har(recorder.clone()).build().unwrap();
+/// client.get("https://www.rust-lang.org").unwrap().send().unwrap();
+/// recorder.save("session.har").unwrap();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct HarRecorder {
+    entries: Arc<Mutex<Vec<Entry>>>,
+}
+
+//one recorded exchange
+#[derive(Debug)]
+pub(crate) struct Entry {
+    started: SystemTime,
+    timings: Timings,
+    method: String,
+    url: String,
+    version: String,
+    request_headers: Vec<(String, String)>,
+    request_body: usize,
+    status: u16,
+    reason: String,
+    response_version: String,
+    response_headers: Vec<(String, String)>,
+    response_body: usize,
+}
+
+impl Entry {
+    //record a request and its response, started at `started`
+    pub(crate) fn new(request: &RequestBuilder, res: &Response, started: SystemTime) -> Self {
+        let mut head = String::new();
+        request.write_header(&mut head);
+        let request_headers = head
+            .split("\r\n")
+            .skip(1)
+            .filter_map(|line| line.split_once(": "))
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+
+        Self {
+            started,
+            timings: res.timings(),
+            method: request.method.to_string(),
+            url: request.raw_url.clone(),
+            version: request.client.inner.version.to_string(),
+            request_headers,
+            request_body: request.body.as_ref().and_then(Body::len).unwrap_or(0) as usize,
+            status: res.status_code(),
+            reason: res.reason().to_owned(),
+            response_version: res.version().to_string(),
+            response_headers: res.headers().iter().map(|(name, value)| (name.to_owned(), value.to_owned())).collect(),
+            response_body: res.bytes().len(),
+        }
+    }
+}
+
+impl HarRecorder {
+    ///return an empty recorder
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///return the number of recorded exchanges
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    ///return if no exchange was recorded
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    ///forget the recorded exchanges
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    pub(crate) fn record(&self, entry: Entry) {
+        self.entries.lock().unwrap().push(entry);
+    }
+
+    ///return the recorded exchanges as HAR JSON
+    pub fn to_json(&self) -> String {
+        let entries = self.entries.lock().unwrap();
+        let mut out = String::new();
+        let _ = write!(out, r#"{{"log":{{"version":"1.2","creator":{{"name":"smolhttp","version":"{}"}},"entries":["#, env!("CARGO_PKG_VERSION"));
+        for (i, entry) in entries.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+            write_entry(&mut out, entry);
+        }
+        out.push_str("]}}");
+        out
+    }
+
+    ///write the recorded exchanges as HAR JSON to the file at `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), HttpError> {
+        fs::write(path, self.to_json())?;
+        Ok(())
+    }
+}
+
+fn write_entry(out: &mut String, entry: &Entry) {
+    let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+    let header = |(name, value): &(String, String)| format!(r#"{{"name":{},"value":{}}}"#, quote(name), quote(value));
+    let find = |headers: &[(String, String)], name: &str| {
+        headers.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.clone()).unwrap_or_default()
+    };
+
+    let query = entry.url.split_once('?').map(|(_, query)| query.split('#').next().unwrap_or_default()).unwrap_or_default();
+    let query: Vec<String> = query
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            format!(r#"{{"name":{},"value":{}}}"#, quote(name), quote(value))
+        })
+        .collect();
+
+    let _ = write!(
+        out,
+        concat!(
+            r#"{{"startedDateTime":{},"time":{:.3},"#,
+            r#""request":{{"method":{},"url":{},"httpVersion":{},"cookies":[],"headers":[{}],"queryString":[{}],"headersSize":-1,"bodySize":{}}},"#,
+            r#""response":{{"status":{},"statusText":{},"httpVersion":{},"cookies":[],"headers":[{}],"#,
+            r#""content":{{"size":{},"mimeType":{}}},"redirectURL":{},"headersSize":-1,"bodySize":{}}},"#,
+            r#""cache":{{}},"timings":{{"blocked":-1,"dns":{:.3},"connect":{:.3},"send":0,"wait":{:.3},"receive":{:.3},"ssl":{}}}}}"#,
+        ),
+        quote(&date::fmt_iso_date(entry.started)),
+        ms(entry.timings.total()),
+        quote(&entry.method),
+        quote(&entry.url),
+        quote(&entry.version),
+        entry.request_headers.iter().map(header).collect::<Vec<_>>().join(","),
+        query.join(","),
+        entry.request_body,
+        entry.status,
+        quote(&entry.reason),
+        quote(&entry.response_version),
+        entry.response_headers.iter().map(header).collect::<Vec<_>>().join(","),
+        entry.response_body,
+        quote(&find(&entry.response_headers, "Content-Type")),
+        quote(&find(&entry.response_headers, "Location")),
+        entry.response_body,
+        ms(entry.timings.dns()),
+        //the connect time of HAR includes the TLS handshake
+        ms(entry.timings.connect() + entry.timings.tls().unwrap_or_default()),
+        ms(entry.timings.first_byte()),
+        ms(entry.timings.receive()),
+        entry.timings.tls().map(|tls| format!("{:.3}", ms(tls))).unwrap_or_else(|| "-1".to_owned()),
+    );
+}
+
+//return a JSON string literal
+pub(crate) fn quote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn har_export() {
+        let request = Client::new().post("http://example.com/search?q=a\"b&page=2").unwrap().body(b"hello".to_vec());
+        let res = Response::new(b"HTTP/1.1 302 Found\r\nLocation: /next\r\nContent-Type: text/plain\r\nContent-Length: 2\r\n\r\nok".to_vec()).unwrap()
+            .with_timings(Timings {
+                dns: Duration::from_millis(2),
+                connect: Duration::from_millis(5),
+                tls: None,
+                first_byte: Duration::from_millis(20),
+                total: Duration::from_millis(30),
+            });
+        let started = UNIX_EPOCH + Duration::from_millis(784111777500);
+
+        let recorder = HarRecorder::new();
+        recorder.record(Entry::new(&request, &res, started));
+        assert_eq!(recorder.len(), 1);
+
+        let json = recorder.to_json();
+        assert!(json.starts_with(r#"{"log":{"version":"1.2","creator":{"name":"smolhttp""#));
+        assert!(json.contains(r#""startedDateTime":"1994-11-06T08:49:37.500Z","time":30.000,"#));
+        assert!(json.contains(r#""method":"POST","url":"http://example.com/search?q=a\"b&page=2","httpVersion":"HTTP/1.1""#));
+        assert!(json.contains(r#"{"name":"Host","value":"example.com"}"#));
+        assert!(json.contains(r#""queryString":[{"name":"q","value":"a\"b"},{"name":"page","value":"2"}],"headersSize":-1,"bodySize":5}"#));
+        assert!(json.contains(r#""status":302,"statusText":"Found""#));
+        assert!(json.contains(r#""content":{"size":2,"mimeType":"text/plain"},"redirectURL":"/next""#));
+        assert!(json.contains(r#""dns":2.000,"connect":5.000,"send":0,"wait":20.000,"receive":3.000,"ssl":-1"#));
+        assert!(json.ends_with("}}]}}"));
+
+        recorder.clear();
+        assert!(recorder.is_empty());
+    }
+}
@@ -0,0 +1,182 @@
+use std::iter::FromIterator;
+use std::slice;
+use std::vec;
+
+///case-insensitive, multi-value http header map, keeping insertion order.
+/// # Example
+/// ```
+/// use smolhttp::HeaderMap;
+///
+/// let mut headers = HeaderMap::new();
+/// headers.append("Set-Cookie", "a=1");
+/// headers.append("set-cookie", "b=2");
+/// assert_eq!(headers.get("SET-COOKIE"), Some("a=1"));
+/// assert_eq!(headers.get_all("Set-Cookie").collect::<Vec<_>>(), ["a=1", "b=2"]);
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    ///return an empty HeaderMap
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///return the first value of the header `name`
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    ///return every value of the header `name`, in the order they were added
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    ///return if the header `name` is present
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    ///set the header `name` to `value`, replacing all of its previous values
+    ///
+    ///The header keeps the position of its first occurrence.
+    pub fn insert<K: Into<String>, V: Into<String>>(&mut self, name: K, value: V) {
+        let name = name.into();
+        match self.entries.iter().position(|(k, _)| k.eq_ignore_ascii_case(&name)) {
+            Some(pos) => {
+                let mut rest = self.entries.split_off(pos + 1);
+                rest.retain(|(k, _)| !k.eq_ignore_ascii_case(&name));
+                self.entries[pos] = (name, value.into());
+                self.entries.append(&mut rest);
+            }
+            None => self.entries.push((name, value.into())),
+        }
+    }
+
+    ///add a value to the header `name`, keeping the previous ones
+    pub fn append<K: Into<String>, V: Into<String>>(&mut self, name: K, value: V) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    ///remove every value of the header `name`, returning the first one
+    pub fn remove(&mut self, name: &str) -> Option<String> {
+        let mut first = None;
+        self.entries.retain(|(k, v)| {
+            if !k.eq_ignore_ascii_case(name) {
+                return true;
+            }
+            if first.is_none() {
+                first = Some(v.clone());
+            }
+            false
+        });
+        first
+    }
+
+    ///return the number of header lines
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    ///return if there is no header
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    ///iterate over the header lines as `(name, value)`, the names keeping the casing they were added with
+    pub fn iter(&self) -> Iter<'_> {
+        Iter(self.entries.iter())
+    }
+
+    ///return a copy with lowercase names, as HTTP/2 sends them, to compare headers whatever their casing
+    /// # Example
+    /// ```
+    /// use smolhttp::HeaderMap;
+    ///
+    /// let headers: HeaderMap = [("Content-Type", "text/html"), ("X-Request-ID", "7")].into_iter().collect();
+    /// let names: Vec<_> = headers.normalized().iter().map(|(name, _)| name.to_owned()).collect();
+    /// assert_eq!(names, ["content-type", "x-request-id"]);
+    /// ```
+    pub fn normalized(&self) -> Self {
+        self.entries.iter().map(|(name, value)| (name.to_ascii_lowercase(), value.clone())).collect()
+    }
+}
+
+///iterator over the lines of a [`HeaderMap`].
+#[derive(Debug)]
+pub struct Iter<'a>(slice::Iter<'a, (String, String)>);
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+}
+
+impl<'a> IntoIterator for &'a HeaderMap {
+    type Item = (&'a str, &'a str);
+    type IntoIter = Iter<'a>;
+
+    fn into_iter(self) -> Iter<'a> {
+        self.iter()
+    }
+}
+
+impl IntoIterator for HeaderMap {
+    type Item = (String, String);
+    type IntoIter = vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.entries.into_iter()
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut headers = HeaderMap::new();
+        for (k, v) in iter {
+            headers.append(k, v);
+        }
+        headers
+    }
+}
+
+impl From<Vec<(String, String)>> for HeaderMap {
+    fn from(entries: Vec<(String, String)>) -> Self {
+        Self { entries }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_replaces_all_values() {
+        let mut headers = HeaderMap::new();
+        headers.append("Accept", "text/html");
+        headers.append("X-Id", "1");
+        headers.append("accept", "text/plain");
+        headers.insert("ACCEPT", "application/json");
+
+        let lines: Vec<_> = headers.iter().collect();
+        assert_eq!(lines, [("ACCEPT", "application/json"), ("X-Id", "1")]);
+    }
+
+    #[test]
+    fn remove_returns_first() {
+        let mut headers: HeaderMap = vec![("a", "1"), ("b", "2"), ("A", "3")].into_iter().collect();
+        assert_eq!(headers.remove("a"), Some("1".to_owned()));
+        assert_eq!(headers.len(), 1);
+        assert_eq!(headers.remove("a"), None);
+    }
+}
@@ -0,0 +1,359 @@
+use super::HttpError;
+
+use std::collections::VecDeque;
+use std::sync::OnceLock;
+
+//size of the dynamic table announced by the HTTP/2 settings, the default one
+pub(crate) const TABLE_SIZE: usize = 4096;
+
+//entries of the static table(RFC 7541 appendix A), indexed from 1
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+//length in bits of the huffman code of each byte and of EOS(RFC 7541 appendix B), the code is canonical
+const HUFFMAN_LENGTHS: [u8; 257] = [
+    13, 23, 28, 28, 28, 28, 28, 28, 28, 24, 30, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 30, 28, 28, 28, 28, 28, 28, 28, 28, 28,
+    6, 10, 10, 12, 13, 6, 8, 11, 10, 10, 8, 11, 8, 6, 6, 6, 5, 5, 5, 6, 6, 6, 6, 6, 6, 6, 7, 8, 15, 6, 12, 10,
+    13, 6, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 7, 8, 7, 8, 13, 19, 13, 14, 6,
+    15, 5, 6, 5, 6, 5, 6, 6, 6, 5, 7, 7, 6, 6, 6, 5, 6, 7, 6, 5, 5, 6, 7, 7, 7, 7, 7, 15, 11, 14, 13, 28,
+    20, 22, 20, 20, 22, 22, 22, 23, 22, 23, 23, 23, 23, 23, 24, 23, 24, 24, 22, 23, 24, 23, 23, 23, 23, 21, 22, 23, 22, 23, 23, 24,
+    22, 21, 20, 22, 22, 23, 23, 21, 23, 22, 22, 24, 21, 22, 23, 23, 21, 21, 22, 21, 23, 22, 23, 23, 20, 22, 22, 22, 23, 22, 22, 23,
+    26, 26, 20, 19, 22, 23, 22, 25, 26, 26, 26, 27, 27, 26, 24, 25, 19, 21, 26, 27, 27, 26, 27, 24, 21, 21, 26, 26, 28, 27, 27, 27,
+    20, 24, 20, 21, 22, 21, 21, 23, 22, 22, 25, 25, 24, 24, 26, 23, 26, 27, 26, 26, 27, 27, 27, 27, 27, 28, 27, 27, 27, 27, 27, 26,
+    30,
+];
+
+const EOS: u16 = 256;
+
+//canonical huffman decoding table: how many codes have each length, and the symbols sorted by code
+struct Huffman {
+    count: [u16; 31],
+    symbols: [u16; 257],
+}
+
+fn huffman() -> &'static Huffman {
+    static TABLE: OnceLock<Huffman> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        let mut table = Huffman { count: [0; 31], symbols: [0; 257] };
+        for &len in HUFFMAN_LENGTHS.iter() {
+            table.count[len as usize] += 1;
+        }
+        let mut symbols = (0..257u16).collect::<Vec<_>>();
+        symbols.sort_by_key(|&symbol| HUFFMAN_LENGTHS[symbol as usize]);
+        table.symbols.copy_from_slice(&symbols);
+        table
+    })
+}
+
+//decode a huffman encoded string, which must be padded with the most significant bits of EOS
+fn decode_huffman(input: &[u8]) -> Result<Vec<u8>, HttpError> {
+    let table = huffman();
+    let mut out = Vec::with_capacity(input.len() * 8 / 5);
+    let (mut code, mut first, mut index, mut len) = (0u32, 0u32, 0usize, 0usize);
+    // bits read since the last symbol, all ones so far
+    let mut ones = true;
+    for byte in input {
+        for shift in (0..8).rev() {
+            let bit = (byte >> shift) as u32 & 1;
+            code |= bit;
+            ones &= bit == 1;
+            len += 1;
+            let count = table.count[len] as u32;
+            if code.wrapping_sub(first) < count {
+                let symbol = table.symbols[index + (code - first) as usize];
+                if symbol == EOS {
+                    return Err(HttpError::Parse("EOS in a huffman encoded header"));
+                }
+                out.push(symbol as u8);
+                (code, first, index, len, ones) = (0, 0, 0, 0, true);
+            } else {
+                if len == 30 {
+                    return Err(HttpError::Parse("invalid huffman code"));
+                }
+                index += count as usize;
+                first = (first + count) << 1;
+                code <<= 1;
+            }
+        }
+    }
+    if len > 7 || !ones {
+        return Err(HttpError::Parse("invalid huffman padding"));
+    }
+    Ok(out)
+}
+
+//append an integer with a `prefix` bits prefix, the high bits of the first byte set to `flags`
+fn encode_int(value: usize, prefix: u8, flags: u8, out: &mut Vec<u8>) {
+    let max = (1usize << prefix) - 1;
+    if value < max {
+        out.push(flags | value as u8);
+        return;
+    }
+    out.push(flags | max as u8);
+    let mut value = value - max;
+    while value >= 128 {
+        out.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+//read an integer with a `prefix` bits prefix at the start of `input`, moving it past the integer
+fn decode_int(input: &mut &[u8], prefix: u8) -> Result<usize, HttpError> {
+    let (&first, rest) = input.split_first().ok_or(HttpError::Parse("truncated header block"))?;
+    *input = rest;
+    let max = (1usize << prefix) - 1;
+    let mut value = first as usize & max;
+    if value < max {
+        return Ok(value);
+    }
+    for shift in (0..28).step_by(7) {
+        let (&byte, rest) = input.split_first().ok_or(HttpError::Parse("truncated header block"))?;
+        *input = rest;
+        value += ((byte & 0x7F) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err(HttpError::Parse("header block integer too large"))
+}
+
+fn encode_str(value: &str, out: &mut Vec<u8>) {
+    encode_int(value.len(), 7, 0, out);
+    out.extend_from_slice(value.as_bytes());
+}
+
+fn decode_str(input: &mut &[u8]) -> Result<String, HttpError> {
+    let huffman = input.first().map(|b| b & 0x80 != 0).unwrap_or(false);
+    let len = decode_int(input, 7)?;
+    if len > input.len() {
+        return Err(HttpError::Parse("truncated header block"));
+    }
+    let (value, rest) = input.split_at(len);
+    *input = rest;
+    Ok(match huffman {
+        true => String::from_utf8_lossy(&decode_huffman(value)?).into_owned(),
+        false => String::from_utf8_lossy(value).into_owned(),
+    })
+}
+
+//encode a header list without touching the dynamic table of the peer, so the encoder keeps no state
+pub(crate) fn encode<'a, I: IntoIterator<Item = (&'a str, &'a str)>>(fields: I, out: &mut Vec<u8>) {
+    for (name, value) in fields {
+        match STATIC_TABLE.iter().position(|&entry| entry == (name, value)) {
+            Some(index) => encode_int(index + 1, 7, 0x80, out),
+            None => {
+                match STATIC_TABLE.iter().position(|&(entry, _)| entry == name) {
+                    Some(index) => encode_int(index + 1, 4, 0, out),
+                    None => {
+                        out.push(0);
+                        encode_str(name, out);
+                    }
+                }
+                encode_str(value, out);
+            }
+        }
+    }
+}
+
+//decoder of header blocks, keeping the dynamic table shared by the blocks of a connection
+#[derive(Debug)]
+pub(crate) struct Decoder {
+    table: VecDeque<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Decoder {
+    pub(crate) fn new() -> Self {
+        Self { table: VecDeque::new(), size: 0, max_size: TABLE_SIZE }
+    }
+
+    pub(crate) fn decode(&mut self, mut input: &[u8]) -> Result<Vec<(String, String)>, HttpError> {
+        let mut fields = Vec::new();
+        while let Some(&first) = input.first() {
+            if first & 0x80 != 0 {
+                let index = decode_int(&mut input, 7)?;
+                fields.push(self.entry(index)?);
+            } else if first & 0xE0 == 0x20 {
+                let size = decode_int(&mut input, 5)?;
+                if size > TABLE_SIZE {
+                    return Err(HttpError::Parse("header table size above the announced one"));
+                }
+                self.max_size = size;
+                self.evict(0);
+            } else {
+                // with incremental indexing, without indexing or never indexed
+                let indexed = first & 0xC0 == 0x40;
+                let index = decode_int(&mut input, if indexed { 6 } else { 4 })?;
+                let name = match index {
+                    0 => decode_str(&mut input)?,
+                    index => self.entry(index)?.0,
+                };
+                let value = decode_str(&mut input)?;
+                if indexed {
+                    self.insert(name.clone(), value.clone());
+                }
+                fields.push((name, value));
+            }
+        }
+        Ok(fields)
+    }
+
+    fn entry(&self, index: usize) -> Result<(String, String), HttpError> {
+        match index {
+            0 => Err(HttpError::Parse("header index 0")),
+            1..=61 => Ok((STATIC_TABLE[index - 1].0.to_owned(), STATIC_TABLE[index - 1].1.to_owned())),
+            _ => self.table.get(index - 62).cloned().ok_or(HttpError::Parse("header index out of the table")),
+        }
+    }
+
+    fn insert(&mut self, name: String, value: String) {
+        let size = name.len() + value.len() + 32;
+        self.evict(size);
+        // an entry larger than the table empties it and isn't added
+        if size <= self.max_size {
+            self.size += size;
+            self.table.push_front((name, value));
+        }
+    }
+
+    //drop the oldest entries until `room` bytes fit in the table
+    fn evict(&mut self, room: usize) {
+        while self.size + room > self.max_size {
+            match self.table.pop_back() {
+                Some((name, value)) => self.size -= name.len() + value.len() + 32,
+                None => break,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn huffman_strings() {
+        // RFC 7541 C.4.1 and C.6.1
+        assert_eq!(decode_huffman(&[0xF1, 0xE3, 0xC2, 0xE5, 0xF2, 0x3A, 0x6B, 0xA0, 0xAB, 0x90, 0xF4, 0xFF]).unwrap(), b"www.example.com");
+        assert_eq!(decode_huffman(&[0x64, 0x02]).unwrap(), b"302");
+        assert_eq!(decode_huffman(&[0xFF, 0xFD, 0xFC, 0x7F]).unwrap(), b"{!");
+        // padding longer than 7 bits, or not made of ones
+        assert!(decode_huffman(&[0xFF, 0xFF]).is_err());
+        assert!(decode_huffman(&[0x00]).is_err());
+    }
+
+    #[test]
+    fn integers() {
+        let mut out = Vec::new();
+        encode_int(1337, 5, 0, &mut out);
+        assert_eq!(out, [0x1F, 0x9A, 0x0A]);
+        assert_eq!(decode_int(&mut &out[..], 5).unwrap(), 1337);
+        assert_eq!(decode_int(&mut &[0x0A][..], 5).unwrap(), 10);
+        assert!(decode_int(&mut &[0x1F, 0x9A][..], 5).is_err());
+    }
+
+    #[test]
+    fn header_blocks() {
+        // RFC 7541 C.3, requests sharing the dynamic table
+        let mut decoder = Decoder::new();
+        let block = b"\x82\x86\x84\x41\x0fwww.example.com";
+        assert_eq!(
+            decoder.decode(block).unwrap(),
+            [(":method", "GET"), (":scheme", "http"), (":path", "/"), (":authority", "www.example.com")].map(|(n, v)| (n.to_owned(), v.to_owned()))
+        );
+        let block = b"\x82\x86\x84\xbe\x58\x08no-cache";
+        assert_eq!(decoder.decode(block).unwrap()[3..], [(":authority".to_owned(), "www.example.com".to_owned()), ("cache-control".to_owned(), "no-cache".to_owned())]);
+        assert_eq!(decoder.size, 110);
+
+        //blocks written by another implementation, with huffman strings and new names
+        let mut decoder = Decoder::new();
+        let block = [
+            0x04, 0x85, 0x62, 0x53, 0x58, 0x84, 0xFF, 0x82, 0x41, 0x8B, 0xA0, 0xE4, 0x1D, 0x13, 0x9D, 0x09, 0xB8, 0x17, 0x80, 0xF0,
+            0x7F, 0x86, 0x40, 0x86, 0xF2, 0xB5, 0x85, 0xAC, 0xA3, 0x4F, 0x8A, 0x18, 0xC6, 0x31, 0x8C, 0x63, 0x18, 0xC6, 0x31, 0x8C,
+            0x63, 0x7A, 0x86, 0x45, 0x27, 0xA2, 0x74, 0xA6, 0xBF, 0x53, 0x91, 0x49, 0x7C, 0xA5, 0x89, 0xD3, 0x4D, 0x1F, 0x6A, 0x76,
+            0x80, 0x0B, 0xBF, 0xF4, 0xA7, 0xCB, 0x1F, 0x3F,
+        ];
+        let first = decoder.decode(&block).unwrap();
+        let expected = [
+            (":path", "/first"),
+            (":method", "GET"),
+            (":authority", "localhost:18081"),
+            (":scheme", "http"),
+            ("x-repeat", "aaaaaaaaaaaaaaaa"),
+            ("user-agent", "smolhttp"),
+            ("accept", "text/html; q=0.9, */*"),
+        ];
+        assert_eq!(first, expected.map(|(n, v)| (n.to_owned(), v.to_owned())));
+        let second = decoder.decode(&[0x04, 0x85, 0x61, 0x05, 0x21, 0xEA, 0x93, 0x82, 0xC1, 0x86, 0xC0, 0xBF, 0xBE]).unwrap();
+        assert_eq!(second[0], (":path".to_owned(), "/second".to_owned()));
+        assert_eq!(second[1..], first[1..]);
+
+        let mut out = Vec::new();
+        encode([(":method", "GET"), (":path", "/a"), ("x-id", "7")], &mut out);
+        assert_eq!(out, b"\x82\x04\x02/a\x00\x04x-id\x017");
+        assert!(Decoder::new().decode(&[0xBE]).is_err());
+    }
+}
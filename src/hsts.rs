@@ -0,0 +1,165 @@
+use super::params::split_params;
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy)]
+struct Policy {
+    expires: SystemTime,
+    include_subdomains: bool,
+}
+
+///store of the hosts known to require https, learned from `Strict-Transport-Security` headers.
+///
+///The store is shared between its clones, so a Client can update it while the
+///application keeps a handle to [`save`](HstsStore::save) it later.
+#[derive(Debug, Clone, Default)]
+pub struct HstsStore {
+    hosts: Arc<Mutex<HashMap<String, Policy>>>,
+}
+
+impl HstsStore {
+    ///return an empty HstsStore
+    /// # Example
+    /// ```
+    /// use smolhttp::{Client, HstsStore};
+    ///
+    /// let hsts = HstsStore::new();
+    /// let client = Client::builder().hsts(hsts.clone()).build().unwrap();
+    /// ```
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///load a store previously written by [`save`](HstsStore::save)
+    pub fn load<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        let store = Self::new();
+        {
+            let mut hosts = store.hosts.lock().unwrap();
+            for line in fs::read_to_string(path)?.lines() {
+                let mut fields = line.split('\t');
+                let (host, expires, subdomains) = match (fields.next(), fields.next(), fields.next()) {
+                    (Some(host), Some(expires), Some(subdomains)) => (host, expires, subdomains),
+                    _ => continue,
+                };
+                if let Ok(expires) = expires.parse() {
+                    hosts.insert(host.to_owned(), Policy {
+                        expires: UNIX_EPOCH + Duration::from_secs(expires),
+                        include_subdomains: subdomains == "1",
+                    });
+                }
+            }
+        }
+        Ok(store)
+    }
+
+    ///write the unexpired entries of the store to `path`
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let now = SystemTime::now();
+        let mut data = String::new();
+        for (host, policy) in self.hosts.lock().unwrap().iter() {
+            if let Ok(expires) = policy.expires.duration_since(UNIX_EPOCH) {
+                if policy.expires > now {
+                    data.push_str(&format!("{}\t{}\t{}\n", host, expires.as_secs(), policy.include_subdomains as u8));
+                }
+            }
+        }
+        fs::write(path, data)
+    }
+
+    ///record the `Strict-Transport-Security` header value received from `host` over https
+    pub fn record(&self, host: &str, value: &str) {
+        if host.parse::<IpAddr>().is_ok() {
+            return;
+        }
+
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        for (name, value) in split_params(value) {
+            match name.as_str() {
+                "max-age" => max_age = value.parse::<u64>().ok(),
+                "includesubdomains" => include_subdomains = true,
+                _ => {}
+            }
+        }
+
+        let host = host.to_ascii_lowercase();
+        let mut hosts = self.hosts.lock().unwrap();
+        match max_age {
+            Some(0) => {
+                hosts.remove(&host);
+            }
+            Some(max_age) => {
+                let expires = SystemTime::now() + Duration::from_secs(max_age);
+                hosts.insert(host, Policy { expires, include_subdomains });
+            }
+            None => {}
+        }
+    }
+
+    ///return if requests to `host` must be upgraded to https
+    /// # Example
+    /// ```
+    /// use smolhttp::HstsStore;
+    ///
+    /// let hsts = HstsStore::new();
+    /// hsts.record("example.com", "max-age=31536000; includeSubDomains");
+    /// assert!(hsts.is_secure("www.example.com"));
+    /// assert!(!hsts.is_secure("example.org"));
+    /// ```
+    pub fn is_secure(&self, host: &str) -> bool {
+        let host = host.to_ascii_lowercase();
+        let now = SystemTime::now();
+        let hosts = self.hosts.lock().unwrap();
+
+        let mut domain = host.as_str();
+        loop {
+            if let Some(policy) = hosts.get(domain) {
+                if policy.expires > now && (domain == host || policy.include_subdomains) {
+                    return true;
+                }
+            }
+            match domain.split_once('.') {
+                Some((_, parent)) => domain = parent,
+                None => return false,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn record_and_expire() {
+        let hsts = HstsStore::new();
+        hsts.record("Example.com", "max-age=60");
+        assert!(hsts.is_secure("example.com"));
+        assert!(!hsts.is_secure("sub.example.com"));
+
+        hsts.record("example.com", "max-age=0");
+        assert!(!hsts.is_secure("example.com"));
+
+        hsts.record("127.0.0.1", "max-age=60");
+        assert!(!hsts.is_secure("127.0.0.1"));
+    }
+
+    #[test]
+    fn save_and_load() {
+        let path = env::temp_dir().join(format!("smolhttp-hsts-{}", std::process::id()));
+        let hsts = HstsStore::new();
+        hsts.record("example.com", "max-age=60; includeSubDomains");
+        hsts.save(&path).unwrap();
+
+        let loaded = HstsStore::load(&path).unwrap();
+        assert!(loaded.is_secure("a.b.example.com"));
+        let _ = fs::remove_file(path);
+    }
+}
@@ -0,0 +1,235 @@
+use super::{Body, HeaderMap, HttpError, Method, Request, Response, StatusCode, Version};
+
+use std::io::Read;
+
+impl From<http::Method> for Method {
+    fn from(method: http::Method) -> Self {
+        match method {
+            http::Method::GET => Method::Get,
+            http::Method::POST => Method::Post,
+            http::Method::PUT => Method::Put,
+            http::Method::HEAD => Method::Head,
+            http::Method::DELETE => Method::Delete,
+            http::Method::OPTIONS => Method::Options,
+            http::Method::PATCH => Method::Patch,
+            http::Method::TRACE => Method::Trace,
+            http::Method::CONNECT => Method::Connect,
+            method => Method::Custom(method.as_str().to_owned()),
+        }
+    }
+}
+
+impl TryFrom<Method> for http::Method {
+    type Error = HttpError;
+
+    ///convert the method, failing with [`HttpError::Config`] when a custom one isn't a valid http token
+    fn try_from(method: Method) -> Result<Self, HttpError> {
+        http::Method::from_bytes(method.as_str().as_bytes()).map_err(|_| HttpError::Config("Method is not a valid token"))
+    }
+}
+
+impl From<http::StatusCode> for StatusCode {
+    fn from(status: http::StatusCode) -> Self {
+        StatusCode::from_u16(status.as_u16()).expect("both crates accept the codes from 100 to 999")
+    }
+}
+
+impl From<StatusCode> for http::StatusCode {
+    fn from(status: StatusCode) -> Self {
+        http::StatusCode::from_u16(status.as_u16()).expect("both crates accept the codes from 100 to 999")
+    }
+}
+
+impl TryFrom<http::Version> for Version {
+    type Error = HttpError;
+
+    ///convert the version, failing with [`HttpError::Parse`] for HTTP/0.9
+    fn try_from(version: http::Version) -> Result<Self, HttpError> {
+        match version {
+            http::Version::HTTP_10 => Ok(Version::Http10),
+            http::Version::HTTP_11 => Ok(Version::Http11),
+            http::Version::HTTP_2 => Ok(Version::Http2),
+            http::Version::HTTP_3 => Ok(Version::Http3),
+            _ => Err(HttpError::Parse("invalid http version")),
+        }
+    }
+}
+
+impl From<Version> for http::Version {
+    fn from(version: Version) -> Self {
+        match version {
+            Version::Http10 => http::Version::HTTP_10,
+            Version::Http11 => http::Version::HTTP_11,
+            Version::Http2 => http::Version::HTTP_2,
+            Version::Http3 => http::Version::HTTP_3,
+        }
+    }
+}
+
+impl TryFrom<&http::HeaderMap> for HeaderMap {
+    type Error = HttpError;
+
+    ///copy the headers, failing with [`HttpError::Parse`] when a value isn't visible ASCII
+    fn try_from(headers: &http::HeaderMap) -> Result<Self, HttpError> {
+        let mut map = HeaderMap::new();
+        for (name, value) in headers {
+            let value = value.to_str().map_err(|_| HttpError::Parse("header value is not visible ASCII"))?;
+            map.append(name.as_str(), value);
+        }
+        Ok(map)
+    }
+}
+
+impl TryFrom<&HeaderMap> for http::HeaderMap {
+    type Error = HttpError;
+
+    ///copy the headers, failing with [`HttpError::Config`] when a name or a value isn't valid
+    fn try_from(headers: &HeaderMap) -> Result<Self, HttpError> {
+        let mut map = http::HeaderMap::with_capacity(headers.len());
+        for (name, value) in headers {
+            let name = http::HeaderName::from_bytes(name.as_bytes()).map_err(|_| HttpError::Config("Header name is not a valid token"))?;
+            let value = http::HeaderValue::from_str(value).map_err(|_| HttpError::Config("Header value contains control characters"))?;
+            map.append(name, value);
+        }
+        Ok(map)
+    }
+}
+
+impl<B: Into<Body>> TryFrom<http::Request<B>> for Request {
+    type Error = HttpError;
+
+    ///convert a request of the `http` crate, its uri being absolute or relative to the base url of the client
+    ///executing it, and an empty body being left out
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::{Client, Request};
+    ///
+    /// let request = http::Request::post("https://example.com/jobs").header("Content-Type", "application/json").body(r#"{"task":"resize"}"#).unwrap();
+    /// let res = Client::new().execute(Request::try_from(request).unwrap()).unwrap();
+    /// ```
+    fn try_from(request: http::Request<B>) -> Result<Self, HttpError> {
+        let (parts, body) = request.into_parts();
+        let mut converted = Request::new(parts.method.as_str(), &parts.uri.to_string())?;
+        *converted.headers_mut() = HeaderMap::try_from(&parts.headers)?;
+        let body = body.into();
+        Ok(match body.len() {
+            Some(0) => converted,
+            _ => converted.with_body(body),
+        })
+    }
+}
+
+impl TryFrom<Response> for http::Response<Vec<u8>> {
+    type Error = HttpError;
+
+    ///convert the response into one of the `http` crate, reading its body back from the disk if it was spilled
+    ///there, its final url being kept in the extensions as a [`Url`](crate::Url)
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://www.rust-lang.org").unwrap();
+    /// let res = http::Response::try_from(res).unwrap();
+    /// assert_eq!(res.status(), http::StatusCode::OK);
+    /// ```
+    fn try_from(res: Response) -> Result<Self, HttpError> {
+        let mut builder = http::Response::builder().status(http::StatusCode::from(res.status())).version(res.version().into());
+        if let Some(url) = res.parsed_url() {
+            builder = builder.extension(url);
+        }
+        let mut converted = builder.body(Vec::new()).map_err(|_| HttpError::Parse("invalid response"))?;
+        *converted.headers_mut() = http::HeaderMap::try_from(res.headers())?;
+        res.into_reader()?.read_to_end(converted.body_mut())?;
+        Ok(converted)
+    }
+}
+
+impl<B: Into<Vec<u8>>> TryFrom<http::Response<B>> for Response {
+    type Error = HttpError;
+
+    ///convert a response of the `http` crate, to answer requests from a [`Middleware`](crate::Middleware) or a
+    ///[`Transport`](crate::Transport) for instance
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::try_from(http::Response::builder().status(404).body("gone").unwrap()).unwrap();
+    /// assert_eq!((res.status_code(), res.text()), (404, "gone".to_owned()));
+    /// ```
+    fn try_from(res: http::Response<B>) -> Result<Self, HttpError> {
+        let (parts, body) = res.into_parts();
+        let version = Version::try_from(parts.version)?;
+        let status = StatusCode::from(parts.status);
+        let reason = parts.status.canonical_reason().unwrap_or_default();
+        Response::from_fields(version, status, reason, &HeaderMap::try_from(&parts.headers)?, body.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::{Client, Url};
+
+    #[test]
+    fn methods_statuses_and_versions() {
+        for method in ["GET", "POST", "PUT", "HEAD", "DELETE", "OPTIONS", "PATCH", "TRACE", "CONNECT", "PROPFIND"] {
+            let converted = Method::from(http::Method::from_bytes(method.as_bytes()).unwrap());
+            assert_eq!(converted, method.parse().unwrap());
+            assert_eq!(http::Method::try_from(converted).unwrap().as_str(), method);
+        }
+        assert!(http::Method::try_from(Method::Custom("GET /".to_owned())).is_err());
+
+        assert_eq!(StatusCode::from(http::StatusCode::IM_A_TEAPOT).as_u16(), 418);
+        assert_eq!(http::StatusCode::from(StatusCode::from_u16(599).unwrap()).as_u16(), 599);
+        assert_eq!(Version::try_from(http::Version::HTTP_2).unwrap(), Version::Http2);
+        assert_eq!(http::Version::from(Version::Http10), http::Version::HTTP_10);
+        assert!(Version::try_from(http::Version::HTTP_09).is_err());
+    }
+
+    #[test]
+    fn header_maps() {
+        let mut headers = http::HeaderMap::new();
+        headers.append("set-cookie", "a=1".parse().unwrap());
+        headers.append("set-cookie", "b=2".parse().unwrap());
+        headers.insert("x-id", "7".parse().unwrap());
+        let converted = HeaderMap::try_from(&headers).unwrap();
+        assert_eq!(converted.get_all("Set-Cookie").collect::<Vec<_>>(), ["a=1", "b=2"]);
+        assert_eq!(http::HeaderMap::try_from(&converted).unwrap(), headers);
+
+        headers.insert("x-raw", http::HeaderValue::from_bytes(b"caf\xc3\xa9").unwrap());
+        assert!(HeaderMap::try_from(&headers).is_err());
+        let invalid: HeaderMap = [("Bad Name", "1")].into_iter().collect();
+        assert!(http::HeaderMap::try_from(&invalid).is_err());
+    }
+
+    #[test]
+    fn execute_http_request() {
+        let mock = MockTransport::new();
+        mock.respond("PUT", "http://example.com/items/1", 201, "created");
+        let client = Client::with_connector(mock.clone());
+
+        let request = http::Request::put("http://example.com/items/1").header("X-Id", "7").body(b"{}".to_vec()).unwrap();
+        let res = client.execute(Request::try_from(request).unwrap()).unwrap();
+        let sent = &mock.requests()[0];
+        assert_eq!(sent.headers().get("x-id"), Some("7"));
+        assert_eq!(sent.body(), b"{}");
+
+        let res = http::Response::try_from(res).unwrap();
+        assert_eq!(res.status(), http::StatusCode::CREATED);
+        assert_eq!(res.body(), b"created");
+        let url = res.extensions().get::<Url>().unwrap();
+        assert_eq!((url.host(), url.path()), ("example.com", "/items/1"));
+
+        let request = Request::try_from(http::Request::get("/items").body(Vec::new()).unwrap()).unwrap();
+        assert_eq!((request.method(), request.url()), ("GET", "/items"));
+        assert!(request.body().is_none());
+    }
+
+    #[test]
+    fn response_from_http() {
+        let res = http::Response::builder().status(http::StatusCode::NOT_FOUND).header("Content-Type", "text/plain").body("gone").unwrap();
+        let res = Response::try_from(res).unwrap();
+        assert_eq!((res.status_code(), res.reason(), res.version()), (404, "Not Found", Version::Http11));
+        assert_eq!(res.header("content-type"), Some("text/plain"));
+        assert_eq!(res.text(), "gone");
+    }
+}
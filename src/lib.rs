@@ -10,7 +10,8 @@
 //! println!("{content}");
 //!
 //! // Using the Client
-//! let content = smolhttp::Client::new("https://www.rust-lang.org").unwrap().get().send().unwrap().text();
+//! let client = smolhttp::Client::new();
+//! let content = client.get("https://www.rust-lang.org").unwrap().send().unwrap().text();
 //! println!("{content}");
 //! ```
 //! 
@@ -21,9 +22,9 @@
 //! println!("{content}");
 //!
 //! // Using the Client
-//! let content = smolhttp::Client::new("https://www.rust-lang.org")
+//! let content = smolhttp::Client::new()
+//!   .post("https://www.rust-lang.org")
 //!   .unwrap()
-//!   .post()
 //!   .send()
 //!   .unwrap()
 //!   .text();
@@ -32,11 +33,11 @@
 //!
 //! ## Using custom headers
 //! ```no_run
-//! use std::collections::HashMap;
-//! let content = smolhttp::Client::new("https://www.rust-lang.org")
+//! let content = smolhttp::Client::new()
+//!   .post("https://www.rust-lang.org")
 //!   .unwrap()
-//!   .post()
 //!   .headers(vec![("User-Agent".to_owned(), "Mozilla/5.0 (Windows NT 10.0; Win64; x64) AppleWebKit/537.36".to_owned())])
+//!   .unwrap()
 //!   .send()
 //!   .unwrap()
 //!   .text();
@@ -45,11 +46,12 @@
 //!
 //! ## Using a proxy
 //! ```no_run
-//! let content = smolhttp::Client::new("http://www.google.com")
-//!   .unwrap()
+//! let content = smolhttp::Client::builder()
 //!   .proxy("http://127.0.0.1:1080")
+//!   .build()
+//!   .unwrap()
+//!   .get("http://www.google.com")
 //!   .unwrap()
-//!   .get()
 //!   .send()
 //!   .unwrap()
 //!   .text();
@@ -58,32 +60,453 @@
 
 #![doc(html_root_url = "https://docs.rs/minihttp")]
 
-extern crate minihttpse;
-extern crate miniurl;
+#[cfg(not(target_arch = "wasm32"))]
 extern crate native_tls;
+#[cfg(not(target_arch = "wasm32"))]
+extern crate socket2;
 
-use minihttpse::Response;
-use miniurl::Url;
-use native_tls::TlsConnector;
-use native_tls::{Error, HandshakeError};
+#[cfg(not(target_arch = "wasm32"))]
+use native_tls as tls;
+use tls::TlsConnector;
+use tls::{Error, HandshakeError};
 
 use std::fmt;
-use std::io;
+use std::fs;
+use std::io::{self, Read, Write};
 use std::net::TcpStream;
+use std::path::Path;
+use std::time::Duration;
+
+///convenience macros module
+#[macro_use]
+mod macros;
 
 ///http request module
 mod request;
-pub use request::{Client, Proxy};
+pub use request::{Client, Proxy, RequestBuilder};
+
+///alt-svc parsing module
+mod alt_svc;
+pub use alt_svc::AltSvc;
+
+///request body module
+mod body;
+pub use body::Body;
+
+///base64 encoding module
+mod base64;
+
+///body charset decoding module
+mod charset;
+
+///client builder module
+mod builder;
+pub use builder::{set_default_client, ClientBuilder};
+
+///http cache module
+mod cache;
+pub use cache::{CacheStore, DiskCache, MemoryCache};
+
+///cache-control parsing module
+mod cache_control;
+pub use cache_control::CacheControl;
+
+///deflate compression module
+mod deflate;
+
+///http date module
+mod date;
+pub use date::{fmt_http_date, parse_http_date};
+
+///chunked transfer coding module
+mod chunked;
+
+///connection streams module
+mod conn;
+
+///host name resolution module
+mod dns;
+pub use dns::{IpPreference, Resolver, SystemResolver};
+
+///content coding module
+mod encoding;
+pub use encoding::Encoding;
+
+///dns over https resolver module
+#[cfg(feature = "doh")]
+mod doh;
+#[cfg(feature = "doh")]
+pub use doh::DohResolver;
+
+///international domain names module
+mod idna;
+
+///content disposition parsing module
+mod disposition;
+
+///http archive recording module
+mod har;
+pub use har::HarRecorder;
+
+///http header map module
+mod header;
+pub use header::HeaderMap;
+
+///link header and pagination module
+mod link;
+pub use link::Paginate;
+
+///netrc credentials module
+mod netrc;
+
+///proxy bypass rules module
+mod no_proxy;
+
+///local urls module
+mod local;
+
+///secure hash algorithms module
+mod sha;
+
+///checksum module
+mod checksum;
+pub use checksum::Algorithm;
+
+///aws signature version 4 module
+#[cfg(feature = "aws-sigv4")]
+mod sigv4;
+#[cfg(feature = "aws-sigv4")]
+pub use sigv4::AwsSigV4;
+
+///oauth 1.0a signature module
+#[cfg(feature = "oauth1")]
+mod oauth1;
+#[cfg(feature = "oauth1")]
+pub use oauth1::{OAuth1, OAuth1Signature};
+
+///header parameters parsing module
+mod params;
+
+///standalone request module
+mod prepared;
+pub use prepared::Request;
+
+///cookie jar module
+mod cookie;
+pub use cookie::CookieJar;
+
+///public suffix list module
+#[cfg(feature = "psl")]
+mod psl;
+
+///HTTP/2 connections module
+#[cfg(feature = "http2")]
+mod h2;
+
+///HTTP/3 connections module
+#[cfg(feature = "http3")]
+mod h3;
+
+///HTTP/2 header compression module
+#[cfg(feature = "http2")]
+mod hpack;
+
+///http strict transport security module
+mod hsts;
+pub use hsts::HstsStore;
+
+///media type guessing module
+mod mime;
+
+///media types module
+mod media_type;
+pub use media_type::MediaType;
+
+///request middleware module
+mod middleware;
+pub use middleware::Middleware;
+
+///request metrics module
+mod metrics;
+pub use metrics::MetricsSink;
+
+///conditional requests module
+mod conditional;
+
+///request signing module
+mod signer;
+pub use signer::SigningRequest;
+
+///cors preflight module
+mod cors;
+pub use cors::Preflight;
+
+///range requests module
+mod range;
+pub use range::{ByteRange, ByteRanges, RemoteMetadata};
+
+///redirect resolution module
+mod redirect;
+
+///http method module
+mod method;
+pub use method::Method;
+
+///http response module
+mod response;
+pub use response::Response;
+
+///temporary body files module
+mod spill;
+
+///http status code module
+mod status;
+pub use status::StatusCode;
+
+///request timings module
+mod timings;
+pub use timings::Timings;
+
+///pluggable transports module
+mod transport;
+pub use transport::{Connector, Transport};
+
+///url parsing module
+mod url;
+pub use url::{Url, UrlError};
+
+///line and NDJSON iterators module
+mod lines;
+pub use lines::Lines;
+#[cfg(feature = "json")]
+pub use lines::Ndjson;
+
+///server-sent events module
+mod sse;
+pub use sse::{Event, EventSource};
+
+///websocket client module
+mod websocket;
+pub use websocket::{Message, WebSocket};
+
+///http version module
+mod version;
+pub use version::Version;
+
+///conversions from and to the types of the http crate module
+#[cfg(feature = "http")]
+mod interop;
+
+///TLS session resumption module
+#[cfg(feature = "openssl")]
+mod session;
+
+///async http request module
+#[cfg(any(feature = "tokio", all(feature = "wasm", target_arch = "wasm32")))]
+pub mod r#async;
+
+///browser fetch API backend of the async client module
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod fetch;
+
+///TLS placeholder module, TLS being set up by the browser on wasm32
+#[cfg(target_arch = "wasm32")]
+mod tls;
+
+///testing utilities module
+pub mod testing;
+
+///request cancellation module
+mod abort;
+pub use abort::AbortHandle;
+
+///circuit breaker module
+mod breaker;
+pub use breaker::CircuitBreaker;
+
+///concurrent batch requests module
+mod batch;
+pub use batch::{pipeline, send_all};
+
+///request policy module
+mod policy;
+pub use policy::RequestPolicy;
+
+///retry policy module
+mod retry;
+pub use retry::{MaxRetries, RetryPolicy};
 
 ///http basic error type
+///
+///New variants may be added, so matches need a wildcard arm: [`kind`](HttpError::kind) and the `is_*`
+///methods classify errors without one.
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum HttpError {
     Parse(&'static str),
     Config(&'static str),
     Proxy(&'static str),
+    ///the proxy requires authentication(407), with its `Proxy-Authenticate` challenge
+    ProxyAuth { challenge: Option<String> },
     IO(io::Error),
     SSL(Error),
     SSLHandshake(HandshakeError<TcpStream>),
+    Aborted,
+    Status { status: StatusCode, url: String },
+    Redirect(&'static str),
+    Url(UrlError),
+    TooLarge { limit: u64 },
+    ///a response head holds more headers than the limit set with [`ClientBuilder::max_response_headers`]
+    TooManyHeaders { limit: usize },
+    ///a response head is larger than the limit set with [`ClientBuilder::max_header_bytes`]
+    HeadersTooLarge { limit: usize },
+    IncompleteBody { expected: u64, received: u64 },
+    ///the circuit of the host is open, see [`CircuitBreaker`], and the request wasn't sent
+    CircuitOpen { host: String, retry_after: Duration },
+    ///the [`RequestPolicy`] of the Client denies the request to `url`, which wasn't sent
+    Denied { url: String, reason: &'static str },
+    ///the body doesn't hash to the checksum set with [`RequestBuilder::expect_checksum`]
+    Checksum { algorithm: Algorithm, expected: String, actual: String },
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    ///a transport error, with the url of the request and the phase that failed
+    Request { kind: ErrorKind, url: String, source: Box<HttpError> },
+}
+
+///phase or cause of an [`HttpError`], returned by [`HttpError::kind`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    ///resolving the host name
+    Dns,
+    ///opening the connection, to the target or the proxy
+    Connect,
+    ///setting up TLS
+    Tls,
+    ///writing the request
+    Write,
+    ///reading the response
+    Read,
+    ///a read, a write or a connection timed out
+    Timeout,
+    ///the response or an url is malformed
+    Parse,
+    ///the response has an error status
+    Status,
+    ///the body is too large or incomplete
+    Body,
+    ///the proxy refused the request
+    Proxy,
+    ///a redirect couldn't be followed
+    Redirect,
+    ///the request was aborted
+    Aborted,
+    ///the Client or the request is misconfigured
+    Config,
+}
+
+impl fmt::Display for ErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phase = match self {
+            ErrorKind::Dns => "DNS resolution",
+            ErrorKind::Connect => "Connection",
+            ErrorKind::Tls => "TLS",
+            ErrorKind::Write => "Write",
+            ErrorKind::Read => "Read",
+            ErrorKind::Timeout => "Timeout",
+            ErrorKind::Parse => "Parse",
+            ErrorKind::Status => "Status",
+            ErrorKind::Body => "Body",
+            ErrorKind::Proxy => "Proxy",
+            ErrorKind::Redirect => "Redirect",
+            ErrorKind::Aborted => "Abort",
+            ErrorKind::Config => "Config",
+        };
+        f.write_str(phase)
+    }
+}
+
+impl HttpError {
+    ///return the phase or cause of the error
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::ErrorKind;
+    ///
+    /// match smolhttp::get("https://unknown.invalid") {
+    ///     Err(err) if err.kind() == ErrorKind::Dns => println!("no such host: {}", err.url().unwrap_or_default()),
+    ///     Err(err) => println!("{}", err),
+    ///     Ok(res) => println!("{}", res.text()),
+    /// }
+    /// ```
+    pub fn kind(&self) -> ErrorKind {
+        match *self {
+            HttpError::Request { kind, .. } => kind,
+            HttpError::Parse(_) | HttpError::Url(_) | HttpError::TooManyHeaders { .. } | HttpError::HeadersTooLarge { .. } => ErrorKind::Parse,
+            HttpError::Config(_) | HttpError::Denied { .. } => ErrorKind::Config,
+            HttpError::Proxy(_) | HttpError::ProxyAuth { .. } => ErrorKind::Proxy,
+            HttpError::CircuitOpen { .. } => ErrorKind::Connect,
+            HttpError::IO(ref err) if is_timeout(err) => ErrorKind::Timeout,
+            HttpError::IO(_) => ErrorKind::Read,
+            HttpError::SSL(_) | HttpError::SSLHandshake(_) => ErrorKind::Tls,
+            HttpError::Aborted => ErrorKind::Aborted,
+            HttpError::Status { .. } => ErrorKind::Status,
+            HttpError::Redirect(_) => ErrorKind::Redirect,
+            HttpError::TooLarge { .. } | HttpError::IncompleteBody { .. } | HttpError::Checksum { .. } => ErrorKind::Body,
+            #[cfg(feature = "json")]
+            HttpError::Json(_) => ErrorKind::Parse,
+        }
+    }
+
+    ///return if a connection, a read or a write timed out
+    pub fn is_timeout(&self) -> bool {
+        self.kind() == ErrorKind::Timeout
+    }
+
+    ///return if the host name couldn't be resolved or the connection couldn't be opened
+    pub fn is_connect(&self) -> bool {
+        matches!(self.kind(), ErrorKind::Dns | ErrorKind::Connect)
+    }
+
+    ///return if TLS couldn't be set up, the certificate of the server being rejected for instance
+    pub fn is_tls(&self) -> bool {
+        self.kind() == ErrorKind::Tls
+    }
+
+    ///return if the response has a 4xx or 5xx status, see [`Response::error_for_status`]
+    pub fn is_status(&self) -> bool {
+        self.kind() == ErrorKind::Status
+    }
+
+    ///return the url of the request that failed, when known
+    pub fn url(&self) -> Option<&str> {
+        match *self {
+            HttpError::Request { ref url, .. } | HttpError::Status { ref url, .. } | HttpError::Denied { ref url, .. } => Some(url),
+            _ => None,
+        }
+    }
+
+    //return the error without its context
+    pub(crate) fn cause(&self) -> &HttpError {
+        match *self {
+            HttpError::Request { ref source, .. } => source.cause(),
+            _ => self,
+        }
+    }
+
+    //attach the url of the request and the phase that failed to the errors of the transport,
+    //a timeout being reported as such whatever the phase
+    pub(crate) fn context(self, kind: ErrorKind, url: &str) -> HttpError {
+        let kind = match self {
+            HttpError::IO(ref err) if is_timeout(err) => ErrorKind::Timeout,
+            HttpError::IO(_) | HttpError::SSL(_) | HttpError::SSLHandshake(_) => kind,
+            _ => return self,
+        };
+        HttpError::Request { kind, url: url.to_owned(), source: Box::new(self) }
+    }
+}
+
+//return if an io error is a read, a write or a connection timing out
+fn is_timeout(err: &io::Error) -> bool {
+    matches!(err.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock)
 }
 
 
@@ -94,7 +517,7 @@ pub enum HttpError {
 /// ```
 #[inline(always)]
 pub fn get(url: &str) -> Result<Response, HttpError> {
-    request::Client::new(url)?.get().send()
+    builder::default_client()?.get(url)?.send()
 }
 
 ///set Request POST method
@@ -104,7 +527,7 @@ pub fn get(url: &str) -> Result<Response, HttpError> {
 /// ```
 #[inline(always)]
 pub fn post(url: &str) -> Result<Response, HttpError> {
-    request::Client::new(url)?.post().send()
+    builder::default_client()?.post(url)?.send()
 }
 
 ///set Request HEAD method
@@ -114,7 +537,7 @@ pub fn post(url: &str) -> Result<Response, HttpError> {
 /// ```
 #[inline(always)]
 pub fn head(url: &str) -> Result<Response, HttpError> {
-    request::Client::new(url)?.head().send()
+    builder::default_client()?.head(url)?.send()
 }
 
 ///set Request DELETE method
@@ -124,7 +547,7 @@ pub fn head(url: &str) -> Result<Response, HttpError> {
 /// ```
 #[inline(always)]
 pub fn delete(url: &str) -> Result<Response, HttpError> {
-    request::Client::new(url)?.delete().send()
+    builder::default_client()?.delete(url)?.send()
 }
 
 ///set Request PUT method
@@ -134,7 +557,7 @@ pub fn delete(url: &str) -> Result<Response, HttpError> {
 /// ```
 #[inline(always)]
 pub fn put(url: &str) -> Result<Response, HttpError> {
-    request::Client::new(url)?.put().send()
+    builder::default_client()?.put(url)?.send()
 }
 
 ///set Request OPTIONS method
@@ -144,10 +567,163 @@ pub fn put(url: &str) -> Result<Response, HttpError> {
 /// ```
 #[inline(always)]
 pub fn options(url: &str) -> Result<Response, HttpError> {
-    request::Client::new(url)?.options().send()
+    builder::default_client()?.options(url)?.send()
+}
+
+///set Request PATCH method
+/// # Example
+/// ```no_run
+/// let content = smolhttp::patch("https://docs.rs").unwrap().text();
+/// ```
+#[inline(always)]
+pub fn patch(url: &str) -> Result<Response, HttpError> {
+    builder::default_client()?.patch(url)?.send()
+}
+
+///send a POST request with a body and headers
+/// # Example
+/// ```no_run
+/// use smolhttp::headers;
+///
+/// let body = br#"{"name": "smolhttp"}"#.to_vec();
+/// let res = smolhttp::post_with("https://httpbin.org/post", body, headers! { "Content-Type" => "application/json" }).unwrap();
+/// ```
+#[inline(always)]
+pub fn post_with(url: &str, body: Vec<u8>, headers: Vec<(String, String)>) -> Result<Response, HttpError> {
+    builder::default_client()?.post(url)?.headers(headers)?.body(body).send()
+}
+
+///send a PUT request with a body and headers
+/// # Example
+/// ```no_run
+/// use smolhttp::headers;
+///
+/// let res = smolhttp::put_with("https://httpbin.org/put", b"hello".to_vec(), headers! { "Content-Type" => "text/plain" }).unwrap();
+/// ```
+#[inline(always)]
+pub fn put_with(url: &str, body: Vec<u8>, headers: Vec<(String, String)>) -> Result<Response, HttpError> {
+    builder::default_client()?.put(url)?.headers(headers)?.body(body).send()
+}
+
+///send a PATCH request with a body and headers
+/// # Example
+/// ```no_run
+/// use smolhttp::headers;
+///
+/// let res = smolhttp::patch_with("https://httpbin.org/patch", b"{}".to_vec(), headers! { "Content-Type" => "application/json" }).unwrap();
+/// ```
+#[inline(always)]
+pub fn patch_with(url: &str, body: Vec<u8>, headers: Vec<(String, String)>) -> Result<Response, HttpError> {
+    builder::default_client()?.patch(url)?.headers(headers)?.body(body).send()
+}
+
+///send a GET request accepting JSON and deserialize the response body
+/// # Example
+/// ```no_run
+/// let repo: serde_json::Value = smolhttp::get_json("https://api.github.com/repos/rust-lang/rust").unwrap();
+/// println!("{}", repo["full_name"]);
+/// ```
+#[cfg(feature = "json")]
+pub fn get_json<T: serde::de::DeserializeOwned>(url: &str) -> Result<T, HttpError> {
+    builder::default_client()?
+        .get(url)?
+        .header("Accept", "application/json")?
+        .send()?
+        .error_for_status()?
+        .json()
+}
+
+///send `payload` serialized as JSON in a POST request and deserialize the response body
+/// # Example
+/// ```no_run
+/// use std::collections::HashMap;
+///
+/// let payload = HashMap::from([("name", "smolhttp")]);
+/// let echo: serde_json::Value = smolhttp::post_json("https://httpbin.org/post", &payload).unwrap();
+/// println!("{}", echo["json"]);
+/// ```
+#[cfg(feature = "json")]
+pub fn post_json<T, R>(url: &str, payload: &T) -> Result<R, HttpError>
+where
+    T: serde::Serialize + ?Sized,
+    R: serde::de::DeserializeOwned,
+{
+    let body = serde_json::to_vec(payload)?;
+    builder::default_client()?
+        .post(url)?
+        .header("Content-Type", "application/json")?
+        .header("Accept", "application/json")?
+        .body(body)
+        .send()?
+        .error_for_status()?
+        .json()
+}
+
+///download `url` to the file at `path`, following redirects, and return the number of bytes written
+///
+///The body is saved as sent by the server, without being decoded, and written to the file as it arrives
+///rather than held in memory. The download fails on 4xx/5xx statuses and with [`HttpError::IncompleteBody`]
+///when the body is shorter than its `Content-Length`, removing the partial file.
+/// # Example
+/// ```no_run
+/// let written = smolhttp::download("https://www.rust-lang.org/logos/rust-logo-512x512.png", "rust-logo.png").unwrap();
+/// println!("{written} bytes");
+/// ```
+pub fn download<P: AsRef<Path>>(url: &str, path: P) -> Result<u64, HttpError> {
+    let client = builder::default_builder()?.redirects(10).build()?;
+    let mut request = client.get(url)?.header("Accept-Encoding", "identity")?;
+    let mut history = Vec::new();
+    let (res, mut body) = loop {
+        let (res, body) = request.intercept()?.stream()?;
+        if !request.redirect(&res, &mut history)? {
+            break (res, body);
+        }
+    };
+    res.error_for_status_ref()?;
+
+    let path = path.as_ref();
+    let mut file = fs::File::create(path)?;
+    let written = save(&mut body, &mut file, res.content_length());
+    if written.is_err() {
+        drop(file);
+        let _ = fs::remove_file(path);
+    }
+    written
 }
 
-impl std::error::Error for HttpError {}
+//copy a downloaded body to its file, checking it against the announced length
+fn save<R: Read>(body: &mut R, file: &mut fs::File, length: Option<u64>) -> Result<u64, HttpError> {
+    let mut buf = vec![0; 64 * 1024];
+    let mut written = 0;
+    loop {
+        let n = match body.read(&mut buf) {
+            Ok(0) => break,
+            Ok(n) => n,
+            Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+            //the length is checked below from what was written
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof && length.is_some() => break,
+            Err(err) => return Err(err.into()),
+        };
+        file.write_all(&buf[..n])?;
+        written += n as u64;
+    }
+    conn::check_complete(length, written)?;
+    Ok(written)
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match *self {
+            HttpError::IO(ref err) => Some(err),
+            HttpError::SSL(ref err) => Some(err),
+            HttpError::SSLHandshake(ref err) => Some(err),
+            HttpError::Request { ref source, .. } => Some(source.as_ref()),
+            #[cfg(feature = "json")]
+            HttpError::Json(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
 
 impl From<io::Error> for HttpError {
     fn from(err: io::Error) -> HttpError {
@@ -155,10 +731,9 @@ impl From<io::Error> for HttpError {
     }
 }
 
-impl From<minihttpse::HttpError> for HttpError {
-    fn from(err: minihttpse::HttpError) -> HttpError {
-        let minihttpse::HttpError::Parse(e) = err;
-        HttpError::Parse(e)
+impl From<UrlError> for HttpError {
+    fn from(err: UrlError) -> HttpError {
+        HttpError::Url(err)
     }
 }
 
@@ -174,6 +749,40 @@ impl From<Error> for HttpError {
     }
 }
 
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for HttpError {
+    fn from(err: serde_json::Error) -> HttpError {
+        HttpError::Json(err)
+    }
+}
+
+///convert the error for `Read` and `Write` adapters, io errors being returned as they were
+/// # Example
+/// ```no_run
+/// use std::io::{self, Read};
+///
+/// fn fetch(url: &str) -> io::Result<impl Read> {
+///     Ok(io::Cursor::new(smolhttp::get(url)?.error_for_status()?.bytes().to_vec()))
+/// }
+/// ```
+impl From<HttpError> for io::Error {
+    fn from(err: HttpError) -> io::Error {
+        if let HttpError::IO(err) = err {
+            return err;
+        }
+        let kind = match (err.cause(), err.kind()) {
+            (HttpError::IO(source), _) => source.kind(),
+            (HttpError::IncompleteBody { .. }, _) => io::ErrorKind::UnexpectedEof,
+            (_, ErrorKind::Timeout) => io::ErrorKind::TimedOut,
+            (_, ErrorKind::Aborted) => io::ErrorKind::Interrupted,
+            (_, ErrorKind::Parse | ErrorKind::Body) => io::ErrorKind::InvalidData,
+            (_, ErrorKind::Config) => io::ErrorKind::InvalidInput,
+            _ => io::ErrorKind::Other,
+        };
+        io::Error::new(kind, err)
+    }
+}
+
 impl fmt::Display for HttpError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -181,8 +790,60 @@ impl fmt::Display for HttpError {
             HttpError::Config(ref err) => write!(f, "Config error: {}", err),
             HttpError::IO(ref err) => write!(f, "IO error: {}", err),
             HttpError::Proxy(ref err) => write!(f, "Proxy error : {}", err),
+            HttpError::ProxyAuth { challenge: Some(ref challenge) } => write!(f, "Proxy authentication required: {}", challenge),
+            HttpError::ProxyAuth { challenge: None } => write!(f, "Proxy authentication required"),
             HttpError::SSL(ref err) => write!(f, "SSL error: {}", err),
             HttpError::SSLHandshake(ref err) => write!(f, "SSL handshake error: {}", err),
+            HttpError::Aborted => write!(f, "Request aborted"),
+            HttpError::Status { ref status, ref url } => write!(f, "Status error: {} for url ({})", status, url),
+            HttpError::Redirect(ref err) => write!(f, "Redirect error: {}", err),
+            HttpError::Url(ref err) => write!(f, "Url error: {}", err),
+            HttpError::TooLarge { limit } => write!(f, "Response too large: body exceeds {} bytes", limit),
+            HttpError::TooManyHeaders { limit } => write!(f, "Response head has more than {} headers", limit),
+            HttpError::HeadersTooLarge { limit } => write!(f, "Response head exceeds {} bytes", limit),
+            HttpError::IncompleteBody { expected, received } => write!(f, "Incomplete body: received {} of {} bytes", received, expected),
+            HttpError::CircuitOpen { ref host, retry_after } => write!(f, "Circuit open for {}: retry in {:?}", host, retry_after),
+            HttpError::Denied { ref url, reason } => write!(f, "Request to {} denied: {}", url, reason),
+            HttpError::Checksum { algorithm, ref expected, ref actual } => write!(f, "Checksum mismatch: expected {} {}, got {}", algorithm, expected, actual),
+            #[cfg(feature = "json")]
+            HttpError::Json(ref err) => write!(f, "JSON error: {}", err),
+            HttpError::Request { kind, ref url, ref source } => write!(f, "{} error for url ({}): {}", kind, url, source),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn download_to_file() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let body = vec![b'x'; 200 * 1024];
+        let server = thread::spawn(move || {
+            let replies = [
+                b"HTTP/1.1 302 Found\r\nLocation: /moved\r\nContent-Length: 0\r\n\r\n".to_vec(),
+                [format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n", body.len()).into_bytes(), body].concat(),
+                b"HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort".to_vec(),
+            ];
+            for reply in replies {
+                let (mut stream, _) = listener.accept().unwrap();
+                let head = conn::read_head(&mut stream, conn::HeaderLimits::default()).unwrap();
+                assert!(String::from_utf8(head).unwrap().contains("\r\nAccept-Encoding: identity\r\n"));
+                stream.write_all(&reply).unwrap();
+            }
+        });
+
+        let path = std::env::temp_dir().join(format!("smolhttp-download-{}", std::process::id()));
+        let url = format!("http://127.0.0.1:{}/file", port);
+        assert_eq!(download(&url, &path).unwrap(), 200 * 1024);
+        assert_eq!(fs::read(&path).unwrap().len(), 200 * 1024);
+        let err = download(&url, &path).unwrap_err();
+        assert!(matches!(err, HttpError::IncompleteBody { expected: 10, received: 5 }));
+        assert!(!path.exists());
+        server.join().unwrap();
+    }
+}
@@ -0,0 +1,168 @@
+use super::conn::TeeReader;
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::fmt;
+use std::io::{BufRead, BufReader};
+#[cfg(feature = "json")]
+use std::marker::PhantomData;
+
+///iterator over the lines of a body, without their `\n` or `\r\n`, returned by [`Response::lines`] and
+///[`RequestBuilder::lines`].
+pub struct Lines<'a> {
+    reader: Box<dyn BufRead + Send + 'a>,
+}
+
+impl fmt::Debug for Lines<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Lines").finish_non_exhaustive()
+    }
+}
+
+impl<'a> Lines<'a> {
+    fn new<R: BufRead + Send + 'a>(reader: R) -> Self {
+        Self { reader: Box::new(reader) }
+    }
+}
+
+impl Iterator for Lines<'_> {
+    type Item = Result<String, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = Vec::new();
+        match self.reader.read_until(b'\n', &mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.last() == Some(&b'\n') {
+                    line.pop();
+                    if line.last() == Some(&b'\r') {
+                        line.pop();
+                    }
+                }
+                Some(Ok(String::from_utf8_lossy(&line).into_owned()))
+            }
+            Err(err) => Some(Err(err.into())),
+        }
+    }
+}
+
+///iterator deserializing each non-empty line of a body as JSON, returned by [`Response::ndjson`] and
+///[`RequestBuilder::ndjson`].
+#[cfg(feature = "json")]
+pub struct Ndjson<'a, T> {
+    lines: Lines<'a>,
+    item: PhantomData<fn() -> T>,
+}
+
+#[cfg(feature = "json")]
+impl<T> fmt::Debug for Ndjson<'_, T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Ndjson").finish_non_exhaustive()
+    }
+}
+
+#[cfg(feature = "json")]
+impl<T: serde::de::DeserializeOwned> Iterator for Ndjson<'_, T> {
+    type Item = Result<T, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(err) => return Some(Err(err)),
+            };
+            if !line.trim().is_empty() {
+                return Some(serde_json::from_str(&line).map_err(HttpError::from));
+            }
+        }
+    }
+}
+
+impl Response {
+    ///return an iterator over the lines of the response body
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 9\r\n\r\none\r\ntwo\n".to_vec()).unwrap();
+    /// let lines = res.lines().collect::<Result<Vec<_>, _>>().unwrap();
+    /// assert_eq!(lines, ["one", "two"]);
+    /// ```
+    pub fn lines(&self) -> Lines<'_> {
+        Lines::new(self.bytes())
+    }
+
+    ///return an iterator deserializing each line of the response body as JSON(NDJSON)
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 8\r\n\r\n1\n\n2\n[3]\n".to_vec()).unwrap();
+    /// let mut items = res.ndjson::<serde_json::Value>();
+    /// assert_eq!(items.next().unwrap().unwrap(), 1);
+    /// assert_eq!(items.next().unwrap().unwrap(), 2);
+    /// assert_eq!(items.next().unwrap().unwrap()[0], 3);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn ndjson<T: serde::de::DeserializeOwned>(&self) -> Ndjson<'_, T> {
+        Ndjson { lines: self.lines(), item: PhantomData }
+    }
+}
+
+impl RequestBuilder {
+    ///send the request and return an iterator over the lines of the response body as they arrive
+    ///
+    ///Fails with [`HttpError::Status`] if the response is not a success.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// for line in Client::new().get("https://example.com/log").unwrap().lines().unwrap() {
+    ///     println!("{}", line.unwrap());
+    /// }
+    /// ```
+    pub fn lines(self) -> Result<Lines<'static>, HttpError> {
+        let request = self.intercept()?;
+        let (res, body) = request.stream()?;
+        res.error_for_status_ref()?;
+        match request.tee {
+            Some(tee) => Ok(Lines::new(BufReader::new(TeeReader { reader: body, tee }))),
+            None => Ok(Lines::new(BufReader::new(body))),
+        }
+    }
+
+    ///send the request and return an iterator deserializing each line of the response body as JSON as it arrives
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let events = Client::new().get("https://example.com/events.ndjson").unwrap().ndjson::<serde_json::Value>().unwrap();
+    /// for event in events {
+    ///     println!("{}", event.unwrap()["type"]);
+    /// }
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn ndjson<T: serde::de::DeserializeOwned>(self) -> Result<Ndjson<'static, T>, HttpError> {
+        Ok(Ndjson { lines: self.lines()?, item: PhantomData })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::Client;
+
+    #[test]
+    fn streamed_lines() {
+        let mock = MockTransport::new();
+        mock.respond_raw("GET", "http://example.com/log", b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\none\n\r\n5\r\ntwo\r\n\r\n5\r\nthree\r\n0\r\n\r\n".to_vec());
+        mock.respond("GET", "http://example.com/missing", 404, "not found");
+        let client = Client::with_connector(mock);
+
+        let lines = client.get("http://example.com/log").unwrap().lines().unwrap();
+        assert_eq!(lines.collect::<Result<Vec<_>, _>>().unwrap(), ["one", "two", "three"]);
+        assert!(matches!(client.get("http://example.com/missing").unwrap().lines(), Err(HttpError::Status { .. })));
+    }
+}
@@ -0,0 +1,113 @@
+use super::params::split_params;
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::collections::HashMap;
+
+//parse a Link header value (RFC 8288) into a `rel -> url` map
+pub(crate) fn parse(value: &str) -> HashMap<String, String> {
+    let mut links = HashMap::new();
+
+    for link in split_links(value) {
+        let link = link.trim();
+        let (url, params) = match link.strip_prefix('<').and_then(|l| l.split_once('>')) {
+            Some(parts) => parts,
+            None => continue,
+        };
+
+        for (name, rels) in split_params(params) {
+            if name == "rel" {
+                for rel in rels.split_whitespace() {
+                    links.entry(rel.to_ascii_lowercase()).or_insert_with(|| url.to_owned());
+                }
+            }
+        }
+    }
+
+    links
+}
+
+//split a Link header value on the commas that are outside of `<...>` and quoted strings
+fn split_links(value: &str) -> Vec<&str> {
+    let mut links = Vec::new();
+    let (mut in_url, mut in_quotes) = (false, false);
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            '<' if !in_quotes => in_url = true,
+            '>' if !in_quotes => in_url = false,
+            '"' if !in_url => in_quotes = !in_quotes,
+            ',' if !in_url && !in_quotes => {
+                links.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    links.push(&value[start..]);
+    links
+}
+
+///iterator over the pages of a paginated resource, following `rel="next"` links.
+///
+///Returned by [`RequestBuilder::paginate`](crate::RequestBuilder::paginate), it stops after the first error
+///or when a page has no `next` link.
+#[derive(Debug)]
+pub struct Paginate {
+    next: Option<RequestBuilder>,
+}
+
+impl Paginate {
+    pub(crate) fn new(request: RequestBuilder) -> Self {
+        Self { next: Some(request) }
+    }
+}
+
+impl Iterator for Paginate {
+    type Item = Result<Response, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut request = self.next.take()?;
+        let res = match request.clone().send() {
+            Ok(res) => res,
+            Err(err) => return Some(Err(err)),
+        };
+
+        if let Some(url) = res.links().get("next") {
+            match request.retarget(url) {
+                Ok(()) => self.next = Some(request),
+                Err(err) => return Some(Err(err)),
+            }
+        }
+        Some(Ok(res))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn github_style_links() {
+        let links = parse("<https://api.github.com/repos?page=2>; rel=\"next\", <https://api.github.com/repos?page=5>; rel=\"last\"");
+        assert_eq!(links["next"], "https://api.github.com/repos?page=2");
+        assert_eq!(links["last"], "https://api.github.com/repos?page=5");
+        assert_eq!(links.len(), 2);
+    }
+
+    #[test]
+    fn multiple_rels_and_commas() {
+        let links = parse("<https://example.com/a,b>; title=\"x, y\"; rel=\"prev first\",<https://example.com/c>;rel=next");
+        assert_eq!(links["prev"], "https://example.com/a,b");
+        assert_eq!(links["first"], "https://example.com/a,b");
+        assert_eq!(links["next"], "https://example.com/c");
+    }
+
+    #[test]
+    fn ignores_garbage() {
+        assert!(parse("not a link").is_empty());
+        assert!(parse("").is_empty());
+    }
+}
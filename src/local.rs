@@ -0,0 +1,159 @@
+use super::base64;
+use super::date;
+use super::disposition;
+use super::mime;
+use super::ErrorKind;
+use super::HeaderMap;
+use super::HttpError;
+use super::Method;
+use super::RequestBuilder;
+use super::Response;
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+impl RequestBuilder {
+    //return the response to a request for a local url, answered without any connection, or None for the
+    //urls of a server
+    pub(crate) fn send_local(&self) -> Option<Result<Response, HttpError>> {
+        let read = match self.scheme.as_str() {
+            "file" => Self::read_file,
+            "data" => Self::read_data,
+            _ => return None,
+        };
+        if let Err(err) = self.check_policy() {
+            return Some(Err(err));
+        }
+        let res = match self.method {
+            Method::Get | Method::Head => read(self),
+            _ => Err(HttpError::Config("local urls can only be read with GET or HEAD")),
+        };
+        Some(res.map_err(|err| err.context(ErrorKind::Read, &self.raw_url)))
+    }
+
+    //answer a `file://` request with the content of the file, as a server would
+    fn read_file(&self) -> Result<Response, HttpError> {
+        let path = file_path(&self.url.path)?;
+        let metadata = fs::metadata(&path)?;
+        if metadata.is_dir() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "file url names a directory").into());
+        }
+        if let Some(limit) = self.client.inner.max_response_size {
+            if metadata.len() > limit {
+                return Err(HttpError::TooLarge { limit });
+            }
+        }
+
+        let content_type = path.extension().and_then(|ext| mime::from_extension(&ext.to_string_lossy()));
+        let mut head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Type: {}\r\n",
+            metadata.len(),
+            content_type.unwrap_or("application/octet-stream")
+        );
+        if let Ok(modified) = metadata.modified() {
+            head.push_str(&format!("Last-Modified: {}\r\n", date::fmt_http_date(modified)));
+        }
+        head.push_str("\r\n");
+        let body = match self.method {
+            Method::Head => Vec::new(),
+            _ => fs::read(&path)?,
+        };
+        Response::from_parts(head.as_bytes(), body, HeaderMap::new())
+    }
+
+    //answer a `data:` request with the content held by the url, `data:[<media type>][;base64],<data>`
+    fn read_data(&self) -> Result<Response, HttpError> {
+        let (meta, data) = self.url.path.split_once(',').ok_or(HttpError::Parse("data url without a comma"))?;
+        let data = disposition::percent_decode(data).ok_or(HttpError::Parse("invalid percent encoding in data url"))?;
+        let (meta, data) = match meta.len().checked_sub(7).filter(|&at| meta[at..].eq_ignore_ascii_case(";base64")) {
+            Some(at) => (&meta[..at], base64::decode(&data).ok_or(HttpError::Parse("invalid base64 in data url"))?),
+            None => (meta, data),
+        };
+        //the media type defaults to `text/plain;charset=US-ASCII`, and only its parameters may be given
+        let content_type = match meta.trim() {
+            "" => "text/plain;charset=US-ASCII".to_owned(),
+            meta if meta.starts_with(';') => format!("text/plain{}", meta),
+            meta => meta.to_owned(),
+        };
+        if content_type.contains(['\r', '\n']) {
+            return Err(HttpError::Parse("invalid media type in data url"));
+        }
+        if let Some(limit) = self.client.inner.max_response_size {
+            if data.len() as u64 > limit {
+                return Err(HttpError::TooLarge { limit });
+            }
+        }
+
+        let head = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nContent-Type: {}\r\n\r\n", data.len(), content_type);
+        let body = match self.method {
+            Method::Head => Vec::new(),
+            _ => data,
+        };
+        Response::from_parts(head.as_bytes(), body, HeaderMap::new())
+    }
+}
+
+//return the local path named by the percent encoded path of a file url
+fn file_path(path: &str) -> Result<PathBuf, HttpError> {
+    let path = disposition::percent_decode(path).ok_or(HttpError::Parse("invalid percent encoding in file url"))?;
+    let path = String::from_utf8(path).map_err(|_| HttpError::Parse("file url path isn't valid utf-8"))?;
+    //`/C:/Users` names `C:/Users` on windows
+    #[cfg(windows)]
+    let path = match path.as_bytes() {
+        [b'/', drive, b':', ..] if drive.is_ascii_alphabetic() => path[1..].to_owned(),
+        _ => path,
+    };
+    Ok(PathBuf::from(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Client, ErrorKind};
+    use std::fs;
+
+    #[test]
+    fn file_urls() {
+        let dir = std::env::temp_dir().join(format!("smolhttp-local-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("hello world.txt");
+        fs::write(&path, "hello").unwrap();
+        let url = format!("file://{}", path.display()).replace(' ', "%20");
+
+        let res = crate::get(&url).unwrap();
+        assert_eq!((res.status_code(), res.text().as_str()), (200, "hello"));
+        assert_eq!(res.content_length(), Some(5));
+        assert!(res.header("Content-Type").is_some() && res.header("Last-Modified").is_some());
+        assert_eq!(res.url(), url);
+
+        let client = Client::new();
+        let head = client.head(&url).unwrap().send().unwrap();
+        assert_eq!((head.content_length(), head.bytes().len()), (Some(5), 0));
+        assert!(client.post(&url).unwrap().send().is_err());
+        let missing = client.get(&format!("file://{}", dir.join("missing").display())).unwrap().send().unwrap_err();
+        assert_eq!(missing.kind(), ErrorKind::Read);
+        assert!(client.get("file://example.com/etc/hosts").is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn data_urls() {
+        let res = crate::get("data:image/png;base64,iVBORw0K GgoA").unwrap();
+        assert_eq!(res.bytes(), b"\x89PNG\r\n\x1a\n\0");
+        assert_eq!(res.header("Content-Type"), Some("image/png"));
+
+        let res = crate::get("DATA:,Hello%2C%20World!#greeting").unwrap();
+        assert_eq!((res.status_code(), res.text().as_str()), (200, "Hello, World!"));
+        assert_eq!(res.header("Content-Type"), Some("text/plain;charset=US-ASCII"));
+        let res = crate::get("data:;charset=utf-8;BASE64,aMOpbGxv").unwrap();
+        assert_eq!((res.text().as_str(), res.header("Content-Type")), ("héllo", Some("text/plain;charset=utf-8")));
+
+        let client = Client::builder().base_url("http://example.com/").build().unwrap();
+        assert_eq!(client.head("data:text/html,<p>hi</p>").unwrap().send().unwrap().content_length(), Some(9));
+        for invalid in ["data:text/plain", "data:;base64,a===", "data:,%zz"] {
+            assert_eq!(client.get(invalid).unwrap().send().unwrap_err().kind(), ErrorKind::Parse);
+        }
+        assert!(client.put("data:,x").unwrap().send().is_err());
+    }
+}
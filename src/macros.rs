@@ -0,0 +1,46 @@
+///build a header list for [`RequestBuilder::headers`](crate::RequestBuilder::headers)
+///
+///Names and values can be anything implementing `Display`.
+/// # Example
+/// ```
+/// use smolhttp::{headers, Client};
+///
+/// let token = String::from("s3cr3t");
+/// let request = Client::new().get("https://www.google.com").unwrap().headers(headers! {
+///     "Accept" => "application/json",
+///     "X-Token" => token,
+///     "X-Retries" => 3,
+/// }).unwrap();
+/// ```
+#[macro_export]
+macro_rules! headers {
+    ($($name:expr => $value:expr),* $(,)?) => {
+        vec![$(($name.to_string(), $value.to_string())),*] as Vec<(String, String)>
+    };
+}
+
+//emit a `log` record under the `smolhttp` target with the `log` feature, do nothing otherwise
+macro_rules! event {
+    ($level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "log")]
+        log::$level!(target: "smolhttp", $($arg)+);
+        #[cfg(not(feature = "log"))]
+        if false {
+            let _ = format_args!($($arg)+);
+        }
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn headers_macro() {
+        let token = "abc".to_owned();
+        let headers = headers! { "Accept" => "application/json", "X-Token" => token };
+        assert_eq!(headers, [
+            ("Accept".to_owned(), "application/json".to_owned()),
+            ("X-Token".to_owned(), "abc".to_owned()),
+        ]);
+        assert!(headers! {}.is_empty());
+    }
+}
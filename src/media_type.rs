@@ -0,0 +1,148 @@
+use super::params::{is_token, split_params};
+use super::HttpError;
+
+use std::fmt;
+use std::str::FromStr;
+
+///parsed media type, like the value of a `Content-Type` header.
+/// # Example
+/// ```
+/// use smolhttp::MediaType;
+///
+/// let media_type = MediaType::parse("application/ld+json; charset=\"UTF-8\"").unwrap();
+/// assert_eq!(media_type.essence(), "application/ld+json");
+/// assert_eq!(media_type.suffix(), Some("json"));
+/// assert_eq!(media_type.charset(), Some("UTF-8"));
+/// assert!(media_type.is_json());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MediaType {
+    type_: String,
+    subtype: String,
+    params: Vec<(String, String)>,
+}
+
+impl MediaType {
+    ///return a `type/subtype` media type without parameters
+    pub fn new(type_: &str, subtype: &str) -> Self {
+        Self { type_: type_.to_ascii_lowercase(), subtype: subtype.to_ascii_lowercase(), params: Vec::new() }
+    }
+
+    ///return the media type with the parameter `name` added
+    pub fn with_param(mut self, name: &str, value: &str) -> Self {
+        self.params.push((name.to_ascii_lowercase(), value.to_owned()));
+        self
+    }
+
+    ///parse a `type/subtype; name=value` media type, the type, the subtype and the parameter names being lowercased
+    pub fn parse(value: &str) -> Option<Self> {
+        let mut params = split_params(value).into_iter();
+        let (essence, _) = params.next()?;
+        let (type_, subtype) = essence.split_once('/')?;
+        let (type_, subtype) = (type_.trim(), subtype.trim());
+        if !is_token(type_) || !is_token(subtype) {
+            return None;
+        }
+        Some(Self { type_: type_.to_owned(), subtype: subtype.to_owned(), params: params.collect() })
+    }
+
+    ///return the type, `text` for `text/html`
+    pub fn type_(&self) -> &str {
+        &self.type_
+    }
+
+    ///return the subtype, `html` for `text/html`
+    pub fn subtype(&self) -> &str {
+        &self.subtype
+    }
+
+    ///return the structured syntax suffix of the subtype, `xml` for `application/atom+xml`
+    pub fn suffix(&self) -> Option<&str> {
+        self.subtype.rsplit_once('+').map(|(_, suffix)| suffix)
+    }
+
+    ///return the `type/subtype` media type without its parameters
+    pub fn essence(&self) -> String {
+        format!("{}/{}", self.type_, self.subtype)
+    }
+
+    ///return the value of the parameter `name`
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    ///return the parameters in the order they were given
+    pub fn params(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.iter().map(|(name, value)| (name.as_str(), value.as_str()))
+    }
+
+    ///return the `charset` parameter
+    pub fn charset(&self) -> Option<&str> {
+        self.param("charset")
+    }
+
+    ///return if the media type is `application/json` or has the `+json` suffix
+    pub fn is_json(&self) -> bool {
+        (self.type_ == "application" && self.subtype == "json") || self.suffix() == Some("json")
+    }
+
+    ///return if the media type is in the media range `range`, like `text/*` or `*/*` of an `Accept` header
+    /// # Example
+    /// ```
+    /// use smolhttp::MediaType;
+    ///
+    /// let html = MediaType::new("text", "html");
+    /// assert!(html.matches("text/*"));
+    /// assert!(html.matches("*/*"));
+    /// assert!(!html.matches("application/json"));
+    /// ```
+    pub fn matches(&self, range: &str) -> bool {
+        let range = range.split(';').next().unwrap_or_default().trim();
+        match range.split_once('/') {
+            Some(("*", "*")) => true,
+            Some((type_, "*")) => type_.eq_ignore_ascii_case(&self.type_),
+            Some((type_, subtype)) => type_.eq_ignore_ascii_case(&self.type_) && subtype.eq_ignore_ascii_case(&self.subtype),
+            None => false,
+        }
+    }
+}
+
+impl FromStr for MediaType {
+    type Err = HttpError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Self::parse(value).ok_or(HttpError::Parse("invalid media type"))
+    }
+}
+
+impl fmt::Display for MediaType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}/{}", self.type_, self.subtype)?;
+        for (name, value) in &self.params {
+            match is_token(value) {
+                true => write!(f, "; {}={}", name, value)?,
+                false => write!(f, "; {}=\"{}\"", name, value.replace('\\', "\\\\").replace('"', "\\\""))?,
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_and_format() {
+        let media_type = MediaType::parse(" Text/HTML ; Charset=utf-8; q=\"a b\"").unwrap();
+        assert_eq!((media_type.type_(), media_type.subtype()), ("text", "html"));
+        assert_eq!(media_type.charset(), Some("utf-8"));
+        assert_eq!(media_type.to_string(), "text/html; charset=utf-8; q=\"a b\"");
+        assert_eq!(media_type.to_string().parse::<MediaType>().unwrap(), media_type);
+
+        assert!(MediaType::parse("text").is_none());
+        assert!(MediaType::parse("text/").is_none());
+        assert!(MediaType::parse("te xt/html").is_none());
+        assert!(!MediaType::new("application", "jsonp").is_json());
+    }
+}
@@ -0,0 +1,109 @@
+use super::params;
+use super::HttpError;
+
+use std::fmt;
+use std::str::FromStr;
+
+///http request method, parsed from its case-sensitive name.
+/// # Example
+/// ```
+/// use smolhttp::Method;
+///
+/// let method: Method = "PUT".parse().unwrap();
+/// assert_eq!(method, Method::Put);
+/// assert!(method.is_idempotent() && !method.is_safe());
+/// assert_eq!("PROPFIND".parse::<Method>().unwrap(), Method::Custom("PROPFIND".to_owned()));
+/// assert!("GET /".parse::<Method>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Post,
+    Put,
+    Head,
+    Delete,
+    Options,
+    Patch,
+    Trace,
+    Connect,
+    ///any other method, like the `PROPFIND` of WebDAV
+    Custom(String),
+}
+
+impl Method {
+    ///return the name of the method, as sent on the request line
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Head => "HEAD",
+            Method::Delete => "DELETE",
+            Method::Options => "OPTIONS",
+            Method::Patch => "PATCH",
+            Method::Trace => "TRACE",
+            Method::Connect => "CONNECT",
+            Method::Custom(method) => method,
+        }
+    }
+
+    ///return if the method is safe, only retrieving data: `GET`, `HEAD`, `OPTIONS` and `TRACE`(RFC 9110 section 9.2.1)
+    pub fn is_safe(&self) -> bool {
+        matches!(self, Method::Get | Method::Head | Method::Options | Method::Trace)
+    }
+
+    ///return if sending a request with the method several times has the effect of sending it once: the safe
+    ///methods, `PUT` and `DELETE`(RFC 9110 section 9.2.2)
+    pub fn is_idempotent(&self) -> bool {
+        self.is_safe() || matches!(self, Method::Put | Method::Delete)
+    }
+}
+
+impl FromStr for Method {
+    type Err = HttpError;
+
+    ///parse a method name, failing with [`HttpError::Config`] when it isn't a valid http token
+    fn from_str(method: &str) -> Result<Self, HttpError> {
+        Ok(match method {
+            "GET" => Method::Get,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "HEAD" => Method::Head,
+            "DELETE" => Method::Delete,
+            "OPTIONS" => Method::Options,
+            "PATCH" => Method::Patch,
+            "TRACE" => Method::Trace,
+            "CONNECT" => Method::Connect,
+            method if params::is_token(method) => Method::Custom(method.to_owned()),
+            _ => return Err(HttpError::Config("Method is not a valid token")),
+        })
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_displays() {
+        for name in ["GET", "POST", "PUT", "HEAD", "DELETE", "OPTIONS", "PATCH", "TRACE", "CONNECT", "MKCOL"] {
+            assert_eq!(name.parse::<Method>().unwrap().to_string(), name);
+        }
+        //method names are case-sensitive
+        assert_eq!("get".parse::<Method>().unwrap(), Method::Custom("get".to_owned()));
+        assert!(matches!("".parse::<Method>(), Err(HttpError::Config(_))));
+        assert!("GE T".parse::<Method>().is_err());
+
+        let idempotent: Vec<_> = ["GET", "POST", "PUT", "HEAD", "DELETE", "OPTIONS", "PATCH", "TRACE", "CONNECT"]
+            .into_iter()
+            .filter(|name| name.parse::<Method>().unwrap().is_idempotent())
+            .collect();
+        assert_eq!(idempotent, ["GET", "PUT", "HEAD", "DELETE", "OPTIONS", "TRACE"]);
+    }
+}
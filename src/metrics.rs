@@ -0,0 +1,124 @@
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::fmt;
+use std::time::Duration;
+
+///receiver of the aggregate statistics of the requests sent by a Client, to bridge them to a metrics system
+///like Prometheus or StatsD, set with [`ClientBuilder::metrics`](crate::ClientBuilder::metrics).
+///
+///Each attempt of a request, the retries included, reports:
+///- the counter `requests`, labeled with its `method` and the `status` class of the response(`2xx`, `4xx`...)
+///- the counter `errors` in place of `requests` when it failed, labeled with its `method` and the error `kind`
+///- the counters `bytes_sent` and `bytes_received`, the sizes of the request and response bodies
+///- the histograms `connect_seconds`, when a connection was opened, `first_byte_seconds` and `duration_seconds`
+///- the counters `pool_hits` and `pool_misses`, when an idle connection was reused or a new one needed
+/// # Example
+/// ```
+/// use smolhttp::{Client, MetricsSink};
+///
+/// #[derive(Debug)]
+/// struct Print;
+///
+/// impl MetricsSink for Print {
+///     fn counter(&self, name: &str, value: u64, labels: &[(&str, &str)]) {
+///         println!("{} {:?} += {}", name, labels, value);
+///     }
+///
+///     fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]) {
+///         println!("{} {:?} <- {}", name, labels, value);
+///     }
+/// }
+///
+/// let client = Client::builder().metrics(Print).build().unwrap();
+/// ```
+pub trait MetricsSink: fmt::Debug + Send + Sync {
+    ///add `value` to the counter `name`
+    fn counter(&self, name: &str, value: u64, labels: &[(&str, &str)]);
+
+    ///record the observation `value` in the histogram `name`, durations being in seconds
+    fn histogram(&self, name: &str, value: f64, labels: &[(&str, &str)]);
+}
+
+impl RequestBuilder {
+    //report an attempt to the metrics sink of the Client
+    pub(crate) fn record_metrics(&self, res: &Result<Response, HttpError>, elapsed: Duration) {
+        let Some(ref sink) = self.client.inner.metrics else {
+            return;
+        };
+        let method = self.method.to_string();
+        let labels = [("method", method.as_str())];
+        let sent = self.body.as_ref().and_then(|body| body.len()).unwrap_or(0);
+        sink.counter("bytes_sent", sent, &labels);
+        match res {
+            Ok(res) => {
+                let class = format!("{}xx", res.status_code() / 100);
+                sink.counter("requests", 1, &[("method", &method), ("status", &class)]);
+                sink.counter("bytes_received", res.bytes().len() as u64, &labels);
+                let timings = res.timings();
+                if timings.connect() > Duration::ZERO {
+                    sink.histogram("connect_seconds", timings.connect().as_secs_f64(), &labels);
+                }
+                sink.histogram("first_byte_seconds", timings.first_byte().as_secs_f64(), &labels);
+            }
+            Err(err) => sink.counter("errors", 1, &[("method", &method), ("kind", &err.kind().to_string())]),
+        }
+        sink.histogram("duration_seconds", elapsed.as_secs_f64(), &labels);
+    }
+
+    //report to the metrics sink of the Client if an idle connection was reused
+    pub(crate) fn record_pool(&self, hit: bool) {
+        if let Some(ref sink) = self.client.inner.metrics {
+            sink.counter(if hit { "pool_hits" } else { "pool_misses" }, 1, &[]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::Client;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug, Default, Clone)]
+    struct Recorder(Arc<Mutex<Vec<String>>>);
+
+    impl MetricsSink for Recorder {
+        fn counter(&self, name: &str, value: u64, labels: &[(&str, &str)]) {
+            let labels = labels.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>();
+            self.0.lock().unwrap().push(format!("{}{{{}}} {}", name, labels.join(","), value));
+        }
+
+        fn histogram(&self, name: &str, _value: f64, _labels: &[(&str, &str)]) {
+            self.0.lock().unwrap().push(name.to_owned());
+        }
+    }
+
+    #[test]
+    fn request_metrics() {
+        let mock = MockTransport::new();
+        mock.respond("POST", "http://example.com/items", 201, "created");
+        let recorder = Recorder::default();
+        let client = Client::builder().connector(mock).metrics(recorder.clone()).build().unwrap();
+
+        client.post("http://example.com/items").unwrap().body("abc").send().unwrap();
+        assert_eq!(
+            *recorder.0.lock().unwrap(),
+            [
+                "pool_misses{} 1",
+                "bytes_sent{method=POST} 3",
+                "requests{method=POST,status=2xx} 1",
+                "bytes_received{method=POST} 7",
+                "connect_seconds",
+                "first_byte_seconds",
+                "duration_seconds",
+            ]
+        );
+
+        recorder.0.lock().unwrap().clear();
+        assert!(client.get("http://example.com/missing").unwrap().send().is_err());
+        assert!(recorder.0.lock().unwrap().iter().any(|metric| metric.starts_with("errors{method=GET,kind=")));
+    }
+}
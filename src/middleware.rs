@@ -0,0 +1,87 @@
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::fmt;
+
+///hooks run around every request sent by a Client, for concerns like authentication, signing or metrics.
+///
+///`before_request` runs on each hop of a redirect chain, after the url is resolved and before the request
+///is sent, in the order the middlewares were registered. `after_response` runs in the reverse order on the
+///response of that hop. Returning an error from either hook aborts the request with that error.
+/// # Example
+/// ```
+/// use smolhttp::{Client, HttpError, Middleware, RequestBuilder, Response};
+///
+/// // attach a bearer token to every request and fail on `401 Unauthorized`
+/// #[derive(Debug)]
+/// struct BearerAuth(String);
+///
+/// impl Middleware for BearerAuth {
+///     fn before_request(&self, request: &mut RequestBuilder) -> Result<(), HttpError> {
+///         *request = request.clone().header("Authorization", &format!("Bearer {}", self.0))?;
+///         Ok(())
+///     }
+///
+///     fn after_response(&self, _request: &RequestBuilder, response: &mut Response) -> Result<(), HttpError> {
+///         if response.status_code() == 401 {
+///             return Err(HttpError::Config("token rejected"));
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let client = Client::builder().middleware(BearerAuth("s3cr3t".to_owned())).build().unwrap();
+/// ```
+pub trait Middleware: fmt::Debug + Send + Sync {
+    ///inspect or change a request before it is sent
+    fn before_request(&self, request: &mut RequestBuilder) -> Result<(), HttpError> {
+        let _ = request;
+        Ok(())
+    }
+
+    ///inspect or change the response to `request`
+    fn after_response(&self, request: &RequestBuilder, response: &mut Response) -> Result<(), HttpError> {
+        let _ = (request, response);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use std::sync::{Arc, Mutex};
+
+    #[derive(Debug)]
+    struct Tag(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Tag {
+        fn before_request(&self, request: &mut RequestBuilder) -> Result<(), HttpError> {
+            self.1.lock().unwrap().push(format!("before {}", self.0));
+            *request = request.clone().header(&format!("X-{}", self.0), "1")?;
+            Ok(())
+        }
+
+        fn after_response(&self, request: &RequestBuilder, _response: &mut Response) -> Result<(), HttpError> {
+            let head = String::from_utf8(request.preview()).unwrap();
+            assert!(head.contains("X-a: 1\r\n") && head.contains("X-b: 1\r\n"));
+            self.1.lock().unwrap().push(format!("after {}", self.0));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn middleware_order() {
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::builder()
+            .middleware(Tag("a", calls.clone()))
+            .middleware(Tag("b", calls.clone()))
+            .build()
+            .unwrap();
+
+        let request = client.get("http://example.com/").unwrap().intercept().unwrap();
+        request.intercept_response(Response::new(b"HTTP/1.1 204 No Content\r\n\r\n".to_vec()).unwrap()).unwrap();
+        assert_eq!(*calls.lock().unwrap(), ["before a", "before b", "after b", "after a"]);
+    }
+}
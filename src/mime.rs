@@ -0,0 +1,79 @@
+//guessing of media types from file extensions, known with the `mime` feature only.
+
+//common extensions and their media type, sorted by extension
+#[cfg(feature = "mime")]
+const TYPES: &[(&str, &str)] = &[
+    ("7z", "application/x-7z-compressed"),
+    ("avif", "image/avif"),
+    ("bin", "application/octet-stream"),
+    ("bmp", "image/bmp"),
+    ("bz2", "application/x-bzip2"),
+    ("css", "text/css"),
+    ("csv", "text/csv"),
+    ("doc", "application/msword"),
+    ("docx", "application/vnd.openxmlformats-officedocument.wordprocessingml.document"),
+    ("gif", "image/gif"),
+    ("gz", "application/gzip"),
+    ("htm", "text/html"),
+    ("html", "text/html"),
+    ("ico", "image/vnd.microsoft.icon"),
+    ("jpeg", "image/jpeg"),
+    ("jpg", "image/jpeg"),
+    ("js", "text/javascript"),
+    ("json", "application/json"),
+    ("md", "text/markdown"),
+    ("mjs", "text/javascript"),
+    ("mp3", "audio/mpeg"),
+    ("mp4", "video/mp4"),
+    ("ndjson", "application/x-ndjson"),
+    ("oga", "audio/ogg"),
+    ("ogg", "audio/ogg"),
+    ("ogv", "video/ogg"),
+    ("otf", "font/otf"),
+    ("pdf", "application/pdf"),
+    ("png", "image/png"),
+    ("svg", "image/svg+xml"),
+    ("tar", "application/x-tar"),
+    ("tif", "image/tiff"),
+    ("tiff", "image/tiff"),
+    ("toml", "application/toml"),
+    ("ttf", "font/ttf"),
+    ("txt", "text/plain"),
+    ("wasm", "application/wasm"),
+    ("wav", "audio/wav"),
+    ("webm", "video/webm"),
+    ("webp", "image/webp"),
+    ("woff", "font/woff"),
+    ("woff2", "font/woff2"),
+    ("xls", "application/vnd.ms-excel"),
+    ("xlsx", "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet"),
+    ("xml", "application/xml"),
+    ("yaml", "application/yaml"),
+    ("yml", "application/yaml"),
+    ("zip", "application/zip"),
+];
+
+//return the media type of files with the extension `ext`, whatever its case
+#[cfg(feature = "mime")]
+pub(crate) fn from_extension(ext: &str) -> Option<&'static str> {
+    let ext = ext.to_ascii_lowercase();
+    TYPES.binary_search_by(|(known, _)| known.cmp(&ext.as_str())).ok().map(|i| TYPES[i].1)
+}
+
+#[cfg(not(feature = "mime"))]
+pub(crate) fn from_extension(_ext: &str) -> Option<&'static str> {
+    None
+}
+
+#[cfg(all(test, feature = "mime"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extensions() {
+        assert!(TYPES.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert_eq!(from_extension("PNG"), Some("image/png"));
+        assert_eq!(from_extension("json"), Some("application/json"));
+        assert_eq!(from_extension("unknown"), None);
+    }
+}
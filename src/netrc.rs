@@ -0,0 +1,95 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+//credentials of a `.netrc` file, by machine
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Netrc {
+    //`None` machine for the `default` entry
+    entries: Vec<(Option<String>, String, String)>,
+}
+
+impl Netrc {
+    //read the file named by `NETRC`, or `~/.netrc`, None if there is none
+    pub(crate) fn load() -> Option<Self> {
+        let path = match env::var_os("NETRC") {
+            Some(path) => PathBuf::from(path),
+            None => PathBuf::from(env::var_os("HOME").or_else(|| env::var_os("USERPROFILE"))?).join(".netrc"),
+        };
+        fs::read_to_string(path).ok().map(|text| Self::parse(&text))
+    }
+
+    //parse the `machine`, `default`, `login` and `password` tokens, skipping `account` and `macdef` ones
+    pub(crate) fn parse(text: &str) -> Self {
+        let mut entries = Vec::new();
+        let mut current: Option<(Option<String>, String, String)> = None;
+        let mut lines = text.lines();
+
+        while let Some(line) = lines.next() {
+            let mut tokens = line.split_whitespace();
+            while let Some(token) = tokens.next() {
+                match token {
+                    "machine" | "default" => {
+                        entries.extend(current.take());
+                        let machine = match token {
+                            "machine" => tokens.next().map(str::to_ascii_lowercase),
+                            _ => None,
+                        };
+                        current = Some((machine, String::new(), String::new()));
+                    }
+                    "login" => {
+                        if let (Some(entry), Some(login)) = (current.as_mut(), tokens.next()) {
+                            entry.1 = login.to_owned();
+                        }
+                    }
+                    "password" => {
+                        if let (Some(entry), Some(password)) = (current.as_mut(), tokens.next()) {
+                            entry.2 = password.to_owned();
+                        }
+                    }
+                    "account" => {
+                        tokens.next();
+                    }
+                    //a macro runs until the next empty line
+                    "macdef" => {
+                        for line in lines.by_ref() {
+                            if line.trim().is_empty() {
+                                break;
+                            }
+                        }
+                        break;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        entries.extend(current);
+        Self { entries }
+    }
+
+    //return the login and password for `host`, from its machine entry or else the default one
+    pub(crate) fn credentials(&self, host: &str) -> Option<(&str, &str)> {
+        let machine = self.entries.iter().find(|(machine, ..)| machine.as_deref().map(|m| m.eq_ignore_ascii_case(host)).unwrap_or(false));
+        let entry = machine.or_else(|| self.entries.iter().find(|(machine, ..)| machine.is_none()))?;
+        Some((&entry.1, &entry.2))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netrc_entries() {
+        let netrc = Netrc::parse(
+            "machine api.example.com\n  login alice\n  password s3cr3t\n\n\
+             macdef init\ncd /pub\nmachine fake login nope\n\n\
+             machine ftp.example.com login bob account x password hunter2\n\
+             default login anonymous password guest\n",
+        );
+        assert_eq!(netrc.credentials("API.example.com"), Some(("alice", "s3cr3t")));
+        assert_eq!(netrc.credentials("ftp.example.com"), Some(("bob", "hunter2")));
+        assert_eq!(netrc.credentials("fake"), Some(("anonymous", "guest")));
+        assert_eq!(Netrc::parse("machine a login b password c").credentials("d"), None);
+    }
+}
@@ -0,0 +1,110 @@
+use super::HttpError;
+
+use std::net::IpAddr;
+
+//hosts reached directly while the other ones go through the proxy, in the formats of `NO_PROXY` and curl's
+//`--noproxy`: `*` for every host, a domain matching itself and its subdomains, `*.domain` matching only its
+//subdomains, an ip address or a CIDR block
+#[derive(Debug, Clone, Default)]
+pub(crate) struct NoProxy {
+    rules: Vec<(String, Rule)>,
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Any,
+    Domain(String),
+    Subdomains(String),
+    Block(IpAddr, u8),
+}
+
+impl NoProxy {
+    //add a rule
+    pub(crate) fn push(&mut self, rule: &str) -> Result<(), HttpError> {
+        let raw = rule.trim();
+        let rule = raw.trim_end_matches('.').to_ascii_lowercase();
+        let rule = if rule == "*" {
+            Rule::Any
+        } else if let Some(domain) = rule.strip_prefix("*.") {
+            Rule::Subdomains(domain.to_owned())
+        } else if let Some((addr, prefix)) = rule.split_once('/') {
+            let addr: IpAddr = addr.parse().map_err(|_| HttpError::Config("invalid CIDR block in no proxy rule"))?;
+            let max = if addr.is_ipv4() { 32 } else { 128 };
+            match prefix.parse() {
+                Ok(prefix) if prefix <= max => Rule::Block(addr, prefix),
+                _ => return Err(HttpError::Config("invalid CIDR block in no proxy rule")),
+            }
+        } else if let Ok(addr) = rule.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Rule::Block(addr, if addr.is_ipv4() { 32 } else { 128 })
+        } else if !rule.trim_start_matches('.').is_empty() {
+            Rule::Domain(rule.trim_start_matches('.').to_owned())
+        } else {
+            return Err(HttpError::Config("empty no proxy rule"));
+        };
+        self.rules.push((raw.to_owned(), rule));
+        Ok(())
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+
+    //return the rules joined by commas, like `NO_PROXY`
+    pub(crate) fn list(&self) -> String {
+        self.rules.iter().map(|(raw, _)| raw.as_str()).collect::<Vec<_>>().join(",")
+    }
+
+    //return if `host` is reached without the proxy
+    pub(crate) fn matches(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']').trim_end_matches('.').to_ascii_lowercase();
+        let addr = host.parse::<IpAddr>().ok();
+        self.rules.iter().any(|(_, rule)| match (rule, addr) {
+            (Rule::Any, _) => true,
+            (Rule::Block(block, prefix), Some(addr)) => in_block(addr, *block, *prefix),
+            (Rule::Domain(domain), None) => host == *domain || is_subdomain(&host, domain),
+            (Rule::Subdomains(domain), None) => is_subdomain(&host, domain),
+            _ => false,
+        })
+    }
+}
+
+fn is_subdomain(host: &str, domain: &str) -> bool {
+    host.strip_suffix(domain).is_some_and(|label| label.ends_with('.'))
+}
+
+//return if `addr` is in the block of the addresses sharing the first `prefix` bits of `block`
+pub(crate) fn in_block(addr: IpAddr, block: IpAddr, prefix: u8) -> bool {
+    let (addr, block, bits) = match (addr, block) {
+        (IpAddr::V4(addr), IpAddr::V4(block)) => (u32::from(addr) as u128, u32::from(block) as u128, 32),
+        (IpAddr::V6(addr), IpAddr::V6(block)) => (u128::from(addr), u128::from(block), 128),
+        _ => return false,
+    };
+    let shift = bits - u32::from(prefix);
+    shift >= bits || addr >> shift == block >> shift
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rules() {
+        let mut no_proxy = NoProxy::default();
+        for rule in ["*.internal", "10.0.0.0/8", "localhost", ".example.com", "::1", "fd00::/8"] {
+            no_proxy.push(rule).unwrap();
+        }
+        for host in ["db.internal", "localhost", "example.com", "www.example.com", "10.1.2.3", "[::1]", "fd12::1"] {
+            assert!(no_proxy.matches(host), "{}", host);
+        }
+        for host in ["internal", "notexample.com", "11.0.0.1", "rust-lang.org", "::2"] {
+            assert!(!no_proxy.matches(host), "{}", host);
+        }
+        assert_eq!(no_proxy.list(), "*.internal,10.0.0.0/8,localhost,.example.com,::1,fd00::/8");
+
+        assert!(no_proxy.push("10.0.0.0/33").is_err());
+        assert!(no_proxy.push("").is_err());
+        no_proxy.push("*").unwrap();
+        assert!(no_proxy.matches("rust-lang.org"));
+        assert!(in_block("192.168.1.1".parse().unwrap(), "0.0.0.0".parse().unwrap(), 0));
+    }
+}
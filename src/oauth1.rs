@@ -0,0 +1,193 @@
+use super::base64;
+use super::Body;
+use super::disposition;
+use super::sha::{hex, hmac_sha1, hmac_sha256};
+use super::url;
+use super::HttpError;
+use super::Middleware;
+use super::RequestBuilder;
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+///signature method of [`OAuth1`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OAuth1Signature {
+    #[default]
+    HmacSha1,
+    HmacSha256,
+}
+
+///middleware signing requests with OAuth 1.0a(RFC 5849) in their `Authorization` header.
+///
+///The signature covers the method, the url, its query parameters and the parameters of
+///`application/x-www-form-urlencoded` bodies.
+/// # Example
+/// ```
+/// use smolhttp::{Client, OAuth1};
+///
+/// let oauth = OAuth1::new("consumer-key", "consumer-secret").token("token", "token-secret");
+/// let client = Client::builder().middleware(oauth).build().unwrap();
+/// let request = client.get("https://api.example.com/1.1/statuses/home_timeline.json").unwrap();
+/// ```
+#[derive(Clone)]
+pub struct OAuth1 {
+    consumer_key: String,
+    consumer_secret: String,
+    token: Option<(String, String)>,
+    signature: OAuth1Signature,
+}
+
+impl fmt::Debug for OAuth1 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OAuth1")
+            .field("consumer_key", &self.consumer_key)
+            .field("signature", &self.signature)
+            .finish_non_exhaustive()
+    }
+}
+
+impl OAuth1 {
+    ///return a signer with the credentials of a client
+    pub fn new(consumer_key: &str, consumer_secret: &str) -> Self {
+        Self {
+            consumer_key: consumer_key.to_owned(),
+            consumer_secret: consumer_secret.to_owned(),
+            token: None,
+            signature: OAuth1Signature::default(),
+        }
+    }
+
+    ///sign the requests with the credentials of a token as well
+    pub fn token(mut self, token: &str, secret: &str) -> Self {
+        self.token = Some((token.to_owned(), secret.to_owned()));
+        self
+    }
+
+    ///set the signature method(default HMAC-SHA1)
+    pub fn signature(mut self, signature: OAuth1Signature) -> Self {
+        self.signature = signature;
+        self
+    }
+
+    //return the `Authorization` header of a request to `base_url` with `params`, its query and form parameters
+    fn authorization(&self, method: &str, base_url: &str, params: &[(Vec<u8>, Vec<u8>)], nonce: &str, timestamp: u64) -> String {
+        let method_name = match self.signature {
+            OAuth1Signature::HmacSha1 => "HMAC-SHA1",
+            OAuth1Signature::HmacSha256 => "HMAC-SHA256",
+        };
+        let mut oauth = vec![
+            ("oauth_consumer_key", self.consumer_key.clone()),
+            ("oauth_nonce", nonce.to_owned()),
+            ("oauth_signature_method", method_name.to_owned()),
+            ("oauth_timestamp", timestamp.to_string()),
+        ];
+        if let Some((ref token, _)) = self.token {
+            oauth.push(("oauth_token", token.clone()));
+        }
+        oauth.push(("oauth_version", "1.0".to_owned()));
+
+        let encode = url::encode_unreserved;
+        let mut all: Vec<(String, String)> = params.iter().map(|(k, v)| (encode(k), encode(v))).collect();
+        all.extend(oauth.iter().map(|(k, v)| (encode(k.as_bytes()), encode(v.as_bytes()))));
+        all.sort();
+        let normalized = all.iter().map(|(k, v)| format!("{}={}", k, v)).collect::<Vec<_>>().join("&");
+
+        let base = format!("{}&{}&{}", method, encode(base_url.as_bytes()), encode(normalized.as_bytes()));
+        let token_secret = self.token.as_ref().map(|(_, secret)| secret.as_str()).unwrap_or_default();
+        let key = format!("{}&{}", encode(self.consumer_secret.as_bytes()), encode(token_secret.as_bytes()));
+        let signature = match self.signature {
+            OAuth1Signature::HmacSha1 => base64::encode(&hmac_sha1(key.as_bytes(), base.as_bytes())),
+            OAuth1Signature::HmacSha256 => base64::encode(&hmac_sha256(key.as_bytes(), base.as_bytes())),
+        };
+
+        oauth.push(("oauth_signature", signature));
+        oauth.sort();
+        let fields: Vec<String> = oauth.iter().map(|(k, v)| format!("{}=\"{}\"", k, encode(v.as_bytes()))).collect();
+        format!("OAuth {}", fields.join(", "))
+    }
+}
+
+impl Middleware for OAuth1 {
+    fn before_request(&self, request: &mut RequestBuilder) -> Result<(), HttpError> {
+        let mut params = parse_params(request.url.query.as_deref().unwrap_or_default());
+        let content_type = request.headers.get("Content-Type").or(request.client.inner.default_headers.get("Content-Type"));
+        if content_type.map(|t| t.starts_with("application/x-www-form-urlencoded")).unwrap_or(false) {
+            if let Some(body) = request.body.as_ref().and_then(Body::as_bytes) {
+                params.extend(parse_params(&String::from_utf8_lossy(body)));
+            }
+        }
+
+        let base_url = format!("{}://{}{}", request.scheme, request.authority(false), request.url.path);
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or_default();
+        let authorization = self.authorization(&request.method.to_string(), &base_url, &params, &nonce(), timestamp);
+        request.headers.insert("Authorization", authorization);
+        Ok(())
+    }
+}
+
+//decode the parameters of a query or a form body
+fn parse_params(data: &str) -> Vec<(Vec<u8>, Vec<u8>)> {
+    let decode = |value: &str| {
+        let value = value.replace('+', " ");
+        disposition::percent_decode(&value).unwrap_or_else(|| value.into_bytes())
+    };
+    data.split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (decode(name), decode(value))
+        })
+        .collect()
+}
+
+//return a value unique to this request, from the random keys of the standard hasher
+fn nonce() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let state = RandomState::new();
+    let time = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_nanos()).unwrap_or_default();
+    let a = state.hash_one((time, COUNTER.fetch_add(1, Ordering::Relaxed)));
+    let b = state.hash_one(a);
+    hex(&[a.to_be_bytes(), b.to_be_bytes()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    #[test]
+    fn oauth_signature() {
+        //example of the Twitter documentation signing a status update
+        let oauth = OAuth1::new("xvz1evFS4wEEPTGEFPHBog", "kAcSOqF21Fu85e7zjz7ZN2U4ZRhfV3WpwPAoE3Z7kBw")
+            .token("370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb", "LswwdoUaIvS8ltyTt5jkRh4J50vUPVVHtR2YPi5kE");
+        let mut params = parse_params("include_entities=true");
+        params.extend(parse_params("status=Hello%20Ladies%20%2b%20Gentlemen%2c%20a%20signed%20OAuth%20request%21"));
+        let header = oauth.authorization("POST", "https://api.twitter.com/1.1/statuses/update.json", &params,
+            "kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg", 1318622958);
+        assert_eq!(header, "OAuth oauth_consumer_key=\"xvz1evFS4wEEPTGEFPHBog\", \
+            oauth_nonce=\"kYjzVBB8Y0ZFabxSWbWovY3uYSQ2pTgmZeNu2VS4cg\", \
+            oauth_signature=\"hCtSmYh%2BiHYCEqBWrE7C7hYmtUk%3D\", oauth_signature_method=\"HMAC-SHA1\", \
+            oauth_timestamp=\"1318622958\", oauth_token=\"370773112-GmHxMAgYyLbNEtIKZeRNFsMKPR9EyMZeS9weJAEb\", \
+            oauth_version=\"1.0\"");
+        assert_ne!(nonce(), nonce());
+    }
+
+    #[test]
+    fn signed_form() {
+        let oauth = OAuth1::new("key", "secret").signature(OAuth1Signature::HmacSha256);
+        let client = Client::builder().middleware(oauth).build().unwrap();
+        let request = client
+            .post("https://api.example.com/update?include=all")
+            .unwrap()
+            .header("Content-Type", "application/x-www-form-urlencoded")
+            .unwrap()
+            .body(b"status=Hello+world%21".to_vec());
+        let head = String::from_utf8(request.intercept().unwrap().preview()).unwrap();
+        assert!(head.contains("\r\nAuthorization: OAuth oauth_consumer_key=\"key\", oauth_nonce=\""));
+        assert!(head.contains("oauth_signature_method=\"HMAC-SHA256\", oauth_timestamp=\""));
+    }
+}
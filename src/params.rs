@@ -0,0 +1,124 @@
+//splitting of structured header values into lists and parameters.
+
+//split a header value into `;` separated parameters, honoring quoted strings
+pub(crate) fn split_params(value: &str) -> Vec<(String, String)> {
+    let mut params = Vec::new();
+    let mut chars = value.chars().peekable();
+
+    loop {
+        let mut name = String::new();
+        while let Some(&c) = chars.peek() {
+            if c == '=' || c == ';' {
+                break;
+            }
+            name.push(c);
+            chars.next();
+        }
+
+        let mut value = String::new();
+        if chars.peek() == Some(&'=') {
+            chars.next();
+            while chars.peek() == Some(&' ') {
+                chars.next();
+            }
+            if chars.peek() == Some(&'"') {
+                chars.next();
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' => value.extend(chars.next()),
+                        c => value.push(c),
+                    }
+                }
+            }
+            while let Some(&c) = chars.peek() {
+                if c == ';' {
+                    break;
+                }
+                value.push(c);
+                chars.next();
+            }
+        }
+
+        let name = name.trim();
+        if !name.is_empty() {
+            params.push((name.to_ascii_lowercase(), value.trim().to_owned()));
+        }
+
+        if chars.next().is_none() {
+            return params;
+        }
+    }
+}
+
+//split a comma separated header value into its elements, honoring quoted strings
+pub(crate) fn split_list(value: &str) -> Vec<&str> {
+    let mut elements = Vec::new();
+    let (mut in_quotes, mut escaped) = (false, false);
+    let mut start = 0;
+
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_quotes => escaped = true,
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                elements.push(value[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    elements.push(value[start..].trim());
+    elements.retain(|e| !e.is_empty());
+    elements
+}
+
+//return if `value` is a non-empty RFC 7230 token
+pub(crate) fn is_token(value: &str) -> bool {
+    !value.is_empty() && value.bytes().all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+//return if `value` can be sent as a header value, rejecting CR, LF and other control characters
+pub(crate) fn is_field_value(value: &str) -> bool {
+    value.bytes().all(|b| b == b'\t' || (b >= 0x20 && b != 0x7f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params() {
+        let params = split_params("attachment; Name=\"a;b\"; flag; x = 1");
+        assert_eq!(params, [
+            ("attachment".to_owned(), String::new()),
+            ("name".to_owned(), "a;b".to_owned()),
+            ("flag".to_owned(), String::new()),
+            ("x".to_owned(), "1".to_owned()),
+        ]);
+    }
+
+    #[test]
+    fn token() {
+        assert!(is_token("PROPFIND"));
+        assert!(is_token("X-Custom_1.0"));
+        assert!(!is_token(""));
+        assert!(!is_token("GET /evil HTTP/1.1\r\n"));
+        assert!(!is_token("a:b"));
+    }
+
+    #[test]
+    fn field_value() {
+        assert!(is_field_value("text/html;\tcharset=utf-8"));
+        assert!(is_field_value("caf\u{e9}"));
+        assert!(!is_field_value("a\r\nX-Injected: 1"));
+        assert!(!is_field_value("a\0b"));
+    }
+
+    #[test]
+    fn list() {
+        assert_eq!(split_list("a, b=\"1, 2\", , c"), ["a", "b=\"1, 2\"", "c"]);
+        assert!(split_list("").is_empty());
+    }
+}
@@ -0,0 +1,227 @@
+use super::no_proxy::{in_block, NoProxy};
+use super::HttpError;
+use super::RequestBuilder;
+
+use std::net::{IpAddr, SocketAddr};
+
+//loopback, private, link-local, shared, unspecified, multicast and reserved blocks
+const PRIVATE_BLOCKS: [&str; 14] = [
+    "0.0.0.0/8",
+    "10.0.0.0/8",
+    "100.64.0.0/10",
+    "127.0.0.0/8",
+    "169.254.0.0/16",
+    "172.16.0.0/12",
+    "192.168.0.0/16",
+    "224.0.0.0/4",
+    "240.0.0.0/4",
+    "::/128",
+    "::1/128",
+    "fc00::/7",
+    "fe80::/10",
+    "ff00::/8",
+];
+
+///limits the urls a Client may request and the addresses it may connect to, to safely fetch urls supplied
+///by users(SSRF protection).
+///
+///The policy is checked before connecting, for every redirect too: a request to a scheme or a host that isn't
+///allowed, or to a denied ip address, fails with [`HttpError::Denied`]. Resolved addresses are checked before
+///connecting to them, so a host name can't lead to a denied address. Every scheme and host is allowed and no
+///address is denied by default.
+///
+///Behind a proxy, only the ip address literals of the urls can be checked since the proxy resolves the names,
+///and likewise with a custom [`Connector`](crate::Connector).
+/// # Example
+/// ```
+/// use smolhttp::{Client, RequestPolicy};
+///
+/// let policy = RequestPolicy::new().allow_scheme("https").deny_private();
+/// let client = Client::builder().request_policy(policy).build().unwrap();
+/// assert!(client.get("https://127.0.0.1/admin").unwrap().send().is_err());
+/// assert!(client.get("file:///etc/passwd").unwrap().send().is_err());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RequestPolicy {
+    schemes: Vec<String>,
+    hosts: NoProxy,
+    denied: Vec<(IpAddr, u8)>,
+}
+
+impl RequestPolicy {
+    ///return a policy allowing every request
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///allow the urls of `scheme`, once a scheme is allowed the other ones aren't
+    pub fn allow_scheme(mut self, scheme: &str) -> Self {
+        self.schemes.push(scheme.to_ascii_lowercase());
+        self
+    }
+
+    ///allow the hosts matching `pattern`, once a pattern is allowed the other hosts aren't
+    ///
+    ///A domain matches itself and its subdomains, `*.domain` only its subdomains, and an ip address or a CIDR
+    ///block the addresses in it.
+    /// # Example
+    /// ```
+    /// use smolhttp::RequestPolicy;
+    ///
+    /// let policy = RequestPolicy::new().allow_host("example.com").unwrap().allow_host("*.cdn.example.net").unwrap();
+    /// assert!(policy.is_allowed("https://www.example.com/a"));
+    /// assert!(!policy.is_allowed("https://cdn.example.net/a"));
+    /// ```
+    pub fn allow_host(mut self, pattern: &str) -> Result<Self, HttpError> {
+        if pattern.trim() == "*" {
+            return Err(HttpError::Config("invalid host pattern"));
+        }
+        self.hosts.push(pattern).map_err(|_| HttpError::Config("invalid host pattern"))?;
+        Ok(self)
+    }
+
+    ///deny the ip addresses of `block`, a CIDR block like `10.0.0.0/8` or a single address
+    pub fn deny_range(mut self, block: &str) -> Result<Self, HttpError> {
+        let (addr, prefix) = block.trim().split_once('/').map_or((block.trim(), None), |(addr, prefix)| (addr, Some(prefix)));
+        let addr: IpAddr = addr.trim_start_matches('[').trim_end_matches(']').parse().map_err(|_| HttpError::Config("invalid CIDR block"))?;
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        let prefix = match prefix.map(str::parse) {
+            None => max,
+            Some(Ok(prefix)) if prefix <= max => prefix,
+            Some(_) => return Err(HttpError::Config("invalid CIDR block")),
+        };
+        self.denied.push((addr, prefix));
+        Ok(self)
+    }
+
+    ///deny the loopback, private(RFC 1918 and unique local), link-local, shared, unspecified, multicast and
+    ///reserved ip addresses
+    pub fn deny_private(self) -> Self {
+        PRIVATE_BLOCKS.iter().fold(self, |policy, block| policy.deny_range(block).expect("valid CIDR block"))
+    }
+
+    ///return if the policy allows requests to `url`, without resolving its host
+    pub fn is_allowed(&self, url: &str) -> bool {
+        match crate::request::parse_url(url) {
+            Ok((url, host)) => self.check_url(&url.scheme, &host).is_ok(),
+            Err(_) => false,
+        }
+    }
+
+    //return why a request to `host` with `scheme` is denied, if it is
+    fn check_url(&self, scheme: &str, host: &str) -> Result<(), &'static str> {
+        if !self.schemes.is_empty() && !self.schemes.iter().any(|allowed| allowed.eq_ignore_ascii_case(scheme)) {
+            return Err("scheme not allowed");
+        }
+        //local urls have no host
+        if matches!(scheme, "file" | "data") {
+            return Ok(());
+        }
+        if !self.hosts.is_empty() && !self.hosts.matches(host) {
+            return Err("host not allowed");
+        }
+        match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+            Ok(addr) if self.is_denied(addr) => Err("address denied"),
+            _ => Ok(()),
+        }
+    }
+
+    fn is_denied(&self, addr: IpAddr) -> bool {
+        //an ipv4 address mapped to ipv6 is checked as the ipv4 address it is
+        let addr = addr.to_canonical();
+        self.denied.iter().any(|&(block, prefix)| in_block(addr, block, prefix))
+    }
+}
+
+impl RequestBuilder {
+    //fail if the policy of the Client denies the request
+    pub(crate) fn check_policy(&self) -> Result<(), HttpError> {
+        match self.client.inner.policy {
+            Some(ref policy) => policy.check_url(&self.scheme, &self.host).map_err(|reason| self.denied(reason)),
+            None => Ok(()),
+        }
+    }
+
+    //return the resolved addresses of the target the policy of the Client allows, failing if there are none
+    pub(crate) fn allowed_addrs(&self, addrs: Vec<SocketAddr>) -> Result<Vec<SocketAddr>, HttpError> {
+        let policy = match self.client.inner.policy {
+            Some(ref policy) => policy,
+            None => return Ok(addrs),
+        };
+        let addrs: Vec<_> = addrs.into_iter().filter(|addr| !policy.is_denied(addr.ip())).collect();
+        match addrs.is_empty() {
+            true => Err(self.denied("address denied")),
+            false => Ok(addrs),
+        }
+    }
+
+    fn denied(&self, reason: &'static str) -> HttpError {
+        HttpError::Denied { url: self.raw_url.clone(), reason }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn::{self, HeaderLimits};
+    use crate::{Client, ErrorKind, Resolver};
+    use std::io::{self, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn policy_rules() {
+        let policy = RequestPolicy::new().allow_scheme("https").allow_host("example.com").unwrap().allow_host("10.0.0.0/8").unwrap().deny_private();
+        assert!(policy.is_allowed("https://api.example.com/v1"));
+        assert!(!policy.is_allowed("http://example.com/"));
+        assert!(!policy.is_allowed("https://example.org/"));
+        assert!(!policy.is_allowed("https://10.1.2.3/"));
+        assert!(!policy.is_allowed("not a url"));
+
+        let policy = RequestPolicy::new().deny_private();
+        for url in ["http://127.0.0.1:8080/", "http://[::1]/", "http://[::ffff:192.168.1.1]/", "http://169.254.169.254/latest/meta-data", "http://[fe80::1]/"] {
+            assert!(!policy.is_allowed(url), "{}", url);
+        }
+        assert!(policy.is_allowed("http://93.184.216.34/"));
+        assert!(policy.is_allowed("file:///etc/hosts"));
+
+        assert!(RequestPolicy::new().deny_range("10.0.0.0/33").is_err());
+        assert!(RequestPolicy::new().deny_range("localhost").is_err());
+        assert!(RequestPolicy::new().allow_host("*").is_err());
+    }
+
+    #[test]
+    fn denies_resolved_and_redirected() {
+        #[derive(Debug)]
+        struct Loopback;
+
+        impl Resolver for Loopback {
+            fn resolve(&self, _host: &str, port: u16) -> io::Result<Vec<SocketAddr>> {
+                Ok(vec![SocketAddr::from(([127, 0, 0, 1], port))])
+            }
+        }
+
+        let client = Client::builder().dns_resolver(Loopback).request_policy(RequestPolicy::new().deny_private()).build().unwrap();
+        let err = client.get("http://internal.example/").unwrap().send().unwrap_err();
+        assert!(matches!(err, HttpError::Denied { reason: "address denied", .. }));
+        assert_eq!(err.kind(), ErrorKind::Config);
+        assert_eq!(err.url(), Some("http://internal.example/"));
+
+        //an allowed host redirecting to a denied one
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            conn::read_head(&mut stream, HeaderLimits::default()).unwrap();
+            stream.write_all(b"HTTP/1.1 302 Found\r\nLocation: http://localhost/admin\r\nContent-Length: 0\r\n\r\n").unwrap();
+        });
+        let policy = RequestPolicy::new().allow_host("127.0.0.1").unwrap();
+        let client = Client::builder().redirects(3).request_policy(policy).build().unwrap();
+        let err = client.get(&format!("http://127.0.0.1:{}/", port)).unwrap().send().unwrap_err();
+        assert!(matches!(err, HttpError::Denied { ref url, reason: "host not allowed" } if url == "http://localhost/admin"));
+        server.join().unwrap();
+
+        let client = Client::builder().request_policy(RequestPolicy::new().allow_scheme("https")).build().unwrap();
+        assert!(matches!(client.get("data:,hello").unwrap().send(), Err(HttpError::Denied { reason: "scheme not allowed", .. })));
+    }
+}
@@ -0,0 +1,226 @@
+use super::params;
+use super::request::validate_header;
+use super::url;
+use super::{Body, Client, HeaderMap, HttpError, RequestBuilder, Response};
+
+use std::time::Duration;
+
+///http request built apart from any [`Client`]: it can be stored, inspected, changed or signed, and then
+///sent any number of times with [`Client::execute`].
+///
+///Its url may be relative to the [`base_url`](crate::ClientBuilder::base_url) of the client executing it.
+/// # Example
+/// ```no_run
+/// use smolhttp::{Client, Request};
+///
+/// let request = Request::new("POST", "https://example.com/jobs").unwrap()
+///     .header("Content-Type", "application/json").unwrap()
+///     .with_body(r#"{"task":"resize"}"#);
+/// let client = Client::new();
+/// let first = client.execute(request.clone()).unwrap();
+/// let again = client.execute(request).unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Request {
+    method: String,
+    url: String,
+    headers: HeaderMap,
+    body: Option<Body>,
+    timeout: Option<Duration>,
+}
+
+impl Request {
+    ///return a request without headers nor body, `method` must be a valid http token
+    pub fn new(method: &str, url: &str) -> Result<Self, HttpError> {
+        if !params::is_token(method) {
+            return Err(HttpError::Config("Method is not a valid token"));
+        }
+        Ok(Self { method: method.to_owned(), url: url.to_owned(), headers: HeaderMap::new(), body: None, timeout: None })
+    }
+
+    ///return the method of the request
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    ///return the url of the request
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    ///set the url of the request
+    pub fn set_url(&mut self, url: &str) {
+        self.url = url.to_owned();
+    }
+
+    ///return the headers of the request
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    ///return the headers of the request to change them, their names and values being checked when executed
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    ///return the body of the request
+    pub fn body(&self) -> Option<&Body> {
+        self.body.as_ref()
+    }
+
+    ///return the read/write timeout of the request, the one of the Client being used when unset
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    ///return the request with the header `name` set to `value`, names must be valid http tokens and values
+    ///can't hold control characters
+    pub fn header(mut self, name: &str, value: &str) -> Result<Self, HttpError> {
+        validate_header(name, value)?;
+        self.headers.insert(name, value);
+        Ok(self)
+    }
+
+    ///return the request with its body set to `data`
+    pub fn with_body<B: Into<Body>>(mut self, data: B) -> Self {
+        self.body = Some(data.into());
+        self
+    }
+
+    ///return the request with its read/write timeout set to `timeout`
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    ///return a copy of the request sent to `url`, sharing the body of this one
+    /// # Example
+    /// ```
+    /// use smolhttp::Request;
+    ///
+    /// let poll = Request::new("GET", "https://api.example.com/devices/1").unwrap().header("Authorization", "Bearer s3cr3t").unwrap();
+    /// let other = poll.for_url("https://api.example.com/devices/2");
+    /// assert_eq!(other.headers().get("Authorization"), Some("Bearer s3cr3t"));
+    /// ```
+    pub fn for_url(&self, url: &str) -> Self {
+        Self { url: url.to_owned(), ..self.clone() }
+    }
+
+    ///return a copy of the request whose url is a template, its `{name}` placeholders being replaced by the
+    ///percent encoded values of `params`, sharing the body of this one
+    ///
+    ///A placeholder without a value fails with [`HttpError::Config`].
+    /// # Example
+    /// ```
+    /// use smolhttp::Request;
+    ///
+    /// let template = Request::new("GET", "https://api.example.com/devices/{id}/status").unwrap();
+    /// let request = template.expand(&[("id", "sensor 7")]).unwrap();
+    /// assert_eq!(request.url(), "https://api.example.com/devices/sensor%207/status");
+    /// ```
+    pub fn expand(&self, params: &[(&str, &str)]) -> Result<Self, HttpError> {
+        let mut url = String::with_capacity(self.url.len());
+        let mut rest = self.url.as_str();
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..].find('}').ok_or(HttpError::Config("unclosed placeholder in url template"))? + start;
+            let name = &rest[start + 1..end];
+            let (_, value) = params.iter().find(|(param, _)| *param == name).ok_or(HttpError::Config("missing value for url template placeholder"))?;
+            url.push_str(&rest[..start]);
+            url.push_str(&url::encode_unreserved(value.as_bytes()));
+            rest = &rest[end + 1..];
+        }
+        url.push_str(rest);
+        Ok(self.for_url(&url))
+    }
+}
+
+impl Client {
+    ///send `request` with the configuration of this client, like [`RequestBuilder::send`]
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::{Client, Request};
+    ///
+    /// let request = Request::new("GET", "https://www.rust-lang.org").unwrap();
+    /// let response = Client::new().execute(request).unwrap();
+    /// ```
+    pub fn execute(&self, request: Request) -> Result<Response, HttpError> {
+        self.prepare(request)?.send()
+    }
+
+    //return a RequestBuilder sending `request` with this client
+    pub(crate) fn prepare(&self, request: Request) -> Result<RequestBuilder, HttpError> {
+        for (name, value) in request.headers.iter() {
+            validate_header(name, value)?;
+        }
+        let mut builder = self.request(&request.method, &request.url)?;
+        builder.headers = request.headers;
+        builder.body = request.body;
+        builder.timeout = request.timeout;
+        Ok(builder)
+    }
+}
+
+impl RequestBuilder {
+    ///return the [`Request`] this builder would send, to store it and send it later with [`Client::execute`]
+    ///
+    ///Its url is resolved against the base url of the client, while the abort handle and the writer set by
+    ///[`tee`](RequestBuilder::tee) aren't kept.
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let request = Client::new().put("http://example.com/items/1").unwrap().body("{}").build();
+    /// assert_eq!((request.method(), request.url()), ("PUT", "http://example.com/items/1"));
+    /// assert_eq!(request.body().unwrap().len(), Some(2));
+    /// ```
+    pub fn build(self) -> Request {
+        Request { method: self.method.to_string(), url: self.raw_url, headers: self.headers, body: self.body, timeout: self.timeout }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::ClientBuilder;
+
+    #[test]
+    fn execute_twice() {
+        let mock = MockTransport::new();
+        mock.respond("PATCH", "http://example.com/items/1", 204, "");
+        let client = ClientBuilder::new().connector(mock.clone()).build().unwrap();
+
+        let mut request = Request::new("PATCH", "http://example.com/items/1").unwrap().header("X-Try", "1").unwrap().with_body("a=b");
+        assert_eq!(client.execute(request.clone()).unwrap().status_code(), 204);
+        request.headers_mut().insert("X-Try", "2");
+        assert_eq!(client.execute(request).unwrap().status_code(), 204);
+
+        let requests = mock.requests();
+        assert_eq!(requests.len(), 2);
+        for (sent, attempt) in requests.iter().zip(["1", "2"]) {
+            assert_eq!(sent.method(), "PATCH");
+            assert_eq!(sent.headers().get("X-Try"), Some(attempt));
+            assert_eq!(sent.body(), b"a=b");
+        }
+
+        assert!(Request::new("GET /", "http://example.com/").is_err());
+        let mut invalid = Request::new("GET", "http://example.com/").unwrap();
+        invalid.headers_mut().insert("X-Bad", "a\r\nb");
+        assert!(client.execute(invalid).is_err());
+    }
+
+    #[test]
+    fn templates() {
+        let template = Request::new("PUT", "http://example.com/{kind}/{id}?v=1").unwrap().header("X-Key", "k").unwrap().with_body(vec![7u8; 1024]);
+        let request = template.expand(&[("id", "a/b"), ("kind", "items")]).unwrap();
+        assert_eq!(request.url(), "http://example.com/items/a%2Fb?v=1");
+        assert_eq!((request.method(), request.headers().get("X-Key")), ("PUT", Some("k")));
+        //the copies share the body of the template
+        let bytes = |request: &Request| request.body().unwrap().as_bytes().unwrap().as_ptr();
+        assert_eq!(bytes(&request), bytes(&template));
+        assert_eq!(bytes(&template.for_url("http://example.com/other")), bytes(&template));
+
+        assert!(template.expand(&[("id", "1")]).is_err());
+        assert!(Request::new("GET", "http://example.com/{id").unwrap().expand(&[("id", "1")]).is_err());
+    }
+}
@@ -0,0 +1,64 @@
+use super::idna;
+
+use std::collections::HashSet;
+use std::sync::OnceLock;
+
+//the public suffix list(https://publicsuffix.org/list/) with its ICANN and private sections, one rule per line
+//with `//` comments, single labels being also covered by the implicit `*` rule
+const LIST: &str = include_str!("public_suffix_list.dat");
+
+struct Rules {
+    suffixes: HashSet<String>,
+    //parents of the `*.` rules
+    wildcards: HashSet<String>,
+    exceptions: HashSet<String>,
+}
+
+fn rules() -> &'static Rules {
+    static RULES_SET: OnceLock<Rules> = OnceLock::new();
+    RULES_SET.get_or_init(|| {
+        let mut rules = Rules { suffixes: HashSet::new(), wildcards: HashSet::new(), exceptions: HashSet::new() };
+        let lines = LIST.lines().filter_map(|line| line.split_whitespace().next()).filter(|rule| !rule.starts_with("//"));
+        for rule in lines {
+            //internationalized rules are matched against the punycode hosts
+            let rule = match idna::to_ascii(rule) {
+                Ok(rule) => rule,
+                Err(_) => continue,
+            };
+            if let Some(parent) = rule.strip_prefix("*.") {
+                rules.wildcards.insert(parent.to_owned());
+            } else if let Some(exception) = rule.strip_prefix('!') {
+                rules.exceptions.insert(exception.to_owned());
+            } else {
+                rules.suffixes.insert(rule);
+            }
+        }
+        rules
+    })
+}
+
+//return if `domain`, lowercase and without a trailing dot, is a public suffix under which anyone can register names
+pub(crate) fn is_public_suffix(domain: &str) -> bool {
+    let rules = rules();
+    match domain.split_once('.') {
+        None => true,
+        Some(_) if rules.suffixes.contains(domain) => true,
+        Some(_) if rules.exceptions.contains(domain) => false,
+        Some((_, parent)) => rules.wildcards.contains(parent),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn public_suffixes() {
+        for domain in ["com", "co.uk", "github.io", "foo.kawasaki.jp", "ec2.compute.amazonaws.com", "eu.org", "js.org", "r2.dev", "uk.com", "xn--55qx5d.cn"] {
+            assert!(is_public_suffix(domain), "{}", domain);
+        }
+        for domain in ["example.com", "bbc.co.uk", "evil.github.io", "city.kawasaki.jp", "www.ck", "amazonaws.com", "a.eu.org", "example.js.org"] {
+            assert!(!is_public_suffix(domain), "{}", domain);
+        }
+    }
+}
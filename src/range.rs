@@ -0,0 +1,457 @@
+use super::redirect;
+use super::url::Url;
+use super::Client;
+use super::HeaderMap;
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::fs::{self, OpenOptions};
+use std::io;
+use std::path::Path;
+use std::time::SystemTime;
+
+///what a server tells about a resource without sending it, returned by [`Client::remote_metadata`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteMetadata {
+    size: Option<u64>,
+    last_modified: Option<SystemTime>,
+    etag: Option<String>,
+    accepts_ranges: bool,
+}
+
+impl RemoteMetadata {
+    ///return the size of the resource in bytes, if the server told it
+    pub fn size(&self) -> Option<u64> {
+        self.size
+    }
+
+    ///return the parsed `Last-Modified` header
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        self.last_modified
+    }
+
+    ///return the `ETag` header, quotes and weak prefix included
+    pub fn etag(&self) -> Option<&str> {
+        self.etag.as_deref()
+    }
+
+    ///return if the server answers range requests, so a download can be resumed
+    pub fn accepts_ranges(&self) -> bool {
+        self.accepts_ranges
+    }
+
+    fn from_response(res: &Response, size: Option<u64>, accepts_ranges: bool) -> Self {
+        Self { size, last_modified: res.last_modified(), etag: res.header("ETag").map(str::to_owned), accepts_ranges }
+    }
+}
+
+impl RequestBuilder {
+    ///ask for the bytes `start..=end` of the resource only, or from `start` to its end when `end` is `None`
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let request = Client::new().get("http://example.com/big.iso").unwrap().range(1024, Some(2047));
+    /// assert!(String::from_utf8(request.preview()).unwrap().contains("\r\nRange: bytes=1024-2047\r\n"));
+    /// ```
+    pub fn range(mut self, start: u64, end: Option<u64>) -> Self {
+        let end = end.map(|end| end.to_string()).unwrap_or_default();
+        self.headers.insert("Range", format!("bytes={}-{}", start, end));
+        self
+    }
+
+    ///ask for several ranges of the resource in one request, each being `start..=end` or from `start` to the end
+    ///
+    ///A server honoring them answers with a `multipart/byteranges` body, read with [`Response::byte_ranges`].
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let request = Client::new().get("http://example.com/big.iso").unwrap().ranges(&[(0, Some(99)), (4096, None)]);
+    /// assert!(String::from_utf8(request.preview()).unwrap().contains("\r\nRange: bytes=0-99,4096-\r\n"));
+    /// ```
+    pub fn ranges(mut self, ranges: &[(u64, Option<u64>)]) -> Self {
+        let ranges: Vec<_> = ranges.iter().map(|(start, end)| format!("{}-{}", start, end.map(|end| end.to_string()).unwrap_or_default())).collect();
+        self.headers.insert("Range", format!("bytes={}", ranges.join(",")));
+        self
+    }
+}
+
+///part of a resource sent in a `206 Partial Content` response, yielded by [`ByteRanges`].
+#[derive(Debug, Clone)]
+pub struct ByteRange<'a> {
+    start: u64,
+    end: u64,
+    complete_length: Option<u64>,
+    headers: HeaderMap,
+    bytes: &'a [u8],
+}
+
+impl<'a> ByteRange<'a> {
+    ///return the offset of the first byte of the part in the resource
+    pub fn start(&self) -> u64 {
+        self.start
+    }
+
+    ///return the offset of the last byte of the part in the resource
+    pub fn end(&self) -> u64 {
+        self.end
+    }
+
+    ///return the size of the whole resource, when the server told it
+    pub fn complete_length(&self) -> Option<u64> {
+        self.complete_length
+    }
+
+    ///return the headers of the part, those of the response for a single range
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    ///return the bytes of the part
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+
+    //return the part of `bytes` described by the `Content-Range` header of `headers`
+    fn new(headers: HeaderMap, bytes: &'a [u8]) -> Result<Self, HttpError> {
+        let (range, complete_length) = headers.get("Content-Range").and_then(content_range).ok_or(HttpError::Parse("missing or invalid Content-Range in partial response"))?;
+        let (start, end) = range.ok_or(HttpError::Parse("missing or invalid Content-Range in partial response"))?;
+        if end - start + 1 != bytes.len() as u64 {
+            return Err(HttpError::Parse("partial content doesn't match its Content-Range"));
+        }
+        Ok(Self { start, end, complete_length, headers, bytes })
+    }
+}
+
+///iterator over the parts of a `206 Partial Content` response, returned by [`Response::byte_ranges`].
+///
+///It stops after the first malformed part.
+#[derive(Debug)]
+pub struct ByteRanges<'a> {
+    parts: Parts<'a>,
+}
+
+#[derive(Debug)]
+enum Parts<'a> {
+    Single(Option<ByteRange<'a>>),
+    Multipart { rest: &'a [u8], delimiter: Vec<u8> },
+    Done,
+}
+
+impl<'a> ByteRanges<'a> {
+    //parse the part following the delimiter at the start of `rest`, returning it with what follows it
+    fn next_part(rest: &'a [u8], delimiter: &[u8]) -> Result<Option<(ByteRange<'a>, &'a [u8])>, HttpError> {
+        let rest = rest.strip_prefix(delimiter).ok_or(HttpError::Parse("invalid multipart/byteranges body"))?;
+        if rest.starts_with(b"--") {
+            return Ok(None);
+        }
+        //the delimiter line may end with whitespace
+        let line = rest.windows(2).position(|w| w == b"\r\n").ok_or(HttpError::Parse("invalid multipart/byteranges body"))?;
+        let rest = &rest[line + 2..];
+        let end = match rest.starts_with(b"\r\n") {
+            true => 0,
+            false => rest.windows(4).position(|w| w == b"\r\n\r\n").ok_or(HttpError::Parse("invalid multipart/byteranges body"))? + 2,
+        };
+        let headers: HeaderMap = String::from_utf8_lossy(&rest[..end])
+            .split("\r\n")
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .collect();
+        let content = &rest[end + 2..];
+        let mut close = b"\r\n".to_vec();
+        close.extend_from_slice(delimiter);
+        let len = content.windows(close.len()).position(|w| w == close).ok_or(HttpError::Parse("unterminated multipart/byteranges body"))?;
+        Ok(Some((ByteRange::new(headers, &content[..len])?, &content[len + 2..])))
+    }
+}
+
+impl<'a> Iterator for ByteRanges<'a> {
+    type Item = Result<ByteRange<'a>, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.parts {
+            Parts::Single(ref mut part) => part.take().map(Ok),
+            Parts::Multipart { ref mut rest, ref delimiter } => match Self::next_part(rest, delimiter) {
+                Ok(Some((part, next))) => {
+                    *rest = next;
+                    Some(Ok(part))
+                }
+                Ok(None) => {
+                    self.parts = Parts::Done;
+                    None
+                }
+                Err(err) => {
+                    self.parts = Parts::Done;
+                    Some(Err(err))
+                }
+            },
+            Parts::Done => None,
+        }
+    }
+}
+
+impl Response {
+    ///return the parts of a `206 Partial Content` response: those of its `multipart/byteranges` body when it
+    ///answers a request for several [`ranges`](RequestBuilder::ranges), or else the single part described by
+    ///its `Content-Range` header
+    ///
+    ///Fails with [`HttpError::Parse`] for any other status, or a multipart body without a boundary. The body
+    ///must be in memory, not [spilled to disk](crate::ClientBuilder::spill_to_disk).
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(
+    ///     b"HTTP/1.1 206 Partial Content\r\nContent-Type: multipart/byteranges; boundary=SEP\r\n\r\n\
+    ///     --SEP\r\nContent-Range: bytes 0-4/11\r\n\r\nhello\r\n\
+    ///     --SEP\r\nContent-Range: bytes 6-10/11\r\n\r\nworld\r\n\
+    ///     --SEP--\r\n".to_vec(),
+    /// ).unwrap();
+    /// for part in res.byte_ranges().unwrap() {
+    ///     let part = part.unwrap();
+    ///     println!("{}-{}: {:?}", part.start(), part.end(), part.bytes());
+    /// }
+    /// ```
+    pub fn byte_ranges(&self) -> Result<ByteRanges<'_>, HttpError> {
+        if self.status_code() != 206 {
+            return Err(HttpError::Parse("response isn't a 206 Partial Content"));
+        }
+        let parts = match self.content_type() {
+            Some(media_type) if media_type.essence() == "multipart/byteranges" => {
+                let boundary = media_type.param("boundary").ok_or(HttpError::Parse("multipart/byteranges without a boundary"))?;
+                let delimiter = format!("--{}", boundary).into_bytes();
+                //skip the preamble
+                let body = self.bytes();
+                let start = match body.starts_with(&delimiter) {
+                    true => 0,
+                    false => {
+                        let mut first = b"\r\n".to_vec();
+                        first.extend_from_slice(&delimiter);
+                        body.windows(first.len()).position(|w| w == first).map(|at| at + 2).unwrap_or(body.len())
+                    }
+                };
+                Parts::Multipart { rest: &body[start..], delimiter }
+            }
+            _ => Parts::Single(Some(ByteRange::new(self.headers().clone(), self.bytes())?)),
+        };
+        Ok(ByteRanges { parts })
+    }
+}
+
+impl Client {
+    ///return the size, modification date, entity tag and range support of `url`, as told by the server
+    ///
+    ///A `HEAD` request is sent first. When the server rejects it with `405 Method Not Allowed` or
+    ///`501 Not Implemented`, the first byte is asked with a ranged `GET` instead, whose `Content-Range` header
+    ///holds the size. Redirects are followed like for any request, and error statuses fail with
+    ///[`HttpError::Status`].
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let client = Client::new();
+    /// let metadata = client.remote_metadata("https://example.com/big.iso").unwrap();
+    /// let local = std::fs::metadata("big.iso").map(|meta| meta.len()).unwrap_or(0);
+    /// if metadata.accepts_ranges() && metadata.size() > Some(local) {
+    ///     client.resume_download("https://example.com/big.iso", "big.iso").unwrap();
+    /// }
+    /// ```
+    pub fn remote_metadata(&self, url: &str) -> Result<RemoteMetadata, HttpError> {
+        let res = self.head(url)?.send()?;
+        if !matches!(res.status_code(), 405 | 501) {
+            let res = res.error_for_status()?;
+            let accepts_ranges = res.headers().get_all("Accept-Ranges").flat_map(|value| value.split(',')).any(|unit| unit.trim().eq_ignore_ascii_case("bytes"));
+            return Ok(RemoteMetadata::from_response(&res, res.content_length(), accepts_ranges));
+        }
+
+        let res = self.get(url)?.range(0, Some(0)).send()?.error_for_status()?;
+        Ok(match res.status_code() {
+            206 => RemoteMetadata::from_response(&res, res.header("Content-Range").and_then(content_range).and_then(|(_, length)| length), true),
+            //the range was ignored and the whole resource sent
+            _ => RemoteMetadata::from_response(&res, Some(res.decoded_size()), false),
+        })
+    }
+
+    ///download `url` to the file at `path`, continuing from the end of the file if it already exists, and
+    ///return the number of bytes written
+    ///
+    ///The rest of the file is asked with a `Range` header, and appended once the `206 Partial Content` response
+    ///is checked to start where the file ends. When the server answers with the whole file instead, the file
+    ///is overwritten. The body is written as it arrives, so a download interrupted again can be resumed later.
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let client = Client::new();
+    /// while let Err(err) = client.resume_download("https://example.com/big.iso", "big.iso") {
+    ///     eprintln!("download interrupted: {err}");
+    /// }
+    /// ```
+    pub fn resume_download<P: AsRef<Path>>(&self, url: &str, path: P) -> Result<u64, HttpError> {
+        let path = path.as_ref();
+        let offset = match fs::metadata(path) {
+            Ok(meta) => meta.len(),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => return Err(err.into()),
+        };
+        let mut request = self.get(url)?;
+        if offset > 0 {
+            request = request.range(offset, None);
+        }
+
+        let mut hops = 0;
+        let (res, mut body) = loop {
+            let (res, body) = request.intercept()?.stream()?;
+            let location = match res.header("Location") {
+                Some(location) if redirect::is_followed(res.status_code()) && hops < self.inner.max_redirects => location,
+                _ => break (res, body),
+            };
+            hops += 1;
+            let location = Url::join(&request.raw_url, location)?;
+            request.retarget(&location)?;
+        };
+
+        let mut file = match res.status_code() {
+            206 => match res.header("Content-Range").and_then(content_range) {
+                Some((Some((start, _)), _)) if start == offset => OpenOptions::new().append(true).open(path)?,
+                _ => return Err(HttpError::Parse("Content-Range doesn't start at the end of the file")),
+            },
+            //the file is already complete
+            416 if offset > 0 && res.header("Content-Range").and_then(content_range) == Some((None, Some(offset))) => return Ok(0),
+            _ => {
+                res.error_for_status_ref()?;
+                fs::File::create(path)?
+            }
+        };
+        Ok(io::copy(&mut body, &mut file)?)
+    }
+}
+
+//range of a `Content-Range` header, `None` for `*`, and the complete length, `None` when unknown
+type ContentRange = (Option<(u64, u64)>, Option<u64>);
+
+//parse a `Content-Range` header
+fn content_range(value: &str) -> Option<ContentRange> {
+    let (unit, rest) = value.trim().split_once(' ')?;
+    if !unit.eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+    let (range, length) = rest.trim().split_once('/')?;
+    let length = match length {
+        "*" => None,
+        length => Some(length.parse().ok()?),
+    };
+    let range = match range {
+        "*" => None,
+        range => {
+            let (start, end) = range.split_once('-')?;
+            let (start, end) = (start.parse().ok()?, end.parse().ok()?);
+            if start > end {
+                return None;
+            }
+            Some((start, end))
+        }
+    };
+    Some((range, length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+
+    #[test]
+    fn content_ranges() {
+        assert_eq!(content_range("bytes 0-99/200"), Some((Some((0, 99)), Some(200))));
+        assert_eq!(content_range("bytes 100-199/*"), Some((Some((100, 199)), None)));
+        assert_eq!(content_range("bytes */200"), Some((None, Some(200))));
+        assert_eq!(content_range("bytes 9-1/200"), None);
+        assert_eq!(content_range("items 0-1/2"), None);
+    }
+
+    #[test]
+    fn resumed_download() {
+        let path = std::env::temp_dir().join(format!("smolhttp-resume-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let mock = MockTransport::new();
+        let client = Client::with_connector(mock.clone());
+
+        mock.respond("GET", "http://example.com/file", 200, "hello");
+        assert_eq!(client.resume_download("http://example.com/file", &path).unwrap(), 5);
+        assert!(mock.requests()[0].headers().get("Range").is_none());
+
+        mock.reset();
+        mock.respond_raw("GET", "http://example.com/file", b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-10/11\r\nContent-Length: 6\r\n\r\n world".to_vec());
+        assert_eq!(client.resume_download("http://example.com/file", &path).unwrap(), 6);
+        assert_eq!(mock.requests()[0].headers().get("Range"), Some("bytes=5-"));
+        assert_eq!(fs::read(&path).unwrap(), b"hello world");
+
+        mock.reset();
+        mock.respond_raw("GET", "http://example.com/file", b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Range: bytes */11\r\nContent-Length: 0\r\n\r\n".to_vec());
+        assert_eq!(client.resume_download("http://example.com/file", &path).unwrap(), 0);
+
+        mock.reset();
+        mock.respond_raw("GET", "http://example.com/file", b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-10/11\r\nContent-Length: 11\r\n\r\nhello world".to_vec());
+        assert!(matches!(client.resume_download("http://example.com/file", &path), Err(HttpError::Parse(_))));
+        assert_eq!(fs::read(&path).unwrap(), b"hello world");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn multipart_byteranges() {
+        let mock = MockTransport::new();
+        mock.respond_raw(
+            "GET",
+            "http://example.com/file",
+            b"HTTP/1.1 206 Partial Content\r\nContent-Type: multipart/byteranges; boundary=\"3d6b6a416f9b5\"\r\nContent-Length: 175\r\n\r\n\
+            preamble\r\n--3d6b6a416f9b5\r\nContent-Type: text/plain\r\nContent-Range: bytes 0-4/11\r\n\r\nhello\r\n\
+            --3d6b6a416f9b5 \r\nContent-Range: bytes 6-10/11\r\n\r\nwo\r\nd\r\n--3d6b6a416f9b5--\r\nepilogue"
+                .to_vec(),
+        );
+        let client = Client::with_connector(mock.clone());
+        let res = client.get("http://example.com/file").unwrap().ranges(&[(0, Some(4)), (6, None)]).send().unwrap();
+        assert_eq!(mock.requests()[0].headers().get("Range"), Some("bytes=0-4,6-"));
+        let parts: Vec<_> = res.byte_ranges().unwrap().map(Result::unwrap).collect();
+        let summary: Vec<_> = parts.iter().map(|part| (part.start(), part.end(), part.complete_length(), part.bytes())).collect();
+        assert_eq!(summary, [(0, 4, Some(11), &b"hello"[..]), (6, 10, Some(11), &b"wo\r\nd"[..])]);
+        assert_eq!(parts[0].headers().get("Content-Type"), Some("text/plain"));
+
+        let single = Response::new(b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 5-9/*\r\nContent-Length: 5\r\n\r\n world".to_vec()).unwrap();
+        let parts: Vec<_> = single.byte_ranges().unwrap().map(Result::unwrap).collect();
+        assert_eq!((parts.len(), parts[0].start(), parts[0].complete_length(), parts[0].bytes()), (1, 5, None, &b" worl"[..]));
+
+        let bad = Response::new(b"HTTP/1.1 206 Partial Content\r\nContent-Type: multipart/byteranges; boundary=B\r\n\r\n--B\r\n\r\nabc\r\n--B--".to_vec()).unwrap();
+        let mut parts = bad.byte_ranges().unwrap();
+        assert!(parts.next().unwrap().is_err());
+        assert!(parts.next().is_none());
+        assert!(Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n".to_vec()).unwrap().byte_ranges().is_err());
+    }
+
+    #[test]
+    fn remote_metadata() {
+        let mock = MockTransport::new();
+        let client = Client::with_connector(mock.clone());
+        mock.respond_raw("HEAD", "http://example.com/file", b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\nAccept-Ranges: bytes\r\nETag: \"v1\"\r\nLast-Modified: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n".to_vec());
+        let metadata = client.remote_metadata("http://example.com/file").unwrap();
+        assert_eq!((metadata.size(), metadata.etag(), metadata.accepts_ranges()), (Some(11), Some("\"v1\""), true));
+        assert_eq!(metadata.last_modified(), Some(SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(784111777)));
+
+        mock.reset();
+        mock.respond("HEAD", "http://example.com/file", 405, "")
+            .respond_raw("GET", "http://example.com/file", b"HTTP/1.1 206 Partial Content\r\nContent-Range: bytes 0-0/11\r\nContent-Length: 1\r\n\r\nh".to_vec());
+        let metadata = client.remote_metadata("http://example.com/file").unwrap();
+        assert_eq!((metadata.size(), metadata.etag(), metadata.accepts_ranges()), (Some(11), None, true));
+        assert_eq!(mock.requests()[1].headers().get("Range"), Some("bytes=0-0"));
+
+        mock.reset();
+        mock.respond("HEAD", "http://example.com/file", 501, "").respond("GET", "http://example.com/file", 200, "hello world");
+        let metadata = client.remote_metadata("http://example.com/file").unwrap();
+        assert_eq!((metadata.size(), metadata.accepts_ranges()), (Some(11), false));
+
+        mock.reset();
+        mock.respond("HEAD", "http://example.com/file", 404, "");
+        assert!(matches!(client.remote_metadata("http://example.com/file"), Err(HttpError::Status { .. })));
+    }
+}
@@ -0,0 +1,125 @@
+//redirect handling, targets are resolved with `Url::join`.
+
+use super::Method;
+
+//return if the status asks the client to follow the Location header
+pub(crate) fn is_followed(status: u16) -> bool {
+    matches!(status, 301 | 302 | 303 | 307 | 308)
+}
+
+//headers carrying credentials, dropped when a redirect leads to another origin
+pub(crate) const SENSITIVE_HEADERS: [&str; 3] = ["Authorization", "Cookie", "Proxy-Authorization"];
+
+//headers describing a body, dropped along with it
+pub(crate) const BODY_HEADERS: [&str; 4] = ["Content-Type", "Content-Length", "Content-Encoding", "Transfer-Encoding"];
+
+//return if a request redirected with `status` is sent again as a GET without its body:
+//always for `303` but HEAD, and for a POST redirected with `301` or `302` unless `strict`
+pub(crate) fn rewrites_to_get(status: u16, method: &Method, strict: bool) -> bool {
+    match status {
+        303 => !matches!(method, Method::Head | Method::Get),
+        301 | 302 => !strict && matches!(method, Method::Post),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockTransport;
+    use crate::ClientBuilder;
+
+    #[test]
+    fn followed_statuses() {
+        assert!(is_followed(302));
+        assert!(is_followed(308));
+        assert!(!is_followed(304));
+        assert!(!is_followed(300));
+    }
+
+    #[test]
+    fn method_rewriting() {
+        assert!(rewrites_to_get(303, &Method::Put, true));
+        assert!(!rewrites_to_get(303, &Method::Head, false));
+        assert!(rewrites_to_get(302, &Method::Post, false));
+        assert!(!rewrites_to_get(302, &Method::Post, true));
+        assert!(!rewrites_to_get(301, &Method::Delete, false));
+        assert!(!rewrites_to_get(307, &Method::Post, false));
+
+        let mock = MockTransport::new();
+        mock.respond_raw("POST", "http://example.com/form", b"HTTP/1.1 302 Found\r\nLocation: /done\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond_raw("POST", "http://example.com/api", b"HTTP/1.1 307 Temporary Redirect\r\nLocation: /v2/api\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond("GET", "http://example.com/done", 200, "");
+        mock.respond("POST", "http://example.com/*", 200, "");
+        let client = ClientBuilder::new().connector(mock.clone()).redirects(5).build().unwrap();
+
+        let form = client.post("http://example.com/form").unwrap().header("Content-Type", "text/plain").unwrap().body("a=1");
+        form.send().unwrap();
+        client.post("http://example.com/api").unwrap().body("{}").send().unwrap();
+        let requests = mock.requests();
+        assert_eq!((requests[1].method(), requests[1].body()), ("GET", &b""[..]));
+        assert_eq!(requests[1].headers().get("Content-Type"), None);
+        assert_eq!((requests[3].method(), requests[3].url(), requests[3].body()), ("POST", "http://example.com/v2/api", &b"{}"[..]));
+    }
+
+    #[test]
+    fn redirect_history() {
+        let mock = MockTransport::new();
+        mock.respond_raw("GET", "http://example.com/old", b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond_raw("GET", "http://example.com/new", b"HTTP/1.1 302 Found\r\nLocation: https://example.org/\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond("GET", "https://example.org/", 200, "home");
+        let client = ClientBuilder::new().connector(mock.clone()).redirects(5).build().unwrap();
+
+        let res = client.get("http://example.com/old").unwrap().send().unwrap();
+        assert_eq!(res.final_url(), "https://example.org/");
+        let history: Vec<(&str, u16)> = res.history().iter().map(|(url, status)| (url.as_str(), status.as_u16())).collect();
+        assert_eq!(history, [("http://example.com/old", 301), ("http://example.com/new", 302)]);
+        assert!(client.get("https://example.org/").unwrap().send().unwrap().history().is_empty());
+    }
+
+    #[test]
+    fn elapsed_covers_redirects() {
+        #[derive(Debug)]
+        struct Slow;
+
+        impl crate::Middleware for Slow {
+            fn before_request(&self, _request: &mut crate::RequestBuilder) -> Result<(), crate::HttpError> {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                Ok(())
+            }
+        }
+
+        let mock = MockTransport::new();
+        mock.respond_raw("GET", "http://example.com/old", b"HTTP/1.1 302 Found\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond("GET", "http://example.com/new", 200, "new");
+        let client = ClientBuilder::new().connector(mock).redirects(5).middleware(Slow).build().unwrap();
+
+        let res = client.get("http://example.com/old").unwrap().send().unwrap();
+        assert!(res.elapsed() >= std::time::Duration::from_millis(40));
+        assert!(res.elapsed() >= res.timings().total());
+    }
+
+    #[test]
+    fn cross_origin_redirects() {
+        let mock = MockTransport::new();
+        mock.respond_raw("GET", "https://api.example.com/file", b"HTTP/1.1 302 Found\r\nLocation: https://cdn.example.net/file\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond_raw("GET", "https://api.example.com/old", b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n".to_vec());
+        mock.respond("GET", "*", 200, "");
+        let send = |client: &crate::Client, url: &str| {
+            let request = client.get(url).unwrap().header("Authorization", "Bearer t").unwrap();
+            request.header("Cookie", "id=1").unwrap().send().unwrap();
+        };
+
+        let client = ClientBuilder::new().connector(mock.clone()).redirects(5).build().unwrap();
+        send(&client, "https://api.example.com/file");
+        send(&client, "https://api.example.com/old");
+        let requests = mock.requests();
+        assert_eq!((requests[1].headers().get("Authorization"), requests[1].headers().get("Cookie")), (None, None));
+        assert_eq!(requests[3].headers().get("Authorization"), Some("Bearer t"));
+
+        let client = ClientBuilder::new().connector(mock.clone()).redirects(5).keep_on_redirect(&["authorization"]).build().unwrap();
+        send(&client, "https://api.example.com/file");
+        let requests = mock.requests();
+        assert_eq!((requests[5].headers().get("Authorization"), requests[5].headers().get("Cookie")), (Some("Bearer t"), None));
+    }
+}
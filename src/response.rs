@@ -0,0 +1,760 @@
+use super::AltSvc;
+use super::charset;
+use super::chunked;
+use super::conn::{self, Framing};
+use super::date;
+use super::CacheControl;
+use super::disposition;
+use super::spill::{SpillFile, SpillReader};
+#[cfg(feature = "decompress")]
+use super::Encoding;
+use super::link;
+use super::MediaType;
+use super::HeaderMap;
+use super::HttpError;
+use super::StatusCode;
+use super::Timings;
+use super::Url;
+use super::Version;
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+///http response object.
+#[derive(Debug, Clone)]
+pub struct Response {
+    url: String,
+    version: Version,
+    status: StatusCode,
+    reason: String,
+    headers: HeaderMap,
+    trailers: HeaderMap,
+    body: Vec<u8>,
+    timings: Timings,
+    history: Vec<(String, StatusCode)>,
+    spilled: Option<Arc<SpillFile>>,
+    elapsed: Duration,
+    alpn: Option<String>,
+    transfer_size: Option<u64>,
+    raw_head: Vec<u8>,
+    close_delimited: bool,
+}
+
+impl Response {
+    ///parse a raw http response, skipping the interim `1xx` responses preceding the final one
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi".to_vec()).unwrap();
+    /// assert_eq!(res.text(), "hi");
+    /// ```
+    pub fn new(mut res: Vec<u8>) -> Result<Self, HttpError> {
+        conn::take_interim(&mut res);
+        let end = res.windows(4).position(|w| w == b"\r\n\r\n").map(|end| end + 4).unwrap_or(res.len());
+        let (head, rest) = res.split_at(end);
+
+        let framing = conn::framing(head, false)?;
+        let (body, trailers) = match framing {
+            Framing::Chunked => chunked::decode(rest)?,
+            Framing::Length(length) => (rest[..rest.len().min(length as usize)].to_vec(), HeaderMap::new()),
+            Framing::Close => (rest.to_vec(), HeaderMap::new()),
+        };
+        Ok(Self::from_parts(head, body, trailers)?.with_framing(framing))
+    }
+
+    //build a response from its head and its already decoded body
+    pub(crate) fn from_parts(head: &[u8], body: Vec<u8>, trailers: HeaderMap) -> Result<Self, HttpError> {
+        let (version, headers) = parse_head(head)?;
+        let (status, reason) = parse_status(head)?;
+
+        Ok(Self {
+            url: String::new(),
+            version,
+            status,
+            reason,
+            headers,
+            trailers,
+            body,
+            timings: Timings::default(),
+            history: Vec::new(),
+            spilled: None,
+            elapsed: Duration::ZERO,
+            alpn: None,
+            transfer_size: None,
+            raw_head: head.to_vec(),
+            close_delimited: false,
+        })
+    }
+
+    //build a response from its status line, headers and body, received by a backend that doesn't give the raw head
+    #[cfg(any(feature = "http", all(feature = "wasm", target_arch = "wasm32")))]
+    pub(crate) fn from_fields(version: Version, status: StatusCode, reason: &str, headers: &HeaderMap, body: Vec<u8>) -> Result<Self, HttpError> {
+        let mut head = format!("{} {} {}\r\n", version, status.as_u16(), reason);
+        for (name, value) in headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        head.push_str("\r\n");
+        Self::from_parts(head.as_bytes(), body, HeaderMap::new())
+    }
+
+    //record the url the response was fetched from
+    pub(crate) fn with_url(mut self, url: &str) -> Self {
+        self.url = url.to_owned();
+        self
+    }
+
+    //record how the body was framed on the connection
+    pub(crate) fn with_framing(mut self, framing: Framing) -> Self {
+        self.close_delimited = framing == Framing::Close;
+        self
+    }
+
+    //record the timings of the exchange that produced the response
+    pub(crate) fn with_timings(mut self, timings: Timings) -> Self {
+        self.timings = timings;
+        self
+    }
+
+    //record the temporary file holding the body, if it was too large to be kept in memory
+    pub(crate) fn with_spilled(mut self, spilled: Option<Arc<SpillFile>>) -> Self {
+        self.spilled = spilled;
+        self
+    }
+
+    //record the time taken by `send`, from its call to the end of the body
+    pub(crate) fn with_elapsed(mut self, elapsed: Duration) -> Self {
+        self.elapsed = elapsed;
+        self
+    }
+
+    //record the protocol negotiated through ALPN on the connection the response came from
+    #[cfg(feature = "alpn")]
+    pub(crate) fn with_alpn(mut self, alpn: Option<String>) -> Self {
+        self.alpn = alpn;
+        self
+    }
+
+    //record the redirect responses followed before this response
+    pub(crate) fn with_history(mut self, history: Vec<(String, StatusCode)>) -> Self {
+        self.history = history;
+        self
+    }
+
+    //decode the body as described by the `Content-Encoding` header, leaving it as is when a coding is unknown
+    #[cfg(feature = "decompress")]
+    pub(crate) fn decode_content(&mut self, limit: Option<u64>) -> Result<(), HttpError> {
+        let header = self.headers().get_all("Content-Encoding").collect::<Vec<_>>().join(",");
+        let names: Vec<&str> = header.split(',').map(str::trim).filter(|name| !name.is_empty() && !name.eq_ignore_ascii_case("identity")).collect();
+        if names.is_empty() || self.bytes().is_empty() {
+            return Ok(());
+        }
+        let codings = match names.iter().map(|name| Encoding::from_name(name)).collect::<Option<Vec<_>>>() {
+            Some(codings) => codings,
+            None => return Ok(()),
+        };
+        //codings are listed in the order they were applied
+        let mut body = std::mem::take(&mut self.body);
+        self.transfer_size = Some(body.len() as u64);
+        for coding in codings.iter().rev() {
+            body = coding.decode(&body, limit)?;
+        }
+        self.body = body;
+        self.headers.remove("Content-Encoding");
+        self.headers.remove("Content-Length");
+        Ok(())
+    }
+
+    #[cfg(not(feature = "decompress"))]
+    pub(crate) fn decode_content(&mut self, _limit: Option<u64>) -> Result<(), HttpError> {
+        Ok(())
+    }
+
+    //serialize the response back to http/1.1 wire format, with a Content-Length framing
+    pub(crate) fn to_raw(&self) -> Vec<u8> {
+        let mut raw = format!("{} {} {}\r\n", self.version, self.status.as_u16(), self.reason);
+        for (k, v) in &self.headers {
+            if !k.eq_ignore_ascii_case("Content-Length") && !k.eq_ignore_ascii_case("Transfer-Encoding") {
+                raw.push_str(&format!("{}: {}\r\n", k, v));
+            }
+        }
+        raw.push_str(&format!("Content-Length: {}\r\n\r\n", self.body.len()));
+
+        let mut raw = raw.into_bytes();
+        raw.extend_from_slice(&self.body);
+        raw
+    }
+
+    ///return the url the response was fetched from(empty for a response parsed with `new`)
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    ///return the parsed url the response was fetched from, after following the redirects(`None` for a
+    ///response parsed with `new`)
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("http://github.com").unwrap();
+    /// let url = res.parsed_url().unwrap();
+    /// assert_eq!((url.scheme(), url.host()), ("https", "github.com"));
+    /// ```
+    pub fn parsed_url(&self) -> Option<Url> {
+        Url::parse(&self.url).ok()
+    }
+
+    ///return the url the content was fetched from, after following the redirects
+    pub fn final_url(&self) -> &str {
+        &self.url
+    }
+
+    ///return the url and status of each redirect response followed to get this response, first one first
+    /// # Example
+    /// ```no_run
+    /// let client = smolhttp::Client::builder().redirects(5).build().unwrap();
+    /// let res = client.get("http://github.com").unwrap().send().unwrap();
+    /// for (url, status) in res.history() {
+    ///     println!("{} redirected with {}", url, status);
+    /// }
+    /// println!("fetched from {}", res.final_url());
+    /// ```
+    pub fn history(&self) -> &[(String, StatusCode)] {
+        &self.history
+    }
+
+    ///return the temporary file holding the body when it was larger than the threshold set with
+    ///[`ClientBuilder::spill_to_disk`](crate::ClientBuilder::spill_to_disk), removed once the response is dropped
+    pub fn body_path(&self) -> Option<&Path> {
+        self.spilled.as_deref().map(SpillFile::path)
+    }
+
+    ///return a reader of the body, from its temporary file if it was spilled to disk
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    /// use std::io::Read;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi".to_vec()).unwrap();
+    /// let mut body = String::new();
+    /// res.into_reader().unwrap().read_to_string(&mut body).unwrap();
+    /// assert_eq!(body, "hi");
+    /// ```
+    pub fn into_reader(self) -> Result<Box<dyn Read + Send>, HttpError> {
+        match self.spilled {
+            Some(spilled) => Ok(Box::new(SpillReader { file: File::open(spilled.path())?, _spilled: spilled })),
+            None => Ok(Box::new(io::Cursor::new(self.body))),
+        }
+    }
+
+    ///save the url, status line and headers of the response to a file, to be reloaded with
+    ///[`load`](Response::load) alongside the file written by [`save_body`](Response::save_body)
+    ///
+    ///The `Content-Length` and `Transfer-Encoding` headers are left out since they described the body as it was
+    ///transferred, and the trailers aren't saved.
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://www.rust-lang.org").unwrap();
+    /// res.save_headers("rust-lang.headers").unwrap();
+    /// res.save_body("rust-lang.html").unwrap();
+    ///
+    /// let res = smolhttp::Response::load("rust-lang.headers", "rust-lang.html").unwrap();
+    /// println!("{} from {}", res.status(), res.url());
+    /// ```
+    pub fn save_headers<P: AsRef<Path>>(&self, path: P) -> Result<(), HttpError> {
+        if self.url.contains(['\r', '\n']) {
+            return Err(HttpError::Config("url can't be saved on a single line"));
+        }
+
+        let mut head = format!("{}\n{} {} {}\r\n", self.url, self.version, self.status.as_u16(), self.reason);
+        for (k, v) in &self.headers {
+            if !k.eq_ignore_ascii_case("Content-Length") && !k.eq_ignore_ascii_case("Transfer-Encoding") {
+                head.push_str(&format!("{}: {}\r\n", k, v));
+            }
+        }
+        head.push_str("\r\n");
+        Ok(fs::write(path, head)?)
+    }
+
+    ///save the body of the response to a file, copying its temporary file if it was spilled to disk
+    pub fn save_body<P: AsRef<Path>>(&self, path: P) -> Result<(), HttpError> {
+        match self.body_path() {
+            Some(spilled) => fs::copy(spilled, path).map(|_| ()),
+            None => fs::write(path, &self.body),
+        }
+        .map_err(HttpError::from)
+    }
+
+    ///load a response saved with [`save_headers`](Response::save_headers) and [`save_body`](Response::save_body)
+    pub fn load<P: AsRef<Path>, Q: AsRef<Path>>(headers: P, body: Q) -> Result<Self, HttpError> {
+        let saved = fs::read(headers)?;
+        let end = saved.iter().position(|&b| b == b'\n').ok_or(HttpError::Parse("missing url line"))?;
+        let url = std::str::from_utf8(&saved[..end]).map_err(|_| HttpError::Parse("url is not valid utf-8"))?;
+
+        let res = Self::from_parts(&saved[end + 1..], fs::read(body)?, HeaderMap::new())?;
+        Ok(res.with_url(url))
+    }
+
+    ///return the size of the body as it was transferred, before its `Content-Encoding` was decoded
+    ///
+    ///The `Content-Length` header of a decoded body is removed, since it described the encoded one: this is
+    ///the length it had. It is the [`decoded_size`](Response::decoded_size) when the body wasn't decoded.
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://www.rust-lang.org").unwrap();
+    /// println!("{} bytes transferred, {} decoded", res.transfer_size(), res.decoded_size());
+    /// ```
+    pub fn transfer_size(&self) -> u64 {
+        self.transfer_size.unwrap_or_else(|| self.decoded_size())
+    }
+
+    ///return if the body lasted until the server closed the connection, HTTP/1.0 style, without a
+    ///`Content-Length` nor a chunked coding
+    ///
+    ///Such a body is complete once the connection is closed, so a connection lost midway can't be told apart
+    ///from its end, while a body shorter than its `Content-Length` fails with [`HttpError::IncompleteBody`].
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.0 200 OK\r\nContent-Type: text/plain\r\n\r\nuntil the end".to_vec()).unwrap();
+    /// assert_eq!(res.text(), "until the end");
+    /// assert!(res.is_close_delimited());
+    /// ```
+    pub fn is_close_delimited(&self) -> bool {
+        self.close_delimited
+    }
+
+    ///return the size of the body once decoded, the one spilled to disk included
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi".to_vec()).unwrap();
+    /// assert_eq!((res.transfer_size(), res.decoded_size()), (2, 2));
+    /// ```
+    pub fn decoded_size(&self) -> u64 {
+        match self.spilled {
+            Some(ref spilled) => spilled.path().metadata().map(|metadata| metadata.len()).unwrap_or(0),
+            None => self.body.len() as u64,
+        }
+    }
+
+    ///return the durations of the phases of the exchange that produced the response
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://www.rust-lang.org").unwrap();
+    /// println!("{:?}", res.timings().total());
+    /// ```
+    pub fn timings(&self) -> Timings {
+        self.timings
+    }
+
+    ///return the protocol the server selected through ALPN during the TLS handshake, like `h2` or `http/1.1`
+    ///
+    ///It is only negotiated with the `alpn` feature, when the Client offers protocols with
+    ///[`alpn_protocols`](crate::ClientBuilder::alpn_protocols) or HTTP/2.
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://www.rust-lang.org").unwrap();
+    /// println!("{}", res.alpn_protocol().unwrap_or("none"));
+    /// ```
+    pub fn alpn_protocol(&self) -> Option<&str> {
+        self.alpn.as_deref()
+    }
+
+    ///return the wall-clock time `send` took, from its call until the whole body was received
+    ///
+    ///Unlike [`Timings::total`], which covers the last attempt only, it includes the retries, the followed
+    ///redirects and the middlewares. Responses not returned by `send` report zero.
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://www.rust-lang.org").unwrap();
+    /// assert!(res.elapsed() >= res.timings().total());
+    /// ```
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+
+    ///return the parsed `Content-Type` header
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{}".to_vec()).unwrap();
+    /// let content_type = res.content_type().unwrap();
+    /// assert!(content_type.is_json());
+    /// assert_eq!(content_type.charset(), Some("utf-8"));
+    /// ```
+    pub fn content_type(&self) -> Option<MediaType> {
+        self.header("Content-Type").and_then(MediaType::parse)
+    }
+
+    ///return an [`HttpError::Status`] error if the status is 4xx or 5xx
+    /// # Example
+    /// ```no_run
+    /// fn fetch(url: &str) -> Result<String, smolhttp::HttpError> {
+    ///     Ok(smolhttp::get(url)?.error_for_status()?.text())
+    /// }
+    /// ```
+    pub fn error_for_status(self) -> Result<Self, HttpError> {
+        self.error_for_status_ref()?;
+        Ok(self)
+    }
+
+    ///same as [`error_for_status`](Response::error_for_status), without consuming the response
+    pub fn error_for_status_ref(&self) -> Result<&Self, HttpError> {
+        if self.status.is_client_error() || self.status.is_server_error() {
+            return Err(HttpError::Status {
+                status: self.status,
+                url: self.url.clone(),
+            });
+        }
+        Ok(self)
+    }
+
+    ///return the response status
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://docs.rs").unwrap();
+    /// if res.status().is_success() {
+    ///     println!("{}", res.text());
+    /// }
+    /// ```
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    ///return the numeric response status code
+    pub fn status_code(&self) -> u16 {
+        self.status.as_u16()
+    }
+
+    ///return the response reason phrase
+    pub fn reason(&self) -> &str {
+        &self.reason
+    }
+
+    ///return the http version of the response status line
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    ///return the value of the `Content-Length` header, if present and valid
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi".to_vec()).unwrap();
+    /// assert_eq!(res.content_length(), Some(2));
+    /// ```
+    pub fn content_length(&self) -> Option<u64> {
+        self.header("Content-Length")?.trim().parse().ok()
+    }
+
+    ///return the status line and the header lines of the response exactly as received, ending with the empty
+    ///line, for captures needing the original bytes, spacing and order
+    ///
+    ///The head of an HTTP/2 response is rebuilt in the HTTP/1.1 format from its decoded fields.
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nx-Trace-ID:  abc\r\nContent-Length: 2\r\n\r\nhi".to_vec()).unwrap();
+    /// assert_eq!(res.raw_head(), b"HTTP/1.1 200 OK\r\nx-Trace-ID:  abc\r\nContent-Length: 2\r\n\r\n");
+    /// assert_eq!(res.headers().get("X-TRACE-ID"), Some("abc"));
+    /// ```
+    pub fn raw_head(&self) -> &[u8] {
+        &self.raw_head
+    }
+
+    ///return the response headers, their names keeping the casing sent by the server while being looked up
+    ///whatever their case
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://docs.rs").unwrap();
+    /// for cookie in res.headers().get_all("Set-Cookie") {
+    ///     println!("{cookie}");
+    /// }
+    /// ```
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    ///return the first value of the response header `name`
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    ///return the parsed `Cache-Control` header
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://docs.rs").unwrap();
+    /// if let Some(max_age) = res.cache_control().and_then(|cc| cc.max_age()) {
+    ///     println!("fresh for {}s", max_age.as_secs());
+    /// }
+    /// ```
+    pub fn cache_control(&self) -> Option<CacheControl> {
+        let values: Vec<&str> = self.headers.get_all("Cache-Control").collect();
+        if values.is_empty() {
+            None
+        } else {
+            Some(CacheControl::parse(&values.join(",")))
+        }
+    }
+
+    ///return the alternative services advertised by the `Alt-Svc` header
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nAlt-Svc: h3=\":443\"; ma=3600\r\nContent-Length: 0\r\n\r\n".to_vec()).unwrap();
+    /// assert_eq!(res.alt_svc()[0].protocol(), "h3");
+    /// ```
+    pub fn alt_svc(&self) -> Vec<AltSvc> {
+        let values: Vec<&str> = self.headers.get_all("Alt-Svc").collect();
+        AltSvc::parse(&values.join(","))
+    }
+
+    ///return the parsed `Expires` header
+    pub fn expires(&self) -> Option<SystemTime> {
+        date::parse_http_date(self.header("Expires")?)
+    }
+
+    ///return the parsed `Last-Modified` header
+    pub fn last_modified(&self) -> Option<SystemTime> {
+        date::parse_http_date(self.header("Last-Modified")?)
+    }
+
+    ///return the parsed `Date` header
+    pub fn date(&self) -> Option<SystemTime> {
+        date::parse_http_date(self.header("Date")?)
+    }
+
+    ///return the file name suggested by the `Content-Disposition` header
+    ///
+    ///`filename*` is preferred over `filename`, and any directory component is removed.
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Disposition: attachment; filename*=UTF-8''na%C3%AFve.txt\r\nContent-Length: 0\r\n\r\n".to_vec()).unwrap();
+    /// assert_eq!(res.filename().unwrap(), "na\u{ef}ve.txt");
+    /// ```
+    pub fn filename(&self) -> Option<String> {
+        disposition::filename(self.header("Content-Disposition")?)
+    }
+
+    ///return the links of the `Link` headers, keyed by relation type
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nLink: <https://api.example.com/items?page=2>; rel=\"next\"\r\nContent-Length: 0\r\n\r\n".to_vec()).unwrap();
+    /// assert_eq!(res.links()["next"], "https://api.example.com/items?page=2");
+    /// ```
+    pub fn links(&self) -> HashMap<String, String> {
+        let mut links = HashMap::new();
+        for value in self.headers.get_all("Link") {
+            for (rel, url) in link::parse(value) {
+                links.entry(rel).or_insert(url);
+            }
+        }
+        links
+    }
+
+    ///return the trailer fields sent after a chunked body(empty for other bodies)
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n2\r\nhi\r\n0\r\ngrpc-status: 0\r\n\r\n".to_vec()).unwrap();
+    /// assert_eq!(res.text(), "hi");
+    /// assert_eq!(res.trailers().get("grpc-status"), Some("0"));
+    /// ```
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    ///return the response body
+    pub fn body(&self) -> Vec<u8> {
+        self.body.clone()
+    }
+
+    ///return the response body without copying it
+    /// # Example
+    /// ```no_run
+    /// let res = smolhttp::get("https://www.rust-lang.org/static/images/rust-logo-blk.svg").unwrap();
+    /// std::fs::write("rust-logo.svg", res.bytes()).unwrap();
+    /// ```
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    ///return the response body, consuming the response
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.body
+    }
+
+    ///return the response body as text, decoded with the charset of the `Content-Type` header(default utf-8)
+    pub fn text(&self) -> String {
+        let charset = self.header("Content-Type").and_then(charset::from_content_type).unwrap_or("utf-8");
+        charset::decode(&self.body, charset)
+    }
+
+    ///deserialize the response body as JSON
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    /// use std::collections::HashMap;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 11\r\n\r\n{\"id\": 42}".to_vec()).unwrap();
+    /// let value: HashMap<String, u32> = res.json().unwrap();
+    /// assert_eq!(value["id"], 42);
+    /// ```
+    #[cfg(feature = "json")]
+    pub fn json<T: serde::de::DeserializeOwned>(&self) -> Result<T, HttpError> {
+        Ok(serde_json::from_slice(&self.body)?)
+    }
+
+    ///return the response body as utf-8 text, replacing invalid sequences with `U+FFFD`
+    ///
+    ///The body is only copied when it contains invalid utf-8.
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\nhi\xff".to_vec()).unwrap();
+    /// assert_eq!(res.text_lossy(), "hi\u{FFFD}");
+    /// ```
+    pub fn text_lossy(&self) -> Cow<'_, str> {
+        String::from_utf8_lossy(&self.body)
+    }
+
+    ///return the response body as text, decoded with `charset` whatever the server announced
+    /// # Example
+    /// ```
+    /// use smolhttp::Response;
+    ///
+    /// let res = Response::new(b"HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\ncaf\xe9".to_vec()).unwrap();
+    /// assert_eq!(res.text_with_charset("iso-8859-1"), "caf\u{e9}");
+    /// ```
+    pub fn text_with_charset(&self, charset: &str) -> String {
+        charset::decode(&self.body, charset)
+    }
+}
+
+//parse the status line version and the header lines of a raw response head, keeping repeated headers
+pub(crate) fn parse_head(res: &[u8]) -> Result<(Version, HeaderMap), HttpError> {
+    let end = res.windows(4).position(|w| w == b"\r\n\r\n").unwrap_or(res.len());
+    let head = String::from_utf8_lossy(&res[..end]);
+    let mut lines = head.split("\r\n");
+
+    let version = lines
+        .next()
+        .and_then(|line| line.split(' ').next())
+        .ok_or(HttpError::Parse("invalid status line"))?
+        .parse()?;
+
+    let headers = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+
+    Ok((version, headers))
+}
+
+//parse the status code and reason phrase of a raw response status line
+fn parse_status(res: &[u8]) -> Result<(StatusCode, String), HttpError> {
+    let end = res.windows(2).position(|w| w == b"\r\n").unwrap_or(res.len());
+    let line = String::from_utf8_lossy(&res[..end]);
+    let mut parts = line.splitn(3, ' ').skip(1);
+
+    let status = parts
+        .next()
+        .and_then(|code| code.parse().ok())
+        .ok_or(HttpError::Parse("invalid status line"))?;
+    let reason = parts.next().unwrap_or_default().to_owned();
+    Ok((StatusCode::from_u16(status)?, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_response_head() {
+        let (version, headers) = parse_head(b"HTTP/1.0 200 OK\r\nSet-Cookie: a=1\r\nContent-Type: text/plain\r\nset-cookie: b=2\r\n\r\nSet-Cookie: body").unwrap();
+        assert_eq!(version, Version::Http10);
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.get("content-type"), Some("text/plain"));
+        assert_eq!(headers.get_all("SET-COOKIE").collect::<Vec<_>>(), ["a=1", "b=2"]);
+    }
+
+    #[test]
+    fn skips_interim_responses() {
+        let res = Response::new(b"HTTP/1.1 103 Early Hints\r\nLink: </a.css>; rel=preload\r\n\r\nHTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok".to_vec()).unwrap();
+        assert_eq!(res.status(), 200);
+        assert!(res.header("Link").is_none());
+        assert_eq!(res.text(), "ok");
+        assert_eq!(res.raw_head(), b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n");
+    }
+
+    #[test]
+    fn save_and_load() {
+        let dir = std::env::temp_dir().join(format!("smolhttp-save-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let (headers, body) = (dir.join("res.headers"), dir.join("res.body"));
+
+        let res = Response::new(b"HTTP/1.1 404 Not Found\r\nContent-Type: text/plain\r\nTransfer-Encoding: chunked\r\nX-Id: 7\r\n\r\n4\r\ngone\r\n0\r\n\r\n".to_vec())
+            .unwrap()
+            .with_url("https://example.com/missing");
+        res.save_headers(&headers).unwrap();
+        res.save_body(&body).unwrap();
+
+        let loaded = Response::load(&headers, &body).unwrap();
+        assert_eq!(loaded.url(), "https://example.com/missing");
+        assert_eq!((loaded.status_code(), loaded.reason()), (404, "Not Found"));
+        assert_eq!(loaded.header("X-Id"), Some("7"));
+        assert!(loaded.header("Transfer-Encoding").is_none());
+        assert_eq!(loaded.text(), "gone");
+
+        assert!(res.with_url("https://example.com/\n").save_headers(&headers).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn error_for_status() {
+        let res = |status: u16| Response {
+            url: "https://docs.rs/missing".to_owned(),
+            version: Version::Http11,
+            status: StatusCode::from_u16(status).unwrap(),
+            reason: String::new(),
+            headers: HeaderMap::new(),
+            trailers: HeaderMap::new(),
+            body: Vec::new(),
+            timings: Timings::default(),
+            history: Vec::new(),
+            spilled: None,
+            elapsed: Duration::ZERO,
+            alpn: None,
+            transfer_size: None,
+            raw_head: Vec::new(),
+            close_delimited: false,
+        };
+
+        assert!(res(200).error_for_status().is_ok());
+        assert!(res(304).error_for_status().is_ok());
+        match res(404).error_for_status() {
+            Err(HttpError::Status { status, url }) => {
+                assert_eq!(status, 404);
+                assert_eq!(url, "https://docs.rs/missing");
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(res(503).error_for_status_ref().is_err());
+    }
+}
@@ -0,0 +1,113 @@
+use super::HttpError;
+use super::Method;
+use super::Response;
+
+use std::fmt;
+use std::time::Duration;
+
+///decides whether (and when) a failed request is sent again.
+///
+///`attempt` starts at 1 for the first try, `method` is the method of the request
+///and `outcome` is the result of that attempt. Returning `Some(delay)`
+///sleeps for `delay` and sends the request again, `None` hands `outcome` back to the caller.
+/// # Example
+/// ```
+/// use smolhttp::{ErrorKind, HttpError, Method, Response, RetryPolicy};
+/// use std::time::Duration;
+///
+/// // retry a POST carrying an idempotency key, but only on connection errors
+/// #[derive(Debug)]
+/// struct IdempotentPost;
+///
+/// impl RetryPolicy for IdempotentPost {
+///     fn retry(&self, attempt: u32, method: &Method, outcome: Result<&Response, &HttpError>) -> Option<Duration> {
+///         match outcome {
+///             Err(err) if *method == Method::Post && err.kind() == ErrorKind::Connect && attempt < 3 => Some(Duration::from_millis(200)),
+///             _ => None,
+///         }
+///     }
+/// }
+/// ```
+pub trait RetryPolicy: fmt::Debug + Send + Sync {
+    fn retry(&self, attempt: u32, method: &Method, outcome: Result<&Response, &HttpError>) -> Option<Duration>;
+}
+
+///retries idempotent requests on I/O errors and `429`/`5xx` responses, up to a fixed number of times.
+#[derive(Debug, Clone)]
+pub struct MaxRetries {
+    retries: u32,
+    delay: Duration,
+}
+
+impl MaxRetries {
+    ///return a policy allowing `retries` extra attempts, one second apart
+    /// # Example
+    /// ```
+    /// use smolhttp::{Client, MaxRetries};
+    ///
+    /// let client = Client::builder().retry(MaxRetries::new(3)).build().unwrap();
+    /// ```
+    pub fn new(retries: u32) -> Self {
+        Self {
+            retries,
+            delay: Duration::from_secs(1),
+        }
+    }
+
+    ///set the delay between two attempts
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+}
+
+impl RetryPolicy for MaxRetries {
+    fn retry(&self, attempt: u32, method: &Method, outcome: Result<&Response, &HttpError>) -> Option<Duration> {
+        if attempt > self.retries || !method.is_idempotent() {
+            return None;
+        }
+
+        let retryable = match outcome {
+            Ok(res) => res.status() == 429 || res.status().is_server_error(),
+            Err(err) => matches!(err.cause(), HttpError::IO(_)),
+        };
+
+        if retryable {
+            Some(self.delay)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io;
+
+    fn io_error() -> HttpError {
+        HttpError::IO(io::Error::new(io::ErrorKind::ConnectionReset, "reset"))
+    }
+
+    #[test]
+    fn max_retries_stops_after_limit() {
+        let policy = MaxRetries::new(2).delay(Duration::from_millis(5));
+        let err = io_error();
+        assert_eq!(policy.retry(1, &Method::Get, Err(&err)), Some(Duration::from_millis(5)));
+        assert_eq!(policy.retry(2, &Method::Get, Err(&err)), Some(Duration::from_millis(5)));
+        assert_eq!(policy.retry(3, &Method::Get, Err(&err)), None);
+    }
+
+    #[test]
+    fn max_retries_skips_non_idempotent() {
+        let policy = MaxRetries::new(2);
+        assert_eq!(policy.retry(1, &Method::Post, Err(&io_error())), None);
+        assert_eq!(policy.retry(1, &Method::Patch, Err(&io_error())), None);
+    }
+
+    #[test]
+    fn max_retries_ignores_config_errors() {
+        let policy = MaxRetries::new(2);
+        assert_eq!(policy.retry(1, &Method::Get, Err(&HttpError::Config("bad"))), None);
+    }
+}
@@ -0,0 +1,207 @@
+use super::conn::{self, Stream};
+use super::Client;
+use super::ErrorKind;
+use super::HttpError;
+use super::RequestBuilder;
+use super::Timings;
+
+use openssl::ex_data::Index;
+use openssl::ssl::{Ssl, SslConnector, SslMethod, SslSession, SslSessionCacheMode, SslStream, SslVerifyMode};
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::net::TcpStream;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+//TLS connector built on openssl, keeping the last session of each origin so the next connection to it resumes
+//the session with an abbreviated handshake
+pub(crate) struct SessionCache {
+    connector: SslConnector,
+    verify: bool,
+    sessions: Arc<Mutex<HashMap<String, SslSession>>>,
+    //origin of a connection, read when the server sends it a session
+    origin: Index<Ssl, String>,
+}
+
+impl fmt::Debug for SessionCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sessions = self.sessions.lock().map(|sessions| sessions.len()).unwrap_or_default();
+        f.debug_struct("SessionCache").field("sessions", &sessions).finish_non_exhaustive()
+    }
+}
+
+impl SessionCache {
+    fn new(client: &Client) -> Result<Self, HttpError> {
+        let mut builder = SslConnector::builder(SslMethod::tls()).map_err(tls_error)?;
+        let verify = client.inner.verify;
+        if !verify {
+            builder.set_verify(SslVerifyMode::NONE);
+        }
+        #[cfg(feature = "alpn")]
+        if !client.inner.alpn.is_empty() {
+            let mut protocols = Vec::new();
+            for protocol in &client.inner.alpn {
+                protocols.push(protocol.len() as u8);
+                protocols.extend_from_slice(protocol.as_bytes());
+            }
+            builder.set_alpn_protos(&protocols).map_err(tls_error)?;
+        }
+
+        let origin = Ssl::new_ex_index::<String>().map_err(tls_error)?;
+        let sessions = Arc::new(Mutex::new(HashMap::new()));
+        let received = sessions.clone();
+        builder.set_session_cache_mode(SslSessionCacheMode::CLIENT);
+        //TLS 1.3 servers send sessions after the handshake, while the response is read
+        builder.set_new_session_callback(move |ssl, session| {
+            if let Some(origin) = ssl.ex_data(origin) {
+                received.lock().unwrap_or_else(|err| err.into_inner()).insert(origin.clone(), session);
+            }
+        });
+        Ok(Self { connector: builder.build(), verify, sessions, origin })
+    }
+
+    //set up TLS over `tcp`, resuming the last session of `origin` if any
+    fn connect(&self, origin: &str, domain: &str, tcp: TcpStream) -> Result<SslStream<TcpStream>, HttpError> {
+        let mut config = self.connector.configure().map_err(tls_error)?;
+        config.set_verify_hostname(self.verify);
+        let mut ssl = config.into_ssl(domain).map_err(tls_error)?;
+        ssl.set_ex_data(self.origin, origin.to_owned());
+        if let Some(session) = self.sessions.lock().unwrap_or_else(|err| err.into_inner()).get(origin) {
+            //SAFETY: the session was negotiated by a connection of this connector
+            unsafe { ssl.set_session(session).map_err(tls_error)? };
+        }
+        ssl.connect(tcp).map_err(|err| {
+            //a session the server refused isn't offered again
+            self.sessions.lock().unwrap_or_else(|err| err.into_inner()).remove(origin);
+            tls_error(err)
+        })
+    }
+}
+
+//connection set up by a SessionCache, closed with a close_notify since openssl stops resuming the session of
+//a connection dropped without it
+pub(crate) struct SessionStream(SslStream<TcpStream>);
+
+impl Deref for SessionStream {
+    type Target = SslStream<TcpStream>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for SessionStream {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl Drop for SessionStream {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown();
+    }
+}
+
+impl Client {
+    //return the session cache shared by the clones of this Client, built on first use
+    fn session_cache(&self) -> Result<&SessionCache, HttpError> {
+        if let Some(cache) = self.inner.sessions.get() {
+            return Ok(cache);
+        }
+        let cache = SessionCache::new(self)?;
+        Ok(self.inner.sessions.get_or_init(|| cache))
+    }
+}
+
+impl RequestBuilder {
+    //set up TLS with the target over an open connection, resuming the last session with its origin
+    pub(crate) fn resume(&self, tcp: TcpStream, started: Instant, timings: &mut Timings) -> Result<Stream, HttpError> {
+        let cache = self.client.session_cache()?;
+        let watchdog = self.client.inner.tls_handshake_timeout.map(|timeout| conn::Watchdog::new(&tcp, timeout)).transpose()?;
+        let tls = cache.connect(&format!("{}:{}", self.host, self.port), self.tls_name(), tcp);
+        if watchdog.map(conn::Watchdog::finish).unwrap_or(false) {
+            let err = io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timed out");
+            return Err(HttpError::from(err).context(ErrorKind::Tls, &self.raw_url));
+        }
+        let tls = tls.map_err(self.fail(ErrorKind::Tls))?;
+        if tls.ssl().session_reused() {
+            self.client.verbose('*', || "TLS session resumed");
+        }
+        self.handshake_done(started, timings, tls.ssl().selected_alpn_protocol());
+        Ok(Stream::Openssl(Box::new(SessionStream(tls))))
+    }
+}
+
+//report an openssl error as an io error, to be given the TLS phase and the url of the request
+fn tls_error<E: std::error::Error + Send + Sync + 'static>(err: E) -> HttpError {
+    HttpError::from(io::Error::other(err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conn::HeaderLimits;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::rsa::Rsa;
+    use openssl::ssl::SslAcceptor;
+    use openssl::x509::{X509NameBuilder, X509};
+
+    use std::io::Write;
+    use std::net::TcpListener;
+    use std::thread;
+
+    //return an acceptor with a self-signed certificate for localhost
+    fn acceptor() -> SslAcceptor {
+        let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+        let mut name = X509NameBuilder::new().unwrap();
+        name.append_entry_by_text("CN", "localhost").unwrap();
+        let name = name.build();
+        let mut cert = X509::builder().unwrap();
+        cert.set_version(2).unwrap();
+        cert.set_subject_name(&name).unwrap();
+        cert.set_issuer_name(&name).unwrap();
+        cert.set_pubkey(&key).unwrap();
+        cert.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+        cert.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+        cert.sign(&key, MessageDigest::sha256()).unwrap();
+
+        let mut acceptor = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        acceptor.set_private_key(&key).unwrap();
+        acceptor.set_certificate(&cert.build()).unwrap();
+        acceptor.build()
+    }
+
+    #[test]
+    fn resumed_session() {
+        //a server answering each request over its own connection, and returning if its session was resumed
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let acceptor = acceptor();
+            let mut resumed = Vec::new();
+            for _ in 0..3 {
+                let (tcp, _) = listener.accept().unwrap();
+                let mut stream = acceptor.accept(tcp).unwrap();
+                resumed.push(stream.ssl().session_reused());
+                conn::read_head(&mut stream, HeaderLimits::default()).unwrap();
+                stream.write_all(b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok").unwrap();
+                let _ = stream.shutdown();
+            }
+            resumed
+        });
+
+        let client = Client::builder().timeout(5).verify(false).build().unwrap();
+        for _ in 0..2 {
+            assert_eq!(client.get(&format!("https://localhost:{}/", port)).unwrap().send().unwrap().text(), "ok");
+        }
+        //the sessions are kept per origin, another host name of the server starts a new one
+        assert_eq!(client.get(&format!("https://127.0.0.1:{}/", port)).unwrap().send().unwrap().text(), "ok");
+        assert_eq!(server.join().unwrap(), [false, true, false]);
+    }
+}
@@ -0,0 +1,140 @@
+use super::request::validate_header;
+use super::HttpError;
+use super::Method;
+use super::RequestBuilder;
+
+use std::fmt;
+use std::sync::Arc;
+
+type SignFn = dyn Fn(&SigningRequest) -> Result<Vec<(String, String)>, HttpError> + Send + Sync;
+
+//callback returning the headers signing a request
+#[derive(Clone)]
+pub(crate) struct Signer(Arc<SignFn>);
+
+impl fmt::Debug for Signer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Signer")
+    }
+}
+
+///the parts of a request as they will be sent, given to the callback of [`RequestBuilder::signer`]
+#[derive(Debug)]
+pub struct SigningRequest<'a> {
+    method: &'a Method,
+    target: String,
+    headers: Vec<(String, String)>,
+    body: Option<Vec<u8>>,
+}
+
+impl SigningRequest<'_> {
+    ///return the method of the request
+    pub fn method(&self) -> &Method {
+        self.method
+    }
+
+    ///return the request target, the path and the query exactly as sent on the request line
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    ///return the headers in the order they are sent, including the generated ones like `Host` or `Content-Length`
+    pub fn headers(&self) -> &[(String, String)] {
+        &self.headers
+    }
+
+    ///return the value of the first header named `name`, ignoring case
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(k, _)| k.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+
+    ///return the body, empty without one, or `None` when it is streamed from a reader that can only be read once
+    pub fn body(&self) -> Option<&[u8]> {
+        self.body.as_deref()
+    }
+}
+
+impl RequestBuilder {
+    ///sign the request with `signer`, called with its final method, target, headers and body just before it
+    ///is sent, after the middlewares ran, the headers it returns being appended to the request
+    ///
+    ///The signer runs again for each redirect followed and each retry, so time-based signatures stay fresh.
+    /// # Example
+    /// ```
+    /// use smolhttp::Client;
+    ///
+    /// let request = Client::new()
+    ///     .post("https://api.example.com/orders?symbol=BTC").unwrap()
+    ///     .body(b"{}".to_vec())
+    ///     .signer(|request| {
+    ///         let payload = format!("{} {} {}", request.method(), request.target(), request.body().unwrap_or_default().len());
+    ///         Ok(vec![("X-Signature".to_owned(), payload)])
+    ///     });
+    /// ```
+    pub fn signer<F>(mut self, signer: F) -> Self
+    where
+        F: Fn(&SigningRequest) -> Result<Vec<(String, String)>, HttpError> + Send + Sync + 'static,
+    {
+        self.signer = Some(Signer(Arc::new(signer)));
+        self
+    }
+
+    //append the headers returned by the signer of this request
+    pub(crate) fn sign(&mut self) -> Result<(), HttpError> {
+        let Some(signer) = self.signer.clone() else {
+            return Ok(());
+        };
+        let mut head = String::new();
+        self.write_header(&mut head);
+        let target = match self.method {
+            Method::Connect => self.authority(true),
+            _ => self.target(),
+        };
+        let headers = head
+            .split("\r\n")
+            .skip(1)
+            .filter_map(|line| line.split_once(": "))
+            .map(|(name, value)| (name.to_owned(), value.to_owned()))
+            .collect();
+        let body = match self.body {
+            Some(ref body) if body.as_bytes().is_none() && body.path().is_none() => None,
+            Some(ref body) => Some(body.read_all()?.into_owned()),
+            None => Some(Vec::new()),
+        };
+
+        let request = SigningRequest { method: &self.method, target, headers, body };
+        for (name, value) in (signer.0)(&request)? {
+            validate_header(&name, &value)?;
+            self.headers.append(name, value);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::sha::{hex, hmac_sha256};
+    use crate::{Client, HttpError};
+
+    #[test]
+    fn signed_headers() {
+        let client = Client::builder().default_header("X-Api-Key", "key").unwrap().build().unwrap();
+        let request = client.put("http://api.example.com/orders/1?a=b").unwrap().body(b"{\"qty\":1}".to_vec()).signer(|request| {
+            assert_eq!(request.header("host"), Some("api.example.com"));
+            assert_eq!(request.header("Content-Length"), Some("9"));
+            assert_eq!(request.header("X-Api-Key"), Some("key"));
+            let payload = format!("{}\n{}\n{}", request.method(), request.target(), String::from_utf8_lossy(request.body().unwrap()));
+            assert_eq!(payload, "PUT\n/orders/1?a=b\n{\"qty\":1}");
+            Ok(vec![("X-Signature".to_owned(), hex(&hmac_sha256(b"secret", payload.as_bytes())))])
+        });
+        let head = String::from_utf8(request.intercept().unwrap().preview()).unwrap();
+        assert!(head.contains("\r\nX-Signature: "));
+        //the request itself isn't changed, so it is signed again when resent
+        assert!(!String::from_utf8(request.preview()).unwrap().contains("X-Signature"));
+
+        let invalid = client.get("http://api.example.com/").unwrap().signer(|_| Ok(vec![("X-Bad".to_owned(), "a\r\nb".to_owned())]));
+        assert!(matches!(invalid.intercept(), Err(HttpError::Config(_))));
+        let failing = client.get("http://api.example.com/").unwrap().signer(|_| Err(HttpError::Config("no key")));
+        assert!(failing.intercept().is_err());
+    }
+}
@@ -0,0 +1,167 @@
+use super::date;
+use super::disposition;
+use super::sha::{hex, hmac_sha256, sha256};
+use super::url;
+use super::HttpError;
+use super::Middleware;
+use super::RequestBuilder;
+
+use std::fmt;
+use std::time::SystemTime;
+
+///middleware signing requests with AWS Signature Version 4, for S3 and other AWS-compatible endpoints.
+///
+///The `host`, `x-amz-*`, `content-type` and `content-md5` headers are signed, along with the method,
+///the path, the query and the SHA-256 of the body, sent in `x-amz-content-sha256` as S3 requires.
+///The path is signed as sent, without the second encoding the services other than S3 expect for
+///paths with reserved characters.
+/// # Example
+/// ```
+/// use smolhttp::{AwsSigV4, Client};
+///
+/// let signer = AwsSigV4::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "us-east-1", "s3");
+/// let client = Client::builder().middleware(signer).build().unwrap();
+/// let request = client.get("https://examplebucket.s3.amazonaws.com/photos/cat.jpg").unwrap();
+/// ```
+#[derive(Clone)]
+pub struct AwsSigV4 {
+    access_key: String,
+    secret_key: String,
+    session_token: Option<String>,
+    region: String,
+    service: String,
+}
+
+impl fmt::Debug for AwsSigV4 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AwsSigV4")
+            .field("access_key", &self.access_key)
+            .field("region", &self.region)
+            .field("service", &self.service)
+            .finish_non_exhaustive()
+    }
+}
+
+impl AwsSigV4 {
+    ///return a signer with the credentials of an access key, for `service` in `region`
+    pub fn new(access_key: &str, secret_key: &str, region: &str, service: &str) -> Self {
+        Self {
+            access_key: access_key.to_owned(),
+            secret_key: secret_key.to_owned(),
+            session_token: None,
+            region: region.to_owned(),
+            service: service.to_owned(),
+        }
+    }
+
+    ///send `token` in `x-amz-security-token`, for temporary credentials
+    pub fn session_token(mut self, token: &str) -> Self {
+        self.session_token = Some(token.to_owned());
+        self
+    }
+
+    //return the `Authorization` header of a request whose canonical parts are given, at `amz_date`
+    fn authorization(&self, method: &str, path: &str, query: &str, headers: &[(String, String)], payload: &str, amz_date: &str) -> String {
+        let mut headers: Vec<(String, &str)> = headers.iter().map(|(k, v)| (k.to_ascii_lowercase(), v.trim())).collect();
+        headers.sort();
+        let signed = headers.iter().map(|(k, _)| k.as_str()).collect::<Vec<_>>().join(";");
+        let canonical_headers: String = headers.iter().map(|(k, v)| format!("{}:{}\n", k, v)).collect();
+        let canonical = format!("{}\n{}\n{}\n{}\n{}\n{}", method, path, query, canonical_headers, signed, payload);
+
+        let scope = format!("{}/{}/{}/aws4_request", &amz_date[..8], self.region, self.service);
+        let to_sign = format!("AWS4-HMAC-SHA256\n{}\n{}\n{}", amz_date, scope, hex(&sha256(canonical.as_bytes())));
+        let key = [&amz_date[..8], &self.region, &self.service, "aws4_request"]
+            .iter()
+            .fold(format!("AWS4{}", self.secret_key).into_bytes(), |key, part| hmac_sha256(&key, part.as_bytes()).to_vec());
+        let signature = hex(&hmac_sha256(&key, to_sign.as_bytes()));
+
+        format!("AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}", self.access_key, scope, signed, signature)
+    }
+}
+
+impl Middleware for AwsSigV4 {
+    fn before_request(&self, request: &mut RequestBuilder) -> Result<(), HttpError> {
+        let iso = date::fmt_iso_date(SystemTime::now());
+        let amz_date = format!("{}Z", iso[..19].replace(['-', ':'], ""));
+        let payload = match request.body {
+            //a reader can only be read once, by the attempt sending it
+            Some(ref body) if body.as_bytes().is_none() && body.path().is_none() => "UNSIGNED-PAYLOAD".to_owned(),
+            Some(ref body) => hex(&sha256(&body.read_all()?)),
+            None => hex(&sha256(b"")),
+        };
+
+        request.headers.insert("x-amz-date", amz_date.as_str());
+        request.headers.insert("x-amz-content-sha256", payload.as_str());
+        if let Some(ref token) = self.session_token {
+            request.headers.insert("x-amz-security-token", token.as_str());
+        }
+
+        let defaults = &request.client.inner.default_headers;
+        let mut headers = vec![("host".to_owned(), request.headers.get("Host").or(defaults.get("Host")).map(str::to_owned).unwrap_or_else(|| request.authority(false)))];
+        for (name, value) in defaults.iter().filter(|(name, _)| !request.headers.contains_key(name)).chain(request.headers.iter()) {
+            let name = name.to_ascii_lowercase();
+            if name.starts_with("x-amz-") || name == "content-type" || name == "content-md5" {
+                headers.push((name, value.to_owned()));
+            }
+        }
+
+        let authorization = self.authorization(&request.method.to_string(), &request.url.path, &canonical_query(request.url.query.as_deref()), &headers, &payload, &amz_date);
+        request.headers.insert("Authorization", authorization);
+        Ok(())
+    }
+}
+
+//return the query with its parameters sorted and encoded as AWS expects
+fn canonical_query(query: Option<&str>) -> String {
+    let decode = |value: &str| disposition::percent_decode(value).unwrap_or_else(|| value.as_bytes().to_vec());
+    let mut params: Vec<(String, String)> = query
+        .unwrap_or_default()
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+            (url::encode_unreserved(&decode(name)), url::encode_unreserved(&decode(value)))
+        })
+        .collect();
+    params.sort();
+    params.iter().map(|(name, value)| format!("{}={}", name, value)).collect::<Vec<_>>().join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    #[test]
+    fn aws_signature() {
+        //example of the AWS documentation signing an IAM request
+        let signer = AwsSigV4::new("AKIDEXAMPLE", "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY", "us-east-1", "iam");
+        let headers = [
+            ("Content-Type".to_owned(), "application/x-www-form-urlencoded; charset=utf-8".to_owned()),
+            ("Host".to_owned(), "iam.amazonaws.com".to_owned()),
+            ("X-Amz-Date".to_owned(), "20150830T123600Z".to_owned()),
+        ];
+        let query = canonical_query(Some("Version=2010-05-08&Action=ListUsers"));
+        assert_eq!(query, "Action=ListUsers&Version=2010-05-08");
+        let payload = hex(&sha256(b""));
+        assert_eq!(
+            signer.authorization("GET", "/", &query, &headers, &payload, "20150830T123600Z"),
+            "AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/20150830/us-east-1/iam/aws4_request, \
+             SignedHeaders=content-type;host;x-amz-date, \
+             Signature=5d672d79c15b13162d9279b0855cfba6789a8edb4c82c400e06b5924a6f2b5d7"
+        );
+    }
+
+    #[test]
+    fn signed_request() {
+        let signer = AwsSigV4::new("AKID", "secret", "eu-west-1", "s3").session_token("token");
+        let client = Client::builder().middleware(signer).build().unwrap();
+        let request = client.put("https://bucket.s3.amazonaws.com/a%20b.txt?x-id=PutObject").unwrap().body(b"hi".to_vec());
+        let head = String::from_utf8(request.intercept().unwrap().preview()).unwrap();
+
+        assert!(head.contains("\r\nx-amz-content-sha256: 8f434346648f6b96df89dda901c5176b10a6d83961dd3c1ac88b59b2dc327aa4\r\n"));
+        assert!(head.contains("\r\nx-amz-security-token: token\r\n"));
+        assert!(head.contains("\r\nAuthorization: AWS4-HMAC-SHA256 Credential=AKID/"));
+        assert!(head.contains("/eu-west-1/s3/aws4_request, SignedHeaders=host;x-amz-content-sha256;x-amz-date;x-amz-security-token, Signature="));
+    }
+}
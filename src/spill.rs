@@ -0,0 +1,119 @@
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+//number of the next temporary file of the process
+static NEXT: AtomicU64 = AtomicU64::new(0);
+
+//temporary file holding a response body, removed once the last response and reader using it are dropped
+#[derive(Debug)]
+pub(crate) struct SpillFile {
+    path: PathBuf,
+}
+
+impl SpillFile {
+    pub(crate) fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+//writer keeping a body in memory until it grows larger than `threshold`, then in a temporary file
+pub(crate) struct Spill {
+    threshold: u64,
+    memory: Vec<u8>,
+    file: Option<(File, SpillFile)>,
+    len: u64,
+}
+
+impl Spill {
+    pub(crate) fn new(threshold: u64) -> Self {
+        Self { threshold, memory: Vec::new(), file: None, len: 0 }
+    }
+
+    //return the number of bytes written
+    pub(crate) fn len(&self) -> u64 {
+        self.len
+    }
+
+    //return the body if it stayed in memory, or else the file holding it
+    pub(crate) fn finish(self) -> io::Result<(Vec<u8>, Option<Arc<SpillFile>>)> {
+        match self.file {
+            Some((mut file, spilled)) => {
+                file.flush()?;
+                Ok((Vec::new(), Some(Arc::new(spilled))))
+            }
+            None => Ok((self.memory, None)),
+        }
+    }
+}
+
+impl Write for Spill {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.file.is_none() && self.len + buf.len() as u64 > self.threshold {
+            let path = std::env::temp_dir().join(format!("smolhttp-{}-{}.body", std::process::id(), NEXT.fetch_add(1, Ordering::Relaxed)));
+            let spilled = SpillFile { path };
+            let mut file = File::options().write(true).create_new(true).open(spilled.path())?;
+            file.write_all(&self.memory)?;
+            self.memory = Vec::new();
+            self.file = Some((file, spilled));
+        }
+        match self.file {
+            Some((ref mut file, _)) => file.write_all(buf)?,
+            None => self.memory.extend_from_slice(buf),
+        }
+        self.len += buf.len() as u64;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.file {
+            Some((ref mut file, _)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+//reader of a spilled body, keeping its file until it is dropped
+pub(crate) struct SpillReader {
+    pub(crate) file: File,
+    pub(crate) _spilled: Arc<SpillFile>,
+}
+
+impl Read for SpillReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.file.read(buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spills_past_threshold() {
+        let mut small = Spill::new(8);
+        small.write_all(b"12345678").unwrap();
+        let (body, file) = small.finish().unwrap();
+        assert_eq!((body.as_slice(), file.is_none()), (&b"12345678"[..], true));
+
+        let mut large = Spill::new(8);
+        large.write_all(b"12345").unwrap();
+        large.write_all(b"6789").unwrap();
+        assert_eq!(large.len(), 9);
+        let (body, file) = large.finish().unwrap();
+        let file = file.unwrap();
+        let path = file.path().to_owned();
+        assert!(body.is_empty());
+        assert_eq!(fs::read(&path).unwrap(), b"123456789");
+        drop(file);
+        assert!(!path.exists());
+    }
+}
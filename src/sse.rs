@@ -0,0 +1,291 @@
+use super::conn::{BodyReader, Stream};
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+
+use std::fmt;
+use std::io::{BufRead, BufReader};
+use std::time::Duration;
+
+//wait before reconnecting until the server sets another one
+const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+///event received from an [`EventSource`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    id: String,
+    event: String,
+    data: String,
+}
+
+impl Event {
+    ///return the last event ID set by the stream, if any
+    pub fn id(&self) -> Option<&str> {
+        match self.id.as_str() {
+            "" => None,
+            id => Some(id),
+        }
+    }
+
+    ///return the type of the event(default "message")
+    pub fn event(&self) -> &str {
+        &self.event
+    }
+
+    ///return the data of the event, its lines joined with `\n`
+    pub fn data(&self) -> &str {
+        &self.data
+    }
+}
+
+///stream of server-sent events(`text/event-stream`), returned by [`RequestBuilder::sse`].
+///
+///When the connection ends or fails, the request is sent again after the delay set by the server(default 3s)
+///with a `Last-Event-ID` header. Errors while reconnecting are returned by the iterator, which tries again on
+///the next call, a response other than `200` ends the stream with an error and `204 No Content` ends it alone.
+/// # Example
+/// ```no_run
+/// use smolhttp::Client;
+///
+/// let events = Client::new().get("https://example.com/events").unwrap().sse().unwrap();
+/// for event in events.take(10) {
+///     let event = event.unwrap();
+///     println!("{}: {}", event.event(), event.data());
+/// }
+/// ```
+pub struct EventSource {
+    request: RequestBuilder,
+    response: Response,
+    body: Option<BufReader<BodyReader<BufReader<Stream>>>>,
+    last_event_id: String,
+    retry: Duration,
+    closed: bool,
+}
+
+impl fmt::Debug for EventSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EventSource")
+            .field("response", &self.response)
+            .field("last_event_id", &self.last_event_id)
+            .field("retry", &self.retry)
+            .field("closed", &self.closed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RequestBuilder {
+    ///send the request and return the stream of server-sent events it answers with
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let mut events = Client::new().get("https://example.com/events").unwrap().sse().unwrap();
+    /// println!("{:?}", events.next());
+    /// ```
+    pub fn sse(self) -> Result<EventSource, HttpError> {
+        let request = self.header("Accept", "text/event-stream")?.header("Cache-Control", "no-cache")?;
+        let mut source = EventSource {
+            request,
+            response: Response::from_parts(b"HTTP/1.1 204 No Content\r\n\r\n", Vec::new(), Default::default())?,
+            body: None,
+            last_event_id: String::new(),
+            retry: DEFAULT_RETRY,
+            closed: false,
+        };
+        source.open()?;
+        Ok(source)
+    }
+}
+
+impl EventSource {
+    ///return the response of the last connection
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    ///return the last event ID received, sent back as `Last-Event-ID` when reconnecting
+    pub fn last_event_id(&self) -> &str {
+        &self.last_event_id
+    }
+
+    //send the request and keep the body of its response to read the events from
+    fn open(&mut self) -> Result<(), HttpError> {
+        let mut request = self.request.clone();
+        if !self.last_event_id.is_empty() {
+            request = request.header("Last-Event-ID", &self.last_event_id)?;
+        }
+        let (response, body) = request.intercept()?.stream()?;
+        self.response = response;
+        match self.response.status_code() {
+            200 => (),
+            204 => {
+                self.closed = true;
+                return Ok(());
+            }
+            _ => {
+                self.closed = true;
+                return Err(HttpError::Status { status: self.response.status(), url: self.response.url().to_owned() });
+            }
+        }
+        let content_type = self.response.header("Content-Type").unwrap_or_default();
+        if !content_type.to_ascii_lowercase().starts_with("text/event-stream") {
+            self.closed = true;
+            return Err(HttpError::Parse("response is not an event stream"));
+        }
+
+        self.body = Some(BufReader::new(body));
+        Ok(())
+    }
+
+    //read lines until an event is dispatched, returning `None` at the end of the body
+    fn read_event(&mut self) -> std::io::Result<Option<Event>> {
+        let body = match self.body {
+            Some(ref mut body) => body,
+            None => return Ok(None),
+        };
+        let mut data = String::new();
+        let mut event = String::new();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            //an event cut by the end of the stream is dropped
+            if body.read_until(b'\n', &mut line)? == 0 || line.last() != Some(&b'\n') {
+                return Ok(None);
+            }
+            line.pop();
+            if line.last() == Some(&b'\r') {
+                line.pop();
+            }
+            let line = String::from_utf8_lossy(&line);
+
+            if line.is_empty() {
+                if data.is_empty() {
+                    event.clear();
+                    continue;
+                }
+                data.pop();
+                return Ok(Some(Event {
+                    id: self.last_event_id.clone(),
+                    event: if event.is_empty() { "message".to_owned() } else { event },
+                    data,
+                }));
+            }
+            let (field, value) = match line.split_once(':') {
+                Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+                None => (&*line, ""),
+            };
+            match field {
+                // a comment
+                "" => (),
+                "data" => {
+                    data.push_str(value);
+                    data.push('\n');
+                }
+                "event" => event = value.to_owned(),
+                "id" if !value.contains('\0') => self.last_event_id = value.to_owned(),
+                "retry" => {
+                    if let Ok(retry) = value.parse() {
+                        self.retry = Duration::from_millis(retry);
+                    }
+                }
+                _ => (),
+            }
+        }
+    }
+}
+
+impl Iterator for EventSource {
+    type Item = Result<Event, HttpError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if self.closed {
+                return None;
+            }
+            if self.body.is_none() {
+                std::thread::sleep(self.retry);
+                if let Err(err) = self.open() {
+                    return Some(Err(err));
+                }
+                continue;
+            }
+            match self.read_event() {
+                Ok(Some(event)) => return Some(Ok(event)),
+                //the connection ended or failed, reconnect
+                Ok(None) | Err(_) => self.body = None,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Client, Connector, Transport};
+    use std::collections::VecDeque;
+    use std::io::{self, Cursor, Read, Write};
+    use std::sync::{Arc, Mutex};
+
+    //connection answering with a canned response and keeping what is written to it
+    struct Server(Cursor<Vec<u8>>, Arc<Mutex<Vec<String>>>);
+
+    impl Read for Server {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for Server {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1.lock().unwrap().last_mut().unwrap().push_str(&String::from_utf8_lossy(buf));
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for Server {}
+
+    //one response per connection
+    #[derive(Debug)]
+    struct Fake(Mutex<VecDeque<&'static str>>, Arc<Mutex<Vec<String>>>);
+
+    impl Connector for Fake {
+        fn connect(&self, _scheme: &str, _host: &str, _port: u16) -> Result<Box<dyn Transport>, HttpError> {
+            let res = self.0.lock().unwrap().pop_front().ok_or(HttpError::Config("no more responses"))?;
+            self.1.lock().unwrap().push(String::new());
+            Ok(Box::new(Server(Cursor::new(res.as_bytes().to_vec()), self.1.clone())))
+        }
+    }
+
+    #[test]
+    fn event_stream() {
+        let responses = [
+            //cut in the middle of an event
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nTransfer-Encoding: chunked\r\n\r\n\
+             24\r\n: hello\nretry: 0\nid: 1\nevent: greet\n\r\n\
+             1f\r\ndata: hello\ndata:  world\n\ndata\n\r\n\
+             f\r\n\ndata: partial\n\r\n",
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream; charset=utf-8\r\nContent-Length: 23\r\n\r\nid: 2\r\ndata: second\r\n\r\n",
+            "HTTP/1.1 204 No Content\r\n\r\n",
+        ];
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::with_connector(Fake(Mutex::new(responses.into()), written.clone()));
+        let events = client.get("http://example.com/events").unwrap().sse().unwrap();
+
+        let events = events.collect::<Result<Vec<_>, _>>().unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!((events[0].id(), events[0].event(), events[0].data()), (Some("1"), "greet", "hello\n world"));
+        assert_eq!((events[1].id(), events[1].event(), events[1].data()), (Some("1"), "message", ""));
+        assert_eq!((events[2].id(), events[2].event(), events[2].data()), (Some("2"), "message", "second"));
+
+        let written = written.lock().unwrap();
+        assert_eq!(written.len(), 3);
+        assert!(written[0].contains("\r\nAccept: text/event-stream\r\n"));
+        assert!(!written[0].contains("Last-Event-ID"));
+        assert!(written[1].contains("\r\nLast-Event-ID: 1\r\n"));
+        assert!(written[2].contains("\r\nLast-Event-ID: 2\r\n"));
+    }
+}
@@ -0,0 +1,183 @@
+use super::HttpError;
+
+use std::fmt;
+
+///http response status code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StatusCode(u16);
+
+impl StatusCode {
+    ///return a StatusCode from a numeric code, which must be three digits long
+    /// # Example
+    /// ```
+    /// use smolhttp::StatusCode;
+    ///
+    /// let status = StatusCode::from_u16(404).unwrap();
+    /// assert!(status.is_client_error());
+    /// ```
+    pub fn from_u16(code: u16) -> Result<Self, HttpError> {
+        if (100..1000).contains(&code) {
+            Ok(StatusCode(code))
+        } else {
+            Err(HttpError::Parse("invalid status code"))
+        }
+    }
+
+    ///return the numeric code
+    pub fn as_u16(&self) -> u16 {
+        self.0
+    }
+
+    ///return if the status is 1xx
+    pub fn is_informational(&self) -> bool {
+        (100..200).contains(&self.0)
+    }
+
+    ///return if the status is 2xx
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.0)
+    }
+
+    ///return if the status is 3xx
+    pub fn is_redirect(&self) -> bool {
+        (300..400).contains(&self.0)
+    }
+
+    ///return if the status is 4xx
+    pub fn is_client_error(&self) -> bool {
+        (400..500).contains(&self.0)
+    }
+
+    ///return if the status is 5xx
+    pub fn is_server_error(&self) -> bool {
+        (500..600).contains(&self.0)
+    }
+
+    ///return the reason phrase registered for this code, if any
+    /// # Example
+    /// ```
+    /// use smolhttp::StatusCode;
+    ///
+    /// assert_eq!(StatusCode::from_u16(404).unwrap().canonical_reason(), Some("Not Found"));
+    /// ```
+    pub fn canonical_reason(&self) -> Option<&'static str> {
+        let reason = match self.0 {
+            100 => "Continue",
+            101 => "Switching Protocols",
+            102 => "Processing",
+            103 => "Early Hints",
+            200 => "OK",
+            201 => "Created",
+            202 => "Accepted",
+            203 => "Non-Authoritative Information",
+            204 => "No Content",
+            205 => "Reset Content",
+            206 => "Partial Content",
+            207 => "Multi-Status",
+            208 => "Already Reported",
+            226 => "IM Used",
+            300 => "Multiple Choices",
+            301 => "Moved Permanently",
+            302 => "Found",
+            303 => "See Other",
+            304 => "Not Modified",
+            305 => "Use Proxy",
+            307 => "Temporary Redirect",
+            308 => "Permanent Redirect",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            402 => "Payment Required",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            407 => "Proxy Authentication Required",
+            408 => "Request Timeout",
+            409 => "Conflict",
+            410 => "Gone",
+            411 => "Length Required",
+            412 => "Precondition Failed",
+            413 => "Content Too Large",
+            414 => "URI Too Long",
+            415 => "Unsupported Media Type",
+            416 => "Range Not Satisfiable",
+            417 => "Expectation Failed",
+            418 => "I'm a teapot",
+            421 => "Misdirected Request",
+            422 => "Unprocessable Content",
+            423 => "Locked",
+            424 => "Failed Dependency",
+            425 => "Too Early",
+            426 => "Upgrade Required",
+            428 => "Precondition Required",
+            429 => "Too Many Requests",
+            431 => "Request Header Fields Too Large",
+            451 => "Unavailable For Legal Reasons",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            502 => "Bad Gateway",
+            503 => "Service Unavailable",
+            504 => "Gateway Timeout",
+            505 => "HTTP Version Not Supported",
+            506 => "Variant Also Negotiates",
+            507 => "Insufficient Storage",
+            508 => "Loop Detected",
+            510 => "Not Extended",
+            511 => "Network Authentication Required",
+            _ => return None,
+        };
+        Some(reason)
+    }
+}
+
+impl From<StatusCode> for u16 {
+    fn from(status: StatusCode) -> u16 {
+        status.0
+    }
+}
+
+impl PartialEq<u16> for StatusCode {
+    fn eq(&self, other: &u16) -> bool {
+        self.0 == *other
+    }
+}
+
+impl fmt::Display for StatusCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.canonical_reason() {
+            Some(reason) => write!(f, "{} {}", self.0, reason),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn status_classes() {
+        let status = |code| StatusCode::from_u16(code).unwrap();
+        assert!(status(101).is_informational());
+        assert!(status(204).is_success());
+        assert!(status(308).is_redirect());
+        assert!(status(429).is_client_error());
+        assert!(status(503).is_server_error());
+        assert!(!status(599).is_client_error());
+        assert!(!status(600).is_server_error());
+    }
+
+    #[test]
+    fn status_range() {
+        assert!(StatusCode::from_u16(99).is_err());
+        assert!(StatusCode::from_u16(1000).is_err());
+        assert!(StatusCode::from_u16(999).is_ok());
+    }
+
+    #[test]
+    fn status_display() {
+        assert_eq!(StatusCode::from_u16(200).unwrap().to_string(), "200 OK");
+        assert_eq!(StatusCode::from_u16(299).unwrap().to_string(), "299");
+        assert_eq!(StatusCode::from_u16(418).unwrap(), 418);
+    }
+}
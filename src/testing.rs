@@ -0,0 +1,871 @@
+//! Mock transport to test code using a [`Client`](crate::Client) without a server.
+//!
+//! A [`MockTransport`] is a [`Connector`](crate::Connector) answering requests with canned responses
+//! and keeping every request it receives, so tests can assert on them. A [`Cassette`] records real
+//! exchanges to a file once, and replays them on the following runs. A [`Server`] answers real
+//! connections on a local port, to exercise timeouts, redirects and streaming over the network.
+//!
+//! ```
+//! use smolhttp::testing::MockTransport;
+//! use smolhttp::Client;
+//!
+//! let mock = MockTransport::new();
+//! mock.respond("GET", "https://api.example.com/users/*", 200, r#"{"name": "ferris"}"#);
+//!
+//! let client = Client::with_connector(mock.clone());
+//! let res = client.get("https://api.example.com/users/1").unwrap().send().unwrap();
+//! assert_eq!(res.text(), r#"{"name": "ferris"}"#);
+//!
+//! let requests = mock.requests();
+//! assert_eq!(requests.len(), 1);
+//! assert_eq!(requests[0].url(), "https://api.example.com/users/1");
+//! ```
+
+use super::chunked;
+use super::url;
+use super::Connector;
+use super::HeaderMap;
+use super::HttpError;
+use super::StatusCode;
+use super::Transport;
+
+use super::tls::TlsConnector;
+
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, SocketAddr, TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+///connector answering requests with the responses registered for them.
+///
+///Requests are matched against the routes in the order they were registered. A route matches a request
+///when its method is the request method or `*`, and its url is the request url or a prefix of it followed
+///by `*`. A request matching no route fails with an I/O error.
+#[derive(Debug, Clone, Default)]
+pub struct MockTransport {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    routes: Vec<Route>,
+    requests: Vec<MockRequest>,
+}
+
+#[derive(Debug)]
+struct Route {
+    method: String,
+    url: String,
+    response: Vec<u8>,
+}
+
+impl Route {
+    fn matches(&self, method: &str, url: &str) -> bool {
+        route_matches(&self.method, &self.url, method, url)
+    }
+}
+
+//return if the route of `route_method` and `route_url`, which may end with `*`, matches a request
+fn route_matches(route_method: &str, route_url: &str, method: &str, url: &str) -> bool {
+    let method = route_method == "*" || route_method.eq_ignore_ascii_case(method);
+    method && match route_url.strip_suffix('*') {
+        Some(prefix) => url.starts_with(prefix),
+        None => route_url == url,
+    }
+}
+
+///request received by a [`MockTransport`].
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    method: String,
+    url: String,
+    headers: HeaderMap,
+    body: Vec<u8>,
+}
+
+impl MockRequest {
+    ///return the request method
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    ///return the absolute url of the request
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    ///return the request headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    ///return the request body
+    pub fn body(&self) -> &[u8] {
+        &self.body
+    }
+}
+
+impl MockTransport {
+    ///return a MockTransport without any route
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    ///answer the requests matching `method` and `url` with `status` and `body`
+    pub fn respond<B: Into<Vec<u8>>>(&self, method: &str, url: &str, status: u16, body: B) -> &Self {
+        let body = body.into();
+        let reason = StatusCode::from_u16(status).ok().and_then(|status| status.canonical_reason()).unwrap_or("");
+        let mut response = format!("HTTP/1.1 {} {}\r\nContent-Length: {}\r\n\r\n", status, reason, body.len()).into_bytes();
+        response.extend_from_slice(&body);
+        self.respond_raw(method, url, response)
+    }
+
+    ///answer the requests matching `method` and `url` with `response`, a whole response as sent by a server
+    /// # Example
+    /// ```
+    /// use smolhttp::testing::MockTransport;
+    ///
+    /// let mock = MockTransport::new();
+    /// mock.respond_raw("*", "http://example.com/old", b"HTTP/1.1 301 Moved Permanently\r\nLocation: /new\r\n\r\n".to_vec());
+    /// ```
+    pub fn respond_raw(&self, method: &str, url: &str, response: Vec<u8>) -> &Self {
+        self.state.lock().unwrap().routes.push(Route {
+            method: method.to_owned(),
+            url: url.to_owned(),
+            response,
+        });
+        self
+    }
+
+    ///return the requests received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    ///forget the routes and the received requests
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.routes.clear();
+        state.requests.clear();
+    }
+}
+
+impl Connector for MockTransport {
+    fn connect(&self, scheme: &str, host: &str, port: u16) -> Result<Box<dyn Transport>, HttpError> {
+        Ok(Box::new(MockConnection::new(self.clone(), scheme, host, port)))
+    }
+}
+
+//answers the requests received by a MockConnection
+trait Responder: Send {
+    fn answer(&self, request: MockRequest) -> io::Result<Vec<u8>>;
+}
+
+impl Responder for MockTransport {
+    fn answer(&self, request: MockRequest) -> io::Result<Vec<u8>> {
+        let mut state = self.state.lock().unwrap();
+        let response = state
+            .routes
+            .iter()
+            .find(|route| route.matches(&request.method, &request.url))
+            .map(|route| route.response.clone());
+        let error = format!("no mock matches {} {}", request.method, request.url);
+        state.requests.push(request);
+        response.ok_or_else(|| io::Error::new(io::ErrorKind::ConnectionRefused, error))
+    }
+}
+
+//return the origin of the urls of the requests sent to `host`
+fn origin(scheme: &str, host: &str, port: u16) -> String {
+    match url::default_port(scheme) == Some(port) {
+        true => format!("{}://{}", scheme, host),
+        false => format!("{}://{}:{}", scheme, host, port),
+    }
+}
+
+//parse the first request written to a connection with `origin` and return it with its length in bytes,
+//or None while its body is incomplete
+fn parse_request(origin: &str, written: &[u8]) -> Option<(MockRequest, usize)> {
+    let end = written.windows(4).position(|w| w == b"\r\n\r\n")?;
+    let head = String::from_utf8_lossy(&written[..end]);
+    let mut lines = head.split("\r\n");
+    let mut parts = lines.next()?.split(' ');
+    let (method, target) = (parts.next()?.to_owned(), parts.next()?);
+
+    let headers: HeaderMap = lines
+        .filter_map(|line| line.split_once(':'))
+        .map(|(k, v)| (k.trim(), v.trim()))
+        .collect();
+    let body = &written[end + 4..];
+    let (body, length) = match headers.get("Transfer-Encoding") {
+        //a chunked body is the last thing written
+        Some(coding) if coding.to_ascii_lowercase().contains("chunked") => (chunked::decode(body).ok()?.0, written.len()),
+        _ => {
+            let length = headers.get("Content-Length").and_then(|len| len.parse().ok()).unwrap_or(0);
+            (body.get(..length)?.to_vec(), end + 4 + length)
+        }
+    };
+
+    //requests through a http proxy carry the absolute url
+    let url = match target.starts_with('/') {
+        true => format!("{}{}", origin, target),
+        false => target.to_owned(),
+    };
+    Some((MockRequest { method, url, headers, body }, length))
+}
+
+//connection answering each request once it is fully written, in order
+struct MockConnection<R> {
+    responder: R,
+    origin: String,
+    written: Vec<u8>,
+    response: Option<io::Cursor<Vec<u8>>>,
+}
+
+impl<R: Responder> MockConnection<R> {
+    fn new(responder: R, scheme: &str, host: &str, port: u16) -> Self {
+        Self {
+            responder,
+            origin: origin(scheme, host, port),
+            written: Vec::new(),
+            response: None,
+        }
+    }
+}
+
+impl<R: Responder> Read for MockConnection<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let answered = self.response.as_ref().map(|response| response.position() == response.get_ref().len() as u64);
+        if answered.unwrap_or(true) {
+            let request = match parse_request(&self.origin, &self.written) {
+                Some((request, length)) => {
+                    self.written.drain(..length);
+                    request
+                }
+                //every request was answered
+                None if answered.is_some() && self.written.is_empty() => return Ok(0),
+                //the client waits for `100 Continue` before sending the body
+                None => return (&b"HTTP/1.1 100 Continue\r\n\r\n"[..]).read(buf),
+            };
+            self.response = Some(io::Cursor::new(self.responder.answer(request)?));
+        }
+        self.response.as_mut().map(|response| response.read(buf)).unwrap_or(Ok(0))
+    }
+}
+
+impl<R> Write for MockConnection<R> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl<R: Responder> Transport for MockConnection<R> {}
+
+///connector recording real exchanges to a cassette file, then replaying them without a network.
+///
+///When the file doesn't exist the cassette records: requests are sent over TCP, and native-tls for https,
+///and every exchange is written to the file once its connection is closed. Otherwise the cassette replays:
+///each request gets the recorded response of the first unused exchange with the same method and url, or the
+///last one when they are all used. A request without any recorded exchange fails with an I/O error.
+///
+///The values of the `Authorization`, `Proxy-Authorization`, `Cookie` and `Set-Cookie` headers are never
+///written to the file, nor those added with [`filter_header`](Cassette::filter_header).
+/// # Example
+/// ```no_run
+/// use smolhttp::testing::Cassette;
+/// use smolhttp::Client;
+///
+/// let cassette = Cassette::open("tests/cassettes/rust-lang.cassette").unwrap();
+/// cassette.filter_header("X-Api-Key");
+///
+/// let client = Client::with_connector(cassette.clone());
+/// let res = client.get("https://www.rust-lang.org").unwrap().send().unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct Cassette {
+    inner: Arc<CassetteRef>,
+}
+
+#[derive(Debug)]
+struct CassetteRef {
+    path: PathBuf,
+    recording: bool,
+    filtered: Mutex<Vec<String>>,
+    interactions: Mutex<Vec<Interaction>>,
+}
+
+//one recorded exchange, with its raw request and response
+#[derive(Debug)]
+struct Interaction {
+    method: String,
+    url: String,
+    request: Vec<u8>,
+    response: Vec<u8>,
+    used: bool,
+}
+
+const CASSETTE_MAGIC: &[u8] = b"smolhttp cassette\n";
+
+impl Cassette {
+    ///open the cassette at `path`, replaying it if it exists and recording to it otherwise
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, HttpError> {
+        let path = path.as_ref().to_path_buf();
+        let (recording, interactions) = match fs::read(&path) {
+            Ok(data) => (false, decode_cassette(&data).ok_or(HttpError::Parse("invalid cassette file"))?),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => (true, Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let filtered = ["Authorization", "Proxy-Authorization", "Cookie", "Set-Cookie"].map(String::from).to_vec();
+        Ok(Self {
+            inner: Arc::new(CassetteRef {
+                path,
+                recording,
+                filtered: Mutex::new(filtered),
+                interactions: Mutex::new(interactions),
+            }),
+        })
+    }
+
+    ///return if the cassette records exchanges rather than replaying them
+    pub fn is_recording(&self) -> bool {
+        self.inner.recording
+    }
+
+    ///keep the values of the `name` header out of the cassette file
+    pub fn filter_header(&self, name: &str) -> &Self {
+        self.inner.filtered.lock().unwrap().push(name.to_owned());
+        self
+    }
+
+    //add an exchange and rewrite the cassette file
+    fn record(&self, written: &[u8], read: &[u8], origin: &str) -> io::Result<()> {
+        let request = match parse_request(origin, written) {
+            Some((request, _)) => request,
+            None => return Ok(()),
+        };
+        let filtered = self.inner.filtered.lock().unwrap();
+        let mut interactions = self.inner.interactions.lock().unwrap();
+        interactions.push(Interaction {
+            method: request.method,
+            url: request.url,
+            request: filter_headers(written, &filtered),
+            response: filter_headers(read, &filtered),
+            used: false,
+        });
+        fs::write(&self.inner.path, encode_cassette(&interactions))
+    }
+}
+
+impl Connector for Cassette {
+    fn connect(&self, scheme: &str, host: &str, port: u16) -> Result<Box<dyn Transport>, HttpError> {
+        if !self.is_recording() {
+            return Ok(Box::new(MockConnection::new(self.clone(), scheme, host, port)));
+        }
+        let tcp = TcpStream::connect((host, port))?;
+        let stream: Box<dyn Transport> = match scheme {
+            "https" => Box::new(TlsConnector::new()?.connect(host, tcp)?),
+            _ => Box::new(tcp),
+        };
+        Ok(Box::new(Recording {
+            cassette: self.clone(),
+            origin: origin(scheme, host, port),
+            stream,
+            written: Vec::new(),
+            read: Vec::new(),
+        }))
+    }
+}
+
+impl Responder for Cassette {
+    fn answer(&self, request: MockRequest) -> io::Result<Vec<u8>> {
+        let mut interactions = self.inner.interactions.lock().unwrap();
+        let matching: Vec<_> = interactions.iter_mut().filter(|i| i.method == request.method && i.url == request.url).collect();
+        let last = matching.len().saturating_sub(1);
+        if let Some((_, interaction)) = matching.into_iter().enumerate().find(|(i, interaction)| !interaction.used || *i == last) {
+            interaction.used = true;
+            return Ok(interaction.response.clone());
+        }
+        let error = format!("no recorded exchange for {} {}", request.method, request.url);
+        Err(io::Error::new(io::ErrorKind::ConnectionRefused, error))
+    }
+}
+
+//connection of a recording cassette, keeping what goes through it until it's closed
+struct Recording {
+    cassette: Cassette,
+    origin: String,
+    stream: Box<dyn Transport>,
+    written: Vec<u8>,
+    read: Vec<u8>,
+}
+
+impl Read for Recording {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.stream.read(buf)?;
+        self.read.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+}
+
+impl Write for Recording {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.stream.write(buf)?;
+        self.written.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+}
+
+impl Transport for Recording {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        self.stream.set_read_timeout(timeout)
+    }
+
+    fn shutdown_handle(&self) -> Option<Box<dyn Fn() + Send + Sync>> {
+        self.stream.shutdown_handle()
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        //nothing reports the error of a connection being closed
+        let _ = self.cassette.record(&self.written, &self.read, &self.origin);
+    }
+}
+
+//replace the values of the `filtered` headers of a raw request or response head
+fn filter_headers(raw: &[u8], filtered: &[String]) -> Vec<u8> {
+    let end = match raw.windows(4).position(|w| w == b"\r\n\r\n") {
+        Some(end) => end,
+        None => return raw.to_vec(),
+    };
+    let head = String::from_utf8_lossy(&raw[..end]);
+    let mut out = String::new();
+    for (i, line) in head.split("\r\n").enumerate() {
+        match line.split_once(':') {
+            Some((name, _)) if i > 0 && filtered.iter().any(|f| f.eq_ignore_ascii_case(name.trim())) => {
+                out.push_str(name);
+                out.push_str(": [FILTERED]");
+            }
+            _ => out.push_str(line),
+        }
+        out.push_str("\r\n");
+    }
+    let mut out = out.into_bytes();
+    out.extend_from_slice(&raw[end + 2..]);
+    out
+}
+
+//serialize exchanges as `> METHOD URL LENGTH` and `< LENGTH` lines each followed by the raw bytes
+fn encode_cassette(interactions: &[Interaction]) -> Vec<u8> {
+    let mut out = CASSETTE_MAGIC.to_vec();
+    for interaction in interactions {
+        out.extend_from_slice(format!("> {} {} {}\n", interaction.method, interaction.url, interaction.request.len()).as_bytes());
+        out.extend_from_slice(&interaction.request);
+        out.extend_from_slice(format!("\n< {}\n", interaction.response.len()).as_bytes());
+        out.extend_from_slice(&interaction.response);
+        out.push(b'\n');
+    }
+    out
+}
+
+fn decode_cassette(mut data: &[u8]) -> Option<Vec<Interaction>> {
+    //split a line then a block of bytes whose length ends the line
+    fn block<'a>(data: &mut &'a [u8], prefix: &str) -> Option<(String, &'a [u8])> {
+        let end = data.iter().position(|&b| b == b'\n')?;
+        let line = std::str::from_utf8(&data[..end]).ok()?.strip_prefix(prefix)?;
+        let (rest, len) = line.rsplit_once(' ').unwrap_or(("", line));
+        let len: usize = len.parse().ok()?;
+        let bytes = data.get(end + 1..end + 1 + len)?;
+        if data.get(end + 1 + len) != Some(&b'\n') {
+            return None;
+        }
+        *data = &data[end + 2 + len..];
+        Some((rest.to_owned(), bytes))
+    }
+
+    data = data.strip_prefix(CASSETTE_MAGIC)?;
+    let mut interactions = Vec::new();
+    while !data.is_empty() {
+        let (target, request) = block(&mut data, "> ")?;
+        let (_, response) = block(&mut data, "< ")?;
+        let (method, url) = target.split_once(' ')?;
+        interactions.push(Interaction {
+            method: method.to_owned(),
+            url: url.to_owned(),
+            request: request.to_vec(),
+            response: response.to_vec(),
+            used: false,
+        });
+    }
+    Some(interactions)
+}
+
+///http server listening on an ephemeral port of the loopback interface, answering with the handlers
+///registered for its routes.
+///
+///Routes are matched like those of a [`MockTransport`], with paths in place of urls: `/items` or `/items/*`.
+///A `HEAD` request without its own route gets the head of the response of the `GET` route, and a request
+///matching no route gets a `404 Not Found`. Each connection is served by its own thread, and the
+///server stops accepting connections once dropped.
+/// # Example
+/// ```
+/// use smolhttp::testing::{MockResponse, Server};
+/// use std::time::Duration;
+///
+/// let server = Server::new().unwrap();
+/// server.respond("GET", "/hello", 200, "hi")
+///     .respond_with("GET", "/slow", |_| MockResponse::new(200).delay(Duration::from_secs(2)));
+///
+/// assert_eq!(smolhttp::get(&server.url("/hello")).unwrap().text(), "hi");
+/// let client = smolhttp::Client::new();
+/// let slow = client.get(&server.url("/slow")).unwrap().timeout(Duration::from_millis(100)).send();
+/// assert!(slow.is_err());
+/// ```
+#[derive(Debug)]
+pub struct Server {
+    addr: SocketAddr,
+    state: Arc<Mutex<ServerState>>,
+    stopped: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct ServerState {
+    routes: Vec<(String, String, Arc<Handler>)>,
+    requests: Vec<MockRequest>,
+}
+
+type Handler = dyn Fn(&MockRequest) -> MockResponse + Send + Sync;
+
+impl std::fmt::Debug for ServerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let routes: Vec<_> = self.routes.iter().map(|(method, path, _)| format!("{} {}", method, path)).collect();
+        f.debug_struct("ServerState").field("routes", &routes).field("requests", &self.requests).finish()
+    }
+}
+
+///response sent by a [`Server`], built by the handler of a route.
+/// # Example
+/// ```
+/// use smolhttp::testing::MockResponse;
+/// use std::time::Duration;
+///
+/// //`hello world` sent in chunks of 2 bytes, 10ms apart, after a second
+/// let res = MockResponse::new(200)
+///     .header("Content-Type", "text/plain")
+///     .body("hello world")
+///     .delay(Duration::from_secs(1))
+///     .chunked(2, Duration::from_millis(10));
+/// ```
+#[derive(Debug, Clone)]
+pub struct MockResponse {
+    status: u16,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+    delay: Duration,
+    chunks: Option<(usize, Duration)>,
+}
+
+impl MockResponse {
+    ///return a response with `status`, without headers nor body
+    pub fn new(status: u16) -> Self {
+        Self { status, headers: Vec::new(), body: Vec::new(), delay: Duration::ZERO, chunks: None }
+    }
+
+    ///add the header `name` with `value`
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    ///set the body, sent with a `Content-Length` unless the response is chunked
+    pub fn body<B: Into<Vec<u8>>>(mut self, body: B) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    ///wait for `delay` before sending anything
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    ///send the body with the chunked transfer coding, in chunks of `size` bytes(at least 1) each followed by a
+    ///pause of `interval`
+    pub fn chunked(mut self, size: usize, interval: Duration) -> Self {
+        self.chunks = Some((size.max(1), interval));
+        self
+    }
+
+    //write the response to `stream`, without its body for a HEAD request
+    fn write_to<W: Write>(&self, stream: &mut W, head_only: bool) -> io::Result<()> {
+        thread::sleep(self.delay);
+        let reason = StatusCode::from_u16(self.status).ok().and_then(|status| status.canonical_reason()).unwrap_or("");
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, reason);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        match self.chunks {
+            Some(_) => head.push_str("Transfer-Encoding: chunked\r\n\r\n"),
+            None => head.push_str(&format!("Content-Length: {}\r\n\r\n", self.body.len())),
+        }
+        stream.write_all(head.as_bytes())?;
+        stream.flush()?;
+        match self.chunks {
+            _ if head_only => Ok(()),
+            Some((size, interval)) => {
+                for chunk in self.body.chunks(size) {
+                    write!(stream, "{:x}\r\n", chunk.len())?;
+                    stream.write_all(chunk)?;
+                    stream.write_all(b"\r\n")?;
+                    stream.flush()?;
+                    thread::sleep(interval);
+                }
+                stream.write_all(b"0\r\n\r\n")?;
+                stream.flush()
+            }
+            None => {
+                stream.write_all(&self.body)?;
+                stream.flush()
+            }
+        }
+    }
+}
+
+impl Server {
+    ///return a server listening on an ephemeral port of `127.0.0.1`, without any route
+    pub fn new() -> Result<Self, HttpError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let server = Self {
+            addr: listener.local_addr()?,
+            state: Arc::default(),
+            stopped: Arc::default(),
+        };
+        let (state, stopped) = (server.state.clone(), server.stopped.clone());
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if stopped.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    let state = state.clone();
+                    //a client closing its connection early isn't an error of the server
+                    thread::spawn(move || {
+                        let _ = serve(stream, &state);
+                    });
+                }
+            }
+        });
+        Ok(server)
+    }
+
+    ///return the address the server listens on
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    ///return the url of `path` on the server, like `http://127.0.0.1:41234/path`
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    ///answer the requests matching `method` and `path` with `status` and `body`
+    pub fn respond<B: Into<Vec<u8>>>(&self, method: &str, path: &str, status: u16, body: B) -> &Self {
+        let response = MockResponse::new(status).body(body);
+        self.respond_with(method, path, move |_| response.clone())
+    }
+
+    ///answer the requests matching `method` and `path` with the response returned by `handler`
+    /// # Example
+    /// ```
+    /// use smolhttp::testing::{MockResponse, Server};
+    ///
+    /// let server = Server::new().unwrap();
+    /// server.respond_with("GET", "/old", |_| MockResponse::new(301).header("Location", "/new"))
+    ///     .respond_with("*", "/echo", |req| MockResponse::new(200).body(req.body()));
+    ///
+    /// let client = smolhttp::Client::new();
+    /// let res = client.post(&server.url("/echo")).unwrap().body("ping").send().unwrap();
+    /// assert_eq!(res.text(), "ping");
+    /// ```
+    pub fn respond_with<F>(&self, method: &str, path: &str, handler: F) -> &Self
+    where
+        F: Fn(&MockRequest) -> MockResponse + Send + Sync + 'static,
+    {
+        self.state.lock().unwrap().routes.push((method.to_owned(), path.to_owned(), Arc::new(handler)));
+        self
+    }
+
+    ///return the requests received so far, oldest first
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        //wake up the thread waiting for a connection
+        let _ = TcpStream::connect(self.addr);
+    }
+}
+
+//answer the requests of a connection until the client closes it
+fn serve(mut stream: TcpStream, state: &Mutex<ServerState>) -> io::Result<()> {
+    let origin = format!("http://{}", stream.local_addr()?);
+    let mut written = Vec::new();
+    let mut continued = false;
+    let mut buf = [0; 8192];
+    loop {
+        let (request, length) = match parse_request(&origin, &written) {
+            Some(parsed) => parsed,
+            None => {
+                //the client waits for `100 Continue` before sending the body
+                let head = written.windows(4).position(|w| w == b"\r\n\r\n").map(|end| String::from_utf8_lossy(&written[..end]).to_ascii_lowercase());
+                if !continued && head.is_some_and(|head| head.contains("\r\nexpect: 100-continue")) {
+                    stream.write_all(b"HTTP/1.1 100 Continue\r\n\r\n")?;
+                    continued = true;
+                }
+                let n = stream.read(&mut buf)?;
+                if n == 0 {
+                    return Ok(());
+                }
+                written.extend_from_slice(&buf[..n]);
+                continue;
+            }
+        };
+        written.drain(..length);
+        continued = false;
+
+        let path = request.url.strip_prefix(&origin).unwrap_or(&request.url).to_owned();
+        let handler = {
+            let mut state = state.lock().unwrap();
+            state.requests.push(request.clone());
+            let find = |method: &str| state.routes.iter().find(|(route_method, route, _)| route_matches(route_method, route, method, &path));
+            let route = find(&request.method).or_else(|| find("GET").filter(|_| request.method == "HEAD"));
+            route.map(|(_, _, handler)| handler.clone())
+        };
+        let response = handler.map(|handler| handler(&request)).unwrap_or_else(|| MockResponse::new(404));
+        response.write_to(&mut stream, request.method == "HEAD")?;
+        if request.headers.get("Connection").is_some_and(|value| value.eq_ignore_ascii_case("close")) {
+            return stream.shutdown(Shutdown::Write);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+
+    #[test]
+    fn mock_routes() {
+        let mock = MockTransport::new();
+        mock.respond("POST", "http://example.com:8080/items", 201, "created")
+            .respond("*", "http://example.com:8080/items/*", 404, "")
+            .respond_raw("GET", "http://example.com:8080/old", b"HTTP/1.1 301 Moved Permanently\r\nLocation: /items/1\r\n\r\n".to_vec());
+        let client = Client::builder().connector(mock.clone()).redirects(1).build().unwrap();
+
+        let res = client.post("http://example.com:8080/items").unwrap().body(b"{}".to_vec()).send().unwrap();
+        assert_eq!((res.status_code(), res.reason(), res.text().as_str()), (201, "Created", "created"));
+        assert_eq!(client.get("http://example.com:8080/old").unwrap().send().unwrap().status_code(), 404);
+        assert!(client.get("http://example.com:8080/other").unwrap().send().is_err());
+
+        let requests = mock.requests();
+        let urls: Vec<_> = requests.iter().map(|req| (req.method(), req.url())).collect();
+        assert_eq!(urls, [
+            ("POST", "http://example.com:8080/items"),
+            ("GET", "http://example.com:8080/old"),
+            ("GET", "http://example.com:8080/items/1"),
+            ("GET", "http://example.com:8080/other"),
+        ]);
+        assert_eq!(requests[0].body(), b"{}");
+        assert_eq!(requests[0].headers().get("Content-Length"), Some("2"));
+
+        mock.reset();
+        assert!(mock.requests().is_empty());
+    }
+
+    #[test]
+    fn cassette_replay() {
+        use std::net::TcpListener;
+        use std::thread;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            for (i, stream) in listener.incoming().take(2).enumerate() {
+                let mut stream = stream.unwrap();
+                let mut buf = [0; 1024];
+                let _ = stream.read(&mut buf).unwrap();
+                let body = format!("call {i}");
+                write!(stream, "HTTP/1.1 200 OK\r\nSet-Cookie: id=secret\r\nContent-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+            }
+        });
+
+        let path = std::env::temp_dir().join(format!("smolhttp-cassette-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let url = format!("http://127.0.0.1:{port}/data");
+        let send = |cassette: &Cassette| {
+            let client = Client::with_connector(cassette.clone());
+            client.get(&url).unwrap().header("Authorization", "Bearer secret").unwrap().send().unwrap().text()
+        };
+
+        let cassette = Cassette::open(&path).unwrap();
+        assert!(cassette.is_recording());
+        assert_eq!((send(&cassette), send(&cassette)), ("call 0".to_owned(), "call 1".to_owned()));
+        server.join().unwrap();
+
+        let data = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&data).contains("secret"));
+
+        let cassette = Cassette::open(&path).unwrap();
+        assert!(!cassette.is_recording());
+        assert_eq!(send(&cassette), "call 0");
+        assert_eq!(send(&cassette), "call 1");
+        assert_eq!(send(&cassette), "call 1");
+        let client = Client::with_connector(cassette);
+        assert!(client.get(&format!("http://127.0.0.1:{port}/other")).unwrap().send().is_err());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn local_server() {
+        let server = Server::new().unwrap();
+        server
+            .respond("GET", "/items/*", 200, "item")
+            .respond_with("GET", "/old", |_| MockResponse::new(302).header("Location", "/items/1"))
+            .respond_with("GET", "/slow", |_| MockResponse::new(200).body("late").delay(Duration::from_millis(500)))
+            .respond_with("GET", "/stream", |_| MockResponse::new(200).body("hello world").chunked(3, Duration::from_millis(5)))
+            .respond_with("PUT", "/echo", |req| MockResponse::new(201).body(req.body()));
+        let client = Client::builder().redirects(1).build().unwrap();
+
+        let res = client.get(&server.url("/old")).unwrap().send().unwrap();
+        assert_eq!((res.status_code(), res.text().as_str()), (200, "item"));
+        let res = client.get(&server.url("/stream")).unwrap().send().unwrap();
+        assert_eq!((res.header("Transfer-Encoding"), res.text().as_str()), (Some("chunked"), "hello world"));
+        let res = client.put(&server.url("/echo")).unwrap().body("ping").send().unwrap();
+        assert_eq!((res.status_code(), res.text().as_str()), (201, "ping"));
+        assert_eq!(client.head(&server.url("/items/2")).unwrap().send().unwrap().content_length(), Some(4));
+        assert_eq!(client.get(&server.url("/missing")).unwrap().send().unwrap().status_code(), 404);
+        let slow = client.get(&server.url("/slow")).unwrap().timeout(Duration::from_millis(50)).send();
+        assert_eq!(slow.unwrap_err().kind(), crate::ErrorKind::Timeout);
+
+        let requests = server.requests();
+        let paths: Vec<_> = requests.iter().map(|req| req.url().trim_start_matches(&server.url(""))).collect();
+        assert_eq!(paths, ["/old", "/items/1", "/stream", "/echo", "/items/2", "/missing", "/slow"]);
+        assert_eq!(requests[3].body(), b"ping");
+    }
+}
@@ -0,0 +1,54 @@
+use std::time::Duration;
+
+///durations of the phases of the exchange that produced a [`Response`](crate::Response).
+///
+///Each phase is measured on its own: `connect` doesn't include `dns`, and `first_byte` starts once the
+///request head is sent. Responses parsed with [`Response::new`](crate::Response::new) or served from a
+///cache have zero timings.
+/// # Example
+/// ```no_run
+/// let res = smolhttp::get("https://www.rust-lang.org").unwrap();
+/// let timings = res.timings();
+/// println!("dns {:?}, connect {:?}, tls {:?}", timings.dns(), timings.connect(), timings.tls());
+/// println!("first byte {:?}, total {:?}", timings.first_byte(), timings.total());
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Timings {
+    pub(crate) dns: Duration,
+    pub(crate) connect: Duration,
+    pub(crate) tls: Option<Duration>,
+    pub(crate) first_byte: Duration,
+    pub(crate) total: Duration,
+}
+
+impl Timings {
+    ///return the time spent resolving the host name(zero when served from the dns cache)
+    pub fn dns(&self) -> Duration {
+        self.dns
+    }
+
+    ///return the time spent establishing the TCP connection, including the proxy tunnel if any
+    pub fn connect(&self) -> Duration {
+        self.connect
+    }
+
+    ///return the time spent in the TLS handshake(None for plain http)
+    pub fn tls(&self) -> Option<Duration> {
+        self.tls
+    }
+
+    ///return the time between sending the request head and receiving the response head
+    pub fn first_byte(&self) -> Duration {
+        self.first_byte
+    }
+
+    ///return the time between the start of the exchange and the end of the response body
+    pub fn total(&self) -> Duration {
+        self.total
+    }
+
+    //return the time spent receiving the response body, what remains of the total once the other phases are removed
+    pub(crate) fn receive(&self) -> Duration {
+        self.total.saturating_sub(self.dns + self.connect + self.tls.unwrap_or_default() + self.first_byte)
+    }
+}
@@ -0,0 +1,103 @@
+//stand-in for the native-tls types on wasm32, where the browser sets up TLS for the fetch API and the crate
+//never opens a connection itself
+
+use std::error;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+///TLS error, returned by every attempt to set up TLS on wasm32
+#[derive(Debug)]
+pub struct Error(());
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("TLS is set up by the browser on wasm32")
+    }
+}
+
+impl error::Error for Error {}
+
+///failed TLS handshake
+#[derive(Debug)]
+pub enum HandshakeError<S> {
+    Failure(Error),
+    #[doc(hidden)]
+    Never(PhantomData<S>),
+}
+
+impl<S: fmt::Debug> fmt::Display for HandshakeError<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HandshakeError::Failure(err) => err.fmt(f),
+            HandshakeError::Never(_) => Ok(()),
+        }
+    }
+}
+
+impl<S: fmt::Debug> error::Error for HandshakeError<S> {}
+
+#[derive(Debug, Clone)]
+pub(crate) struct TlsConnector(());
+
+impl TlsConnector {
+    pub(crate) fn new() -> Result<Self, Error> {
+        Err(Error(()))
+    }
+
+    pub(crate) fn builder() -> TlsConnectorBuilder {
+        TlsConnectorBuilder(())
+    }
+
+    pub(crate) fn connect<S>(&self, _domain: &str, _stream: S) -> Result<TlsStream<S>, HandshakeError<S>> {
+        Err(HandshakeError::Failure(Error(())))
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct TlsConnectorBuilder(());
+
+impl TlsConnectorBuilder {
+    pub(crate) fn danger_accept_invalid_certs(&mut self, _accept: bool) -> &mut Self {
+        self
+    }
+
+    #[cfg(feature = "alpn")]
+    pub(crate) fn request_alpns(&mut self, _protocols: &[&str]) -> &mut Self {
+        self
+    }
+
+    pub(crate) fn build(&self) -> Result<TlsConnector, Error> {
+        Err(Error(()))
+    }
+}
+
+//never built, the handshake always fails
+pub(crate) struct TlsStream<S>(std::convert::Infallible, PhantomData<S>);
+
+impl<S> TlsStream<S> {
+    pub(crate) fn get_ref(&self) -> &S {
+        match self.0 {}
+    }
+
+    #[cfg(feature = "alpn")]
+    pub(crate) fn negotiated_alpn(&self) -> Result<Option<Vec<u8>>, Error> {
+        match self.0 {}
+    }
+}
+
+impl<S> Read for TlsStream<S> {
+    fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+        match self.0 {}
+    }
+}
+
+impl<S> Write for TlsStream<S> {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        match self.0 {}
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.0 {}
+    }
+}
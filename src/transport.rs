@@ -0,0 +1,135 @@
+use super::HttpError;
+
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::{Shutdown, TcpStream};
+use std::time::Duration;
+
+///connection opened by a [`Connector`], the request is written to it and the response read from it.
+///
+///It's implemented for the standard streams, and for any `Read + Write` type in a few lines.
+pub trait Transport: Read + Write + Send {
+    ///set the read timeout, used while waiting for `100 Continue`(default does nothing)
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        let _ = timeout;
+        Ok(())
+    }
+
+    ///return a function shutting the connection down from another thread, called when the request using it
+    ///is aborted with an [`AbortHandle`](crate::AbortHandle)(default none, a blocked read or write of the
+    ///request then waits for the timeout)
+    fn shutdown_handle(&self) -> Option<Box<dyn Fn() + Send + Sync>> {
+        None
+    }
+}
+
+impl Transport for TcpStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        TcpStream::set_read_timeout(self, timeout)
+    }
+
+    fn shutdown_handle(&self) -> Option<Box<dyn Fn() + Send + Sync>> {
+        let stream = self.try_clone().ok()?;
+        Some(Box::new(move || {
+            let _ = stream.shutdown(Shutdown::Both);
+        }))
+    }
+}
+
+#[cfg(unix)]
+impl Transport for std::os::unix::net::UnixStream {
+    fn set_read_timeout(&mut self, timeout: Option<Duration>) -> io::Result<()> {
+        std::os::unix::net::UnixStream::set_read_timeout(self, timeout)
+    }
+
+    fn shutdown_handle(&self) -> Option<Box<dyn Fn() + Send + Sync>> {
+        let stream = self.try_clone().ok()?;
+        Some(Box::new(move || {
+            let _ = stream.shutdown(Shutdown::Both);
+        }))
+    }
+}
+
+impl<S: Read + Write + Send> Transport for super::tls::TlsStream<S> {}
+
+///opens the connections of a blocking [`Client`](crate::Client) in place of the default TCP and native-tls ones.
+///
+///`scheme`, `host` and `port` are those of the request url, the connector is responsible for TLS when
+///`scheme` is `https`. The proxy, dns and socket options of the Client aren't used, and an
+///[`AbortHandle`](crate::AbortHandle) shuts the connections down through [`Transport::shutdown_handle`].
+///The async Client doesn't support connectors.
+/// # Example
+/// ```
+/// use smolhttp::{Client, Connector, HttpError, Transport};
+/// use std::net::{Shutdown, TcpStream};
+///
+/// // send every request to a local test server, whatever its url
+/// #[derive(Debug)]
+/// struct Loopback(u16);
+///
+/// impl Connector for Loopback {
+///     fn connect(&self, _scheme: &str, _host: &str, _port: u16) -> Result<Box<dyn Transport>, HttpError> {
+///         Ok(Box::new(TcpStream::connect(("127.0.0.1", self.0))?))
+///     }
+/// }
+///
+/// let client = Client::builder().connector(Loopback(8080)).build().unwrap();
+/// ```
+pub trait Connector: fmt::Debug + Send + Sync {
+    fn connect(&self, scheme: &str, host: &str, port: u16) -> Result<Box<dyn Transport>, HttpError>;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Client;
+    use std::io::Cursor;
+    use std::sync::{Arc, Mutex};
+
+    //in-memory connection replaying a canned response and keeping what is written to it
+    struct Pipe(Cursor<Vec<u8>>, Arc<Mutex<Vec<u8>>>);
+
+    impl Read for Pipe {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for Pipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for Pipe {}
+
+    #[derive(Debug, Default)]
+    struct Fake(Arc<Mutex<Vec<u8>>>, Arc<Mutex<Vec<String>>>);
+
+    impl Connector for Fake {
+        fn connect(&self, scheme: &str, host: &str, port: u16) -> Result<Box<dyn Transport>, HttpError> {
+            self.1.lock().unwrap().push(format!("{scheme}://{host}:{port}"));
+            let res = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nhello".to_vec();
+            Ok(Box::new(Pipe(Cursor::new(res), self.0.clone())))
+        }
+    }
+
+    #[test]
+    fn custom_connector() {
+        let fake = Fake::default();
+        let (written, targets) = (fake.0.clone(), fake.1.clone());
+        let client = Client::with_connector(fake);
+
+        let res = client.post("https://example.com/upload").unwrap().body(b"data".to_vec()).send().unwrap();
+        assert_eq!(res.text(), "hello");
+        assert_eq!(*targets.lock().unwrap(), ["https://example.com:443"]);
+
+        let written = String::from_utf8(written.lock().unwrap().clone()).unwrap();
+        assert!(written.starts_with("POST /upload HTTP/1.1\r\n"));
+        assert!(written.ends_with("\r\n\r\ndata"));
+    }
+}
@@ -0,0 +1,495 @@
+use super::disposition;
+use super::HttpError;
+
+use std::borrow::Cow;
+use std::fmt;
+use std::net::Ipv6Addr;
+
+///reason an url was rejected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlError {
+    ///the url doesn't start with `scheme://`
+    MissingScheme,
+    ///the url has no host
+    EmptyHost,
+    ///the host holds characters not allowed in it, or is a malformed IPv6 literal
+    InvalidHost,
+    ///the port isn't a number between 0 and 65535
+    InvalidPort,
+    ///the scheme has no default port and the url doesn't set one
+    MissingPort,
+}
+
+impl fmt::Display for UrlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let reason = match *self {
+            UrlError::MissingScheme => "missing scheme",
+            UrlError::EmptyHost => "empty host",
+            UrlError::InvalidHost => "invalid host",
+            UrlError::InvalidPort => "invalid port",
+            UrlError::MissingPort => "missing port for this scheme",
+        };
+        f.write_str(reason)
+    }
+}
+
+impl std::error::Error for UrlError {}
+
+///parsed absolute url, the path, the query and the fragment are percent encoded, the fragment is never sent.
+///
+///Spaces, control characters, non-ASCII characters and those RFC 3986 doesn't allow, a `%` not starting an
+///escape included, are encoded while the existing escapes are kept.
+/// # Example
+/// ```
+/// use smolhttp::Url;
+///
+/// let url = Url::parse("HTTPS://[::1]:8443/search?q=rust+http&lang=en#results").unwrap();
+/// assert_eq!(url.scheme(), "https");
+/// assert_eq!(url.host(), "::1");
+/// assert_eq!(url.port(), 8443);
+/// assert_eq!(url.path(), "/search");
+/// assert_eq!(url.query(), Some("q=rust+http&lang=en"));
+/// assert_eq!(url.query_pairs()[0], ("q".to_owned(), "rust http".to_owned()));
+/// assert_eq!(url.fragment(), Some("results"));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Url {
+    pub(crate) scheme: String,
+    pub(crate) userinfo: Option<String>,
+    //without the brackets of IPv6 literals
+    pub(crate) host: String,
+    pub(crate) port: u16,
+    pub(crate) path: String,
+    pub(crate) query: Option<String>,
+    pub(crate) fragment: Option<String>,
+}
+
+//return the port used when an url of `scheme` doesn't set one
+pub(crate) fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        _ => None,
+    }
+}
+
+impl Url {
+    ///parse an absolute `scheme://[userinfo@]host[:port][/path][?query][#fragment]` url
+    ///
+    ///`file:///path` urls have an empty host, or `localhost`, and no port. `data:` urls hold their content in
+    ///place of a path(RFC 2397).
+    /// # Example
+    /// ```
+    /// use smolhttp::{HttpError, Url, UrlError};
+    ///
+    /// assert!(Url::parse("https://example.com:8443/a?b=c").is_ok());
+    /// assert!(matches!(Url::parse("example.com"), Err(HttpError::Url(UrlError::MissingScheme))));
+    /// ```
+    pub fn parse(input: &str) -> Result<Url, HttpError> {
+        let input = input.trim();
+        let (input, fragment) = match input.split_once('#') {
+            Some((input, fragment)) => (input, Some(encode(fragment))),
+            None => (input, None),
+        };
+        if is_data(input) {
+            return Ok(Url { scheme: "data".to_owned(), userinfo: None, host: String::new(), port: 0, path: input[5..].to_owned(), query: None, fragment });
+        }
+        let (scheme, rest) = input.split_once("://").ok_or(UrlError::MissingScheme)?;
+        let valid_scheme = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+        if !valid_scheme {
+            return Err(UrlError::MissingScheme.into());
+        }
+        let scheme = scheme.to_ascii_lowercase();
+
+        let end = rest.find(['/', '?']).unwrap_or(rest.len());
+        let (authority, rest) = rest.split_at(end);
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(encode(query))),
+            None => (rest, None),
+        };
+        let path = match path.is_empty() {
+            true => "/".to_owned(),
+            false => encode(path),
+        };
+
+        //file urls name a local path, their host being empty or `localhost`
+        if scheme == "file" {
+            if !authority.is_empty() && !authority.eq_ignore_ascii_case("localhost") {
+                return Err(UrlError::InvalidHost.into());
+            }
+            return Ok(Url { scheme, userinfo: None, host: String::new(), port: 0, path, query, fragment });
+        }
+
+        let (userinfo, host_port) = match authority.rsplit_once('@') {
+            Some((userinfo, host_port)) => (Some(userinfo.to_owned()), host_port),
+            None => (None, authority),
+        };
+        let (host, port) = split_port(host_port)?;
+        let port = match port {
+            Some(port) => port,
+            None => default_port(&scheme).ok_or(UrlError::MissingPort)?,
+        };
+
+        Ok(Url { scheme, userinfo, host, port, path, query, fragment })
+    }
+
+    ///return the scheme, lowercased
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    ///return the host, without the brackets of an IPv6 literal, empty for `file:` and `data:` urls
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    ///return the port, the default one of the scheme when the url doesn't set it
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    ///return the percent encoded path, `/` when the url has none, or the content of a `data:` url
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    ///return the percent encoded query, without the `?`
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    ///return the decoded `name=value` pairs of the query, in order, a `+` standing for a space
+    pub fn query_pairs(&self) -> Vec<(String, String)> {
+        let decode = |value: &str| {
+            let value = value.replace('+', " ");
+            String::from_utf8_lossy(&disposition::percent_decode(&value).unwrap_or_else(|| value.clone().into_bytes())).into_owned()
+        };
+        self.query
+            .as_deref()
+            .unwrap_or_default()
+            .split('&')
+            .filter(|pair| !pair.is_empty())
+            .map(|pair| {
+                let (name, value) = pair.split_once('=').unwrap_or((pair, ""));
+                (decode(name), decode(value))
+            })
+            .collect()
+    }
+
+    ///return the percent encoded fragment, without the `#`
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    ///resolve a reference, like a link or a `Location` header, against an absolute base url(RFC 3986 section 5.2)
+    ///
+    ///Dot segments are removed and the fragment of the reference is kept.
+    /// # Example
+    /// ```
+    /// use smolhttp::Url;
+    ///
+    /// let base = "https://example.com/docs/guide/intro.html?lang=en";
+    /// assert_eq!(Url::join(base, "../api/").unwrap(), "https://example.com/docs/api/");
+    /// assert_eq!(Url::join(base, "//cdn.example.com/app.js").unwrap(), "https://cdn.example.com/app.js");
+    /// assert_eq!(Url::join(base, "#setup").unwrap(), "https://example.com/docs/guide/intro.html?lang=en#setup");
+    /// ```
+    pub fn join(base: &str, reference: &str) -> Result<String, HttpError> {
+        let base = Reference::split(base.trim());
+        let reference = Reference::split(reference.trim());
+        let scheme = base.scheme.ok_or(UrlError::MissingScheme)?;
+
+        let target = if reference.scheme.is_some() {
+            Reference { path: remove_dot_segments(reference.path), ..reference }
+        } else if reference.authority.is_some() {
+            Reference { scheme: Some(scheme), path: remove_dot_segments(reference.path), ..reference }
+        } else if reference.path.is_empty() {
+            Reference {
+                query: reference.query.or(base.query),
+                ..Reference { fragment: reference.fragment, ..base }
+            }
+        } else {
+            let path = match reference.path.starts_with('/') {
+                true => remove_dot_segments(reference.path),
+                false => {
+                    // merge with the base path, up to its last segment
+                    let dir = match base.path.rfind('/') {
+                        Some(i) => &base.path[..=i],
+                        None if base.authority.is_some() => "/",
+                        None => "",
+                    };
+                    remove_dot_segments(format!("{}{}", dir, reference.path).into())
+                }
+            };
+            Reference { path, query: reference.query, fragment: reference.fragment, ..base }
+        };
+        Ok(target.to_string())
+    }
+
+    //return the origin-form request target, the path and the query
+    pub(crate) fn request_string(&self) -> String {
+        match self.query {
+            Some(ref query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+}
+
+//return the path and the query of the absolute url `input` exactly as written, or None when they can't be
+//sent on a request line
+pub(crate) fn raw_target(input: &str) -> Option<String> {
+    let (_, rest) = input.trim().split_once("://")?;
+    let rest = rest.split('#').next().unwrap_or_default();
+    let target = &rest[rest.find(['/', '?']).unwrap_or(rest.len())..];
+    if target.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return None;
+    }
+    Some(match target.starts_with('/') {
+        true => target.to_owned(),
+        false => format!("/{}", target),
+    })
+}
+
+//components of an uri reference(RFC 3986 appendix B)
+struct Reference<'a> {
+    scheme: Option<&'a str>,
+    authority: Option<&'a str>,
+    path: Cow<'a, str>,
+    query: Option<&'a str>,
+    fragment: Option<&'a str>,
+}
+
+impl<'a> Reference<'a> {
+    fn split(input: &'a str) -> Self {
+        let (input, fragment) = match input.split_once('#') {
+            Some((input, fragment)) => (input, Some(fragment)),
+            None => (input, None),
+        };
+        let (input, query) = match input.split_once('?') {
+            Some((input, query)) => (input, Some(query)),
+            None => (input, None),
+        };
+        // a scheme is only found before the first `/`
+        let (scheme, input) = match input.split_once(':') {
+            Some((scheme, rest)) if !scheme.is_empty() && !scheme.contains('/') => (Some(scheme), rest),
+            _ => (None, input),
+        };
+        let (authority, path) = match input.strip_prefix("//") {
+            Some(rest) => {
+                let end = rest.find('/').unwrap_or(rest.len());
+                (Some(&rest[..end]), &rest[end..])
+            }
+            None => (None, input),
+        };
+        Self { scheme, authority, path: path.into(), query, fragment }
+    }
+}
+
+impl fmt::Display for Reference<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(scheme) = self.scheme {
+            write!(f, "{}:", scheme)?;
+        }
+        if let Some(authority) = self.authority {
+            write!(f, "//{}", authority)?;
+        }
+        f.write_str(&self.path)?;
+        if let Some(query) = self.query {
+            write!(f, "?{}", query)?;
+        }
+        if let Some(fragment) = self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+//remove the `.` and `..` segments of a path(RFC 3986 section 5.2.4)
+fn remove_dot_segments(path: Cow<str>) -> Cow<str> {
+    if !path.split('/').any(|segment| segment == "." || segment == "..") {
+        return path;
+    }
+
+    let mut output: Vec<&str> = Vec::new();
+    let segments: Vec<&str> = path.split('/').collect();
+    for (i, &segment) in segments.iter().enumerate() {
+        let last = i == segments.len() - 1;
+        match segment {
+            "." | ".." => {
+                // `a/..` removes `a`, but never the empty segment of an absolute path
+                if segment == ".." && output.len() > 1 {
+                    output.pop();
+                }
+                if last {
+                    output.push("");
+                }
+            }
+            segment => output.push(segment),
+        }
+    }
+    if path.starts_with('/') && output.first() != Some(&"") {
+        output.insert(0, "");
+    }
+    output.join("/").into()
+}
+
+//split `host[:port]`, checking both
+fn split_port(host_port: &str) -> Result<(String, Option<u16>), UrlError> {
+    let (host, port) = match host_port.strip_prefix('[') {
+        Some(literal) => {
+            let (host, rest) = literal.split_once(']').ok_or(UrlError::InvalidHost)?;
+            host.parse::<Ipv6Addr>().map_err(|_| UrlError::InvalidHost)?;
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or(UrlError::InvalidHost)?)),
+            }
+        }
+        None => match host_port.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (host_port, None),
+        },
+    };
+
+    if host.is_empty() {
+        return Err(UrlError::EmptyHost);
+    }
+    if !host_port.starts_with('[') && host.chars().any(|c| c.is_ascii_control() || " \"#%/:<>?@[\\]^`{|}".contains(c)) {
+        return Err(UrlError::InvalidHost);
+    }
+    let port = match port {
+        // an empty port is the default one
+        Some("") | None => None,
+        Some(port) if port.bytes().all(|b| b.is_ascii_digit()) => Some(port.parse().map_err(|_| UrlError::InvalidPort)?),
+        Some(_) => return Err(UrlError::InvalidPort),
+    };
+    Ok((host.to_ascii_lowercase(), port))
+}
+
+//percent encode the bytes not allowed in a path or a query, keeping the existing escapes
+fn encode(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let bytes = value.as_bytes();
+    for (i, &b) in bytes.iter().enumerate() {
+        let escape = b == b'%' && bytes.get(i + 1..i + 3).is_some_and(|hex| hex.iter().all(u8::is_ascii_hexdigit));
+        match b {
+            b if b.is_ascii_control() || !b.is_ascii() || b" \"<>\\^`{|}".contains(&b) || (b == b'%' && !escape) => {
+                out.push_str(&format!("%{:02X}", b));
+            }
+            b => out.push(b as char),
+        }
+    }
+    out
+}
+
+//return if `url` is a `data:` url, which has no authority
+pub(crate) fn is_data(url: &str) -> bool {
+    url.get(..5).is_some_and(|scheme| scheme.eq_ignore_ascii_case("data:"))
+}
+
+//percent encode everything but the unreserved characters(RFC 3986), as request signatures and path segments expect
+pub(crate) fn encode_unreserved(value: &[u8]) -> String {
+    let mut out = String::with_capacity(value.len());
+    for &b in value {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(b as char),
+            _ => out.push_str(&format!("%{:02X}", b)),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(url: &str) -> UrlError {
+        match Url::parse(url) {
+            Err(HttpError::Url(err)) => err,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_urls() {
+        let url = Url::parse("HTTPS://user:pw@Example.com:8443/a b/c?q=1 2&x=%20#frag").unwrap();
+        assert_eq!(url.scheme, "https");
+        assert_eq!(url.userinfo.as_deref(), Some("user:pw"));
+        assert_eq!(url.host, "example.com");
+        assert_eq!(url.port, 8443);
+        assert_eq!(url.request_string(), "/a%20b/c?q=1%202&x=%20");
+        assert_eq!(url.fragment(), Some("frag"));
+        assert_eq!(url.query_pairs(), [("q".to_owned(), "1 2".to_owned()), ("x".to_owned(), " ".to_owned())]);
+        assert_eq!(Url::parse("http://example.com/?a=1&&b#").unwrap().query_pairs(), [("a".to_owned(), "1".to_owned()), ("b".to_owned(), String::new())]);
+        assert_eq!(Url::parse("data:,a#b").unwrap().fragment(), Some("b"));
+        assert_eq!(Url::parse("http://example.com/a#x y").unwrap().fragment(), Some("x%20y"));
+
+        let url = Url::parse("http://[::1]?x").unwrap();
+        assert_eq!((url.host.as_str(), url.port, url.request_string().as_str()), ("::1", 80, "/?x"));
+        assert_eq!(Url::parse("https://example.com:/").unwrap().port, 443);
+        assert_eq!(Url::parse("http://bücher.example/ü").unwrap().path, "/%C3%BC");
+        assert_eq!(Url::parse("http://example.com/100%/a%2Fb?q=5%&r=%zz").unwrap().request_string(), "/100%25/a%2Fb?q=5%25&r=%25zz");
+
+        assert_eq!(raw_target("http://example.com/ü/{x}?a|b#frag").as_deref(), Some("/ü/{x}?a|b"));
+        assert_eq!(raw_target("http://example.com?x").as_deref(), Some("/?x"));
+        assert_eq!(raw_target("http://example.com/a b"), None);
+    }
+
+    #[test]
+    fn join_references() {
+        // RFC 3986 section 5.4
+        let base = "http://a/b/c/d;p?q";
+        for (reference, target) in [
+            ("g:h", "g:h"),
+            ("g", "http://a/b/c/g"),
+            ("./g", "http://a/b/c/g"),
+            ("g/", "http://a/b/c/g/"),
+            ("/g", "http://a/g"),
+            ("//g", "http://g"),
+            ("?y", "http://a/b/c/d;p?y"),
+            ("g?y", "http://a/b/c/g?y"),
+            ("#s", "http://a/b/c/d;p?q#s"),
+            ("g#s", "http://a/b/c/g#s"),
+            ("", "http://a/b/c/d;p?q"),
+            (".", "http://a/b/c/"),
+            ("./", "http://a/b/c/"),
+            ("..", "http://a/b/"),
+            ("../", "http://a/b/"),
+            ("../g", "http://a/b/g"),
+            ("../..", "http://a/"),
+            ("../../g", "http://a/g"),
+            ("../../../g", "http://a/g"),
+            ("/./g", "http://a/g"),
+            ("/../g", "http://a/g"),
+            ("g.", "http://a/b/c/g."),
+            ("..g", "http://a/b/c/..g"),
+            ("./../g", "http://a/b/g"),
+            ("g/./h", "http://a/b/c/g/h"),
+            ("g/../h", "http://a/b/c/h"),
+        ] {
+            assert_eq!(Url::join(base, reference).unwrap(), target, "{}", reference);
+        }
+
+        // redirect locations
+        let current = "https://example.com/a/b?x=1";
+        assert_eq!(Url::join(current, "http://other.org/").unwrap(), "http://other.org/");
+        assert_eq!(Url::join(current, "//cdn.example.com/f").unwrap(), "https://cdn.example.com/f");
+        assert_eq!(Url::join(current, "/login").unwrap(), "https://example.com/login");
+        assert_eq!(Url::join(current, "c?y=2").unwrap(), "https://example.com/a/c?y=2");
+        assert_eq!(Url::join(current, "?y=2").unwrap(), "https://example.com/a/b?y=2");
+        assert_eq!(Url::join("https://example.com", "next").unwrap(), "https://example.com/next");
+        assert!(Url::join("/relative/base", "next").is_err());
+    }
+
+    #[test]
+    fn url_errors() {
+        assert_eq!(error("example.com/path"), UrlError::MissingScheme);
+        assert_eq!(error("//example.com/path"), UrlError::MissingScheme);
+        assert_eq!(error("http:///path"), UrlError::EmptyHost);
+        assert_eq!(error("http://user@:80/"), UrlError::EmptyHost);
+        assert_eq!(error("http://exa mple.com/"), UrlError::InvalidHost);
+        assert_eq!(error("http://[::1/"), UrlError::InvalidHost);
+        assert_eq!(error("http://example.com:99999/"), UrlError::InvalidPort);
+        assert_eq!(error("http://example.com:http/"), UrlError::InvalidPort);
+        assert_eq!(error("ftp://example.com/"), UrlError::MissingPort);
+    }
+}
@@ -0,0 +1,52 @@
+use super::HttpError;
+
+use std::fmt;
+use std::str::FromStr;
+
+///http protocol version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Version {
+    Http10,
+    Http11,
+    Http2,
+    Http3,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::Version::*;
+        match self {
+            Http10 => write!(f, "HTTP/1.0"),
+            Http11 => write!(f, "HTTP/1.1"),
+            Http2 => write!(f, "HTTP/2"),
+            Http3 => write!(f, "HTTP/3"),
+        }
+    }
+}
+
+impl FromStr for Version {
+    type Err = HttpError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "HTTP/1.0" => Ok(Version::Http10),
+            "HTTP/1.1" => Ok(Version::Http11),
+            "HTTP/2" | "HTTP/2.0" => Ok(Version::Http2),
+            "HTTP/3" | "HTTP/3.0" => Ok(Version::Http3),
+            _ => Err(HttpError::Parse("invalid http version")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn version_round_trip() {
+        for version in [Version::Http10, Version::Http11, Version::Http2, Version::Http3] {
+            assert_eq!(version.to_string().parse::<Version>().unwrap(), version);
+        }
+        assert!("HTTP/1.2".parse::<Version>().is_err());
+    }
+}
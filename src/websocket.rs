@@ -0,0 +1,337 @@
+use super::base64;
+use super::conn::{self, Stream};
+use super::sha;
+use super::Client;
+use super::HttpError;
+use super::RequestBuilder;
+use super::Response;
+use super::Timings;
+
+use std::collections::hash_map::RandomState;
+use std::fmt;
+use std::hash::BuildHasher;
+use std::io::{BufReader, Read, Write};
+use std::time::Instant;
+
+//appended to the key of the handshake to compute `Sec-WebSocket-Accept`(RFC 6455)
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+///message sent or received over a [`WebSocket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Message {
+    Text(String),
+    Binary(Vec<u8>),
+    Ping(Vec<u8>),
+    Pong(Vec<u8>),
+    ///closing handshake, with its status code and reason if any
+    Close(Option<(u16, String)>),
+}
+
+///WebSocket connection(RFC 6455), returned by [`Client::upgrade`] and [`RequestBuilder::websocket`].
+///
+///Pings are answered automatically and returned by [`recv`](WebSocket::recv) as well, and a received
+///close is echoed back before being returned.
+/// # Example
+/// ```no_run
+/// use smolhttp::{Client, Message};
+///
+/// let mut ws = Client::new().upgrade("wss://echo.websocket.org").unwrap();
+/// ws.send(Message::Text("hello".to_owned())).unwrap();
+/// println!("{:?}", ws.recv().unwrap());
+/// ws.close(1000, "done").unwrap();
+/// ```
+pub struct WebSocket {
+    stream: BufReader<Stream>,
+    response: Response,
+    max_size: Option<u64>,
+    //opcode and data of the fragments received so far
+    partial: Option<(u8, Vec<u8>)>,
+    closed: bool,
+}
+
+impl fmt::Debug for WebSocket {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebSocket").field("response", &self.response).field("closed", &self.closed).finish_non_exhaustive()
+    }
+}
+
+impl Client {
+    ///open a WebSocket to `url`, with a `ws`, `wss`, `http` or `https` scheme
+    pub fn upgrade(&self, url: &str) -> Result<WebSocket, HttpError> {
+        self.get(url)?.websocket()
+    }
+}
+
+impl RequestBuilder {
+    ///send the request as a WebSocket handshake and return the connection it opens
+    /// # Example
+    /// ```no_run
+    /// use smolhttp::Client;
+    ///
+    /// let ws = Client::new()
+    ///     .get("wss://example.com/chat")
+    ///     .unwrap()
+    ///     .header("Sec-WebSocket-Protocol", "chat")
+    ///     .unwrap()
+    ///     .websocket()
+    ///     .unwrap();
+    /// println!("{:?}", ws.response().header("Sec-WebSocket-Protocol"));
+    /// ```
+    pub fn websocket(mut self) -> Result<WebSocket, HttpError> {
+        self.scheme = match self.scheme.as_str() {
+            "ws" | "http" => "http".to_owned(),
+            "wss" | "https" => "https".to_owned(),
+            _ => return Err(HttpError::Config("a websocket url must have a ws, wss, http or https scheme")),
+        };
+        let key = base64::encode(&[random().to_be_bytes(), random().to_be_bytes()].concat());
+        let request = self
+            .header("Connection", "Upgrade")?
+            .header("Upgrade", "websocket")?
+            .header("Sec-WebSocket-Version", "13")?
+            .header("Sec-WebSocket-Key", &key)?
+            .intercept()?;
+
+        let stream = request.connect(Instant::now(), request.effective_timeout(), &mut Timings::default())?;
+        let mut stream = BufReader::new(stream);
+        let mut head = String::new();
+        request.write_header(&mut head);
+        request.client.verbose('>', || &head);
+        stream.get_mut().write_all(head.as_bytes())?;
+        stream.get_mut().flush()?;
+
+        let head = conn::read_head(&mut stream, request.client.inner.header_limits)?;
+        request.client.verbose('<', || String::from_utf8_lossy(&head));
+        let response = request.intercept_response(Response::from_parts(&head, Vec::new(), Default::default())?.with_url(&request.raw_url))?;
+        if response.status_code() != 101 {
+            return Err(HttpError::Status { status: response.status(), url: request.raw_url });
+        }
+        let expected = base64::encode(&sha::sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+        if response.header("Sec-WebSocket-Accept") != Some(expected.as_str()) {
+            return Err(HttpError::Parse("invalid Sec-WebSocket-Accept"));
+        }
+
+        Ok(WebSocket {
+            stream,
+            response,
+            max_size: request.client.inner.max_response_size,
+            partial: None,
+            closed: false,
+        })
+    }
+}
+
+impl WebSocket {
+    ///return the response of the handshake
+    pub fn response(&self) -> &Response {
+        &self.response
+    }
+
+    ///send a message in a single frame
+    pub fn send(&mut self, message: Message) -> Result<(), HttpError> {
+        let (opcode, payload) = match message {
+            Message::Text(text) => (0x1, text.into_bytes()),
+            Message::Binary(data) => (0x2, data),
+            Message::Close(close) => {
+                self.closed = true;
+                let payload = close.map(|(code, reason)| [&code.to_be_bytes()[..], reason.as_bytes()].concat());
+                (0x8, payload.unwrap_or_default())
+            }
+            Message::Ping(data) => (0x9, data),
+            Message::Pong(data) => (0xA, data),
+        };
+        self.write_frame(opcode, &payload)
+    }
+
+    ///receive the next message, joining fragmented ones
+    pub fn recv(&mut self) -> Result<Message, HttpError> {
+        loop {
+            let (fin, opcode, payload) = self.read_frame()?;
+            match opcode {
+                0x8 => {
+                    let close = match payload.len() {
+                        0 | 1 => None,
+                        _ => Some((u16::from_be_bytes([payload[0], payload[1]]), String::from_utf8_lossy(&payload[2..]).into_owned())),
+                    };
+                    if !self.closed {
+                        self.send(Message::Close(close.clone()))?;
+                    }
+                    return Ok(Message::Close(close));
+                }
+                0x9 => {
+                    self.write_frame(0xA, &payload)?;
+                    return Ok(Message::Ping(payload));
+                }
+                0xA => return Ok(Message::Pong(payload)),
+                0x0 => match self.partial {
+                    Some((_, ref mut data)) => data.extend_from_slice(&payload),
+                    None => return Err(HttpError::Parse("websocket continuation without a message")),
+                },
+                0x1 | 0x2 if self.partial.is_none() => self.partial = Some((opcode, payload)),
+                _ => return Err(HttpError::Parse("unexpected websocket opcode")),
+            }
+
+            if let Some((opcode, ref data)) = self.partial {
+                if let Some(limit) = self.max_size {
+                    if data.len() as u64 > limit {
+                        return Err(HttpError::TooLarge { limit });
+                    }
+                }
+                if fin {
+                    let data = self.partial.take().map(|(_, data)| data).unwrap_or_default();
+                    return match opcode {
+                        0x1 => String::from_utf8(data).map(Message::Text).map_err(|_| HttpError::Parse("websocket text is not UTF-8")),
+                        _ => Ok(Message::Binary(data)),
+                    };
+                }
+            }
+        }
+    }
+
+    ///start the closing handshake with a status code and a reason
+    pub fn close(&mut self, code: u16, reason: &str) -> Result<(), HttpError> {
+        self.send(Message::Close(Some((code, reason.to_owned()))))
+    }
+
+    //write a frame masked as clients must
+    fn write_frame(&mut self, opcode: u8, payload: &[u8]) -> Result<(), HttpError> {
+        let mut frame = vec![0x80 | opcode];
+        match payload.len() {
+            len @ 0..=125 => frame.push(0x80 | len as u8),
+            len @ 126..=0xFFFF => {
+                frame.push(0x80 | 126);
+                frame.extend_from_slice(&(len as u16).to_be_bytes());
+            }
+            len => {
+                frame.push(0x80 | 127);
+                frame.extend_from_slice(&(len as u64).to_be_bytes());
+            }
+        }
+        let mask = (random() as u32).to_be_bytes();
+        frame.extend_from_slice(&mask);
+        frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+
+        let stream = self.stream.get_mut();
+        stream.write_all(&frame)?;
+        stream.flush()?;
+        Ok(())
+    }
+
+    //read a frame, returning its final flag, opcode and unmasked payload
+    fn read_frame(&mut self) -> Result<(bool, u8, Vec<u8>), HttpError> {
+        let mut head = [0u8; 2];
+        self.stream.read_exact(&mut head)?;
+        if head[0] & 0x70 != 0 {
+            return Err(HttpError::Parse("websocket frame with reserved bits"));
+        }
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0u8; 2];
+                self.stream.read_exact(&mut len)?;
+                u16::from_be_bytes(len) as u64
+            }
+            127 => {
+                let mut len = [0u8; 8];
+                self.stream.read_exact(&mut len)?;
+                u64::from_be_bytes(len)
+            }
+            len => len as u64,
+        };
+        let mut mask = [0u8; 4];
+        if head[1] & 0x80 != 0 {
+            self.stream.read_exact(&mut mask)?;
+        }
+        conn::check_size(Some(len), self.max_size)?;
+
+        let mut payload = Vec::with_capacity(conn::preallocation(Some(len), self.max_size));
+        (&mut self.stream).take(len).read_to_end(&mut payload)?;
+        if (payload.len() as u64) < len {
+            return Err(HttpError::Parse("connection closed inside a websocket frame"));
+        }
+        payload.iter_mut().enumerate().for_each(|(i, b)| *b ^= mask[i % 4]);
+        Ok((head[0] & 0x80 != 0, head[0] & 0x0F, payload))
+    }
+}
+
+//return a value unpredictable enough for the handshake key and the frame masks, from the random keys of the standard hasher
+fn random() -> u64 {
+    RandomState::new().hash_one(Instant::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Connector, Transport};
+    use std::io::{self, Cursor};
+    use std::sync::{Arc, Mutex};
+
+    //server side of a connection, answering the handshake with the accept of the key it receives
+    struct Server(Cursor<Vec<u8>>, Arc<Mutex<Vec<u8>>>, Vec<u8>);
+
+    impl Read for Server {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.get_ref().is_empty() {
+                let written = String::from_utf8_lossy(&self.1.lock().unwrap()).into_owned();
+                let key = written.split("Sec-WebSocket-Key: ").nth(1).and_then(|rest| rest.split("\r\n").next()).unwrap().to_owned();
+                let accept = base64::encode(&sha::sha1(format!("{}{}", key, ACCEPT_GUID).as_bytes()));
+                let mut res = format!("HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n", accept).into_bytes();
+                res.extend_from_slice(&self.2);
+                self.0 = Cursor::new(res);
+            }
+            self.0.read(buf)
+        }
+    }
+
+    impl Write for Server {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.1.lock().unwrap().write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl Transport for Server {}
+
+    #[derive(Debug)]
+    struct Fake(Arc<Mutex<Vec<u8>>>, Vec<u8>);
+
+    impl Connector for Fake {
+        fn connect(&self, scheme: &str, _host: &str, _port: u16) -> Result<Box<dyn Transport>, HttpError> {
+            assert_eq!(scheme, "https");
+            Ok(Box::new(Server(Cursor::new(Vec::new()), self.0.clone(), self.1.clone())))
+        }
+    }
+
+    #[test]
+    fn websocket_frames() {
+        //a text message in two fragments around a ping, then a close
+        let frames = [
+            &[0x01, 0x03][..], b"hel", &[0x89, 0x01], b"!", &[0x80, 0x02], b"lo",
+            &[0x88, 0x04, 0x03, 0xE8], b"ok",
+        ]
+        .concat();
+        let written = Arc::new(Mutex::new(Vec::new()));
+        let client = Client::with_connector(Fake(written.clone(), frames));
+
+        let mut ws = client.upgrade("wss://example.com/chat").unwrap();
+        assert_eq!(ws.response().status_code(), 101);
+        assert_eq!(ws.recv().unwrap(), Message::Ping(b"!".to_vec()));
+        assert_eq!(ws.recv().unwrap(), Message::Text("hello".to_owned()));
+        assert_eq!(ws.recv().unwrap(), Message::Close(Some((1000, "ok".to_owned()))));
+
+        let written = written.lock().unwrap();
+        let end = written.windows(4).position(|w| w == b"\r\n\r\n").unwrap() + 4;
+        let head = String::from_utf8_lossy(&written[..end]);
+        assert!(head.starts_with("GET /chat HTTP/1.1\r\n"));
+        assert!(head.contains("\r\nConnection: Upgrade\r\nUpgrade: websocket\r\nSec-WebSocket-Version: 13\r\n"));
+
+        //the pong echoing the ping then the close echoing the server's, both masked
+        let unmask = |frame: &[u8]| frame[6..].iter().enumerate().map(|(i, b)| b ^ frame[2 + i % 4]).collect::<Vec<_>>();
+        let frames = &written[end..];
+        assert_eq!(&frames[..2], [0x8A, 0x81]);
+        assert_eq!(unmask(&frames[..7]), b"!");
+        assert_eq!(&frames[7..9], [0x88, 0x84]);
+        assert_eq!(unmask(&frames[7..]), [0x03, 0xE8, b'o', b'k']);
+    }
+}